@@ -0,0 +1,423 @@
+use benchmark::proof_system::{
+    bench_aggregated, bench_batched, bench_parallel_with_progress, bench_plonkish_backend,
+    create_output_for, interrupt, output_dir, parse_k_range, write_manifest, BenchConfig,
+    BenchError, BenchJob, BenchManifest, BenchOptions, CircuitMetadata, NoProgress, OutputFormat,
+    ProgressReporter, ProofMode, StderrProgress, System,
+};
+use clap::{Parser, ValueEnum};
+use plonkish_backend::{
+    backend::{
+        hyperplonk::{util::rand_vanilla_plonk_circuit, HyperPlonk},
+        plonk::Plonk,
+        unihyperplonk::UniHyperPlonk,
+        PlonkishCircuit, PlonkishCircuitInfo,
+    },
+    halo2_curves::bn256::{Bn256, Fr},
+    pcs::{multilinear::MultilinearKzg, univariate::UnivariateKzg},
+    util::{
+        expression::rotate::{BinaryField, Lexical},
+        set_timers_quiet,
+        test::std_rng,
+        transcript::Keccak256Transcript,
+        Itertools,
+    },
+    Error,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::{num::NonZeroUsize, ops::Range, path::PathBuf, process::ExitCode};
+
+/// Benchmarks vanilla PLONK circuits on the selected systems for every `k` in
+/// the given range.
+///
+/// Ctrl-C stops the run once the prove in flight is done, keeping the rows
+/// written so far and marking the `k` it was at as `interrupted`, and a
+/// second Ctrl-C exits right away.
+#[derive(Parser)]
+struct Args {
+    /// Read the systems, `k` range and run parameters from a TOML file, see
+    /// `BenchConfig`, instead of the flags.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "system", "k_range", "format", "seed", "parallelism", "sync_output", "keep_output"
+        ]
+    )]
+    config: Option<PathBuf>,
+    /// Comma separated systems to benchmark, e.g. `hyperplonk,unihyperplonk`.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        required_unless_present = "config"
+    )]
+    system: Vec<BenchSystem>,
+    /// Range of `k` to benchmark, e.g. `10..18` or `10..=18`.
+    #[arg(long, value_parser = parse_k_range, required_unless_present = "config")]
+    k_range: Option<Range<usize>>,
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// Seed for setup, prove and verify randomness, for replaying a run.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Maximum number of systems benchmarked concurrently, 1 by default.
+    #[arg(long)]
+    parallelism: Option<NonZeroUsize>,
+    /// Prove and verify once per `k` without recording results.
+    #[arg(long)]
+    dry_run: bool,
+    /// Aggregate the proofs of this many circuits per `k` into one, which
+    /// fails for systems without an aggregation primitive.
+    #[arg(long)]
+    aggregate: Option<NonZeroUsize>,
+    /// Prove a batch of this many circuits per `k` with one setup, recording
+    /// the amortized prove time next to that of a single proof.
+    #[arg(long, conflicts_with = "aggregate")]
+    batch: Option<NonZeroUsize>,
+    /// Profile a single prove per `k` into a `.folded` stack file under the
+    /// output directory instead of sampling, which needs the `profile`
+    /// feature.
+    #[arg(long, conflicts_with_all = ["aggregate", "batch"])]
+    profile: bool,
+    /// Read setup parameters from the structured reference string of a
+    /// trusted setup at this path instead of an insecure setup from the RNG,
+    /// which must be in the format of every selected system's PCS.
+    #[arg(long, conflicts_with_all = ["aggregate", "batch"])]
+    srs: Option<PathBuf>,
+    /// Sync every result row to disk after writing it, which is slow but
+    /// keeps the rows of a long run across a machine crash.
+    #[arg(long)]
+    sync_output: bool,
+    /// Fail rather than truncate the output of a system that already has
+    /// results from an earlier run.
+    #[arg(long)]
+    keep_output: bool,
+    /// Print the number of `(system, k)` done and the time of the last one to
+    /// a single line on stderr as the run goes.
+    #[arg(long)]
+    progress: bool,
+    /// Verify the proof of every timed prove rather than only the last, which
+    /// is slower but catches proofs that only sometimes verify.
+    #[arg(long)]
+    verify_all: bool,
+    /// Don't print the start and end of each timed phase, which the `timer`
+    /// feature does through plonkish_backend. Durations are measured either
+    /// way.
+    #[arg(long)]
+    quiet: bool,
+}
+
+/// [`System`] this binary benchmarks, which are the ones `--system` accepts.
+#[derive(Clone, Copy, ValueEnum)]
+enum BenchSystem {
+    #[value(name = "hyperplonk")]
+    HyperPlonk,
+    #[value(name = "unihyperplonk")]
+    UniHyperPlonk,
+    #[value(name = "plonk")]
+    Plonk,
+}
+
+impl From<BenchSystem> for System {
+    fn from(system: BenchSystem) -> Self {
+        match system {
+            BenchSystem::HyperPlonk => System::HyperPlonk,
+            BenchSystem::UniHyperPlonk => System::UniHyperPlonk,
+            BenchSystem::Plonk => System::Plonk,
+        }
+    }
+}
+
+impl TryFrom<System> for BenchSystem {
+    type Error = BenchError;
+
+    fn try_from(system: System) -> Result<Self, BenchError> {
+        match system {
+            System::HyperPlonk => Ok(BenchSystem::HyperPlonk),
+            System::UniHyperPlonk => Ok(BenchSystem::UniHyperPlonk),
+            System::Plonk => Ok(BenchSystem::Plonk),
+            system => Err(BenchError::UnsupportedSystem { system }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    Json,
+    /// `k`, mean prove time and its error bars for GNUplot's `with yerrorbars`.
+    Gnuplot,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Csv => OutputFormat::Csv,
+            Format::Json => OutputFormat::Json,
+            Format::Gnuplot => OutputFormat::Gnuplot,
+        }
+    }
+}
+
+/// Pairs a random vanilla PLONK circuit with its circuit info, which the
+/// circuit itself doesn't provide.
+#[derive(Clone)]
+struct VanillaPlonk<C> {
+    circuit_info: PlonkishCircuitInfo<Fr>,
+    circuit: C,
+}
+
+impl<C> VanillaPlonk<C> {
+    fn new((circuit_info, circuit): (PlonkishCircuitInfo<Fr>, C)) -> Self {
+        Self {
+            circuit_info,
+            circuit,
+        }
+    }
+}
+
+impl<C: PlonkishCircuit<Fr>> PlonkishCircuit<Fr> for VanillaPlonk<C> {
+    fn circuit_info_without_preprocess(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+        Ok(self.circuit_info.clone())
+    }
+
+    fn circuit_info(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+        Ok(self.circuit_info.clone())
+    }
+
+    fn instances(&self) -> &[Vec<Fr>] {
+        self.circuit.instances()
+    }
+
+    fn synthesize(&self, round: usize, challenges: &[Fr]) -> Result<Vec<Vec<Fr>>, Error> {
+        self.circuit.synthesize(round, challenges)
+    }
+}
+
+impl<C> CircuitMetadata for VanillaPlonk<C> {}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    install_interrupt_handler();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(BenchError::Interrupted) => {
+            eprintln!(
+                "Interrupted, the rows written before are kept under {}",
+                output_dir().display()
+            );
+            ExitCode::from(130)
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Makes the first SIGINT [`interrupt`] the run and a second one exit right
+/// away, without waiting for the prove in flight.
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        if interrupt() {
+            // SAFETY: `_exit` is async-signal-safe.
+            unsafe { libc::_exit(130) };
+        }
+    }
+    // SAFETY: the handler only stores to an atomic or exits, both of which
+    // are async-signal-safe.
+    unsafe { libc::signal(libc::SIGINT, on_sigint as libc::sighandler_t) };
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+/// Returns the config of `--config`, or the one of the flags otherwise.
+fn config(args: &Args) -> Result<BenchConfig, BenchError> {
+    if let Some(path) = &args.config {
+        return BenchConfig::load(path);
+    }
+    Ok(BenchConfig {
+        systems: args.system.iter().copied().map(System::from).collect(),
+        ks: args.k_range.clone().unwrap(),
+        format: args.format.unwrap_or(Format::Csv).into(),
+        sample_size: None,
+        warmup: None,
+        output_dir: None,
+        timeout: None,
+        seed: args.seed,
+        reuse_setup: false,
+        sync_output: args.sync_output,
+        keep_output: args.keep_output,
+        parallelism: args.parallelism.unwrap_or(NonZeroUsize::MIN),
+        resume: false,
+    })
+}
+
+fn run(args: &Args) -> Result<(), BenchError> {
+    let config = config(args)?;
+    config.apply_env();
+    set_timers_quiet(args.quiet);
+    let options = &BenchOptions {
+        dry_run: args.dry_run,
+        profile: args.profile,
+        verify_all: args.verify_all,
+        srs_path: args.srs.clone(),
+        ..config.options()
+    };
+    // Fail before any output on a system of the config file this binary can't
+    // run, which `--system` already rejects.
+    let bench_systems = config
+        .systems
+        .iter()
+        .copied()
+        .map(BenchSystem::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    let systems = config.systems.clone();
+    let mode = match (args.aggregate, args.batch) {
+        (Some(_), _) => ProofMode::Aggregated,
+        (None, Some(_)) => ProofMode::Batched,
+        (None, None) => ProofMode::Single,
+    };
+    if !options.dry_run && !options.profile {
+        create_output_for(&systems, mode, options.format, !options.keep_output)?;
+        write_manifest(&BenchManifest::new(
+            &systems,
+            config.ks.clone(),
+            options.seed,
+        ))?;
+    }
+    let jobs = bench_systems
+        .into_iter()
+        .cartesian_product(config.ks.clone())
+        .map(|(system, k)| {
+            BenchJob::new(system.into(), k, move || {
+                bench(system, k, mode, args, options)
+            })
+        })
+        .collect();
+    let progress: &dyn ProgressReporter = match args.progress {
+        true => &StderrProgress,
+        false => &NoProgress,
+    };
+    bench_parallel_with_progress(jobs, config.parallelism, progress)
+}
+
+/// Benchmarks a random circuit at `k`, or aggregates or batches
+/// `--aggregate` or `--batch` of them, which share their preprocessed
+/// polynomials but differ in witness.
+fn bench(
+    bench_system: BenchSystem,
+    k: usize,
+    mode: ProofMode,
+    args: &Args,
+    options: &BenchOptions,
+) -> Result<(), BenchError> {
+    let system = System::from(bench_system);
+    let num_circuits = args.aggregate.or(args.batch).map_or(1, NonZeroUsize::get);
+    let witness_rng = |idx: usize| ChaCha20Rng::seed_from_u64(idx as u64);
+    let hyperplonk_circuits = || {
+        (0..num_circuits)
+            .map(|idx| {
+                VanillaPlonk::new(rand_vanilla_plonk_circuit::<_, BinaryField>(
+                    k,
+                    std_rng(),
+                    witness_rng(idx),
+                ))
+            })
+            .collect_vec()
+    };
+    let lexical_circuits = || {
+        (0..num_circuits)
+            .map(|idx| {
+                VanillaPlonk::new(rand_vanilla_plonk_circuit::<_, Lexical>(
+                    k,
+                    std_rng(),
+                    witness_rng(idx),
+                ))
+            })
+            .collect_vec()
+    };
+    match (bench_system, mode) {
+        (BenchSystem::HyperPlonk, ProofMode::Single) => {
+            let circuit = VanillaPlonk::new(rand_vanilla_plonk_circuit::<_, BinaryField>(
+                k,
+                std_rng(),
+                std_rng(),
+            ));
+            bench_plonkish_backend::<HyperPlonk<MultilinearKzg<Bn256>>, Fr, Keccak256Transcript<_>>(
+                system, k, &circuit, options,
+            )
+            .map(drop)
+        }
+        (BenchSystem::HyperPlonk, ProofMode::Aggregated) => {
+            bench_aggregated::<HyperPlonk<MultilinearKzg<Bn256>>, Keccak256Transcript<_>>(
+                system,
+                k,
+                &hyperplonk_circuits(),
+                options,
+            )
+        }
+        (BenchSystem::HyperPlonk, ProofMode::Batched) => {
+            bench_batched::<HyperPlonk<MultilinearKzg<Bn256>>, Keccak256Transcript<_>>(
+                system,
+                k,
+                &hyperplonk_circuits(),
+                options,
+            )
+        }
+        (BenchSystem::UniHyperPlonk, ProofMode::Single) => {
+            let circuit = VanillaPlonk::new(rand_vanilla_plonk_circuit::<_, Lexical>(
+                k,
+                std_rng(),
+                std_rng(),
+            ));
+            bench_plonkish_backend::<
+                UniHyperPlonk<UnivariateKzg<Bn256>, true>,
+                Fr,
+                Keccak256Transcript<_>,
+            >(system, k, &circuit, options)
+            .map(drop)
+        }
+        (BenchSystem::UniHyperPlonk, ProofMode::Aggregated) => {
+            bench_aggregated::<UniHyperPlonk<UnivariateKzg<Bn256>, true>, Keccak256Transcript<_>>(
+                system,
+                k,
+                &lexical_circuits(),
+                options,
+            )
+        }
+        (BenchSystem::UniHyperPlonk, ProofMode::Batched) => {
+            bench_batched::<UniHyperPlonk<UnivariateKzg<Bn256>, true>, Keccak256Transcript<_>>(
+                system,
+                k,
+                &lexical_circuits(),
+                options,
+            )
+        }
+        (BenchSystem::Plonk, ProofMode::Single) => {
+            let circuit = VanillaPlonk::new(rand_vanilla_plonk_circuit::<_, Lexical>(
+                k,
+                std_rng(),
+                std_rng(),
+            ));
+            bench_plonkish_backend::<Plonk<UnivariateKzg<Bn256>>, Fr, Keccak256Transcript<_>>(
+                system, k, &circuit, options,
+            )
+            .map(drop)
+        }
+        (BenchSystem::Plonk, ProofMode::Aggregated) => bench_aggregated::<
+            Plonk<UnivariateKzg<Bn256>>,
+            Keccak256Transcript<_>,
+        >(
+            system, k, &lexical_circuits(), options
+        ),
+        (BenchSystem::Plonk, ProofMode::Batched) => bench_batched::<
+            Plonk<UnivariateKzg<Bn256>>,
+            Keccak256Transcript<_>,
+        >(
+            system, k, &lexical_circuits(), options
+        ),
+    }
+}
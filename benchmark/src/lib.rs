@@ -1,3 +1,4 @@
 pub mod espresso;
+#[cfg(feature = "halo2")]
 pub mod halo2;
 pub mod proof_system;
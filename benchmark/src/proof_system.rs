@@ -1,80 +1,856 @@
 use plonkish_backend::util::arithmetic::Field;
 use plonkish_backend::{
-    backend::{PlonkishBackend, PlonkishCircuit, WitnessEncoding},
+    backend::{PlonkishBackend, PlonkishCircuit, PlonkishCircuitInfo, WitnessEncoding},
     halo2_curves::bn256::Fr,
-    pcs::CommitmentChunk,
+    pcs::{CommitmentChunk, PolynomialCommitmentScheme},
     util::{
-        end_timer, start_timer,
+        chain, end_timer,
+        hash::{Hash, Keccak256},
+        start_timer,
         test::std_rng,
-        transcript::{InMemoryTranscript, Keccak256Transcript, TranscriptRead, TranscriptWrite},
+        transcript::{InMemoryTranscript, TranscriptRead, TranscriptWrite},
+        Itertools,
     },
+    Error,
 };
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sparql::{circuit::format_instances, CompileError};
 use std::{
+    env,
     fmt::Display,
-    fs::{create_dir, File, OpenOptions},
-    io::{Cursor, Write},
-    iter,
-    path::Path,
+    fs::{self, create_dir_all, File, OpenOptions},
+    io::{self, Write},
+    num::NonZeroUsize,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{mpsc, Arc, Mutex},
+    thread,
     time::{Duration, Instant},
 };
 
-const OUTPUT_DIR: &str = "../chiquito/target";
+mod aggregate;
+mod alloc;
+mod batch;
+mod config;
+mod consistency;
+mod describe;
+mod driver;
+mod interrupt;
+mod manifest;
+mod matrix;
+mod metadata;
+mod ops;
+mod profile;
+mod progress;
+mod report;
+mod sink;
+mod srs;
+mod verifier;
 
-pub fn bench_plonkish_backend<B, F: Field>(
+pub use aggregate::{bench_aggregated, AggregationBackend, AGGREGATED_OUTPUT_COLUMNS};
+pub use alloc::AllocStats;
+pub use batch::{bench_batched, BATCHED_OUTPUT_COLUMNS};
+pub use config::{parse_k_range, BenchConfig};
+pub use consistency::{assert_deterministic_proof, assert_proof_round_trip};
+pub use describe::{describe_circuit, CircuitDescription};
+pub use driver::{bench_parallel, bench_parallel_with_progress, BenchJob};
+pub use interrupt::{interrupt, is_interrupted};
+pub use manifest::{manifest_path, write_manifest, BenchManifest, MANIFEST_FILE};
+pub use matrix::{
+    bench_all_systems, run_matrix, run_matrix_with_progress, MatrixDataset, QueryDataset,
+    SystemOutcome,
+};
+pub use metadata::{CircuitMetadata, TimedCircuit};
+pub use ops::VerifyOps;
+pub use progress::{NoProgress, Progress, ProgressReporter, StderrProgress};
+pub use report::{
+    detect_regressions, write_comparison_report, write_delta_table, Regression, RegressionReport,
+    DEFAULT_REGRESSION_THRESHOLD,
+};
+pub use sink::{
+    AggregatedRecord, BatchedRecord, BenchRecord, BenchRow, FileSink, OutputSink, SingleRecord,
+};
+pub use srs::{min_srs_degree, SrsDegree, TrustedSetup};
+pub use verifier::{
+    prove_to_file, verify_batched, verify_proof_file, verify_serially, VerifierParamInstances,
+};
+
+/// Environment variable overriding the directory benchmark results are written to.
+pub const OUTPUT_DIR_ENV: &str = "SHIN_BENCH_OUTPUT_DIR";
+
+const DEFAULT_OUTPUT_DIR: &str = "../chiquito/target";
+
+/// Environment variable overriding the number of untimed prove runs before sampling.
+pub const WARMUP_ENV: &str = "SHIN_BENCH_WARMUP";
+
+const DEFAULT_WARMUP: usize = 1;
+
+/// Columns of each row written by [`bench_plonkish_backend`], in order.
+/// `bytes_allocated` and `alloc_count`, the mean allocations of a prove, are
+/// empty without the `alloc-stats` feature, `triples_per_second` for circuits
+/// without [`CircuitMetadata::num_matched_triples`], `dataset` without
+/// [`BenchOptions::dataset`] and `witness_gen_ms` for circuits without
+/// [`CircuitMetadata::witness_gen_time`]. The `verify_*` counts are the
+/// [`VerifyOps`] of a verify, identical on every machine for the same proof,
+/// the transcript operations after `verify_ms` and the MSMs and pairings of
+/// the PCS at the end. `preprocess_ms` is the time of the single preprocess,
+/// which is empty when the setup is loaded by [`BenchOptions::reuse_setup`].
+///
+/// The order is stable, with new columns only ever appended, so plotting
+/// scripts can refer to columns by position.
+pub const OUTPUT_COLUMNS: &[&str] = &[
+    "k",
+    "prove_ms",
+    "prove_min_ms",
+    "prove_max_ms",
+    "prove_stddev_ms",
+    "prove_p50_ms",
+    "prove_p95_ms",
+    "prove_p99_ms",
+    "peak_mem_kb",
+    "bytes_allocated",
+    "alloc_count",
+    "verify_ms",
+    "verify_field_reads",
+    "verify_commitment_reads",
+    "verify_absorbs",
+    "verify_challenges",
+    "proof_bytes",
+    "triples_per_second",
+    "dataset",
+    "witness_gen_ms",
+    "preprocess_ms",
+    "verify_msms",
+    "verify_msm_bases",
+    "verify_pairing_checks",
+    "verify_pairings",
+];
+
+/// Columns of each row of [`OutputFormat::Gnuplot`], in order: `k`, the mean
+/// prove time and the mean minus and plus its standard deviation, all in
+/// milliseconds, as GNUplot's `with yerrorbars` expects them. The order is
+/// stable.
+pub const GNUPLOT_OUTPUT_COLUMNS: &[&str] = &["k", "prove_ms", "prove_low_ms", "prove_high_ms"];
+
+#[derive(Debug)]
+pub enum BenchError {
+    Io(io::Error),
+    Backend(Error),
+    /// The verifier rejected the proof with `error`. The proof is dumped to
+    /// `proof_path` unless writing it failed. With
+    /// [`BenchOptions::verify_all`], `iteration` is the index of the timed
+    /// prove the proof is of.
+    VerificationFailed {
+        system: System,
+        k: usize,
+        error: Error,
+        proof_path: Option<PathBuf>,
+        iteration: Option<usize>,
+    },
+    /// The requested `k` is smaller than the `k` the circuit is built for.
+    InsufficientK {
+        requested: usize,
+        required: usize,
+    },
+    /// The backend of `system` has no aggregation primitive, see
+    /// [`AggregationBackend`].
+    AggregationUnsupported {
+        system: System,
+        num_circuits: usize,
+    },
+    /// The proof at `proof_path` of `num_bytes` ended before the verifier
+    /// finished reading it.
+    TruncatedProof {
+        proof_path: PathBuf,
+        num_bytes: usize,
+    },
+    /// The number of instance values in each instance polynomial differs from
+    /// what the `vp` expects.
+    InstanceMismatch {
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// Profiling the prove failed or isn't available, see
+    /// [`BenchOptions::profile`].
+    Profiling(String),
+    /// The query of a [`QueryDataset`] doesn't compile.
+    Compile(CompileError),
+    /// The [`BenchConfig`] at `path` is invalid.
+    Config {
+        path: PathBuf,
+        reason: String,
+    },
+    /// The output at `path` already has data, which [`create_output`] was
+    /// told not to overwrite.
+    OutputExists {
+        path: PathBuf,
+    },
+    /// The run was stopped by [`interrupt`] before it finished.
+    Interrupted,
+    /// The `param` supports polynomials of degree up to `have`, but the
+    /// circuit needs `need`, see [`min_srs_degree`].
+    SrsTooSmall {
+        have: usize,
+        need: usize,
+    },
+    /// `system` isn't benchmarked through `plonkish_backend`, so the `bench`
+    /// binary can't run it.
+    UnsupportedSystem {
+        system: System,
+    },
+    /// The [`BenchOptions::dataset`] `id` has a comma, quote or newline,
+    /// which would break the rows it's written to.
+    InvalidDataset {
+        id: String,
+    },
+}
+
+impl From<io::Error> for BenchError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<Error> for BenchError {
+    fn from(err: Error) -> Self {
+        Self::Backend(err)
+    }
+}
+
+impl From<CompileError> for BenchError {
+    fn from(err: CompileError) -> Self {
+        Self::Compile(err)
+    }
+}
+
+impl Display for BenchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BenchError::Io(err) => write!(f, "io error: {err}"),
+            BenchError::Backend(err) => write!(f, "backend error: {err:?}"),
+            BenchError::VerificationFailed {
+                system,
+                k,
+                error,
+                proof_path,
+                iteration,
+            } => {
+                write!(f, "{system} proof")?;
+                if let Some(iteration) = iteration {
+                    write!(f, " of sample {iteration}")?;
+                }
+                write!(f, " failed to verify at k = {k}: {error:?}")?;
+                match proof_path {
+                    Some(path) => write!(f, " (proof dumped to {})", path.display()),
+                    None => Ok(()),
+                }
+            }
+            BenchError::InsufficientK {
+                requested,
+                required,
+            } => {
+                write!(
+                    f,
+                    "k = {requested} is too small, circuit requires k >= {required}"
+                )
+            }
+            BenchError::AggregationUnsupported {
+                system,
+                num_circuits,
+            } => {
+                write!(
+                    f,
+                    "{system} has no aggregation primitive to aggregate {num_circuits} proofs"
+                )
+            }
+            BenchError::TruncatedProof {
+                proof_path,
+                num_bytes,
+            } => {
+                write!(
+                    f,
+                    "proof {} is truncated at {num_bytes} bytes",
+                    proof_path.display()
+                )
+            }
+            BenchError::InstanceMismatch { expected, found } => {
+                write!(
+                    f,
+                    "instances of lengths {found:?} don't match vp, which expects {expected:?}"
+                )
+            }
+            BenchError::Profiling(reason) => write!(f, "profiling failed: {reason}"),
+            BenchError::Compile(err) => write!(f, "query failed to compile: {err}"),
+            BenchError::Config { path, reason } => {
+                write!(f, "invalid config {}: {reason}", path.display())
+            }
+            BenchError::OutputExists { path } => write!(
+                f,
+                "output {} already has data, move it away or allow overwriting it",
+                path.display()
+            ),
+            BenchError::Interrupted => {
+                write!(f, "interrupted, the rows written before are kept")
+            }
+            BenchError::SrsTooSmall { have, need } => write!(
+                f,
+                "SRS of degree {have} is too small for the circuit, which needs degree {need}"
+            ),
+            BenchError::UnsupportedSystem { system } => write!(
+                f,
+                "{system} isn't benchmarked through plonkish_backend, select hyperplonk, unihyperplonk or plonk"
+            ),
+            BenchError::InvalidDataset { id } => write!(
+                f,
+                "dataset {id:?} has a comma, quote or newline, which the rows can't hold"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BenchError {}
+
+/// Format of the rows appended to each system's output file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Comma separated values following a header row of [`OUTPUT_COLUMNS`].
+    #[default]
+    Csv,
+    /// One JSON object per line.
+    Json,
+    /// Space separated values of [`GNUPLOT_OUTPUT_COLUMNS`], so
+    /// `plot "hyperplonk" with yerrorbars` plots `k` against the prove time
+    /// with error bars. The header and `timeout` markers are `#` comments,
+    /// which GNUplot skips, and the other columns aren't written.
+    Gnuplot,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BenchOptions {
+    pub format: OutputFormat,
+    /// Load `param`, `pp` and `vp` from the setup cache under [`output_dir`]
+    /// when present, and store them there after computing them otherwise.
+    pub reuse_setup: bool,
+    /// Number of timed runs per phase for every `k`, falling back to the
+    /// [`sample_size`] heuristic when `None`. More samples give tighter
+    /// statistics at the cost of proportionally longer runs, which is what
+    /// the heuristic trades off for large `k`.
+    pub sample_size: Option<NonZeroUsize>,
+    /// Abandon the run once a single prove exceeds this duration, recording a
+    /// `timeout` marker for `k` instead of timings. Proving runs on a detached
+    /// worker thread, so [`bench_plonkish_backend`] returns right after the
+    /// marker is written while the abandoned prove, which can't be cancelled,
+    /// keeps using its CPU and memory in the background until it returns.
+    pub timeout: Option<Duration>,
+    /// Seed of the `ChaCha20Rng` every setup, prove and verify RNG is derived
+    /// from, each phase on its own stream. `None` keeps using `std_rng()`.
+    pub seed: Option<u64>,
+    /// Only check that setup, preprocess and a single prove and verify go
+    /// through, without warmup, timeout or writing to the output.
+    pub dry_run: bool,
+    /// Run a single prove under a sampling profiler instead of sampling,
+    /// writing its stacks to `{system}_prove-{k}.folded` under [`output_dir`]
+    /// for e.g. `inferno-flamegraph`, without verifying or writing to the
+    /// output. Needs the `profile` feature.
+    pub profile: bool,
+    /// Read `param` from the structured reference string of a trusted setup
+    /// at this path with [`TrustedSetup::load_srs`] instead of running an
+    /// insecure setup from the RNG. The setup cache isn't consulted then.
+    pub srs_path: Option<PathBuf>,
+    /// Identifier of the dataset the circuit is over, written to the
+    /// `dataset` column of each row so rows of the same `k` over different
+    /// datasets are told apart, see [`run_matrix`]. One with a comma, quote
+    /// or newline fails with [`BenchError::InvalidDataset`].
+    pub dataset: Option<String>,
+    /// Sync each row to disk after writing it, so rows survive a crash of the
+    /// machine and not only of the process, at the cost of an `fsync` per
+    /// row. Rows are always flushed.
+    pub sync_output: bool,
+    /// Verify the proof of every timed prove right after it, untimed, rather
+    /// than only the one of the last, failing with the index of the first
+    /// that doesn't verify. That catches nondeterministic witness generation
+    /// at the cost of a verify per sample.
+    pub verify_all: bool,
+    /// Don't write a row or `timeout` marker to the output, nor open it, so a
+    /// run only returns its [`BenchResult`], e.g. when calling the benchmark
+    /// from other code that keeps the numbers itself. Aggregated and batched
+    /// runs then only check their proofs.
+    pub skip_output: bool,
+    /// Fail with [`BenchError::OutputExists`] instead of truncating an output
+    /// that already has data when starting a run, see [`create_output`].
+    pub keep_output: bool,
+    /// Write each row and marker to this sink rather than the output file of
+    /// its system, `None` being a [`FileSink`] of [`Self::sync_output`].
+    pub sink: Option<Arc<dyn OutputSink>>,
+}
+
+/// Timings of each phase of a run of [`bench_plonkish_backend`], also
+/// written to its row of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+    /// Duration of setup, or `None` if `param` was passed, loaded from
+    /// [`BenchOptions::srs_path`] or from the setup cache.
+    pub setup: Option<Duration>,
+    /// Duration of preprocess, or `None` if the setup cache was hit.
+    pub preprocess: Option<Duration>,
+    /// Duration of generating the witness apart from proving, see
+    /// [`CircuitMetadata::witness_gen_time`].
+    pub witness_gen: Option<Duration>,
+    /// Mean duration of the timed proves.
+    pub prove_avg: Duration,
+    /// Mean duration of the timed verifies.
+    pub verify: Duration,
+    pub proof_bytes: usize,
+}
+
+/// Benchmarks backend `B` on `circuit` with Fiat-Shamir transcript `T`, e.g.
+/// `Keccak256Transcript<Cursor<Vec<u8>>>`, appending a row for `k` to the
+/// output of `system` unless [`BenchOptions::skip_output`]. Call
+/// [`create_output`] first to start from an empty output instead of appending
+/// to a previous run.
+///
+/// Returns the [`BenchResult`] of the run, or `None` if it only
+/// [`BenchOptions::profile`]d or timed out. With [`BenchOptions::timeout`]
+/// the prover param is shared with and `circuit` cloned for the worker thread
+/// proving, which may outlive the call.
+pub fn bench_plonkish_backend<B, F: Field, T>(
     system: System,
     k: usize,
-    circuit: &impl PlonkishCircuit<Fr>,
-) where
-    B: PlonkishBackend<Fr> + WitnessEncoding,
-    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>
+    circuit: &(impl PlonkishCircuit<Fr> + CircuitMetadata + Clone + Send + Sync + 'static),
+    options: &BenchOptions,
+) -> Result<Option<BenchResult>, BenchError>
+where
+    B: TrustedSetup + WitnessEncoding + 'static,
+    B::ProverParam: Send + Sync + 'static,
+    T: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>
         + TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>
-        + InMemoryTranscript,
+        + InMemoryTranscript<Param = ()>
+        + Default
+        + 'static,
 {
-    create_output(&[system]);
-    let circuit_info = circuit.circuit_info().unwrap();
+    bench_plonkish_backend_with_param::<B, F, T>(system, k, circuit, None, options)
+}
+
+/// Same as [`bench_plonkish_backend`], but preprocesses with `param` when
+/// given instead of running setup, e.g. to share one [`setup_param`] across a
+/// sweep of circuits at the same `k`. Neither the setup cache nor
+/// [`BenchOptions::srs_path`] is consulted then. Either way, a `param` below
+/// the [`min_srs_degree`] of the circuit fails with
+/// [`BenchError::SrsTooSmall`] before preprocessing.
+pub fn bench_plonkish_backend_with_param<B, F: Field, T>(
+    system: System,
+    k: usize,
+    circuit: &(impl PlonkishCircuit<Fr> + CircuitMetadata + Clone + Send + Sync + 'static),
+    param: Option<&Param<B>>,
+    options: &BenchOptions,
+) -> Result<Option<BenchResult>, BenchError>
+where
+    B: TrustedSetup + WitnessEncoding + 'static,
+    B::ProverParam: Send + Sync + 'static,
+    T: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + InMemoryTranscript<Param = ()>
+        + Default
+        + 'static,
+{
+    if !options.dry_run && !options.profile && !options.skip_output {
+        open_output(system, ProofMode::Single, options)?;
+    }
+    let circuit_info = circuit.circuit_info()?;
     let instances = circuit.instances();
+    let required = required_k(&circuit_info);
+    if k < required {
+        return Err(BenchError::InsufficientK {
+            requested: k,
+            required,
+        });
+    }
 
-    let timer = start_timer(|| format!("{system}_setup-{k}"));
-    let param = B::setup(&circuit_info, std_rng()).unwrap();
-    end_timer(timer);
+    match options.seed {
+        Some(seed) => eprintln!("Seeding {system}-{k} with {seed}"),
+        None => eprintln!("Seeding {system}-{k} with std_rng()"),
+    }
+    let rng = |phase: Phase| bench_rng(options.seed, phase);
 
-    let timer = start_timer(|| format!("{system}_preprocess-{k}"));
-    let (pp, vp) = B::preprocess(&param, &circuit_info).unwrap();
-    end_timer(timer);
+    let mut setup_time = None;
+    let mut preprocess_time = None;
+    let mut timed_preprocess = |param: &Param<B>| {
+        srs::check_srs_degree::<B>(param, &circuit_info)?;
+        let start = Instant::now();
+        let output = preprocess::<B>(system, k, param, &circuit_info);
+        preprocess_time = Some(start.elapsed());
+        output
+    };
+    let (pp, vp) = match (param, options.srs_path.as_deref()) {
+        (Some(param), _) => timed_preprocess(param)?,
+        (None, Some(srs_path)) => {
+            let param = srs::load_param::<B>(system, k, srs_path, &circuit_info)?;
+            timed_preprocess(&param)?
+        }
+        (None, None) => {
+            let cache_path = options
+                .reuse_setup
+                .then(|| setup_cache_path(system, k, &circuit_info));
+            match cache_path.as_deref().and_then(load_setup::<B>) {
+                Some((_, pp, vp)) => (pp, vp),
+                None => {
+                    let start = Instant::now();
+                    let param = setup_param::<B>(system, k, &circuit_info, options.seed)?;
+                    setup_time = Some(start.elapsed());
+                    let (pp, vp) = timed_preprocess(&param)?;
+                    let setup = (param, pp, vp);
+                    if let Some(cache_path) = cache_path.as_deref() {
+                        store_setup::<B>(cache_path, &setup);
+                    }
+                    let (_, pp, vp) = setup;
+                    (pp, vp)
+                }
+            }
+        }
+    };
 
-    let proof = sample(system, k, || {
-        let _timer = start_timer(|| format!("{system}_prove-{k}"));
-        let mut transcript = Keccak256Transcript::default();
-        B::prove(&pp, circuit, &mut transcript, std_rng()).unwrap();
-        transcript.into_proof()
-    });
+    if !reset_peak_rss() {
+        eprintln!("Peak RSS of {system}_prove-{k} includes memory used before proving");
+    }
+    let (sample_size, warmup) = match options.dry_run {
+        true => (NonZeroUsize::MIN, 0),
+        false => (
+            options.sample_size.unwrap_or_else(|| sample_size(k)),
+            warmup(),
+        ),
+    };
+    // Shared with the worker thread of `sample_with_timeout`, which may
+    // outlive this call.
+    let pp = Arc::new(pp);
+    // Allocations of the prove runs alone, leaving out those of `check`.
+    let prove_allocs = Arc::new(Mutex::new(
+        alloc::alloc_stats().map(|_| alloc::AllocStats::default()),
+    ));
+    let prove = || prove_once::<B, T>(system, k, &pp, circuit, options.seed, &prove_allocs);
+    if options.profile {
+        let output_dir = output_dir();
+        create_dir(&output_dir)?;
+        let path = output_dir.join(format!("{system}_prove-{k}.folded"));
+        profile::profile(&path, prove)?;
+        eprintln!("Wrote {system}_prove-{k} profile to {}", path.display());
+        return Ok(None);
+    }
+    let verify_failed = |error, proof: &[u8], iteration| {
+        eprint!(
+            "{system}-{k} verified against\n{}",
+            format_instances(instances, circuit.instance_layout().as_ref())
+        );
+        let proof_path = dump_proof(system, k, proof)
+            .map_err(|err| eprintln!("Failed to dump {system}-{k} proof: {err}"))
+            .ok();
+        BenchError::VerificationFailed {
+            system,
+            k,
+            error,
+            proof_path,
+            iteration,
+        }
+    };
+    let check = |iteration, proof: &Vec<u8>| {
+        if !options.verify_all {
+            return Ok(());
+        }
+        let mut transcript = T::from_proof((), proof.as_slice());
+        B::verify(&vp, instances, &mut transcript, rng(Phase::Verify))
+            .map_err(|error| verify_failed(error, proof, Some(iteration)))
+    };
+    // Marks `k` as interrupted rather than leaving no trace of it.
+    let on_interrupt = |err: BenchError| {
+        if matches!(err, BenchError::Interrupted) && !options.dry_run && !options.skip_output {
+            if let Err(err) = write_row(system, k, BenchRecord::Interrupted, options) {
+                eprintln!("Failed to mark {system}-{k} interrupted: {err}");
+            }
+        }
+        err
+    };
+    let (proof, prove_time) = match options.timeout.filter(|_| !options.dry_run) {
+        Some(timeout) => {
+            let on_timeout = || match options.skip_output {
+                true => Ok(()),
+                false => write_row(system, k, BenchRecord::Timeout, options),
+            };
+            let prove = {
+                let (pp, circuit, seed) = (pp.clone(), circuit.clone(), options.seed);
+                let prove_allocs = prove_allocs.clone();
+                move || prove_once::<B, T>(system, k, &pp, &circuit, seed, &prove_allocs)
+            };
+            let sampled =
+                sample_with_timeout(sample_size, warmup, timeout, on_timeout, prove, check);
+            match sampled.map_err(on_interrupt)? {
+                Some(sampled) => sampled,
+                None => return Ok(None),
+            }
+        }
+        None => sample_checked(sample_size, warmup, prove, check).map_err(on_interrupt)?,
+    };
+    let peak_mem_kb = peak_rss_kb();
+    let allocs = prove_allocs
+        .lock()
+        .unwrap()
+        .take()
+        .map(|allocs| allocs.mean(warmup + sample_size.get()));
 
-    let _timer = start_timer(|| format!("{system}_verify-{k}"));
-    let accept = {
-        let mut transcript = Keccak256Transcript::from_proof((), proof.as_slice());
-        B::verify(&vp, instances, &mut transcript, std_rng()).is_ok()
+    let (verified, verify_time) = sample(sample_size, 0, || {
+        in_phase(system, Phase::Verify, k, || {
+            let mut transcript = T::from_proof((), proof.as_slice());
+            Ok(B::verify(
+                &vp,
+                instances,
+                &mut transcript,
+                rng(Phase::Verify),
+            ))
+        })
+    })
+    .map_err(on_interrupt)?;
+    if let Err(error) = verified {
+        return Err(verify_failed(error, &proof, None));
+    }
+    let result = BenchResult {
+        setup: setup_time,
+        preprocess: preprocess_time,
+        witness_gen: circuit.witness_gen_time(),
+        prove_avg: prove_time.mean,
+        verify: verify_time.mean,
+        proof_bytes: proof.len(),
     };
-    assert!(accept);
+    if options.dry_run || options.skip_output {
+        return Ok(Some(result));
+    }
+    let verify_ops = ops::verify_ops(T::from_proof((), proof.as_slice()), |transcript| {
+        B::verify(&vp, instances, transcript, rng(Phase::Verify))
+    })?;
+
+    // Matched triples over the mean prove time, comparable across `k`.
+    let triples_per_second = circuit
+        .num_matched_triples()
+        .map(|num_triples| num_triples as f64 / prove_time.mean.as_secs_f64());
+    let record = SingleRecord {
+        prove: prove_time,
+        peak_mem_kb,
+        allocs,
+        verify: verify_time.mean,
+        verify_ops,
+        proof_bytes: proof.len(),
+        triples_per_second,
+        witness_gen: result.witness_gen,
+        preprocess: preprocess_time,
+    };
+    write_row(system, k, BenchRecord::Single(record), options)?;
+    Ok(Some(result))
+}
+
+/// Proves `circuit` once with `pp`, adding the allocations of the prove to
+/// `allocs` when counting.
+fn prove_once<B, T>(
+    system: System,
+    k: usize,
+    pp: &B::ProverParam,
+    circuit: &impl PlonkishCircuit<Fr>,
+    seed: Option<u64>,
+    allocs: &Mutex<Option<alloc::AllocStats>>,
+) -> Result<Vec<u8>, BenchError>
+where
+    B: PlonkishBackend<Fr>,
+    T: TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr> + InMemoryTranscript + Default,
+{
+    in_phase(system, Phase::Prove, k, || {
+        let before = alloc::alloc_stats();
+        let mut transcript = T::default();
+        B::prove(pp, circuit, &mut transcript, bench_rng(seed, Phase::Prove))?;
+        let proof = transcript.into_proof();
+        if let (Some(before), Some(after)) = (before, alloc::alloc_stats()) {
+            if let Some(allocs) = allocs.lock().unwrap().as_mut() {
+                allocs.add_since(&before, &after);
+            }
+        }
+        Ok(proof)
+    })
+}
+
+/// Returns the smallest `k` of `ks` at which setup, preprocess, prove and
+/// verify of backend `B` all go through for the circuit built by `circuit`,
+/// trying each in increasing order by a [`BenchOptions::dry_run`] of
+/// [`bench_plonkish_backend`] and stopping at the first that verifies, or
+/// `None` if none does. A `k` fails when building its circuit fails too,
+/// e.g. with [`CompileError::CapacityExceeded`], and why is logged.
+pub fn find_min_k<B, T, C>(
+    system: System,
+    circuit: impl Fn(usize) -> Result<C, BenchError>,
+    ks: Range<usize>,
+    options: &BenchOptions,
+) -> Option<usize>
+where
+    B: TrustedSetup + WitnessEncoding + 'static,
+    B::ProverParam: Send + Sync + 'static,
+    T: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + InMemoryTranscript<Param = ()>
+        + Default
+        + 'static,
+    C: PlonkishCircuit<Fr> + CircuitMetadata + Clone + Send + Sync + 'static,
+{
+    let options = BenchOptions {
+        dry_run: true,
+        profile: false,
+        ..options.clone()
+    };
+    ks.into_iter().find(|k| {
+        let result = circuit(*k)
+            .and_then(|circuit| bench_plonkish_backend::<B, Fr, T>(system, *k, &circuit, &options));
+        match result {
+            Ok(_) => true,
+            Err(err) => {
+                eprintln!("{system}-{k} doesn't work: {err}");
+                false
+            }
+        }
+    })
+}
+
+/// Returns the smallest `k` whose `2^k` rows fit the preprocessed polynomials
+/// and instances of `circuit_info`, which is at least `circuit_info.k`.
+fn required_k(circuit_info: &PlonkishCircuitInfo<Fr>) -> usize {
+    let num_rows = chain![
+        circuit_info.preprocess_polys.iter().map(Vec::len),
+        circuit_info.num_instances.iter().copied(),
+    ]
+    .max()
+    .unwrap_or_default();
+    circuit_info
+        .k
+        .max(num_rows.next_power_of_two().trailing_zeros() as usize)
+}
+
+/// Runs setup of backend `B` for `circuit_info`, which only depends on its
+/// size, so the returned `param` can be passed to
+/// [`bench_plonkish_backend_with_param`] for every circuit of same `k`.
+///
+/// The setup is insecure as its RNG is known, which is warned about once.
+pub fn setup_param<B: PlonkishBackend<Fr>>(
+    system: System,
+    k: usize,
+    circuit_info: &PlonkishCircuitInfo<Fr>,
+    seed: Option<u64>,
+) -> Result<Param<B>, BenchError> {
+    srs::warn_insecure_setup();
+    in_phase(system, Phase::Setup, k, || {
+        Ok(B::setup(circuit_info, bench_rng(seed, Phase::Setup))?)
+    })
+}
+
+fn preprocess<B: PlonkishBackend<Fr>>(
+    system: System,
+    k: usize,
+    param: &Param<B>,
+    circuit_info: &PlonkishCircuitInfo<Fr>,
+) -> Result<(B::ProverParam, B::VerifierParam), BenchError> {
+    in_phase(system, Phase::Preprocess, k, || {
+        Ok(B::preprocess(param, circuit_info)?)
+    })
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum System {
     HyperPlonk,
     UniHyperPlonk,
+    /// Only with the `halo2` feature, which builds the Halo2 circuits.
+    #[cfg(feature = "halo2")]
     Halo2,
     EspressoHyperPlonk,
+    /// Classic PLONK with univariate KZG. Declared last to keep the ordering
+    /// of the existing systems.
+    Plonk,
 }
 
 impl System {
-    fn output_path(&self) -> String {
-        format!("{OUTPUT_DIR}/{self}")
+    /// Every system in order of declaration.
+    pub const ALL: &'static [System] = &[
+        System::HyperPlonk,
+        System::UniHyperPlonk,
+        #[cfg(feature = "halo2")]
+        System::Halo2,
+        System::EspressoHyperPlonk,
+        System::Plonk,
+    ];
+
+    fn output_path(&self) -> PathBuf {
+        self.output_path_for(ProofMode::Single)
     }
 
-    fn output(&self) -> File {
-        OpenOptions::new()
+    fn output_path_for(&self, mode: ProofMode) -> PathBuf {
+        match mode {
+            ProofMode::Single => output_dir().join(self.to_string()),
+            ProofMode::Aggregated => output_dir().join(format!("{self}_aggregated")),
+            ProofMode::Batched => output_dir().join(format!("{self}_batched")),
+        }
+    }
+
+    /// Appends `row` and a newline to the output of `mode` in a single write,
+    /// flushed before returning so rows written before a crash are kept, and
+    /// with `sync` also synced to disk so they survive a power loss.
+    fn append_row(&self, mode: ProofMode, row: &str, sync: bool) -> io::Result<()> {
+        let mut output = OpenOptions::new()
             .append(true)
-            .open(self.output_path())
-            .unwrap()
+            .open(self.output_path_for(mode))?;
+        output.write_all(format!("{row}\n").as_bytes())?;
+        output.flush()?;
+        if sync {
+            output.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether each circuit gets its own proof, the proofs of several circuits
+/// are aggregated into one or a batch of circuits is proven one after another,
+/// each written to a separate output of the system with its own columns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProofMode {
+    /// Rows of [`OUTPUT_COLUMNS`] written by [`bench_plonkish_backend`].
+    #[default]
+    Single,
+    /// Rows of [`AGGREGATED_OUTPUT_COLUMNS`] written by [`bench_aggregated`].
+    Aggregated,
+    /// Rows of [`BATCHED_OUTPUT_COLUMNS`] written by [`bench_batched`].
+    Batched,
+}
+
+impl ProofMode {
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            ProofMode::Single => OUTPUT_COLUMNS,
+            ProofMode::Aggregated => AGGREGATED_OUTPUT_COLUMNS,
+            ProofMode::Batched => BATCHED_OUTPUT_COLUMNS,
+        }
+    }
+}
+
+impl FromStr for System {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "hyperplonk" => System::HyperPlonk,
+            "unihyperplonk" => System::UniHyperPlonk,
+            #[cfg(feature = "halo2")]
+            "halo2" => System::Halo2,
+            #[cfg(not(feature = "halo2"))]
+            "halo2" => return Err("system halo2 needs the halo2 feature".to_string()),
+            "espresso_hyperplonk" => System::EspressoHyperPlonk,
+            "plonk" => System::Plonk,
+            _ => {
+                return Err(format!(
+                    "unknown system {s:?}, expected one of hyperplonk, unihyperplonk, halo2, espresso_hyperplonk, plonk"
+                ))
+            }
+        })
     }
 }
 
@@ -83,42 +859,962 @@ impl Display for System {
         match self {
             System::HyperPlonk => write!(f, "hyperplonk"),
             System::UniHyperPlonk => write!(f, "unihyperplonk"),
+            #[cfg(feature = "halo2")]
             System::Halo2 => write!(f, "halo2"),
             System::EspressoHyperPlonk => write!(f, "espresso_hyperplonk"),
+            System::Plonk => write!(f, "plonk"),
         }
     }
 }
 
-fn create_output(systems: &[System]) {
-    if !Path::new(OUTPUT_DIR).exists() {
-        create_dir(OUTPUT_DIR).unwrap();
+/// Phase of a benchmark run, recorded as `phase` of the `tracing` span
+/// [`in_phase`] enters. Its discriminant also selects the RNG stream of the
+/// phase, so new phases go last to keep seeded runs replayable.
+#[derive(Clone, Copy, Debug)]
+enum Phase {
+    Setup,
+    Prove,
+    Verify,
+    Preprocess,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Phase::Setup => write!(f, "setup"),
+            Phase::Prove => write!(f, "prove"),
+            Phase::Verify => write!(f, "verify"),
+            Phase::Preprocess => write!(f, "preprocess"),
+        }
     }
+}
+
+/// Runs `f` within the timer `{system}_{phase}-{k}` and a `tracing` span with
+/// fields `system`, `phase`, `k` and `elapsed_ms`, the latter recorded once `f`
+/// returns. Without a `tracing` subscriber the span is a no-op.
+fn in_phase<T>(system: System, phase: Phase, k: usize, f: impl FnOnce() -> T) -> T {
+    let span = tracing::info_span!(
+        "phase",
+        %system,
+        %phase,
+        k,
+        elapsed_ms = tracing::field::Empty
+    );
+    let _guard = span.enter();
+    let timer = start_timer(|| format!("{system}_{phase}-{k}"));
+    let start = Instant::now();
+    let output = f();
+    span.record("elapsed_ms", as_millis_f64(start.elapsed()));
+    end_timer(timer);
+    output
+}
+
+fn bench_rng(seed: Option<u64>, phase: Phase) -> Box<dyn RngCore> {
+    match seed {
+        Some(seed) => {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            rng.set_stream(phase as u64);
+            Box::new(rng)
+        }
+        None => Box::new(std_rng()),
+    }
+}
+
+/// Returns the directory benchmark results are written to, which is
+/// `$SHIN_BENCH_OUTPUT_DIR` if set or `../chiquito/target` otherwise.
+pub fn output_dir() -> PathBuf {
+    env::var_os(OUTPUT_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_OUTPUT_DIR))
+}
+
+/// Truncates the output of each of `systems`, writing the header row for
+/// [`OutputFormat::Csv`] and [`OutputFormat::Gnuplot`].
+///
+/// Without `overwrite`, an output that already has data fails with
+/// [`BenchError::OutputExists`] instead, before any output is truncated, so
+/// the results of an earlier run aren't lost by accident.
+pub fn create_output(
+    systems: &[System],
+    format: OutputFormat,
+    overwrite: bool,
+) -> Result<(), BenchError> {
+    create_output_for(systems, ProofMode::Single, format, overwrite)
+}
+
+/// Same as [`create_output`] but for the output of `systems` in `mode`.
+pub fn create_output_for(
+    systems: &[System],
+    mode: ProofMode,
+    format: OutputFormat,
+    overwrite: bool,
+) -> Result<(), BenchError> {
+    if !overwrite {
+        for system in systems {
+            let path = system.output_path_for(mode);
+            match fs::metadata(&path) {
+                Ok(metadata) if metadata.len() > 0 => {
+                    return Err(BenchError::OutputExists { path })
+                }
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+    }
+    create_dir(&output_dir())?;
     for system in systems {
-        File::create(system.output_path()).unwrap();
+        let mut output = File::create(system.output_path_for(mode))?;
+        match format {
+            OutputFormat::Csv => writeln!(&mut output, "{}", mode.columns().join(","))?,
+            OutputFormat::Json => {}
+            OutputFormat::Gnuplot => {
+                writeln!(&mut output, "# {}", GNUPLOT_OUTPUT_COLUMNS.join(" "))?
+            }
+        }
     }
+    Ok(())
 }
 
-fn sample<T>(system: System, k: usize, prove: impl Fn() -> T) -> T {
-    let mut proof = None;
-    let sample_size = sample_size(k);
-    let sum = iter::repeat_with(|| {
-        let start = Instant::now();
-        proof = Some(prove());
-        start.elapsed()
+/// Creates `dir` and its missing parents, succeeding if it already exists,
+/// e.g. when a concurrent benchmark driver created it in between.
+fn create_dir(dir: &Path) -> io::Result<()> {
+    match create_dir_all(dir) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        result => result,
+    }
+}
+
+fn ensure_output(system: System, mode: ProofMode, format: OutputFormat) -> Result<(), BenchError> {
+    if system.output_path_for(mode).exists() {
+        return Ok(());
+    }
+    create_output_for(&[system], mode, format, false)
+}
+
+/// Returns the sink of `options`, or a [`FileSink`] if none is set.
+fn output_sink(options: &BenchOptions) -> Arc<dyn OutputSink> {
+    options.sink.clone().unwrap_or_else(|| {
+        Arc::new(FileSink {
+            sync: options.sync_output,
+        })
     })
-    .take(sample_size)
-    .sum::<Duration>();
-    let avg = sum / sample_size as u32;
-    writeln!(&mut system.output(), "{k}, {}", avg.as_millis()).unwrap();
-    proof.unwrap()
 }
 
-fn sample_size(k: usize) -> usize {
-    if k < 16 {
+/// Prepares the output of `system` in `mode` by the sink of `options`,
+/// failing first if the rows can't hold [`BenchOptions::dataset`].
+fn open_output(system: System, mode: ProofMode, options: &BenchOptions) -> Result<(), BenchError> {
+    if let Some(id) = &options.dataset {
+        check_dataset(id)?;
+    }
+    output_sink(options).open(system, mode, options.format)
+}
+
+/// Fails with [`BenchError::InvalidDataset`] if `id` has a comma, quote or
+/// newline, which would split or break the rows it's written to.
+pub(crate) fn check_dataset(id: &str) -> Result<(), BenchError> {
+    match id.contains([',', '"', '\n', '\r']) {
+        true => Err(BenchError::InvalidDataset { id: id.to_string() }),
+        false => Ok(()),
+    }
+}
+
+/// Writes `record` of `k` to the output of `system` by the sink of `options`.
+fn write_row(
+    system: System,
+    k: usize,
+    record: BenchRecord,
+    options: &BenchOptions,
+) -> Result<(), BenchError> {
+    let row = BenchRow {
+        format: options.format,
+        k,
+        dataset: options.dataset.clone(),
+        record,
+    };
+    output_sink(options).write_row(system, &row)
+}
+
+fn dump_proof(system: System, k: usize, proof: &[u8]) -> io::Result<PathBuf> {
+    let dir = output_dir().join("failed_proofs");
+    create_dir(&dir)?;
+    let path = dir.join(format!("{system}-{k}.proof"));
+    fs::write(&path, proof)?;
+    Ok(path)
+}
+
+/// Returns `value` as a JSON string literal, escaping what JSON requires.
+/// Unlike `{:?}` it leaves non-ASCII characters as they are and escapes
+/// control characters as `\u` followed by 4 hex digits.
+pub(crate) fn json_string(value: &str) -> String {
+    let mut string = String::with_capacity(value.len() + 2);
+    string.push('"');
+    for char in value.chars() {
+        match char {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\r' => string.push_str("\\r"),
+            '\t' => string.push_str("\\t"),
+            char if (char as u32) < 0x20 => string.push_str(&format!("\\u{:04x}", char as u32)),
+            char => string.push(char),
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// Statistics over the durations collected by [`sample`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    pub mean: Duration,
+    pub min: Duration,
+    pub max: Duration,
+    /// Sample standard deviation, which is zero when there is a single sample.
+    pub stddev: Duration,
+    /// Nearest-rank percentiles, which collapse onto `max` for small samples.
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+}
+
+impl Timing {
+    /// Returns the statistics of `durations`, which [`sample`] never leaves
+    /// empty.
+    fn new(durations: &[Duration]) -> Self {
+        assert!(!durations.is_empty(), "no durations to time");
+        let n = durations.len();
+        let sorted = durations.iter().copied().sorted().collect_vec();
+        let percentile = |p: usize| sorted[(p * n).div_ceil(100).max(1) - 1];
+        let mean = mean(durations);
+        let min = sorted[0];
+        let max = sorted[n - 1];
+        let stddev = if n > 1 {
+            let mean = mean.as_secs_f64();
+            let variance = durations
+                .iter()
+                .map(|duration| (duration.as_secs_f64() - mean).powi(2))
+                .sum::<f64>()
+                / (n - 1) as f64;
+            Duration::from_secs_f64(variance.sqrt())
+        } else {
+            Duration::ZERO
+        };
+        Self {
+            mean,
+            min,
+            max,
+            stddev,
+            p50: percentile(50),
+            p95: percentile(95),
+            p99: percentile(99),
+        }
+    }
+}
+
+/// Returns the mean of `durations`, dividing in nanoseconds so neither a
+/// count past `u32::MAX` nor a sum past `Duration::MAX` is truncated.
+fn mean(durations: &[Duration]) -> Duration {
+    let n = durations.len() as u128;
+    let nanos = match durations
+        .iter()
+        .try_fold(Duration::ZERO, |sum, duration| sum.checked_add(*duration))
+    {
+        Some(sum) => sum.as_nanos() / n,
+        None => durations.iter().map(Duration::as_nanos).sum::<u128>() / n,
+    };
+    // The mean is at most the longest duration, so it fits.
+    let nanos_per_sec = Duration::from_secs(1).as_nanos();
+    Duration::new(
+        (nanos / nanos_per_sec) as u64,
+        (nanos % nanos_per_sec) as u32,
+    )
+}
+
+fn as_millis_f64(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Output of setup for the PCS of backend `B`.
+pub type Param<B> = <<B as PlonkishBackend<Fr>>::Pcs as PolynomialCommitmentScheme<Fr>>::Param;
+
+type Setup<B> = (
+    Param<B>,
+    <B as PlonkishBackend<Fr>>::ProverParam,
+    <B as PlonkishBackend<Fr>>::VerifierParam,
+);
+
+/// Returns the key of the setup artifacts of `circuit_info` at `k` on
+/// `system`, the hex Keccak256 digest of the three serialized, so circuits of
+/// the same constraints, permutations and fixed polynomials share their setup
+/// while any change to them, `k` or the system misses it. It only depends on
+/// its inputs, so it's the same across runs and machines.
+pub fn setup_cache_key(system: System, k: usize, circuit_info: &PlonkishCircuitInfo<Fr>) -> String {
+    let mut bytes = bincode::serialize(circuit_info).unwrap();
+    bytes.extend((k as u64).to_le_bytes());
+    bytes.extend(system.to_string().as_bytes());
+    let digest = Keccak256::digest(bytes);
+    digest.iter().map(|byte| format!("{byte:02x}")).join("")
+}
+
+/// Returns the setup cache file for `(system, k)` named by its
+/// [`setup_cache_key`].
+fn setup_cache_path(system: System, k: usize, circuit_info: &PlonkishCircuitInfo<Fr>) -> PathBuf {
+    let key = setup_cache_key(system, k, circuit_info);
+    output_dir()
+        .join("setup_cache")
+        .join(format!("{system}-{k}-{key}"))
+}
+
+/// Loads cached setup artifacts, returning `None` when the cache is missing.
+/// The backend bounds already require `Serialize + DeserializeOwned` for all
+/// artifacts, so a cache that fails to deserialize (e.g. written by another
+/// version of the backend) is skipped with a note instead of failing the run.
+fn load_setup<B: PlonkishBackend<Fr>>(path: &Path) -> Option<Setup<B>> {
+    let bytes = fs::read(path).ok()?;
+    bincode::deserialize(&bytes)
+        .map_err(|err| eprintln!("Skipping setup cache {}: {err}", path.display()))
+        .ok()
+}
+
+fn store_setup<B: PlonkishBackend<Fr>>(path: &Path, setup: &Setup<B>) {
+    let result = bincode::serialize(setup)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| {
+            create_dir(path.parent().unwrap())
+                .and_then(|_| fs::write(path, bytes))
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        eprintln!("Skipping setup cache {}: {err}", path.display());
+    }
+}
+
+/// Resets the peak resident set size of the process, returning whether it
+/// succeeded so that [`peak_rss_kb`] only covers what follows.
+#[cfg(target_os = "linux")]
+fn reset_peak_rss() -> bool {
+    fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reset_peak_rss() -> bool {
+    false
+}
+
+/// Returns the peak resident set size of the process in kB, or `None` on
+/// platforms without procfs.
+#[cfg(target_os = "linux")]
+fn peak_rss_kb() -> Option<u64> {
+    fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_rss_kb() -> Option<u64> {
+    None
+}
+
+/// Returns the number of warm-up prove runs, which is `$SHIN_BENCH_WARMUP` if
+/// set or 1 otherwise.
+pub fn warmup() -> usize {
+    env::var(WARMUP_ENV)
+        .ok()
+        .and_then(|warmup| warmup.parse().ok())
+        .unwrap_or(DEFAULT_WARMUP)
+}
+
+/// Runs `f` `warmup` times untimed to warm caches and allocator, then
+/// `sample_size` times timed, returning the output of the last timed run
+/// together with the timing statistics.
+fn sample<T>(
+    sample_size: NonZeroUsize,
+    warmup: usize,
+    f: impl Fn() -> Result<T, BenchError>,
+) -> Result<(T, Timing), BenchError> {
+    sample_checked(sample_size, warmup, f, |_, _| Ok(()))
+}
+
+/// Same as [`sample`] but passes the index and output of every timed run to
+/// `check` right after it, untimed. Fails with [`BenchError::Interrupted`]
+/// instead of starting a run once [`interrupt`] was called.
+fn sample_checked<T>(
+    sample_size: NonZeroUsize,
+    warmup: usize,
+    f: impl Fn() -> Result<T, BenchError>,
+    check: impl Fn(usize, &T) -> Result<(), BenchError>,
+) -> Result<(T, Timing), BenchError> {
+    for _ in 0..warmup {
+        interrupt::check_interrupted()?;
+        f()?;
+    }
+    let mut output = None;
+    let durations = (0..sample_size.get())
+        .map(|iteration| {
+            interrupt::check_interrupted()?;
+            let start = Instant::now();
+            let result = f()?;
+            let elapsed = start.elapsed();
+            check(iteration, &result)?;
+            output = Some(result);
+            Ok(elapsed)
+        })
+        .collect::<Result<Vec<_>, BenchError>>()?;
+    let output = output.expect("sample_size is nonzero");
+    Ok((output, Timing::new(&durations)))
+}
+
+/// Same as [`sample_checked`] but runs `f` on a detached worker thread,
+/// giving up once a single run takes longer than `timeout`. On timeout
+/// `on_timeout` is called and `None` is returned right away, leaving the
+/// abandoned run to finish in the background before the worker exits, which
+/// is why `f` owns its inputs. `check` runs on the calling thread, and the
+/// worker waits for it before the next run, so the allocations of a run and
+/// of `check` stay apart. Once [`interrupt`] was called it fails with
+/// [`BenchError::Interrupted`] after the run in flight.
+fn sample_with_timeout<T: Send + 'static>(
+    sample_size: NonZeroUsize,
+    warmup: usize,
+    timeout: Duration,
+    on_timeout: impl FnOnce() -> Result<(), BenchError>,
+    f: impl Fn() -> Result<T, BenchError> + Send + 'static,
+    check: impl Fn(usize, &T) -> Result<(), BenchError>,
+) -> Result<Option<(T, Timing)>, BenchError> {
+    let (tx, rx) = mpsc::channel();
+    let (checked_tx, checked_rx) = mpsc::channel();
+    thread::spawn(move || {
+        for _ in 0..warmup + sample_size.get() {
+            let start = Instant::now();
+            let output = f();
+            // Senders and receiver are dropped on timeout or error, so stop proving.
+            if tx.send((output, start.elapsed())).is_err() || checked_rx.recv().is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut output = None;
+    let mut durations = Vec::with_capacity(sample_size.get());
+    for idx in 0..warmup + sample_size.get() {
+        let Ok((result, duration)) = rx.recv_timeout(timeout) else {
+            on_timeout()?;
+            return Ok(None);
+        };
+        interrupt::check_interrupted()?;
+        let result = result?;
+        if idx >= warmup {
+            check(idx - warmup, &result)?;
+            output = Some(result);
+            durations.push(duration);
+        }
+        // The worker is gone after the last run, which is fine.
+        let _ = checked_tx.send(());
+    }
+    let output = output.expect("sample_size is nonzero");
+    Ok(Some((output, Timing::new(&durations))))
+}
+
+/// Default number of timed runs for `k`, shrinking as runs get slower.
+pub fn sample_size(k: usize) -> NonZeroUsize {
+    let sample_size = if k < 16 {
         20
     } else if k < 20 {
         5
     } else {
         1
+    };
+    NonZeroUsize::new(sample_size).unwrap()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::proof_system::{
+        bench_plonkish_backend_with_param, check_dataset, create_output, find_min_k, mean,
+        sample_checked, sample_with_timeout, setup_cache_key, setup_param, BenchError,
+        BenchOptions, CircuitMetadata, OutputFormat, System, Timing, OUTPUT_COLUMNS,
+        OUTPUT_DIR_ENV,
+    };
+    use plonkish_backend::{
+        backend::{
+            hyperplonk::{util::rand_vanilla_plonk_circuit, HyperPlonk},
+            PlonkishCircuit, PlonkishCircuitInfo,
+        },
+        halo2_curves::bn256::{Bn256, Fr},
+        pcs::multilinear::MultilinearKzg,
+        util::{
+            expression::{rotate::BinaryField, Expression},
+            transcript::{
+                FieldTranscript, FieldTranscriptRead, FieldTranscriptWrite, InMemoryTranscript,
+                Keccak256Transcript, Transcript, TranscriptRead, TranscriptWrite,
+            },
+        },
+        Error,
+    };
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use std::{
+        cell::{Cell, RefCell},
+        env, fs,
+        io::Cursor,
+        num::NonZeroUsize,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, OnceLock,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Random vanilla PLONK circuit paired with its circuit info, which the
+    /// circuit itself doesn't provide.
+    #[derive(Clone)]
+    pub(crate) struct VanillaPlonk<C> {
+        circuit_info: PlonkishCircuitInfo<Fr>,
+        circuit: C,
+    }
+
+    impl<C: PlonkishCircuit<Fr>> PlonkishCircuit<Fr> for VanillaPlonk<C> {
+        fn circuit_info_without_preprocess(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+            Ok(self.circuit_info.clone())
+        }
+
+        fn circuit_info(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+            Ok(self.circuit_info.clone())
+        }
+
+        fn instances(&self) -> &[Vec<Fr>] {
+            self.circuit.instances()
+        }
+
+        fn synthesize(&self, round: usize, challenges: &[Fr]) -> Result<Vec<Vec<Fr>>, Error> {
+            self.circuit.synthesize(round, challenges)
+        }
+    }
+
+    impl<C> CircuitMetadata for VanillaPlonk<C> {}
+
+    /// Returns a vanilla PLONK circuit of `2^k` rows for the row mapping of
+    /// [`HyperPlonk`], of the same circuit info for every `seed` of the
+    /// witness.
+    ///
+    /// [`HyperPlonk`]: plonkish_backend::backend::hyperplonk::HyperPlonk
+    pub(crate) fn vanilla_plonk(
+        k: usize,
+        seed: u64,
+    ) -> VanillaPlonk<impl PlonkishCircuit<Fr> + Clone + Send + Sync + 'static> {
+        let preprocess_rng = ChaCha20Rng::seed_from_u64(0);
+        let witness_rng = ChaCha20Rng::seed_from_u64(seed);
+        let (circuit_info, circuit) =
+            rand_vanilla_plonk_circuit::<Fr, BinaryField>(k, preprocess_rng, witness_rng);
+        VanillaPlonk {
+            circuit_info,
+            circuit,
+        }
+    }
+
+    /// Returns the output dir of the tests, set once per process since
+    /// [`output_dir`] reads it from [`OUTPUT_DIR_ENV`], so tests writing
+    /// outputs at once don't race on the variable. They write the outputs of
+    /// distinct systems or modes instead.
+    ///
+    /// [`output_dir`]: crate::proof_system::output_dir
+    pub(crate) fn test_output_dir() -> PathBuf {
+        static OUTPUT_DIR: OnceLock<PathBuf> = OnceLock::new();
+        OUTPUT_DIR
+            .get_or_init(|| {
+                let dir = env::temp_dir().join(format!("bench-output-{}", std::process::id()));
+                env::set_var(OUTPUT_DIR_ENV, &dir);
+                dir
+            })
+            .clone()
+    }
+
+    #[test]
+    fn mean_past_duration_max() {
+        assert_eq!(mean(&[Duration::MAX, Duration::MAX]), Duration::MAX);
+        let half_past_max = (Duration::MAX.as_nanos() + 1_000_000_000) / 2;
+        assert_eq!(
+            mean(&[Duration::MAX, Duration::from_secs(1)]).as_nanos(),
+            half_past_max
+        );
+    }
+
+    #[test]
+    fn mean_with_remainder() {
+        let durations = [1, 2, 2].map(Duration::from_secs);
+        assert_eq!(mean(&durations), Duration::new(1, 666_666_666));
+        let durations = [1, 2].map(Duration::from_nanos);
+        assert_eq!(mean(&durations), Duration::from_nanos(1));
+    }
+
+    #[test]
+    fn timing_spread() {
+        let timing = Timing::new(&[4, 1, 3, 2].map(Duration::from_secs));
+        assert_eq!(timing.mean, Duration::from_millis(2500));
+        assert_eq!(timing.min, Duration::from_secs(1));
+        assert_eq!(timing.max, Duration::from_secs(4));
+        // Sample standard deviation, sqrt(5 / 3) seconds.
+        assert_eq!(timing.stddev.as_micros(), 1_290_994);
+
+        let timing = Timing::new(&[Duration::from_secs(1)]);
+        assert_eq!(timing.stddev, Duration::ZERO);
+        assert_eq!((timing.min, timing.max), (timing.mean, timing.mean));
+    }
+
+    #[test]
+    fn timing_percentiles() {
+        let durations = (1..=100)
+            .rev()
+            .map(Duration::from_millis)
+            .collect::<Vec<_>>();
+        let timing = Timing::new(&durations);
+        assert_eq!(
+            [timing.p50, timing.p95, timing.p99],
+            [50, 95, 99].map(Duration::from_millis)
+        );
+        // Nearest rank of few samples collapses onto the max.
+        let timing = Timing::new(&[4, 1, 3, 2].map(Duration::from_secs));
+        assert_eq!(timing.p50, Duration::from_secs(2));
+        assert_eq!([timing.p95, timing.p99], [timing.max; 2]);
+    }
+
+    #[test]
+    fn sample_after_warmup() {
+        let runs = Cell::new(0);
+        let checked = RefCell::new(Vec::new());
+        let sampled = sample_checked(
+            NonZeroUsize::new(3).unwrap(),
+            2,
+            || {
+                runs.set(runs.get() + 1);
+                Ok(runs.get())
+            },
+            |idx, output| {
+                checked.borrow_mut().push((idx, *output));
+                Ok(())
+            },
+        );
+        // Warm-up runs are neither checked nor timed.
+        let (output, _) = sampled.unwrap();
+        assert_eq!(output, 5);
+        assert_eq!(checked.into_inner(), [(0, 3), (1, 4), (2, 5)]);
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let checked = RefCell::new(Vec::new());
+        let sampled = sample_with_timeout(
+            NonZeroUsize::new(3).unwrap(),
+            2,
+            Duration::from_secs(10),
+            || panic!("timed out"),
+            {
+                let runs = runs.clone();
+                move || Ok(runs.fetch_add(1, Ordering::Relaxed) + 1)
+            },
+            |idx, output| {
+                checked.borrow_mut().push((idx, *output));
+                Ok(())
+            },
+        );
+        let (output, _) = sampled.unwrap().unwrap();
+        assert_eq!(output, 5);
+        assert_eq!(checked.into_inner(), [(0, 3), (1, 4), (2, 5)]);
+    }
+
+    #[test]
+    fn create_output_refuses_overwrite() {
+        let systems = [System::UniHyperPlonk, System::EspressoHyperPlonk];
+        let [first, second] = systems.map(|system| test_output_dir().join(system.to_string()));
+        let create = |overwrite| create_output(&systems, OutputFormat::Csv, overwrite);
+        let header = format!("{}\n", OUTPUT_COLUMNS.join(","));
+
+        let created = create(false);
+        let contents = [&first, &second].map(fs::read_to_string);
+        fs::write(&second, format!("{header}4, 1.0\n")).unwrap();
+        fs::write(&first, "").unwrap();
+        let refused = create(false);
+        let kept = [&first, &second].map(fs::read_to_string);
+        let overwritten = create(true);
+        let truncated = fs::read_to_string(&second);
+        [&first, &second].map(|path| fs::remove_file(path).unwrap());
+
+        created.unwrap();
+        assert_eq!(
+            contents.map(Result::unwrap),
+            [header.clone(), header.clone()]
+        );
+        // Neither output is written, not even the empty one the header row is
+        // missing from.
+        assert!(matches!(
+            refused,
+            Err(BenchError::OutputExists { path }) if path == second
+        ));
+        assert_eq!(
+            kept.map(Result::unwrap),
+            [String::new(), format!("{header}4, 1.0\n")]
+        );
+        overwritten.unwrap();
+        assert_eq!(truncated.unwrap(), header);
+    }
+
+    #[test]
+    fn check_dataset_separators() {
+        assert!(check_dataset("lubm-1k").is_ok());
+        for id in ["a,b", "a\"b", "a\nb", "a\r\nb"] {
+            assert!(
+                matches!(check_dataset(id), Err(BenchError::InvalidDataset { id: found }) if found == id),
+                "{id:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn setup_cache_key_of_circuit() {
+        let circuit_info = || {
+            let rng = || ChaCha20Rng::seed_from_u64(0);
+            rand_vanilla_plonk_circuit::<Fr, BinaryField>(4, rng(), rng()).0
+        };
+        let key = |k, circuit_info: &PlonkishCircuitInfo<Fr>| {
+            setup_cache_key(System::HyperPlonk, k, circuit_info)
+        };
+        let expected = key(4, &circuit_info());
+        assert_eq!(key(4, &circuit_info()), expected);
+
+        assert_ne!(key(5, &circuit_info()), expected);
+        assert_ne!(
+            setup_cache_key(System::UniHyperPlonk, 4, &circuit_info()),
+            expected
+        );
+        let mut constrained = circuit_info();
+        constrained
+            .constraints
+            .push(Expression::Constant(Fr::from(0)));
+        assert_ne!(key(4, &constrained), expected);
+        let mut permuted = circuit_info();
+        permuted.permutations.push(vec![(6, 2), (7, 2)]);
+        assert_ne!(key(4, &permuted), expected);
+    }
+
+    #[test]
+    fn min_k_of_circuit() {
+        type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+        let options = BenchOptions {
+            seed: Some(0),
+            ..Default::default()
+        };
+        // Below the 2^4 rows of the circuit fails with `InsufficientK`, and
+        // so does building it at `k` 4.
+        let circuit = |k| match k {
+            4 => Err(BenchError::Interrupted),
+            k => Ok(vanilla_plonk(usize::max(k, 4), 0)),
+        };
+        let min_k = |ks| find_min_k::<Pb, Inner, _>(System::HyperPlonk, circuit, ks, &options);
+        assert_eq!(min_k(2..8), Some(5));
+        assert_eq!(min_k(2..5), None);
+    }
+
+    #[test]
+    fn sample_with_timeout_returns_before_hung_run() {
+        let timed_out = Cell::new(false);
+        let start = Instant::now();
+        let sampled = sample_with_timeout(
+            NonZeroUsize::MIN,
+            0,
+            Duration::from_millis(50),
+            || {
+                timed_out.set(true);
+                Ok(())
+            },
+            || {
+                thread::sleep(Duration::from_secs(10));
+                Ok(())
+            },
+            |_, _| Ok(()),
+        );
+        assert!(matches!(sampled, Ok(None)));
+        assert!(timed_out.get());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn sample_with_timeout_within_timeout() {
+        let sampled = sample_with_timeout(
+            NonZeroUsize::new(3).unwrap(),
+            1,
+            Duration::from_secs(10),
+            || panic!("timed out"),
+            || Ok(7),
+            |_, output| {
+                assert_eq!(*output, 7);
+                Ok(())
+            },
+        );
+        let (output, timing) = sampled.unwrap().unwrap();
+        assert_eq!(output, 7);
+        assert!(timing.min <= timing.max);
+    }
+
+    #[test]
+    fn sample_with_timeout_of_later_run() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let checked = Cell::new(0);
+        let sampled = sample_with_timeout(
+            NonZeroUsize::new(3).unwrap(),
+            1,
+            Duration::from_millis(50),
+            || Ok(()),
+            {
+                let runs = runs.clone();
+                move || {
+                    if runs.fetch_add(1, Ordering::Relaxed) == 2 {
+                        thread::sleep(Duration::from_secs(10));
+                    }
+                    Ok(())
+                }
+            },
+            |_, _| {
+                checked.set(checked.get() + 1);
+                Ok(())
+            },
+        );
+        // The second timed run hangs, so the first one isn't kept either.
+        assert!(matches!(sampled, Ok(None)));
+        assert_eq!(checked.get(), 1);
+        assert_eq!(runs.load(Ordering::Relaxed), 3);
+    }
+
+    thread_local! {
+        /// Position and nonzero mask of the byte [`MutatedTranscript`] flips,
+        /// the position taken modulo the length of the proof.
+        static MUTATION: Cell<(u64, u8)> = Cell::new((0, 0));
+    }
+
+    /// Keccak transcript reading proofs mutated by [`MUTATION`], so the verify
+    /// of [`bench_plonkish_backend`] reads a mutated proof.
+    #[derive(Default)]
+    struct MutatedTranscript(Keccak256Transcript<Cursor<Vec<u8>>>);
+
+    type Inner = Keccak256Transcript<Cursor<Vec<u8>>>;
+
+    impl InMemoryTranscript for MutatedTranscript {
+        type Param = ();
+
+        fn new(_: Self::Param) -> Self {
+            Self::default()
+        }
+
+        fn into_proof(self) -> Vec<u8> {
+            self.0.into_proof()
+        }
+
+        fn from_proof(_: Self::Param, proof: &[u8]) -> Self {
+            let mut proof = proof.to_vec();
+            let (position, mask) = MUTATION.with(Cell::get);
+            let idx = (position % proof.len() as u64) as usize;
+            proof[idx] ^= mask;
+            Self(Inner::from_proof((), &proof))
+        }
+    }
+
+    impl<F> FieldTranscript<F> for MutatedTranscript
+    where
+        Inner: FieldTranscript<F>,
+    {
+        fn squeeze_challenge(&mut self) -> F {
+            self.0.squeeze_challenge()
+        }
+
+        fn common_field_element(&mut self, fe: &F) -> Result<(), Error> {
+            self.0.common_field_element(fe)
+        }
+    }
+
+    impl<F> FieldTranscriptRead<F> for MutatedTranscript
+    where
+        Inner: FieldTranscriptRead<F>,
+    {
+        fn read_field_element(&mut self) -> Result<F, Error> {
+            self.0.read_field_element()
+        }
+    }
+
+    impl<F> FieldTranscriptWrite<F> for MutatedTranscript
+    where
+        Inner: FieldTranscriptWrite<F>,
+    {
+        fn write_field_element(&mut self, fe: &F) -> Result<(), Error> {
+            self.0.write_field_element(fe)
+        }
+    }
+
+    impl<C, F> Transcript<C, F> for MutatedTranscript
+    where
+        Inner: Transcript<C, F>,
+    {
+        fn common_commitment(&mut self, comm: &C) -> Result<(), Error> {
+            self.0.common_commitment(comm)
+        }
+    }
+
+    impl<C, F> TranscriptRead<C, F> for MutatedTranscript
+    where
+        Inner: TranscriptRead<C, F>,
+    {
+        fn read_commitment(&mut self) -> Result<C, Error> {
+            self.0.read_commitment()
+        }
+    }
+
+    impl<C, F> TranscriptWrite<C, F> for MutatedTranscript
+    where
+        Inner: TranscriptWrite<C, F>,
+    {
+        fn write_commitment(&mut self, comm: &C) -> Result<(), Error> {
+            self.0.write_commitment(comm)
+        }
+    }
+
+    /// Asserts the verify of [`bench_plonkish_backend`] rejects every one of
+    /// random mutations of the proof it proves, each of a byte flipped by a
+    /// random mask, from a fixed seed. As for `run_mutated_proofs` of the
+    /// backend, every byte of the proof is read, so an accepted mutation is
+    /// a soundness bug to investigate rather than one of unused padding.
+    #[test]
+    fn bench_rejects_mutated_proofs() {
+        type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+        // A rejected proof is dumped under the output dir.
+        let failed_proofs = test_output_dir().join("failed_proofs");
+        let circuit = vanilla_plonk(4, 0);
+        let circuit_info = circuit.circuit_info().unwrap();
+        let param = setup_param::<Pb>(System::HyperPlonk, 4, &circuit_info, Some(0)).unwrap();
+        let options = BenchOptions {
+            sample_size: NonZeroUsize::new(1),
+            seed: Some(0),
+            skip_output: true,
+            ..Default::default()
+        };
+        let bench = |mutation| {
+            MUTATION.with(|cell| cell.set(mutation));
+            bench_plonkish_backend_with_param::<Pb, Fr, MutatedTranscript>(
+                System::HyperPlonk,
+                4,
+                &circuit,
+                Some(&param),
+                &options,
+            )
+        };
+        assert!(bench((0, 0)).unwrap().is_some());
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for _ in 0..64 {
+            let mutation = (rng.next_u64(), 1 + (rng.next_u32() % 255) as u8);
+            assert!(
+                matches!(bench(mutation), Err(BenchError::VerificationFailed { .. })),
+                "mutation {mutation:?} accepted"
+            );
+        }
+        let _ = fs::remove_dir_all(failed_proofs);
     }
 }
@@ -0,0 +1,242 @@
+use crate::proof_system::{
+    bench_rng, in_phase, open_output, preprocess, required_k, sample, sample_size, setup_param,
+    warmup, write_row, AggregatedRecord, BenchError, BenchOptions, BenchRecord, Phase, ProofMode,
+    System,
+};
+use plonkish_backend::{
+    backend::{
+        hyperplonk::HyperPlonk, plonk::Plonk, unihyperplonk::UniHyperPlonk, PlonkishBackend,
+        PlonkishCircuit, WitnessEncoding,
+    },
+    halo2_curves::bn256::Fr,
+    pcs::{CommitmentChunk, PolynomialCommitmentScheme},
+    poly::multilinear::MultilinearPolynomial,
+    util::transcript::{InMemoryTranscript, TranscriptRead, TranscriptWrite},
+    Error,
+};
+use rand::RngCore;
+use std::{num::NonZeroUsize, time::Duration};
+
+/// Columns of each row written by [`bench_aggregated`], in order. `prove_ms`,
+/// `verify_ms` and `proof_bytes` are of the aggregated proof, while the
+/// `baseline_` columns sum the same over proving each circuit on its own.
+pub const AGGREGATED_OUTPUT_COLUMNS: &[&str] = &[
+    "k",
+    "num_circuits",
+    "prove_ms",
+    "verify_ms",
+    "proof_bytes",
+    "baseline_prove_ms",
+    "baseline_verify_ms",
+    "baseline_proof_bytes",
+];
+
+/// Backend able to prove several circuits in one aggregated proof, which is
+/// verified against the instances of every circuit at once.
+///
+/// Backends without an aggregation primitive implement this with the
+/// defaults, for which [`bench_aggregated`] fails with
+/// [`BenchError::AggregationUnsupported`] instead of proving each circuit on
+/// its own.
+pub trait AggregationBackend: PlonkishBackend<Fr> {
+    const SUPPORTS_AGGREGATION: bool = false;
+
+    /// Proves `circuits`, each preprocessed into the `pp` of same index.
+    fn prove_aggregated<C: PlonkishCircuit<Fr>>(
+        pps: &[Self::ProverParam],
+        circuits: &[C],
+        transcript: &mut impl TranscriptWrite<CommitmentChunk<Fr, Self::Pcs>, Fr>,
+        rng: impl RngCore,
+    ) -> Result<(), Error> {
+        let _ = (pps, circuits, transcript, rng);
+        Err(Error::InvalidSnark(
+            "aggregation is not supported".to_string(),
+        ))
+    }
+
+    /// Verifies the proof of [`Self::prove_aggregated`], with the `vp` and
+    /// instances of each circuit.
+    fn verify_aggregated(
+        vps: &[Self::VerifierParam],
+        instances: &[&[Vec<Fr>]],
+        transcript: &mut impl TranscriptRead<CommitmentChunk<Fr, Self::Pcs>, Fr>,
+        rng: impl RngCore,
+    ) -> Result<(), Error> {
+        let _ = (vps, instances, transcript, rng);
+        Err(Error::InvalidSnark(
+            "aggregation is not supported".to_string(),
+        ))
+    }
+}
+
+/// Aggregates with [`HyperPlonk::prove_aggregated`], which opens the
+/// polynomials of every circuit in a single PCS opening.
+impl<Pcs> AggregationBackend for HyperPlonk<Pcs>
+where
+    Pcs: PolynomialCommitmentScheme<Fr, Polynomial = MultilinearPolynomial<Fr>>,
+{
+    const SUPPORTS_AGGREGATION: bool = true;
+
+    fn prove_aggregated<C: PlonkishCircuit<Fr>>(
+        pps: &[Self::ProverParam],
+        circuits: &[C],
+        transcript: &mut impl TranscriptWrite<CommitmentChunk<Fr, Self::Pcs>, Fr>,
+        _: impl RngCore,
+    ) -> Result<(), Error> {
+        HyperPlonk::prove_aggregated(pps, circuits, transcript)
+    }
+
+    fn verify_aggregated(
+        vps: &[Self::VerifierParam],
+        instances: &[&[Vec<Fr>]],
+        transcript: &mut impl TranscriptRead<CommitmentChunk<Fr, Self::Pcs>, Fr>,
+        _: impl RngCore,
+    ) -> Result<(), Error> {
+        HyperPlonk::verify_aggregated(vps, instances, transcript)
+    }
+}
+
+impl<Pcs> AggregationBackend for UniHyperPlonk<Pcs, true> where Self: PlonkishBackend<Fr> {}
+
+impl<Pcs> AggregationBackend for Plonk<Pcs> where Self: PlonkishBackend<Fr> {}
+
+/// Benchmarks aggregating the proofs of `circuits` with backend `B` at `k`,
+/// appending a row of [`AGGREGATED_OUTPUT_COLUMNS`] to the aggregated output
+/// of `system` unless [`BenchOptions::skip_output`], see
+/// [`create_output_for`].
+///
+/// Circuits are preprocessed with one setup, so they are expected to be of
+/// the same size, e.g. the witnesses of one query circuit shape over several
+/// datasets. Each is also proven and verified on its own as the baseline.
+/// Fails with [`BenchError::AggregationUnsupported`] before any setup if `B`
+/// can't aggregate. The setup cache and [`BenchOptions::timeout`] aren't
+/// supported.
+///
+/// [`create_output_for`]: crate::proof_system::create_output_for
+pub fn bench_aggregated<B, T>(
+    system: System,
+    k: usize,
+    circuits: &[impl PlonkishCircuit<Fr>],
+    options: &BenchOptions,
+) -> Result<(), BenchError>
+where
+    B: AggregationBackend + WitnessEncoding,
+    T: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + InMemoryTranscript<Param = ()>
+        + Default,
+{
+    if !B::SUPPORTS_AGGREGATION {
+        return Err(BenchError::AggregationUnsupported {
+            system,
+            num_circuits: circuits.len(),
+        });
+    }
+    if !options.dry_run && !options.skip_output {
+        open_output(system, ProofMode::Aggregated, options)?;
+    }
+    let circuit_infos = circuits
+        .iter()
+        .map(PlonkishCircuit::circuit_info)
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(required) = circuit_infos.iter().map(required_k).max() {
+        if k < required {
+            return Err(BenchError::InsufficientK {
+                requested: k,
+                required,
+            });
+        }
+    }
+    let Some(first) = circuit_infos.first() else {
+        return Ok(());
+    };
+    let rng = |phase: Phase| bench_rng(options.seed, phase);
+
+    let param = setup_param::<B>(system, k, first, options.seed)?;
+    let (pps, vps): (Vec<_>, Vec<_>) = circuit_infos
+        .iter()
+        .map(|circuit_info| preprocess::<B>(system, k, &param, circuit_info))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .unzip();
+
+    let (sample_size, warmup) = match options.dry_run {
+        true => (NonZeroUsize::MIN, 0),
+        false => (
+            options.sample_size.unwrap_or_else(|| sample_size(k)),
+            warmup(),
+        ),
+    };
+    let verify_failed = |error| BenchError::VerificationFailed {
+        system,
+        k,
+        error,
+        proof_path: None,
+        iteration: None,
+    };
+
+    let mut baseline = (Duration::ZERO, Duration::ZERO, 0);
+    for ((pp, vp), circuit) in pps.iter().zip(vps.iter()).zip(circuits) {
+        let (proof, prove_time) = sample(sample_size, warmup, || {
+            in_phase(system, Phase::Prove, k, || {
+                let mut transcript = T::default();
+                B::prove(pp, circuit, &mut transcript, rng(Phase::Prove))?;
+                Ok(transcript.into_proof())
+            })
+        })?;
+        let (verified, verify_time) = sample(sample_size, 0, || {
+            in_phase(system, Phase::Verify, k, || {
+                let mut transcript = T::from_proof((), proof.as_slice());
+                Ok(B::verify(
+                    vp,
+                    circuit.instances(),
+                    &mut transcript,
+                    rng(Phase::Verify),
+                ))
+            })
+        })?;
+        verified.map_err(verify_failed)?;
+        baseline.0 += prove_time.mean;
+        baseline.1 += verify_time.mean;
+        baseline.2 += proof.len();
+    }
+
+    let (proof, prove_time) = sample(sample_size, warmup, || {
+        in_phase(system, Phase::Prove, k, || {
+            let mut transcript = T::default();
+            B::prove_aggregated(&pps, circuits, &mut transcript, rng(Phase::Prove))?;
+            Ok(transcript.into_proof())
+        })
+    })?;
+    let instances = circuits
+        .iter()
+        .map(PlonkishCircuit::instances)
+        .collect::<Vec<_>>();
+    let (verified, verify_time) = sample(sample_size, 0, || {
+        in_phase(system, Phase::Verify, k, || {
+            let mut transcript = T::from_proof((), proof.as_slice());
+            Ok(B::verify_aggregated(
+                &vps,
+                &instances,
+                &mut transcript,
+                rng(Phase::Verify),
+            ))
+        })
+    })?;
+    verified.map_err(verify_failed)?;
+    if options.dry_run || options.skip_output {
+        return Ok(());
+    }
+
+    let (baseline_prove, baseline_verify, baseline_proof_bytes) = baseline;
+    let record = AggregatedRecord {
+        num_circuits: circuits.len(),
+        prove: prove_time,
+        verify: verify_time.mean,
+        proof_bytes: proof.len(),
+        baseline_prove,
+        baseline_verify,
+        baseline_proof_bytes,
+    };
+    write_row(system, k, BenchRecord::Aggregated(record), options)
+}
@@ -0,0 +1,87 @@
+/// Cumulative allocations of the process since it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    pub bytes_allocated: u64,
+    pub alloc_count: u64,
+}
+
+impl AllocStats {
+    /// Adds the allocations from `before` until `after`, e.g. of a single
+    /// run, so the allocations between runs are left out.
+    pub(super) fn add_since(&mut self, before: &Self, after: &Self) {
+        self.bytes_allocated += after.bytes_allocated - before.bytes_allocated;
+        self.alloc_count += after.alloc_count - before.alloc_count;
+    }
+
+    /// Returns the mean allocations of each of `num_runs` runs added up in
+    /// `self`.
+    pub(super) fn mean(&self, num_runs: usize) -> Self {
+        let num_runs = num_runs.max(1) as u64;
+        Self {
+            bytes_allocated: self.bytes_allocated / num_runs,
+            alloc_count: self.alloc_count / num_runs,
+        }
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    pub(super) static BYTES_ALLOCATED: AtomicU64 = AtomicU64::new(0);
+    pub(super) static ALLOC_COUNT: AtomicU64 = AtomicU64::new(0);
+
+    /// System allocator counting every allocation, with a reallocation
+    /// counted as an allocation of the new size.
+    struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            System.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count(new_size);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    fn count(size: usize) {
+        BYTES_ALLOCATED.fetch_add(size as u64, Ordering::Relaxed);
+        ALLOC_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+}
+
+/// Returns the allocations of the process so far, counted by the global
+/// allocator the `alloc-stats` feature installs, or `None` without it where
+/// the system allocator is used as is. Allocations of every thread are
+/// counted, so concurrent benchmarks inflate each other's.
+#[cfg(feature = "alloc-stats")]
+pub(super) fn alloc_stats() -> Option<AllocStats> {
+    use std::sync::atomic::Ordering;
+    Some(AllocStats {
+        bytes_allocated: counting::BYTES_ALLOCATED.load(Ordering::Relaxed),
+        alloc_count: counting::ALLOC_COUNT.load(Ordering::Relaxed),
+    })
+}
+
+#[cfg(not(feature = "alloc-stats"))]
+pub(super) fn alloc_stats() -> Option<AllocStats> {
+    None
+}
@@ -0,0 +1,205 @@
+use crate::proof_system::{
+    bench_rng, in_phase, load_setup, open_output, preprocess, required_k, sample, sample_size,
+    setup_cache_path, setup_param, store_setup, warmup, write_row, BatchedRecord, BenchError,
+    BenchOptions, BenchRecord, Phase, ProofMode, System, Timing,
+};
+use plonkish_backend::{
+    backend::{PlonkishBackend, PlonkishCircuit, WitnessEncoding},
+    halo2_curves::bn256::Fr,
+    pcs::CommitmentChunk,
+    util::transcript::{InMemoryTranscript, TranscriptRead, TranscriptWrite},
+};
+use std::{num::NonZeroUsize, time::Duration};
+
+/// Columns of each row written by [`bench_batched`], in order. `prove_ms` is
+/// of proving the whole batch, `amortized_prove_ms` that over `batch_size`,
+/// and `single_prove_ms` of proving the first circuit on its own.
+pub const BATCHED_OUTPUT_COLUMNS: &[&str] = &[
+    "k",
+    "batch_size",
+    "prove_ms",
+    "amortized_prove_ms",
+    "single_prove_ms",
+];
+
+/// Benchmarks proving `circuits` one after another with backend `B` at `k`,
+/// appending a row of [`BATCHED_OUTPUT_COLUMNS`] to the batched output of
+/// `system` unless [`BenchOptions::skip_output`], see [`create_output_for`].
+///
+/// The circuits are preprocessed once, from the first, so they are expected
+/// to be of the same [`PlonkishCircuitInfo`], e.g. the witnesses of one query
+/// circuit shape over several datasets. Every proof of the batch is verified
+/// after it's timed. The setup cache is used with
+/// [`BenchOptions::reuse_setup`], while [`BenchOptions::timeout`] isn't
+/// supported.
+///
+/// [`create_output_for`]: crate::proof_system::create_output_for
+/// [`PlonkishCircuitInfo`]: plonkish_backend::backend::PlonkishCircuitInfo
+pub fn bench_batched<B, T>(
+    system: System,
+    k: usize,
+    circuits: &[impl PlonkishCircuit<Fr>],
+    options: &BenchOptions,
+) -> Result<(), BenchError>
+where
+    B: PlonkishBackend<Fr> + WitnessEncoding,
+    T: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>
+        + InMemoryTranscript<Param = ()>
+        + Default,
+{
+    if !options.dry_run && !options.skip_output {
+        open_output(system, ProofMode::Batched, options)?;
+    }
+    let Some(first) = circuits.first() else {
+        return Ok(());
+    };
+    let circuit_info = first.circuit_info()?;
+    let required = required_k(&circuit_info);
+    if k < required {
+        return Err(BenchError::InsufficientK {
+            requested: k,
+            required,
+        });
+    }
+    let rng = |phase: Phase| bench_rng(options.seed, phase);
+
+    let cache_path = options
+        .reuse_setup
+        .then(|| setup_cache_path(system, k, &circuit_info));
+    let (pp, vp) = match cache_path.as_deref().and_then(load_setup::<B>) {
+        Some((_, pp, vp)) => (pp, vp),
+        None => {
+            let param = setup_param::<B>(system, k, &circuit_info, options.seed)?;
+            let (pp, vp) = preprocess::<B>(system, k, &param, &circuit_info)?;
+            let setup = (param, pp, vp);
+            if let Some(cache_path) = cache_path.as_deref() {
+                store_setup::<B>(cache_path, &setup);
+            }
+            let (_, pp, vp) = setup;
+            (pp, vp)
+        }
+    };
+
+    let (sample_size, warmup) = match options.dry_run {
+        true => (NonZeroUsize::MIN, 0),
+        false => (
+            options.sample_size.unwrap_or_else(|| sample_size(k)),
+            warmup(),
+        ),
+    };
+    let prove = |circuit| {
+        in_phase(system, Phase::Prove, k, || {
+            let mut transcript = T::default();
+            B::prove(&pp, circuit, &mut transcript, rng(Phase::Prove))?;
+            Ok(transcript.into_proof())
+        })
+    };
+
+    let (_, single_time) = sample(sample_size, warmup, || prove(first))?;
+    let (proofs, batch_time) = sample(sample_size, warmup, || {
+        circuits.iter().map(prove).collect::<Result<Vec<_>, _>>()
+    })?;
+    for (iteration, (circuit, proof)) in circuits.iter().zip(&proofs).enumerate() {
+        let mut transcript = T::from_proof((), proof.as_slice());
+        B::verify(
+            &vp,
+            circuit.instances(),
+            &mut transcript,
+            rng(Phase::Verify),
+        )
+        .map_err(|error| BenchError::VerificationFailed {
+            system,
+            k,
+            error,
+            proof_path: None,
+            iteration: Some(iteration),
+        })?;
+    }
+    if options.dry_run || options.skip_output {
+        return Ok(());
+    }
+
+    let batch_size = circuits.len();
+    let record = BatchedRecord {
+        batch_size,
+        prove: batch_time,
+        amortized_prove: per_proof(&batch_time, batch_size),
+        single_prove: single_time,
+    };
+    write_row(system, k, BenchRecord::Batched(record), options)
+}
+
+/// Returns the timing of proving a batch of `batch_size` per proof.
+fn per_proof(timing: &Timing, batch_size: usize) -> Timing {
+    let per_proof = |duration: Duration| duration.div_f64(batch_size as f64);
+    Timing {
+        mean: per_proof(timing.mean),
+        min: per_proof(timing.min),
+        max: per_proof(timing.max),
+        stddev: per_proof(timing.stddev),
+        p50: per_proof(timing.p50),
+        p95: per_proof(timing.p95),
+        p99: per_proof(timing.p99),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{
+        batch::bench_batched,
+        sink::{BenchRow, OutputSink},
+        test::vanilla_plonk,
+        BenchError, BenchOptions, OutputFormat, ProofMode, System,
+    };
+    use plonkish_backend::{
+        backend::hyperplonk::HyperPlonk, halo2_curves::bn256::Bn256,
+        pcs::multilinear::MultilinearKzg, util::transcript::Keccak256Transcript,
+    };
+    use std::{
+        num::NonZeroUsize,
+        sync::{Arc, Mutex},
+    };
+
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        opened: Mutex<Vec<ProofMode>>,
+        rows: Mutex<Vec<BenchRow>>,
+    }
+
+    impl OutputSink for RecordingSink {
+        fn open(&self, _: System, mode: ProofMode, _: OutputFormat) -> Result<(), BenchError> {
+            self.opened.lock().unwrap().push(mode);
+            Ok(())
+        }
+
+        fn write_row(&self, _: System, row: &BenchRow) -> Result<(), BenchError> {
+            self.rows.lock().unwrap().push(row.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn batched_skip_output() {
+        type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+        let circuits = [vanilla_plonk(4, 1), vanilla_plonk(4, 2)];
+        for skip_output in [true, false] {
+            let sink = Arc::new(RecordingSink::default());
+            let options = BenchOptions {
+                sample_size: NonZeroUsize::new(1),
+                seed: Some(0),
+                skip_output,
+                sink: Some(sink.clone()),
+                ..Default::default()
+            };
+            bench_batched::<Pb, Keccak256Transcript<_>>(System::HyperPlonk, 4, &circuits, &options)
+                .unwrap();
+            let num_rows = (!skip_output) as usize;
+            assert_eq!(
+                *sink.opened.lock().unwrap(),
+                [ProofMode::Batched; 1][..num_rows]
+            );
+            assert_eq!(sink.rows.lock().unwrap().len(), num_rows);
+        }
+    }
+}
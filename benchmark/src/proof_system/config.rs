@@ -0,0 +1,208 @@
+use crate::proof_system::{
+    run_matrix, BenchError, BenchOptions, MatrixDataset, OutputFormat, System, OUTPUT_DIR_ENV,
+    WARMUP_ENV,
+};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    num::NonZeroUsize,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Benchmark matrix read from a TOML file by [`BenchConfig::load`], so the
+/// parameters of an experiment are kept and replayed together, e.g.
+///
+/// ```toml
+/// systems = ["hyperplonk", "unihyperplonk"]
+/// k_range = "10..=18"
+/// sample_size = 10
+/// warmup = 2
+/// output_dir = "target/bench"
+/// ```
+///
+/// Only `systems` and `k_range` are required. Omitted fields keep the
+/// defaults of the `bench` flags and of [`BenchOptions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchConfig {
+    pub systems: Vec<System>,
+    pub ks: Range<usize>,
+    pub format: OutputFormat,
+    /// See [`BenchOptions::sample_size`].
+    pub sample_size: Option<NonZeroUsize>,
+    /// Number of untimed prove runs before sampling, overriding
+    /// [`WARMUP_ENV`] when set.
+    pub warmup: Option<usize>,
+    /// Directory of the outputs, overriding [`OUTPUT_DIR_ENV`] when set.
+    pub output_dir: Option<PathBuf>,
+    /// See [`BenchOptions::timeout`].
+    pub timeout: Option<Duration>,
+    /// See [`BenchOptions::seed`].
+    pub seed: Option<u64>,
+    /// See [`BenchOptions::reuse_setup`].
+    pub reuse_setup: bool,
+    /// See [`BenchOptions::sync_output`].
+    pub sync_output: bool,
+    /// See [`BenchOptions::keep_output`].
+    pub keep_output: bool,
+    /// Maximum number of systems benchmarked concurrently.
+    pub parallelism: NonZeroUsize,
+    /// Keep the outputs and skip what they already have, see [`run_matrix`].
+    /// Only [`Self::run_matrix`] resumes.
+    pub resume: bool,
+}
+
+/// [`BenchConfig`] as written in the file, before validation.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBenchConfig {
+    systems: Vec<String>,
+    k_range: String,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    sample_size: Option<NonZeroUsize>,
+    #[serde(default)]
+    warmup: Option<usize>,
+    #[serde(default)]
+    output_dir: Option<PathBuf>,
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    reuse_setup: bool,
+    #[serde(default)]
+    sync_output: bool,
+    #[serde(default)]
+    keep_output: bool,
+    #[serde(default)]
+    parallelism: Option<NonZeroUsize>,
+    #[serde(default)]
+    resume: bool,
+}
+
+impl BenchConfig {
+    /// Reads the config at `path`, failing on unknown fields or systems, or on
+    /// an invalid `k_range` before anything is benchmarked.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BenchError> {
+        let path = path.as_ref();
+        let invalid = |reason: String| BenchError::Config {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let raw = toml::from_str::<RawBenchConfig>(&fs::read_to_string(path)?)
+            .map_err(|err| invalid(err.to_string()))?;
+        if raw.systems.is_empty() {
+            return Err(invalid("no systems to benchmark".to_string()));
+        }
+        let systems = raw
+            .systems
+            .iter()
+            .map(|system| system.parse())
+            .collect::<Result<Vec<System>, _>>()
+            .map_err(invalid)?;
+        let format = match raw.format.as_deref() {
+            None | Some("csv") => OutputFormat::Csv,
+            Some("json") => OutputFormat::Json,
+            Some("gnuplot") => OutputFormat::Gnuplot,
+            Some(format) => {
+                return Err(invalid(format!(
+                    "unknown format {format:?}, expected csv, json or gnuplot"
+                )))
+            }
+        };
+        Ok(Self {
+            systems,
+            ks: parse_k_range(&raw.k_range).map_err(invalid)?,
+            format,
+            sample_size: raw.sample_size,
+            warmup: raw.warmup,
+            output_dir: raw.output_dir,
+            timeout: raw.timeout_secs.map(Duration::from_secs),
+            seed: raw.seed,
+            reuse_setup: raw.reuse_setup,
+            sync_output: raw.sync_output,
+            keep_output: raw.keep_output,
+            parallelism: raw.parallelism.unwrap_or(NonZeroUsize::MIN),
+            resume: raw.resume,
+        })
+    }
+
+    /// Returns the [`BenchOptions`] of the config.
+    pub fn options(&self) -> BenchOptions {
+        BenchOptions {
+            format: self.format,
+            reuse_setup: self.reuse_setup,
+            sample_size: self.sample_size,
+            timeout: self.timeout,
+            seed: self.seed,
+            sync_output: self.sync_output,
+            keep_output: self.keep_output,
+            ..Default::default()
+        }
+    }
+
+    /// Sets [`WARMUP_ENV`] and [`OUTPUT_DIR_ENV`] to [`Self::warmup`] and
+    /// [`Self::output_dir`] when set, which the benchmarks read them from.
+    /// Call it before spawning any benchmark thread.
+    pub fn apply_env(&self) {
+        if let Some(warmup) = self.warmup {
+            env::set_var(WARMUP_ENV, warmup.to_string());
+        }
+        if let Some(output_dir) = &self.output_dir {
+            env::set_var(OUTPUT_DIR_ENV, output_dir);
+        }
+    }
+
+    /// Benchmarks `datasets` by [`run_matrix`] at every `k` of the config.
+    pub fn run_matrix<D: MatrixDataset>(&self, datasets: &[D]) -> Result<(), BenchError> {
+        self.apply_env();
+        let ks = self.ks.clone().collect::<Vec<_>>();
+        run_matrix(&self.systems, &ks, datasets, &self.options(), self.resume)
+    }
+}
+
+/// Parses a range of `k`, e.g. `10..18` or `10..=18`.
+pub fn parse_k_range(value: &str) -> Result<Range<usize>, String> {
+    let invalid = || format!("invalid k range {value:?}, expected e.g. 10..18 or 10..=18");
+    let (start, end) = value.split_once("..").ok_or_else(invalid)?;
+    let (end, inclusive) = match end.strip_prefix('=') {
+        Some(end) => (end, true),
+        None => (end, false),
+    };
+    let start = start.parse::<usize>().map_err(|_| invalid())?;
+    let end = end.parse::<usize>().map_err(|_| invalid())? + inclusive as usize;
+    if start >= end {
+        return Err(format!("empty k range {value:?}"));
+    }
+    Ok(start..end)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{config::BenchConfig, BenchError};
+    use std::{env, fs, num::NonZeroUsize};
+
+    fn load(name: &str, config: &str) -> Result<BenchConfig, BenchError> {
+        let path = env::temp_dir().join(format!("bench-config-{}-{name}.toml", std::process::id()));
+        fs::write(&path, config).unwrap();
+        let loaded = BenchConfig::load(&path);
+        fs::remove_file(&path).unwrap();
+        loaded
+    }
+
+    #[test]
+    fn sample_size() {
+        let config = "systems = [\"hyperplonk\"]\nk_range = \"4..6\"\n";
+        assert_eq!(load("default", config).unwrap().sample_size, None);
+        let ten = load("ten", &format!("{config}sample_size = 10\n")).unwrap();
+        assert_eq!(ten.sample_size, NonZeroUsize::new(10));
+        assert_eq!(ten.options().sample_size, NonZeroUsize::new(10));
+
+        // A sample size of 0 would leave nothing to time.
+        let zero = load("zero", &format!("{config}sample_size = 0\n"));
+        assert!(matches!(zero, Err(BenchError::Config { .. })));
+    }
+}
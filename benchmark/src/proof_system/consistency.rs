@@ -0,0 +1,170 @@
+use crate::proof_system::{bench_rng, prove_to_file, BenchError, Phase};
+use plonkish_backend::{
+    backend::{PlonkishBackend, PlonkishCircuit},
+    halo2_curves::bn256::Fr,
+    pcs::CommitmentChunk,
+    util::transcript::{InMemoryTranscript, Keccak256Transcript, TranscriptRead, TranscriptWrite},
+};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Cursor},
+    path::Path,
+};
+
+/// Proves `circuit` twice with backend `B` and the RNG of `seed`, asserting
+/// both proofs are byte-identical, which catches nondeterminism in witness
+/// generation or proving. Returns the proof.
+///
+/// Only seeded runs are replayable, see [`BenchOptions::seed`], so `seed`
+/// isn't optional.
+///
+/// # Panics
+///
+/// Panics at the first byte the proofs differ at.
+///
+/// [`BenchOptions::seed`]: crate::proof_system::BenchOptions::seed
+pub fn assert_deterministic_proof<B>(
+    pp: &B::ProverParam,
+    circuit: &impl PlonkishCircuit<Fr>,
+    seed: u64,
+) -> Result<Vec<u8>, BenchError>
+where
+    B: PlonkishBackend<Fr>,
+    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    let prove = || -> Result<_, BenchError> {
+        let mut transcript = Keccak256Transcript::new(());
+        B::prove(
+            pp,
+            circuit,
+            &mut transcript,
+            bench_rng(Some(seed), Phase::Prove),
+        )?;
+        Ok(transcript.into_proof())
+    };
+    let (first, second) = (prove()?, prove()?);
+    if let Some(idx) =
+        (0..first.len().max(second.len())).find(|idx| first.get(*idx) != second.get(*idx))
+    {
+        panic!(
+            "proofs of seed {seed} differ at byte {idx} of {} and {}",
+            first.len(),
+            second.len()
+        );
+    }
+    Ok(first)
+}
+
+/// Proves `circuit` with backend `B` to a file at `proof_path` by
+/// [`prove_to_file`], then reads it back and asserts `vp` still verifies it
+/// against the instances of `circuit`. Returns the number of bytes of the
+/// proof.
+///
+/// # Panics
+///
+/// Panics if the proof read back doesn't verify.
+pub fn assert_proof_round_trip<B>(
+    pp: &B::ProverParam,
+    vp: &B::VerifierParam,
+    circuit: &impl PlonkishCircuit<Fr>,
+    proof_path: &Path,
+    seed: Option<u64>,
+) -> Result<u64, BenchError>
+where
+    B: PlonkishBackend<Fr>,
+    Keccak256Transcript<BufWriter<File>>: TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>,
+    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    let num_bytes = prove_to_file::<B>(pp, circuit, proof_path, bench_rng(seed, Phase::Prove))?;
+    let proof = fs::read(proof_path)?;
+    let mut transcript = Keccak256Transcript::from_proof((), proof.as_slice());
+    let verified = B::verify(
+        vp,
+        circuit.instances(),
+        &mut transcript,
+        bench_rng(seed, Phase::Verify),
+    );
+    if let Err(err) = verified {
+        panic!(
+            "proof read back from {} doesn't verify: {err:?}",
+            proof_path.display()
+        );
+    }
+    Ok(num_bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{
+        bench_rng,
+        consistency::{assert_deterministic_proof, assert_proof_round_trip},
+        test::vanilla_plonk,
+        Phase,
+    };
+    use plonkish_backend::{
+        backend::{
+            hyperplonk::{util::rand_vanilla_plonk_circuit, HyperPlonk},
+            PlonkishBackend, PlonkishCircuit, PlonkishCircuitInfo,
+        },
+        halo2_curves::bn256::{Bn256, Fr},
+        pcs::multilinear::MultilinearKzg,
+        util::expression::rotate::BinaryField,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use std::{env, fs, panic};
+
+    type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+
+    /// Returns the witnesses and proof of the circuit of `seed`.
+    fn prove(seed: u64) -> (Vec<Vec<Fr>>, Vec<u8>) {
+        let (circuit_info, circuit): (PlonkishCircuitInfo<Fr>, _) =
+            rand_vanilla_plonk_circuit::<_, BinaryField>(
+                4,
+                ChaCha20Rng::seed_from_u64(seed),
+                ChaCha20Rng::seed_from_u64(seed),
+            );
+        let param = Pb::setup(&circuit_info, bench_rng(Some(seed), Phase::Setup)).unwrap();
+        let (pp, _) = Pb::preprocess(&param, &circuit_info).unwrap();
+        let witnesses = circuit.synthesize(0, &[]).unwrap();
+        let proof = assert_deterministic_proof::<Pb>(&pp, &circuit, seed).unwrap();
+        (witnesses, proof)
+    }
+
+    #[test]
+    fn deterministic_proof() {
+        let (witnesses, proof) = prove(1);
+        assert_eq!(prove(1), (witnesses.clone(), proof.clone()));
+
+        let (other_witnesses, other_proof) = prove(2);
+        assert_ne!(other_witnesses, witnesses);
+        assert_ne!(other_proof, proof);
+    }
+
+    #[test]
+    fn proof_round_trip() {
+        let circuit = vanilla_plonk(4, 0);
+        let circuit_info = circuit.circuit_info().unwrap();
+        let param = Pb::setup(&circuit_info, bench_rng(Some(0), Phase::Setup)).unwrap();
+        let (pp, vp) = Pb::preprocess(&param, &circuit_info).unwrap();
+        // Preprocessed from other fixed columns, the vp of another circuit.
+        let (other_circuit_info, _) = rand_vanilla_plonk_circuit::<Fr, BinaryField>(
+            4,
+            ChaCha20Rng::seed_from_u64(1),
+            ChaCha20Rng::seed_from_u64(1),
+        );
+        let (_, other_vp) = Pb::preprocess(&param, &other_circuit_info).unwrap();
+        let proof_path = env::temp_dir().join(format!("bench-round-trip-{}", std::process::id()));
+
+        let num_bytes = assert_proof_round_trip::<Pb>(&pp, &vp, &circuit, &proof_path, Some(0));
+        let written = fs::metadata(&proof_path).map(|metadata| metadata.len());
+        let other = panic::catch_unwind(|| {
+            assert_proof_round_trip::<Pb>(&pp, &other_vp, &circuit, &proof_path, Some(0))
+        });
+        fs::remove_file(&proof_path).unwrap();
+
+        assert_eq!(num_bytes.unwrap(), written.unwrap());
+        let message = other.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("doesn't verify"), "{message}");
+    }
+}
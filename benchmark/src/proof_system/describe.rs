@@ -0,0 +1,103 @@
+use crate::proof_system::BenchError;
+use plonkish_backend::{
+    backend::{PlonkishCircuit, PlonkishCircuitInfo},
+    halo2_curves::bn256::Fr,
+    util::{expression::Expression, Itertools},
+};
+use std::fmt::{self, Display};
+
+/// Summary of the [`PlonkishCircuitInfo`] of a circuit by
+/// [`describe_circuit`], e.g. to see what makes the circuit of a query
+/// expensive before benchmarking it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitDescription {
+    pub k: usize,
+    /// Number of values of each instance polynomial.
+    pub num_instances: Vec<usize>,
+    pub num_preprocess_polys: usize,
+    /// Number of witness polynomials of each phase.
+    pub num_witness_polys: Vec<usize>,
+    /// Number of challenges of each phase.
+    pub num_challenges: Vec<usize>,
+    /// Degree of each constraint.
+    pub constraint_degrees: Vec<usize>,
+    /// Number of input and table pairs of each lookup.
+    pub lookup_widths: Vec<usize>,
+    /// Maximum degree of the constraints and lookups, or the one the circuit
+    /// declares if higher.
+    pub max_degree: usize,
+    pub num_permutation_cycles: usize,
+    /// Number of cells in all permutation cycles.
+    pub num_copied_cells: usize,
+}
+
+impl CircuitDescription {
+    pub fn new(circuit_info: &PlonkishCircuitInfo<Fr>) -> Self {
+        let constraint_degrees = circuit_info
+            .constraints
+            .iter()
+            .map(Expression::degree)
+            .collect_vec();
+        let lookup_degree = circuit_info
+            .lookups
+            .iter()
+            .flatten()
+            .flat_map(|(input, table)| [input.degree(), table.degree()])
+            .max();
+        let max_degree = constraint_degrees
+            .iter()
+            .copied()
+            .chain(lookup_degree)
+            .chain(circuit_info.max_degree)
+            .max()
+            .unwrap_or_default();
+        Self {
+            k: circuit_info.k,
+            num_instances: circuit_info.num_instances.clone(),
+            num_preprocess_polys: circuit_info.preprocess_polys.len(),
+            num_witness_polys: circuit_info.num_witness_polys.clone(),
+            num_challenges: circuit_info.num_challenges.clone(),
+            constraint_degrees,
+            lookup_widths: circuit_info.lookups.iter().map(Vec::len).collect(),
+            max_degree,
+            num_permutation_cycles: circuit_info.permutations.len(),
+            num_copied_cells: circuit_info.permutations.iter().map(Vec::len).sum(),
+        }
+    }
+}
+
+impl Display for CircuitDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "k: {} ({} rows)", self.k, 1usize << self.k)?;
+        writeln!(f, "instances: {:?}", self.num_instances)?;
+        writeln!(f, "preprocessed polys: {}", self.num_preprocess_polys)?;
+        writeln!(f, "witness polys per phase: {:?}", self.num_witness_polys)?;
+        writeln!(f, "challenges per phase: {:?}", self.num_challenges)?;
+        writeln!(
+            f,
+            "constraints: {} of degrees {:?}",
+            self.constraint_degrees.len(),
+            self.constraint_degrees
+        )?;
+        writeln!(
+            f,
+            "lookups: {} of widths {:?}",
+            self.lookup_widths.len(),
+            self.lookup_widths
+        )?;
+        writeln!(f, "max degree: {}", self.max_degree)?;
+        write!(
+            f,
+            "permutation: {} cycles over {} cells",
+            self.num_permutation_cycles, self.num_copied_cells
+        )
+    }
+}
+
+/// Returns the [`CircuitDescription`] of `circuit`, which only reads its
+/// [`PlonkishCircuit::circuit_info`] without any setup or preprocess.
+pub fn describe_circuit(
+    circuit: &impl PlonkishCircuit<Fr>,
+) -> Result<CircuitDescription, BenchError> {
+    Ok(CircuitDescription::new(&circuit.circuit_info()?))
+}
@@ -0,0 +1,191 @@
+use crate::proof_system::{
+    interrupt::check_interrupted,
+    progress::{NoProgress, ProgressReporter, ProgressTracker},
+    BenchError, System,
+};
+use itertools::Itertools;
+use std::{num::NonZeroUsize, sync::Mutex, thread};
+
+/// A single benchmark of `system` at `k`, usually a closure calling
+/// [`bench_plonkish_backend`](crate::proof_system::bench_plonkish_backend)
+/// with the circuit and backend of `system`.
+pub struct BenchJob<'a> {
+    pub system: System,
+    pub k: usize,
+    run: Box<dyn FnOnce() -> Result<(), BenchError> + Send + 'a>,
+}
+
+impl<'a> BenchJob<'a> {
+    pub fn new(
+        system: System,
+        k: usize,
+        run: impl FnOnce() -> Result<(), BenchError> + Send + 'a,
+    ) -> Self {
+        Self {
+            system,
+            k,
+            run: Box::new(run),
+        }
+    }
+}
+
+/// Runs `jobs` on up to `max_parallelism` threads. Jobs of the same system
+/// share an output file, so they all run on one thread in the given order,
+/// while different systems run concurrently. The remaining jobs of a system
+/// are skipped once one of them fails, and the first failure is returned after
+/// every thread finishes. Once [`interrupt`] was called no job is started, so
+/// the run fails with [`BenchError::Interrupted`] after the running ones.
+///
+/// [`interrupt`]: crate::proof_system::interrupt
+///
+/// Call [`create_output`](crate::proof_system::create_output) before, since
+/// truncating the output of a system while its jobs run would lose rows.
+/// Concurrent jobs compete for cores and memory, so timings, and
+/// `peak_mem_kb` and the allocation columns, which are tracked per process,
+/// are only comparable between runs with the same parallelism.
+pub fn bench_parallel(
+    jobs: Vec<BenchJob>,
+    max_parallelism: NonZeroUsize,
+) -> Result<(), BenchError> {
+    bench_parallel_with_progress(jobs, max_parallelism, &NoProgress)
+}
+
+/// Runs `jobs` as [`bench_parallel`], reporting to `progress` before and after
+/// each job. Jobs skipped after a failure aren't reported, so the completed
+/// count then stays below the total.
+pub fn bench_parallel_with_progress(
+    jobs: Vec<BenchJob>,
+    max_parallelism: NonZeroUsize,
+    progress: &dyn ProgressReporter,
+) -> Result<(), BenchError> {
+    let tracker = ProgressTracker::new(progress, jobs.len());
+    let by_system = jobs
+        .into_iter()
+        .sorted_by_key(|job| job.system)
+        .group_by(|job| job.system)
+        .into_iter()
+        .map(|(_, jobs)| jobs.collect_vec())
+        .collect_vec();
+    let num_threads = max_parallelism.get().min(by_system.len());
+    let queue = Mutex::new(by_system.into_iter());
+
+    let results = thread::scope(|s| {
+        let workers = (0..num_threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut result = Ok(());
+                    while let Some(jobs) = next(&queue) {
+                        for job in jobs {
+                            let ran = check_interrupted()
+                                .and_then(|()| tracker.run(job.system, job.k, job.run));
+                            if let Err(err) = ran {
+                                eprintln!("{}-{} failed: {err}", job.system, job.k);
+                                result = result.and(Err(err));
+                                break;
+                            }
+                        }
+                    }
+                    result
+                })
+            })
+            .collect_vec();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect_vec()
+    });
+    results.into_iter().collect()
+}
+
+fn next<T>(queue: &Mutex<impl Iterator<Item = T>>) -> Option<T> {
+    queue.lock().unwrap().next()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{
+        driver::{bench_parallel, BenchJob},
+        BenchError, System,
+    };
+    use std::{num::NonZeroUsize, sync::Mutex};
+
+    /// Runs jobs of `(system, k)` on `max_parallelism` threads, failing at `k`
+    /// of `failing`, and returns the result with the jobs run in order.
+    fn run(
+        jobs: &[(System, usize)],
+        failing: (System, usize),
+        max_parallelism: usize,
+    ) -> (Result<(), BenchError>, Vec<(System, usize)>) {
+        let ran = Mutex::new(Vec::new());
+        let jobs = jobs
+            .iter()
+            .map(|&(system, k)| {
+                let ran = &ran;
+                BenchJob::new(system, k, move || {
+                    ran.lock().unwrap().push((system, k));
+                    if (system, k) == failing {
+                        return Err(BenchError::InsufficientK {
+                            requested: k,
+                            required: k + 1,
+                        });
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+        let result = bench_parallel(jobs, NonZeroUsize::new(max_parallelism).unwrap());
+        (result, ran.into_inner().unwrap())
+    }
+
+    #[test]
+    fn jobs_of_a_system_in_order() {
+        let jobs = [
+            (System::Plonk, 4),
+            (System::HyperPlonk, 6),
+            (System::Plonk, 5),
+            (System::HyperPlonk, 4),
+            (System::HyperPlonk, 5),
+        ];
+        let (result, ran) = run(&jobs, (System::Plonk, 0), 2);
+        assert!(result.is_ok());
+        let ran_of = |system| {
+            ran.iter()
+                .filter(|(of, _)| *of == system)
+                .map(|(_, k)| *k)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ran_of(System::HyperPlonk), [6, 4, 5]);
+        assert_eq!(ran_of(System::Plonk), [4, 5]);
+    }
+
+    #[test]
+    fn failure_skips_rest_of_system() {
+        let jobs = [
+            (System::HyperPlonk, 4),
+            (System::HyperPlonk, 5),
+            (System::HyperPlonk, 6),
+            (System::Plonk, 4),
+            (System::Plonk, 5),
+        ];
+        for max_parallelism in [1, 2] {
+            let (result, mut ran) = run(&jobs, (System::HyperPlonk, 5), max_parallelism);
+            assert!(matches!(
+                result,
+                Err(BenchError::InsufficientK {
+                    requested: 5,
+                    required: 6
+                })
+            ));
+            ran.sort();
+            assert_eq!(
+                ran,
+                [
+                    (System::HyperPlonk, 4),
+                    (System::HyperPlonk, 5),
+                    (System::Plonk, 4),
+                    (System::Plonk, 5),
+                ]
+            );
+        }
+    }
+}
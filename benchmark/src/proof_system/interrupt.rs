@@ -0,0 +1,30 @@
+use crate::proof_system::BenchError;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the running benchmark to stop, which it does before the next prove
+/// or verify it samples, writing an `interrupted` marker for the `k` it was
+/// at and failing with [`BenchError::Interrupted`]. The one in flight
+/// finishes first, or is abandoned by [`BenchOptions::timeout`] if set.
+/// Returns whether it was interrupted already.
+///
+/// Nothing is installed to call this, e.g. on SIGINT, which is up to the
+/// binary. It only stores to an atomic, so it's safe in a signal handler.
+///
+/// [`BenchOptions::timeout`]: crate::proof_system::BenchOptions::timeout
+pub fn interrupt() -> bool {
+    INTERRUPTED.swap(true, Ordering::SeqCst)
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Fails with [`BenchError::Interrupted`] once [`interrupt`] was called.
+pub(super) fn check_interrupted() -> Result<(), BenchError> {
+    match is_interrupted() {
+        true => Err(BenchError::Interrupted),
+        false => Ok(()),
+    }
+}
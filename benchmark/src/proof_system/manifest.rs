@@ -0,0 +1,153 @@
+use crate::proof_system::{create_dir, json_string, output_dir, BenchError, System};
+use std::{fs, path::PathBuf, process::Command, thread};
+
+/// Name of the file [`write_manifest`] writes under [`output_dir`].
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Environment of a benchmark session, which [`write_manifest`] records next
+/// to its outputs so a directory of results says where they came from when
+/// compared across machines or over time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BenchManifest {
+    /// `CARGO_PKG_VERSION` of the benchmark crate.
+    pub crate_version: String,
+    /// `HEAD` of the repository the benchmark crate was built from, `None` if
+    /// it's not in one or `git` isn't available.
+    pub git_commit: Option<String>,
+    pub systems: Vec<System>,
+    pub ks: Vec<usize>,
+    /// CPU model as reported by the OS, `None` where it isn't.
+    pub cpu_model: Option<String>,
+    /// Number of cores available to the benchmark, see
+    /// [`thread::available_parallelism`].
+    pub num_cores: usize,
+    /// See [`BenchOptions::seed`].
+    ///
+    /// [`BenchOptions::seed`]: crate::proof_system::BenchOptions::seed
+    pub seed: Option<u64>,
+}
+
+impl BenchManifest {
+    /// Returns the manifest of benchmarking `systems` at each of `ks` with
+    /// `seed` in the current environment.
+    pub fn new(systems: &[System], ks: impl IntoIterator<Item = usize>, seed: Option<u64>) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: git_commit(),
+            systems: systems.to_vec(),
+            ks: ks.into_iter().collect(),
+            cpu_model: cpu_model(),
+            num_cores: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed,
+        }
+    }
+
+    /// Returns the manifest as a JSON object of a field per line.
+    pub fn to_json(&self) -> String {
+        let string = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map_or_else(|| "null".to_string(), json_string)
+        };
+        let systems = self.systems.iter().map(|system| format!("\"{system}\""));
+        let ks = self.ks.iter().map(usize::to_string);
+        let seed = self
+            .seed
+            .map_or_else(|| "null".to_string(), |seed| seed.to_string());
+        let fields = [
+            format!("  \"crate_version\": {}", json_string(&self.crate_version)),
+            format!("  \"git_commit\": {}", string(&self.git_commit)),
+            format!(
+                "  \"systems\": [{}]",
+                systems.collect::<Vec<_>>().join(", ")
+            ),
+            format!("  \"ks\": [{}]", ks.collect::<Vec<_>>().join(", ")),
+            format!("  \"cpu_model\": {}", string(&self.cpu_model)),
+            format!("  \"num_cores\": {}", self.num_cores),
+            format!("  \"seed\": {seed}"),
+        ];
+        format!("{{\n{}\n}}", fields.join(",\n"))
+    }
+}
+
+/// Returns the path [`write_manifest`] writes to.
+pub fn manifest_path() -> PathBuf {
+    output_dir().join(MANIFEST_FILE)
+}
+
+/// Writes `manifest` to [`manifest_path`], replacing the one of an earlier
+/// session. It's meant to be written once per session, next to
+/// [`create_output`], rather than per row.
+///
+/// [`create_output`]: crate::proof_system::create_output
+pub fn write_manifest(manifest: &BenchManifest) -> Result<(), BenchError> {
+    create_dir(&output_dir())?;
+    fs::write(manifest_path(), manifest.to_json() + "\n")?;
+    Ok(())
+}
+
+/// Returns the commit of `HEAD` of the repository of the benchmark crate.
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["-C", env!("CARGO_MANIFEST_DIR"), "rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    let commit = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| commit.trim().to_string())
+}
+
+/// Returns the `model name` of the first processor in `/proc/cpuinfo`.
+#[cfg(target_os = "linux")]
+fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "model name").then(|| value.trim().to_string())
+    })
+}
+
+/// Returns `machdep.cpu.brand_string` of `sysctl`.
+#[cfg(target_os = "macos")]
+fn cpu_model() -> Option<String> {
+    let output = Command::new("sysctl")
+        .args(["-n", "machdep.cpu.brand_string"])
+        .output()
+        .ok()?;
+    let model = String::from_utf8(output.stdout).ok()?;
+    output.status.success().then(|| model.trim().to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn cpu_model() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{manifest::BenchManifest, System};
+
+    #[test]
+    fn to_json_escapes_strings() {
+        let manifest = BenchManifest {
+            crate_version: "0.1.0".to_string(),
+            git_commit: None,
+            systems: vec![System::HyperPlonk],
+            ks: vec![4, 5],
+            cpu_model: Some("Intel\u{ae} \"Core\" \\ i7\t\u{1}".to_string()),
+            num_cores: 8,
+            seed: Some(7),
+        };
+        let expected = [
+            "{",
+            "  \"crate_version\": \"0.1.0\",",
+            "  \"git_commit\": null,",
+            "  \"systems\": [\"hyperplonk\"],",
+            "  \"ks\": [4, 5],",
+            "  \"cpu_model\": \"Intel\u{ae} \\\"Core\\\" \\\\ i7\\t\\u0001\",",
+            "  \"num_cores\": 8,",
+            "  \"seed\": 7",
+            "}",
+        ];
+        assert_eq!(manifest.to_json(), expected.join("\n"));
+    }
+}
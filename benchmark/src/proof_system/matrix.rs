@@ -0,0 +1,363 @@
+use crate::proof_system::{
+    bench_plonkish_backend, check_dataset, create_output,
+    interrupt::check_interrupted,
+    manifest::{write_manifest, BenchManifest},
+    progress::{NoProgress, ProgressReporter, ProgressTracker},
+    required_k, BenchError, BenchOptions, CircuitMetadata, OutputFormat, System, TimedCircuit,
+};
+use plonkish_backend::{
+    backend::{
+        hyperplonk::HyperPlonk, plonk::Plonk, unihyperplonk::UniHyperPlonk, PlonkishCircuit,
+        WitnessEncoding,
+    },
+    halo2_curves::bn256::{Bn256, Fr},
+    pcs::{multilinear::MultilinearKzg, univariate::UnivariateKzg},
+    util::{transcript::Keccak256Transcript, Itertools},
+};
+use sparql::{compile_bgp_with_strategy, MatchStrategy, QueryCircuit, RdfGraph, SelectQuery};
+use std::{collections::HashSet, fs, io};
+
+/// Dataset benchmarked by [`run_matrix`] at every `k`, e.g. a dataset of some
+/// size and a query over it.
+pub trait MatrixDataset {
+    type Circuit: PlonkishCircuit<Fr> + CircuitMetadata + Clone + Send + Sync + 'static;
+
+    /// Identifier written to the `dataset` column, see
+    /// [`BenchOptions::dataset`].
+    fn id(&self) -> &str;
+
+    /// Returns the circuit of the dataset at `k` laid out for the row mapping
+    /// of `E`, or `None` if the dataset has none at `k`.
+    fn circuit<E: WitnessEncoding>(&self, k: usize) -> Result<Option<Self::Circuit>, BenchError>;
+}
+
+/// Circuit of `query` over `graph`, whose size only depends on them, so it's
+/// only benchmarked at the smallest `k` it fits, with its witness generated
+/// again by [`TimedCircuit::query`] to time it.
+///
+/// The matched triples are proven by `strategy`, so two datasets of the same
+/// query and graph but either strategy, under different ids, compare a
+/// Merkle path per matched triple to a lookup into the table of the graph.
+pub struct QueryDataset {
+    pub id: String,
+    pub query: SelectQuery,
+    pub graph: RdfGraph,
+    pub strategy: MatchStrategy,
+}
+
+impl MatrixDataset for QueryDataset {
+    type Circuit = TimedCircuit<QueryCircuit>;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn circuit<E: WitnessEncoding>(&self, k: usize) -> Result<Option<Self::Circuit>, BenchError> {
+        let circuit = compile_bgp_with_strategy::<E>(&self.query, &self.graph, self.strategy)?;
+        if required_k(&circuit.circuit_info()?) != k {
+            return Ok(None);
+        }
+        let circuit = TimedCircuit::query::<E>(circuit.shape().clone(), &self.graph)?;
+        Ok(Some(circuit))
+    }
+}
+
+/// Benchmarks every combination of `systems`, `ks` and `datasets`, tagging the
+/// rows of each with the dataset by [`BenchOptions::dataset`], and returns the
+/// first failure. Systems not benchmarked through `plonkish_backend` are
+/// skipped, as are the `k` a dataset has no circuit at.
+///
+/// With `resume`, the outputs are kept and combinations they already have a
+/// row of, timeouts included, are skipped, so a partially completed matrix
+/// can be run again, except from [`OutputFormat::Gnuplot`] rows, which have
+/// no dataset. Combinations marked `interrupted` are run again. Otherwise
+/// the outputs are truncated first by [`create_output`], or it fails if they
+/// have data with [`BenchOptions::keep_output`], and the [`BenchManifest`] of
+/// the run is written by [`write_manifest`]. The outputs are files either
+/// way, so with a [`BenchOptions::sink`] they're neither truncated nor
+/// resumed from.
+///
+/// A dataset id the rows can't hold fails with
+/// [`BenchError::InvalidDataset`] before any output.
+pub fn run_matrix<D: MatrixDataset>(
+    systems: &[System],
+    ks: &[usize],
+    datasets: &[D],
+    options: &BenchOptions,
+    resume: bool,
+) -> Result<(), BenchError> {
+    run_matrix_with_progress(systems, ks, datasets, options, resume, &NoProgress)
+}
+
+/// Benchmarks the matrix as [`run_matrix`], reporting to `progress` before
+/// and after each combination left to run, of which skipped ones aren't
+/// counted.
+pub fn run_matrix_with_progress<D: MatrixDataset>(
+    systems: &[System],
+    ks: &[usize],
+    datasets: &[D],
+    options: &BenchOptions,
+    resume: bool,
+    progress: &dyn ProgressReporter,
+) -> Result<(), BenchError> {
+    for dataset in datasets {
+        check_dataset(dataset.id())?;
+    }
+    if !resume && !options.dry_run && options.sink.is_none() {
+        create_output(systems, options.format, !options.keep_output)?;
+        write_manifest(&BenchManifest::new(
+            systems,
+            ks.iter().copied(),
+            options.seed,
+        ))?;
+    }
+    let mut units = Vec::new();
+    for system in systems {
+        if !matches!(
+            system,
+            System::HyperPlonk | System::UniHyperPlonk | System::Plonk
+        ) {
+            eprintln!("Skipping {system}, which isn't benchmarked through plonkish_backend");
+            continue;
+        }
+        let completed = match resume && options.sink.is_none() {
+            true => completed(*system, options.format)?,
+            false => HashSet::new(),
+        };
+        for (k, dataset) in ks.iter().cartesian_product(datasets) {
+            if completed.contains(&(*k, dataset.id().to_string())) {
+                eprintln!("Skipping {system}-{k} over {}, which is done", dataset.id());
+                continue;
+            }
+            units.push((*system, *k, dataset));
+        }
+    }
+    let tracker = ProgressTracker::new(progress, units.len());
+    for (system, k, dataset) in units {
+        check_interrupted()?;
+        let options = BenchOptions {
+            dataset: Some(dataset.id().to_string()),
+            ..options.clone()
+        };
+        tracker.run(system, k, || bench_dataset(system, k, dataset, &options))?;
+    }
+    Ok(())
+}
+
+/// Outcome of a system of [`bench_all_systems`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SystemOutcome {
+    /// A row of the `k` was appended to the output of the system.
+    Benchmarked,
+    /// The system was skipped for `reason` without a row.
+    Skipped { reason: String },
+}
+
+/// Benchmarks the circuit of `dataset` at `k` on every [`System::ALL`] in
+/// turn by [`bench_plonkish_backend`] with the backend of the system laid out
+/// for its row mapping, tagging the rows as [`run_matrix`] does, and returns
+/// the outcome of each system.
+///
+/// Systems not benchmarked through `plonkish_backend`, the ones `dataset` has
+/// no circuit for at `k` and the ones whose circuit doesn't compile or needs
+/// a larger `k` are skipped with the reason, which is also printed. Any other
+/// failure is returned, without benchmarking the systems after it.
+pub fn bench_all_systems<D: MatrixDataset>(
+    k: usize,
+    dataset: &D,
+    options: &BenchOptions,
+) -> Result<Vec<(System, SystemOutcome)>, BenchError> {
+    let options = BenchOptions {
+        dataset: Some(dataset.id().to_string()),
+        ..options.clone()
+    };
+    System::ALL
+        .iter()
+        .copied()
+        .map(|system| {
+            let outcome = match bench_dataset(system, k, dataset, &options) {
+                Err(err @ (BenchError::InsufficientK { .. } | BenchError::Compile(_))) => {
+                    SystemOutcome::Skipped {
+                        reason: err.to_string(),
+                    }
+                }
+                outcome => outcome?,
+            };
+            if let SystemOutcome::Skipped { reason } = &outcome {
+                eprintln!("Skipping {system}-{k} over {}: {reason}", dataset.id());
+            }
+            Ok((system, outcome))
+        })
+        .collect()
+}
+
+fn bench_dataset<D: MatrixDataset>(
+    system: System,
+    k: usize,
+    dataset: &D,
+    options: &BenchOptions,
+) -> Result<SystemOutcome, BenchError> {
+    let no_circuit = || SystemOutcome::Skipped {
+        reason: format!("no circuit at k = {k}"),
+    };
+    let not_plonkish = || SystemOutcome::Skipped {
+        reason: "not benchmarked through plonkish_backend".to_string(),
+    };
+    match system {
+        System::HyperPlonk => {
+            type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+            let Some(circuit) = dataset.circuit::<Pb>(k)? else {
+                return Ok(no_circuit());
+            };
+            bench_plonkish_backend::<Pb, Fr, Keccak256Transcript<_>>(system, k, &circuit, options)?;
+        }
+        System::UniHyperPlonk => {
+            type Pb = UniHyperPlonk<UnivariateKzg<Bn256>, true>;
+            let Some(circuit) = dataset.circuit::<Pb>(k)? else {
+                return Ok(no_circuit());
+            };
+            bench_plonkish_backend::<Pb, Fr, Keccak256Transcript<_>>(system, k, &circuit, options)?;
+        }
+        System::Plonk => {
+            type Pb = Plonk<UnivariateKzg<Bn256>>;
+            let Some(circuit) = dataset.circuit::<Pb>(k)? else {
+                return Ok(no_circuit());
+            };
+            bench_plonkish_backend::<Pb, Fr, Keccak256Transcript<_>>(system, k, &circuit, options)?;
+        }
+        #[cfg(feature = "halo2")]
+        System::Halo2 => return Ok(not_plonkish()),
+        System::EspressoHyperPlonk => return Ok(not_plonkish()),
+    }
+    Ok(SystemOutcome::Benchmarked)
+}
+
+/// Returns the `(k, dataset)` of every row in the output of `system`, which
+/// is empty if there's no output yet.
+fn completed(system: System, format: OutputFormat) -> Result<HashSet<(usize, String)>, BenchError> {
+    let path = system.output_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let invalid_data = |msg: &str| {
+        let msg = format!("{}: {msg}", path.display());
+        BenchError::Io(io::Error::new(io::ErrorKind::InvalidData, msg))
+    };
+
+    let mut lines = content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !is_interrupted_marker(line));
+    let rows = match format {
+        OutputFormat::Csv => {
+            let header = lines.next().unwrap_or_default();
+            let idx = header
+                .split(',')
+                .position(|name| name.trim() == "dataset")
+                .ok_or_else(|| invalid_data("missing column dataset to resume from"))?;
+            lines
+                .map(|line| {
+                    let values = line.split(',').map(str::trim).collect_vec();
+                    // Timeout markers are followed by the dataset.
+                    let idx = if values.get(1) == Some(&"timeout") {
+                        2
+                    } else {
+                        idx
+                    };
+                    let dataset = values.get(idx).copied().unwrap_or_default();
+                    Some((values[0].parse().ok()?, dataset.to_string()))
+                })
+                .collect::<Option<Vec<_>>>()
+        }
+        OutputFormat::Gnuplot => {
+            return Err(invalid_data(
+                "no column dataset in gnuplot rows to resume from",
+            ))
+        }
+        OutputFormat::Json => lines
+            .map(|line| {
+                let k = json_field(line, "k")?.parse().ok()?;
+                let dataset = match json_field(line, "dataset") {
+                    None | Some("null") => "",
+                    Some(dataset) => dataset.strip_prefix('"')?.strip_suffix('"')?,
+                };
+                Some((k, dataset.to_string()))
+            })
+            .collect(),
+    };
+    let rows = rows.ok_or_else(|| invalid_data("invalid row to resume from"))?;
+    Ok(rows
+        .into_iter()
+        .filter(|(_, dataset)| !dataset.is_empty())
+        .collect())
+}
+
+/// Returns whether `line` is the `interrupted` marker of a CSV or JSON output
+/// rather than a row.
+fn is_interrupted_marker(line: &str) -> bool {
+    line.split(',').nth(1).map(str::trim) == Some("interrupted")
+        || line.contains(r#""interrupted":true"#)
+}
+
+/// Returns the raw value of `field` of a JSON row written by
+/// [`bench_plonkish_backend`], whose values have no commas or braces.
+fn json_field<'a>(line: &'a str, field: &str) -> Option<&'a str> {
+    let start = line.find(&format!(r#""{field}":"#))? + field.len() + 3;
+    let rest = &line[start..];
+    Some(&rest[..rest.find([',', '}'])?])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{
+        matrix::completed, sink::test::single_record, test::test_output_dir, BenchError,
+        BenchRecord, BenchRow, FileSink, OutputFormat, System, OUTPUT_COLUMNS,
+    };
+    use std::{collections::HashSet, fs, io};
+
+    #[test]
+    fn completed_of_output() {
+        let system = System::Plonk;
+        let path = test_output_dir().join(system.to_string());
+        fs::create_dir_all(test_output_dir()).unwrap();
+        let completed_of = |format, header: String| {
+            let line = |k, dataset: Option<&str>, record| {
+                let row = BenchRow {
+                    format,
+                    k,
+                    dataset: dataset.map(str::to_string),
+                    record,
+                };
+                FileSink::line(system, &row)
+            };
+            let lines = [
+                line(4, Some("lubm"), BenchRecord::Single(single_record())),
+                line(5, Some("lubm"), BenchRecord::Timeout),
+                line(6, Some("lubm"), BenchRecord::Interrupted),
+                line(4, Some("dbpedia"), BenchRecord::Single(single_record())),
+                line(7, None, BenchRecord::Single(single_record())),
+            ];
+            fs::write(&path, format!("{header}{}\n", lines.join("\n"))).unwrap();
+            completed(system, format)
+        };
+        let csv = completed_of(OutputFormat::Csv, format!("{}\n", OUTPUT_COLUMNS.join(",")));
+        let json = completed_of(OutputFormat::Json, String::new());
+        let gnuplot = completed_of(OutputFormat::Gnuplot, String::new());
+        fs::remove_file(&path).unwrap();
+        let missing = completed(system, OutputFormat::Csv);
+
+        // A timed out row is completed, while an interrupted one and a row
+        // without a dataset are run again.
+        let expected = HashSet::from(
+            [(4, "lubm"), (5, "lubm"), (4, "dbpedia")].map(|(k, dataset)| (k, dataset.to_string())),
+        );
+        assert_eq!(csv.unwrap(), expected);
+        assert_eq!(json.unwrap(), expected);
+        assert!(matches!(
+            gnuplot,
+            Err(BenchError::Io(err)) if err.kind() == io::ErrorKind::InvalidData
+        ));
+        assert_eq!(missing.unwrap(), HashSet::new());
+    }
+}
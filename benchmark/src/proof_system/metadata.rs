@@ -0,0 +1,109 @@
+use plonkish_backend::{
+    backend::{PlonkishCircuit, PlonkishCircuitInfo, WitnessEncoding},
+    halo2_curves::bn256::Fr,
+    Error,
+};
+use sparql::{
+    circuit::{InstanceLayout, QueryCircuit, QueryCircuitShape},
+    CompileError, RdfGraph,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Metadata of a benchmarked circuit its [`PlonkishCircuit`] doesn't expose,
+/// recorded alongside the timings by [`bench_plonkish_backend`].
+///
+/// [`bench_plonkish_backend`]: crate::proof_system::bench_plonkish_backend
+pub trait CircuitMetadata {
+    /// Returns the number of dataset triples the circuit proves to match,
+    /// which divided by the prove time gives the `triples_per_second` column.
+    /// `None` for circuits not over a dataset.
+    fn num_matched_triples(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the layout of the instances, which are printed by what each
+    /// holds when the proof fails to verify. `None` for circuits other than
+    /// a [`QueryCircuit`], whose instances are printed as they are.
+    fn instance_layout(&self) -> Option<InstanceLayout> {
+        None
+    }
+
+    /// Returns how long generating the witness took before proving, written
+    /// to the `witness_gen_ms` column. `None` for circuits whose witness
+    /// isn't generated apart from building them, see [`TimedCircuit`].
+    fn witness_gen_time(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl CircuitMetadata for QueryCircuit {
+    fn num_matched_triples(&self) -> Option<usize> {
+        Some(self.shape().num_matched_triples())
+    }
+
+    fn instance_layout(&self) -> Option<InstanceLayout> {
+        Some(*QueryCircuit::instance_layout(self))
+    }
+}
+
+/// Circuit with the time its witness took to generate, which is what
+/// [`CircuitMetadata::witness_gen_time`] returns.
+#[derive(Clone)]
+pub struct TimedCircuit<C> {
+    pub circuit: C,
+    pub witness_gen_time: Duration,
+}
+
+impl TimedCircuit<QueryCircuit> {
+    /// Returns the circuit of `shape` over `dataset` with the time of
+    /// [`QueryCircuitShape::witness_for`], which evaluates the query and
+    /// fills the advice columns, as a prover of a preprocessed shape does for
+    /// every dataset.
+    pub fn query<E: WitnessEncoding>(
+        shape: Arc<QueryCircuitShape>,
+        dataset: &RdfGraph,
+    ) -> Result<Self, CompileError> {
+        let start = Instant::now();
+        let witness = shape.witness_for::<E>(dataset)?;
+        let witness_gen_time = start.elapsed();
+        Ok(Self {
+            circuit: QueryCircuit::new(shape, witness),
+            witness_gen_time,
+        })
+    }
+}
+
+impl<C: PlonkishCircuit<Fr>> PlonkishCircuit<Fr> for TimedCircuit<C> {
+    fn circuit_info_without_preprocess(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+        self.circuit.circuit_info_without_preprocess()
+    }
+
+    fn circuit_info(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+        self.circuit.circuit_info()
+    }
+
+    fn instances(&self) -> &[Vec<Fr>] {
+        self.circuit.instances()
+    }
+
+    fn synthesize(&self, round: usize, challenges: &[Fr]) -> Result<Vec<Vec<Fr>>, Error> {
+        self.circuit.synthesize(round, challenges)
+    }
+}
+
+impl<C: CircuitMetadata> CircuitMetadata for TimedCircuit<C> {
+    fn num_matched_triples(&self) -> Option<usize> {
+        self.circuit.num_matched_triples()
+    }
+
+    fn instance_layout(&self) -> Option<InstanceLayout> {
+        self.circuit.instance_layout()
+    }
+
+    fn witness_gen_time(&self) -> Option<Duration> {
+        Some(self.witness_gen_time)
+    }
+}
@@ -0,0 +1,162 @@
+use plonkish_backend::{
+    halo2_curves::bn256::Bn256,
+    util::{
+        arithmetic::{count_msms, defer_pairings},
+        transcript::{FieldTranscript, FieldTranscriptRead, Transcript, TranscriptRead},
+    },
+    Error,
+};
+
+/// Operations a verifier performs, a machine independent proxy of the verify
+/// cost written alongside `verify_ms`. Field elements read are the scalars and
+/// commitments read the points, e.g. of KZG, the verifier works on, while
+/// absorbed values and squeezed challenges are its hashing. The group work of
+/// the PCS is counted as the variable base MSMs with their total number of
+/// bases, see [`count_msms`], and the pairing checks with their total number
+/// of pairings, each check a multi Miller loop and final exponentiation. Fixed
+/// base MSMs and single scalar multiplications aren't counted.
+///
+/// The counts only depend on the proof, so a single verify suffices.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerifyOps {
+    pub field_reads: usize,
+    pub commitment_reads: usize,
+    pub absorbs: usize,
+    pub challenges: usize,
+    pub msms: usize,
+    pub msm_bases: usize,
+    pub pairing_checks: usize,
+    pub pairings: usize,
+}
+
+/// Returns the [`VerifyOps`] of `verify` reading from `transcript`. Its
+/// pairing checks are counted but deferred rather than checked, so it must be
+/// of a proof verified before.
+pub(super) fn verify_ops<T>(
+    transcript: T,
+    verify: impl FnOnce(&mut CountingTranscript<T>) -> Result<(), Error>,
+) -> Result<VerifyOps, Error> {
+    let mut transcript = CountingTranscript::new(transcript);
+    let ((verified, msms), checks) =
+        defer_pairings::<Bn256, _>(|| count_msms(|| verify(&mut transcript)));
+    verified?;
+    Ok(VerifyOps {
+        msms: msms.msms,
+        msm_bases: msms.bases,
+        pairing_checks: checks.len(),
+        pairings: checks.iter().map(Vec::len).sum(),
+        ..transcript.ops
+    })
+}
+
+/// Transcript counting the [`VerifyOps`] performed on `inner`.
+pub(super) struct CountingTranscript<T> {
+    inner: T,
+    ops: VerifyOps,
+}
+
+impl<T> CountingTranscript<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            ops: VerifyOps::default(),
+        }
+    }
+}
+
+impl<F, T: FieldTranscript<F>> FieldTranscript<F> for CountingTranscript<T> {
+    fn squeeze_challenge(&mut self) -> F {
+        self.ops.challenges += 1;
+        self.inner.squeeze_challenge()
+    }
+
+    fn squeeze_challenges(&mut self, n: usize) -> Vec<F> {
+        self.ops.challenges += n;
+        self.inner.squeeze_challenges(n)
+    }
+
+    fn common_field_element(&mut self, fe: &F) -> Result<(), Error> {
+        self.ops.absorbs += 1;
+        self.inner.common_field_element(fe)
+    }
+
+    fn common_field_elements(&mut self, fes: &[F]) -> Result<(), Error> {
+        self.ops.absorbs += fes.len();
+        self.inner.common_field_elements(fes)
+    }
+}
+
+impl<F, T: FieldTranscriptRead<F>> FieldTranscriptRead<F> for CountingTranscript<T> {
+    fn read_field_element(&mut self) -> Result<F, Error> {
+        self.ops.field_reads += 1;
+        self.inner.read_field_element()
+    }
+
+    fn read_field_elements(&mut self, n: usize) -> Result<Vec<F>, Error> {
+        self.ops.field_reads += n;
+        self.inner.read_field_elements(n)
+    }
+}
+
+impl<C, F, T: Transcript<C, F>> Transcript<C, F> for CountingTranscript<T> {
+    fn common_commitment(&mut self, comm: &C) -> Result<(), Error> {
+        self.ops.absorbs += 1;
+        self.inner.common_commitment(comm)
+    }
+
+    fn common_commitments(&mut self, comms: &[C]) -> Result<(), Error> {
+        self.ops.absorbs += comms.len();
+        self.inner.common_commitments(comms)
+    }
+}
+
+impl<C, F, T: TranscriptRead<C, F>> TranscriptRead<C, F> for CountingTranscript<T> {
+    fn read_commitment(&mut self) -> Result<C, Error> {
+        self.ops.commitment_reads += 1;
+        self.inner.read_commitment()
+    }
+
+    fn read_commitments(&mut self, n: usize) -> Result<Vec<C>, Error> {
+        self.ops.commitment_reads += n;
+        self.inner.read_commitments(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{ops::verify_ops, test::vanilla_plonk};
+    use plonkish_backend::{
+        backend::{hyperplonk::HyperPlonk, PlonkishBackend, PlonkishCircuit},
+        halo2_curves::bn256::Bn256,
+        pcs::multilinear::MultilinearKzg,
+        util::{
+            test::std_rng,
+            transcript::{InMemoryTranscript, Keccak256Transcript},
+        },
+    };
+
+    #[test]
+    fn pcs_ops_of_multilinear_kzg() {
+        type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+        let circuit = vanilla_plonk(4, 0);
+        let circuit_info = circuit.circuit_info().unwrap();
+        let param = Pb::setup(&circuit_info, std_rng()).unwrap();
+        let (pp, vp) = Pb::preprocess(&param, &circuit_info).unwrap();
+        let mut transcript = Keccak256Transcript::new(());
+        Pb::prove(&pp, &circuit, &mut transcript, std_rng()).unwrap();
+        let proof = transcript.into_proof();
+
+        let ops = |proof: &[u8]| {
+            verify_ops(Keccak256Transcript::from_proof((), proof), |transcript| {
+                Pb::verify(&vp, circuit.instances(), transcript, std_rng())
+            })
+        };
+        let ops_of_proof = ops(&proof).unwrap();
+        // A single opening of 4 variables, paired with `-g2` and each `s_i`.
+        let (pairing_checks, pairings) = (ops_of_proof.pairing_checks, ops_of_proof.pairings);
+        assert_eq!((pairing_checks, pairings), (1, 5));
+        assert!(ops_of_proof.msms > 0 && ops_of_proof.msm_bases >= ops_of_proof.msms);
+        assert!(ops_of_proof.commitment_reads > 0 && ops_of_proof.challenges > 0);
+        assert!(ops(&proof[..proof.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use crate::proof_system::BenchError;
+use std::path::Path;
+
+/// Sampling frequency of the profiler in Hz, off the round 100 so samples
+/// don't line up with periodic work.
+#[cfg(feature = "profile")]
+const FREQUENCY: i32 = 999;
+
+/// Runs `f` under a sampling profiler, writing the sampled stacks to `path`
+/// in the folded format of `inferno` and `flamegraph.pl`, one line of
+/// semicolon separated frames from the thread down followed by its count.
+#[cfg(feature = "profile")]
+pub(super) fn profile<T>(
+    path: &Path,
+    f: impl FnOnce() -> Result<T, BenchError>,
+) -> Result<T, BenchError> {
+    use itertools::Itertools;
+    use std::{fs::File, io::Write, iter};
+
+    let profiling = |err: pprof::Error| BenchError::Profiling(err.to_string());
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(FREQUENCY)
+        .build()
+        .map_err(profiling)?;
+    let output = f()?;
+    let report = guard.report().build().map_err(profiling)?;
+    let mut file = File::create(path)?;
+    for (frames, count) in report.data.iter() {
+        let symbols = frames
+            .frames
+            .iter()
+            .rev()
+            .flat_map(|frame| frame.iter().rev().map(ToString::to_string));
+        let stack = iter::once(frames.thread_name_or_id())
+            .chain(symbols)
+            .join(";");
+        writeln!(&mut file, "{stack} {count}")?;
+    }
+    Ok(output)
+}
+
+#[cfg(not(feature = "profile"))]
+pub(super) fn profile<T>(
+    _: &Path,
+    _: impl FnOnce() -> Result<T, BenchError>,
+) -> Result<T, BenchError> {
+    Err(BenchError::Profiling(
+        "benchmark is built without the `profile` feature".to_string(),
+    ))
+}
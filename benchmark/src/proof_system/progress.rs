@@ -0,0 +1,115 @@
+use crate::proof_system::System;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Progress of a sweep when a `(system, k)` unit of it starts or finishes,
+/// see [`ProgressReporter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub system: System,
+    pub k: usize,
+    /// Number of units finished so far, failed ones included, which counts
+    /// this one once it finished.
+    pub completed: usize,
+    pub total: usize,
+    /// Wall time of the unit finished last, which is this one once it
+    /// finished, or `None` before any did.
+    pub last_elapsed: Option<Duration>,
+}
+
+/// Callback reporting the progress of a sweep such as [`bench_parallel`] or
+/// [`run_matrix`] around each `(system, k)` unit, e.g. to feed a larger tool.
+/// It's called outside of the timed closures, so it doesn't affect the
+/// recorded measurements, but blocks the unit while it runs.
+///
+/// Units run concurrently with a parallelism above 1, so reports of
+/// different systems interleave.
+///
+/// [`bench_parallel`]: crate::proof_system::bench_parallel
+/// [`run_matrix`]: crate::proof_system::run_matrix
+pub trait ProgressReporter: Sync {
+    fn started(&self, _progress: &Progress) {}
+
+    fn finished(&self, _progress: &Progress) {}
+}
+
+/// [`ProgressReporter`] reporting nothing, which the sweeps default to.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {}
+
+/// [`ProgressReporter`] printing a single line to stderr, rewritten in place
+/// on every report and ended once every unit finished.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrProgress;
+
+impl ProgressReporter for StderrProgress {
+    fn started(&self, progress: &Progress) {
+        let last = match progress.last_elapsed {
+            Some(elapsed) => format!(", last took {:.3}s", elapsed.as_secs_f64()),
+            None => String::new(),
+        };
+        eprint!(
+            "\r[{}/{}] {}-{} running{last}\x1b[K",
+            progress.completed, progress.total, progress.system, progress.k
+        );
+    }
+
+    fn finished(&self, progress: &Progress) {
+        eprint!(
+            "\r[{}/{}] {}-{} done in {:.3}s\x1b[K",
+            progress.completed,
+            progress.total,
+            progress.system,
+            progress.k,
+            progress.last_elapsed.unwrap_or_default().as_secs_f64()
+        );
+        if progress.completed == progress.total {
+            eprintln!();
+        }
+    }
+}
+
+/// Counts the finished units of a sweep of `total` units, shared by the
+/// threads running them.
+pub(crate) struct ProgressTracker<'a> {
+    reporter: &'a dyn ProgressReporter,
+    total: usize,
+    state: Mutex<(usize, Option<Duration>)>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    pub(crate) fn new(reporter: &'a dyn ProgressReporter, total: usize) -> Self {
+        Self {
+            reporter,
+            total,
+            state: Mutex::new((0, None)),
+        }
+    }
+
+    /// Runs the unit of `system` at `k` by `f`, reporting before and after.
+    pub(crate) fn run<T>(&self, system: System, k: usize, f: impl FnOnce() -> T) -> T {
+        let progress = |(completed, last_elapsed)| Progress {
+            system,
+            k,
+            completed,
+            total: self.total,
+            last_elapsed,
+        };
+        let state = *self.state.lock().unwrap();
+        self.reporter.started(&progress(state));
+        let start = Instant::now();
+        let output = f();
+        let elapsed = start.elapsed();
+        let state = {
+            let mut state = self.state.lock().unwrap();
+            *state = (state.0 + 1, Some(elapsed));
+            *state
+        };
+        self.reporter.finished(&progress(state));
+        output
+    }
+}
@@ -0,0 +1,419 @@
+use crate::proof_system::{output_dir, BenchError, System};
+use itertools::Itertools;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+/// Default threshold in percent by which prove time is allowed to exceed the
+/// baseline before [`detect_regressions`] reports it.
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 10.0;
+
+/// Joins the `prove_ms` column of each system's CSV output on `k` and
+/// `dataset`, writing `comparison.csv` and `comparison.md` under
+/// [`output_dir`] with one column per system. Entries for `k` a system wasn't
+/// benchmarked at are left blank.
+pub fn write_comparison_report(systems: &[System]) -> Result<(), BenchError> {
+    write_comparison(systems, &output_dir())
+}
+
+/// Writes the report of [`write_comparison_report`] of the outputs of
+/// `systems` under `dir`.
+fn write_comparison(systems: &[System], dir: &Path) -> Result<(), BenchError> {
+    let columns = systems
+        .iter()
+        .map(|system| read_column(&dir.join(system.to_string()), "prove_ms"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let keys = columns
+        .iter()
+        .flat_map(BTreeMap::keys)
+        .collect::<BTreeSet<_>>();
+    let rows = keys
+        .into_iter()
+        .map(|key| {
+            let values = columns
+                .iter()
+                .map(|column| column.get(key).map(String::as_str).unwrap_or_default());
+            (key, values.collect_vec())
+        })
+        .collect_vec();
+
+    let mut csv = fs::File::create(dir.join("comparison.csv"))?;
+    writeln!(&mut csv, "k,dataset,{}", systems.iter().join(","))?;
+    for ((k, dataset), values) in rows.iter() {
+        writeln!(&mut csv, "{k},{dataset},{}", values.iter().join(","))?;
+    }
+
+    let mut md = fs::File::create(dir.join("comparison.md"))?;
+    writeln!(&mut md, "| k | dataset | {} |", systems.iter().join(" | "))?;
+    writeln!(&mut md, "|---|---|{}", "---|".repeat(systems.len()))?;
+    for ((k, dataset), values) in rows.iter() {
+        writeln!(
+            &mut md,
+            "| {k} | {dataset} | {} |",
+            values.iter().join(" | ")
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Prove time of a `k` that exceeds the baseline by more than the threshold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regression {
+    pub k: usize,
+    /// `dataset` column of the row, empty without one.
+    pub dataset: String,
+    pub baseline_ms: f64,
+    pub current_ms: f64,
+}
+
+impl Regression {
+    pub fn increase_percent(&self) -> f64 {
+        (self.current_ms / self.baseline_ms - 1.0) * 100.0
+    }
+}
+
+/// Outcome of comparing a fresh run of `system` against its baseline.
+#[derive(Clone, Debug)]
+pub struct RegressionReport {
+    pub system: System,
+    pub threshold_percent: f64,
+    pub regressions: Vec<Regression>,
+}
+
+impl RegressionReport {
+    pub fn has_regressions(&self) -> bool {
+        !self.regressions.is_empty()
+    }
+}
+
+/// Compares the `prove_ms` of the current output of `system` against the
+/// baseline saved under `baseline_dir`, which mirrors [`output_dir`], and
+/// reports every `k` and dataset whose prove time exceeds the baseline by
+/// more than `threshold_percent`. A `k` missing or timed out on either side
+/// is skipped.
+pub fn detect_regressions(
+    system: System,
+    baseline_dir: impl AsRef<Path>,
+    threshold_percent: f64,
+) -> Result<RegressionReport, BenchError> {
+    let baseline = baseline_dir.as_ref().join(system.to_string());
+    Ok(RegressionReport {
+        system,
+        threshold_percent,
+        regressions: regressions(&baseline, &system.output_path(), threshold_percent)?,
+    })
+}
+
+/// Returns the regressions of [`detect_regressions`] of the CSV output at
+/// `current` against that at `baseline`.
+fn regressions(
+    baseline: &Path,
+    current: &Path,
+    threshold_percent: f64,
+) -> Result<Vec<Regression>, BenchError> {
+    let baseline = read_column(baseline, "prove_ms")?;
+    let current = read_column(current, "prove_ms")?;
+    let regressions = current
+        .iter()
+        .filter_map(|((k, dataset), current_ms)| {
+            let baseline_ms = baseline.get(&(*k, dataset.clone()))?.parse::<f64>().ok()?;
+            let current_ms = current_ms.parse::<f64>().ok()?;
+            Some(Regression {
+                k: *k,
+                dataset: dataset.clone(),
+                baseline_ms,
+                current_ms,
+            })
+        })
+        .filter(|regression| regression.increase_percent() > threshold_percent)
+        .collect();
+    Ok(regressions)
+}
+
+/// Writes a table of the `prove_ms` of the CSV outputs at `baseline` and
+/// `current`, for the same system, side by side per `k` and dataset with their
+/// difference, annotating a `k` whose prove time changed by more than
+/// `threshold_percent` as a regression or an improvement. A `k` in only one
+/// of them, or timed out, is listed without a difference. Unlike
+/// [`detect_regressions`] this only reports and never fails on a regression.
+pub fn write_delta_table(
+    baseline: impl AsRef<Path>,
+    current: impl AsRef<Path>,
+    threshold_percent: f64,
+    mut writer: impl Write,
+) -> Result<(), BenchError> {
+    let baseline = read_column(baseline.as_ref(), "prove_ms")?;
+    let current = read_column(current.as_ref(), "prove_ms")?;
+    let keys = baseline
+        .keys()
+        .chain(current.keys())
+        .collect::<BTreeSet<_>>();
+
+    writeln!(
+        writer,
+        "{:>4} {:>12} {:>12} {:>12} {:>10} dataset",
+        "k", "baseline_ms", "current_ms", "delta_ms", "delta_pct"
+    )?;
+    for key @ (k, dataset) in keys {
+        let [baseline_ms, current_ms] =
+            [&baseline, &current].map(|column| column.get(key).map(String::as_str).unwrap_or("-"));
+        let (delta_ms, delta_pct, note) =
+            match [baseline_ms, current_ms].map(|ms| ms.parse::<f64>().ok()) {
+                [Some(baseline_ms), Some(current_ms)] => {
+                    let delta_pct = (current_ms / baseline_ms - 1.0) * 100.0;
+                    let note = if delta_pct > threshold_percent {
+                        "  regression"
+                    } else if delta_pct < -threshold_percent {
+                        "  improvement"
+                    } else {
+                        ""
+                    };
+                    let delta_ms = format!("{:+.3}", current_ms - baseline_ms);
+                    (delta_ms, format!("{delta_pct:+.1}%"), note)
+                }
+                _ if !baseline.contains_key(key) => ("-".to_string(), "-".to_string(), "  new"),
+                _ if !current.contains_key(key) => ("-".to_string(), "-".to_string(), "  missing"),
+                _ => ("-".to_string(), "-".to_string(), ""),
+            };
+        writeln!(
+            writer,
+            "{k:>4} {baseline_ms:>12} {current_ms:>12} {delta_ms:>12} {delta_pct:>10} {dataset}{note}"
+        )?;
+    }
+    Ok(())
+}
+
+/// Reads `column` of the CSV output at `path` keyed by `k` and `dataset`,
+/// which is empty for rows without one, so rows of the same `k` on different
+/// datasets are kept apart. Rows recorded as timed out or interrupted keep
+/// their `timeout` or `interrupted` marker as value.
+pub(crate) fn read_column(
+    path: &Path,
+    column: &str,
+) -> Result<BTreeMap<(usize, String), String>, BenchError> {
+    let invalid_data = |msg: String| {
+        let msg = format!("{}: {msg}", path.display());
+        BenchError::Io(io::Error::new(io::ErrorKind::InvalidData, msg))
+    };
+
+    let content = fs::read_to_string(path)?;
+    let mut lines = content.lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| invalid_data("missing CSV header".to_string()))?;
+    let position = |column: &str| header.split(',').position(|name| name.trim() == column);
+    let idx = position(column).ok_or_else(|| invalid_data(format!("missing column {column}")))?;
+    let dataset_idx = position("dataset");
+
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let values = line.split(',').map(str::trim).collect_vec();
+            let k = values[0]
+                .parse()
+                .map_err(|_| invalid_data(format!("invalid k in row {line:?}")))?;
+            // Markers are written as `{k}, {marker}` or `{k}, {marker}, {dataset}`
+            // rather than in the columns of the header.
+            let (value, dataset) = match values.get(1) {
+                Some(&marker @ ("timeout" | "interrupted")) => {
+                    (marker, values.get(2).copied().unwrap_or_default())
+                }
+                _ => {
+                    let value = values
+                        .get(idx)
+                        .ok_or_else(|| invalid_data(format!("missing {column} in row {line:?}")))?;
+                    let dataset = dataset_idx.and_then(|idx| values.get(idx).copied());
+                    (*value, dataset.unwrap_or_default())
+                }
+            };
+            Ok(((k, dataset.to_string()), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{
+        report::{read_column, regressions, write_comparison, write_delta_table, Regression},
+        System,
+    };
+    use std::{env, fs, path::PathBuf};
+
+    /// Writes `lines` to a CSV file `name` under the temporary directory.
+    fn write_csv(name: &str, lines: &[&str]) -> PathBuf {
+        let path = env::temp_dir().join(format!("bench-{name}-{}.csv", std::process::id()));
+        fs::write(&path, lines.join("\n")).unwrap();
+        path
+    }
+
+    #[test]
+    fn read_column_per_dataset() {
+        let path = env::temp_dir().join(format!("bench-report-{}.csv", std::process::id()));
+        let csv = [
+            "k,prove_ms,verify_ms,dataset",
+            "4, 10, 1, lubm",
+            "4, 20, 2, dbpedia",
+            "5, timeout, lubm",
+            "5, 40, 4, dbpedia",
+            "6, interrupted",
+        ];
+        fs::write(&path, csv.join("\n")).unwrap();
+        let column = read_column(&path, "prove_ms");
+        fs::remove_file(&path).unwrap();
+
+        let column = column.unwrap();
+        let values = column
+            .iter()
+            .map(|((k, dataset), value)| (*k, dataset.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                (4, "dbpedia", "20"),
+                (4, "lubm", "10"),
+                (5, "dbpedia", "40"),
+                (5, "lubm", "timeout"),
+                (6, "", "interrupted"),
+            ]
+        );
+    }
+
+    #[test]
+    fn regressions_over_threshold() {
+        let baseline = write_csv(
+            "regressions-baseline",
+            &[
+                "k,prove_ms,dataset",
+                "4, 100, lubm",
+                "4, 100, dbpedia",
+                "5, 100, lubm",
+                "6, timeout, lubm",
+                "7, 100, lubm",
+            ],
+        );
+        let current = write_csv(
+            "regressions-current",
+            &[
+                "k,prove_ms,dataset",
+                "4, 115, lubm",
+                "4, 90, dbpedia",
+                "5, 105, lubm",
+                "6, 200, lubm",
+                "8, 500, lubm",
+            ],
+        );
+        let found = regressions(&baseline, &current, 10.0);
+        let lenient = regressions(&baseline, &current, 20.0);
+        [baseline, current].map(|path| fs::remove_file(path).unwrap());
+
+        // 5% is within the threshold, and a `k` timed out or missing on
+        // either side is skipped.
+        let found = found.unwrap();
+        assert_eq!(
+            found,
+            [Regression {
+                k: 4,
+                dataset: "lubm".to_string(),
+                baseline_ms: 100.0,
+                current_ms: 115.0,
+            }]
+        );
+        assert!((found[0].increase_percent() - 15.0).abs() < 1e-9);
+        assert_eq!(lenient.unwrap(), []);
+    }
+
+    #[test]
+    fn comparison_per_system() {
+        let dir = env::temp_dir().join(format!("bench-comparison-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let outputs = [
+            (
+                System::HyperPlonk,
+                "k,prove_ms,dataset\n4, 10, lubm\n5, 20, lubm\n",
+            ),
+            (
+                System::Plonk,
+                "k,prove_ms,dataset\n4, 30, lubm\n5, timeout, lubm\n6, 40, lubm\n",
+            ),
+        ];
+        for (system, output) in outputs {
+            fs::write(dir.join(system.to_string()), output).unwrap();
+        }
+        let result = write_comparison(&[System::HyperPlonk, System::Plonk], &dir);
+        let [csv, md] =
+            ["comparison.csv", "comparison.md"].map(|name| fs::read_to_string(dir.join(name)));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // A `k` the first system wasn't benchmarked at is left blank.
+        result.unwrap();
+        assert_eq!(
+            csv.unwrap(),
+            format!(
+                "k,dataset,{},{}\n4,lubm,10,30\n5,lubm,20,timeout\n6,lubm,,40\n",
+                System::HyperPlonk,
+                System::Plonk
+            )
+        );
+        assert_eq!(
+            md.unwrap(),
+            format!(
+                "| k | dataset | {} | {} |\n|---|---|---|---|\n| 4 | lubm | 10 | 30 |\n\
+                 | 5 | lubm | 20 | timeout |\n| 6 | lubm |  | 40 |\n",
+                System::HyperPlonk,
+                System::Plonk
+            )
+        );
+    }
+
+    #[test]
+    fn delta_table_notes() {
+        let baseline = write_csv(
+            "delta-baseline",
+            &[
+                "k,prove_ms,dataset",
+                "4, 100, lubm",
+                "5, 100, lubm",
+                "6, 100, lubm",
+                "7, 100, lubm",
+                "8, timeout, lubm",
+            ],
+        );
+        let current = write_csv(
+            "delta-current",
+            &[
+                "k,prove_ms,dataset",
+                "4, 120, lubm",
+                "5, 80, lubm",
+                "6, 105, lubm",
+                "8, timeout, lubm",
+                "9, 100, lubm",
+            ],
+        );
+        let mut table = Vec::new();
+        let result = write_delta_table(&baseline, &current, 10.0, &mut table);
+        [baseline, current].map(|path| fs::remove_file(path).unwrap());
+
+        result.unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let rows = table
+            .lines()
+            .skip(1)
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                vec!["4", "100", "120", "+20.000", "+20.0%", "lubm", "regression"],
+                vec!["5", "100", "80", "-20.000", "-20.0%", "lubm", "improvement"],
+                vec!["6", "100", "105", "+5.000", "+5.0%", "lubm"],
+                vec!["7", "100", "-", "-", "-", "lubm", "missing"],
+                vec!["8", "timeout", "timeout", "-", "-", "lubm"],
+                vec!["9", "-", "100", "-", "-", "lubm", "new"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use crate::proof_system::{
+    as_millis_f64, ensure_output, json_string, AllocStats, BenchError, OutputFormat, ProofMode,
+    System, Timing, VerifyOps,
+};
+use std::{fmt::Debug, time::Duration};
+
+/// Row of a benchmark run, a `timeout` or `interrupted` marker included, with
+/// the results of the run rather than their formatted line, which
+/// [`FileSink::line`] formats for the output of its mode.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchRow {
+    pub format: OutputFormat,
+    pub k: usize,
+    /// See [`BenchOptions::dataset`].
+    ///
+    /// [`BenchOptions::dataset`]: crate::proof_system::BenchOptions::dataset
+    pub dataset: Option<String>,
+    pub record: BenchRecord,
+}
+
+impl BenchRow {
+    /// Returns the mode of the output the row is of.
+    pub fn mode(&self) -> ProofMode {
+        match self.record {
+            BenchRecord::Single(_) | BenchRecord::Timeout | BenchRecord::Interrupted => {
+                ProofMode::Single
+            }
+            BenchRecord::Batched(_) => ProofMode::Batched,
+            BenchRecord::Aggregated(_) => ProofMode::Aggregated,
+        }
+    }
+}
+
+/// Results of the run of a [`BenchRow`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum BenchRecord {
+    Single(SingleRecord),
+    Batched(BatchedRecord),
+    Aggregated(AggregatedRecord),
+    /// Marker of a run of [`ProofMode::Single`] abandoned once a prove took
+    /// longer than [`BenchOptions::timeout`].
+    ///
+    /// [`BenchOptions::timeout`]: crate::proof_system::BenchOptions::timeout
+    Timeout,
+    /// Marker of a run of [`ProofMode::Single`] stopped by [`interrupt`].
+    ///
+    /// [`interrupt`]: crate::proof_system::interrupt()
+    Interrupted,
+}
+
+/// Results of [`bench_plonkish_backend`], of the columns of
+/// [`OUTPUT_COLUMNS`].
+///
+/// [`bench_plonkish_backend`]: crate::proof_system::bench_plonkish_backend
+/// [`OUTPUT_COLUMNS`]: crate::proof_system::OUTPUT_COLUMNS
+#[derive(Clone, Debug, PartialEq)]
+pub struct SingleRecord {
+    pub prove: Timing,
+    pub peak_mem_kb: Option<u64>,
+    /// Mean allocations of a prove, `None` without the `alloc-stats` feature.
+    pub allocs: Option<AllocStats>,
+    pub verify: Duration,
+    pub verify_ops: VerifyOps,
+    pub proof_bytes: usize,
+    pub triples_per_second: Option<f64>,
+    pub witness_gen: Option<Duration>,
+    pub preprocess: Option<Duration>,
+}
+
+/// Results of [`bench_batched`], of the columns of
+/// [`BATCHED_OUTPUT_COLUMNS`].
+///
+/// [`bench_batched`]: crate::proof_system::bench_batched
+/// [`BATCHED_OUTPUT_COLUMNS`]: crate::proof_system::BATCHED_OUTPUT_COLUMNS
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchedRecord {
+    pub batch_size: usize,
+    pub prove: Timing,
+    /// Timing of [`Self::prove`] per proof.
+    pub amortized_prove: Timing,
+    pub single_prove: Timing,
+}
+
+/// Results of [`bench_aggregated`], of the columns of
+/// [`AGGREGATED_OUTPUT_COLUMNS`].
+///
+/// [`bench_aggregated`]: crate::proof_system::bench_aggregated
+/// [`AGGREGATED_OUTPUT_COLUMNS`]: crate::proof_system::AGGREGATED_OUTPUT_COLUMNS
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregatedRecord {
+    pub num_circuits: usize,
+    pub prove: Timing,
+    pub verify: Duration,
+    pub proof_bytes: usize,
+    pub baseline_prove: Duration,
+    pub baseline_verify: Duration,
+    pub baseline_proof_bytes: usize,
+}
+
+/// Destination of the rows of the benchmarks, set by
+/// [`BenchOptions::sink`], e.g. to keep them in memory or send them to a
+/// database when embedding the benchmark in a larger tool. Benchmarks
+/// running in parallel share it, so rows of different systems interleave.
+///
+/// [`BenchOptions::sink`]: crate::proof_system::BenchOptions::sink
+pub trait OutputSink: Debug + Send + Sync {
+    /// Prepares the output of `system` in `mode` before the first row of a
+    /// run is written, which does nothing by default.
+    fn open(
+        &self,
+        _system: System,
+        _mode: ProofMode,
+        _format: OutputFormat,
+    ) -> Result<(), BenchError> {
+        Ok(())
+    }
+
+    fn write_row(&self, system: System, row: &BenchRow) -> Result<(), BenchError>;
+}
+
+/// [`OutputSink`] appending each row to the output file of its system and
+/// mode under [`output_dir`], created with its header by [`create_output_for`]
+/// if missing, which the benchmarks default to.
+///
+/// [`output_dir`]: crate::proof_system::output_dir
+/// [`create_output_for`]: crate::proof_system::create_output_for
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileSink {
+    /// See [`BenchOptions::sync_output`].
+    ///
+    /// [`BenchOptions::sync_output`]: crate::proof_system::BenchOptions::sync_output
+    pub sync: bool,
+}
+
+impl FileSink {
+    /// Returns the line of `row` of `system` in [`BenchRow::format`] without
+    /// the trailing newline, as written to the output of its mode.
+    pub fn line(system: System, row: &BenchRow) -> String {
+        let k = row.k;
+        let dataset = row.dataset.as_deref();
+        match (row.format, &row.record) {
+            (OutputFormat::Csv, BenchRecord::Single(record)) => format!(
+                "{k}, {}, {}, {}, {:.1}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}",
+                record.prove.mean.as_millis(),
+                record.prove.min.as_millis(),
+                record.prove.max.as_millis(),
+                as_millis_f64(record.prove.stddev),
+                record.prove.p50.as_millis(),
+                record.prove.p95.as_millis(),
+                record.prove.p99.as_millis(),
+                record.peak_mem_kb.unwrap_or(0),
+                csv_value(record.allocs.map(|allocs| allocs.bytes_allocated)),
+                csv_value(record.allocs.map(|allocs| allocs.alloc_count)),
+                record.verify.as_millis(),
+                record.verify_ops.field_reads,
+                record.verify_ops.commitment_reads,
+                record.verify_ops.absorbs,
+                record.verify_ops.challenges,
+                record.proof_bytes,
+                csv_value(record.triples_per_second.map(|tps| format!("{tps:.1}"))),
+                dataset.unwrap_or_default(),
+                csv_value(record.witness_gen.map(|time| time.as_millis())),
+                csv_value(record.preprocess.map(|time| time.as_millis())),
+                record.verify_ops.msms,
+                record.verify_ops.msm_bases,
+                record.verify_ops.pairing_checks,
+                record.verify_ops.pairings,
+            ),
+            (OutputFormat::Json, BenchRecord::Single(record)) => format!(
+                r#"{{"system":"{system}","k":{k},"prove_ms":{},"peak_mem_kb":{},"bytes_allocated":{},"alloc_count":{},"verify_ms":{},"verify_field_reads":{},"verify_commitment_reads":{},"verify_absorbs":{},"verify_challenges":{},"verify_msms":{},"verify_msm_bases":{},"verify_pairing_checks":{},"verify_pairings":{},"proof_bytes":{},"triples_per_second":{},"dataset":{},"witness_gen_ms":{},"preprocess_ms":{}}}"#,
+                record.prove.mean.as_millis(),
+                json_value(record.peak_mem_kb),
+                json_value(record.allocs.map(|allocs| allocs.bytes_allocated)),
+                json_value(record.allocs.map(|allocs| allocs.alloc_count)),
+                record.verify.as_millis(),
+                record.verify_ops.field_reads,
+                record.verify_ops.commitment_reads,
+                record.verify_ops.absorbs,
+                record.verify_ops.challenges,
+                record.verify_ops.msms,
+                record.verify_ops.msm_bases,
+                record.verify_ops.pairing_checks,
+                record.verify_ops.pairings,
+                record.proof_bytes,
+                json_value(record.triples_per_second.map(|tps| format!("{tps:.1}"))),
+                json_dataset(dataset),
+                json_value(record.witness_gen.map(|time| time.as_millis())),
+                json_value(record.preprocess.map(|time| time.as_millis())),
+            ),
+            (OutputFormat::Gnuplot, BenchRecord::Single(record)) => gnuplot_line(k, &record.prove),
+            (OutputFormat::Csv, BenchRecord::Batched(record)) => format!(
+                "{k}, {}, {}, {}, {}",
+                record.batch_size,
+                record.prove.mean.as_millis(),
+                record.amortized_prove.mean.as_millis(),
+                record.single_prove.mean.as_millis(),
+            ),
+            (OutputFormat::Json, BenchRecord::Batched(record)) => format!(
+                r#"{{"system":"{system}","k":{k},"batch_size":{},"prove_ms":{},"amortized_prove_ms":{},"single_prove_ms":{}}}"#,
+                record.batch_size,
+                record.prove.mean.as_millis(),
+                record.amortized_prove.mean.as_millis(),
+                record.single_prove.mean.as_millis(),
+            ),
+            (OutputFormat::Gnuplot, BenchRecord::Batched(record)) => {
+                gnuplot_line(k, &record.amortized_prove)
+            }
+            (OutputFormat::Csv, BenchRecord::Aggregated(record)) => format!(
+                "{k}, {}, {}, {}, {}, {}, {}, {}",
+                record.num_circuits,
+                record.prove.mean.as_millis(),
+                record.verify.as_millis(),
+                record.proof_bytes,
+                record.baseline_prove.as_millis(),
+                record.baseline_verify.as_millis(),
+                record.baseline_proof_bytes,
+            ),
+            (OutputFormat::Json, BenchRecord::Aggregated(record)) => format!(
+                r#"{{"system":"{system}","k":{k},"num_circuits":{},"prove_ms":{},"verify_ms":{},"proof_bytes":{},"baseline_prove_ms":{},"baseline_verify_ms":{},"baseline_proof_bytes":{}}}"#,
+                record.num_circuits,
+                record.prove.mean.as_millis(),
+                record.verify.as_millis(),
+                record.proof_bytes,
+                record.baseline_prove.as_millis(),
+                record.baseline_verify.as_millis(),
+                record.baseline_proof_bytes,
+            ),
+            (OutputFormat::Gnuplot, BenchRecord::Aggregated(record)) => {
+                gnuplot_line(k, &record.prove)
+            }
+            (format, BenchRecord::Timeout | BenchRecord::Interrupted) => {
+                let marker = match row.record {
+                    BenchRecord::Timeout => "timeout",
+                    _ => "interrupted",
+                };
+                match (format, dataset) {
+                    (OutputFormat::Csv, None) => format!("{k}, {marker}"),
+                    (OutputFormat::Csv, Some(dataset)) => format!("{k}, {marker}, {dataset}"),
+                    (OutputFormat::Json, _) => format!(
+                        r#"{{"system":"{system}","k":{k},"{marker}":true,"dataset":{}}}"#,
+                        json_dataset(dataset)
+                    ),
+                    (OutputFormat::Gnuplot, None) => format!("# {k} {marker}"),
+                    (OutputFormat::Gnuplot, Some(dataset)) => format!("# {k} {marker} {dataset}"),
+                }
+            }
+        }
+    }
+}
+
+impl OutputSink for FileSink {
+    fn open(
+        &self,
+        system: System,
+        mode: ProofMode,
+        format: OutputFormat,
+    ) -> Result<(), BenchError> {
+        ensure_output(system, mode, format)
+    }
+
+    fn write_row(&self, system: System, row: &BenchRow) -> Result<(), BenchError> {
+        system.append_row(row.mode(), &Self::line(system, row), self.sync)?;
+        Ok(())
+    }
+}
+
+/// Returns the row of [`GNUPLOT_OUTPUT_COLUMNS`] of `k` proven in `timing`.
+///
+/// [`GNUPLOT_OUTPUT_COLUMNS`]: crate::proof_system::GNUPLOT_OUTPUT_COLUMNS
+fn gnuplot_line(k: usize, timing: &Timing) -> String {
+    let [mean, stddev] = [timing.mean, timing.stddev].map(as_millis_f64);
+    format!("{k} {mean:.3} {:.3} {:.3}", mean - stddev, mean + stddev)
+}
+
+/// Returns `value`, or an empty column if `None`.
+fn csv_value(value: Option<impl ToString>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+/// Returns `value`, or `null` if `None`.
+fn json_value(value: Option<impl ToString>) -> String {
+    value.map_or_else(|| "null".to_string(), |value| value.to_string())
+}
+
+fn json_dataset(dataset: Option<&str>) -> String {
+    dataset.map_or_else(|| "null".to_string(), json_string)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::proof_system::{
+        test::test_output_dir, AggregatedRecord, BatchedRecord, BenchRecord, BenchRow, FileSink,
+        OutputFormat, OutputSink, ProofMode, SingleRecord, System, Timing, VerifyOps,
+    };
+    use std::{fs, time::Duration};
+
+    fn row(format: OutputFormat, dataset: Option<&str>, record: BenchRecord) -> BenchRow {
+        BenchRow {
+            format,
+            k: 4,
+            dataset: dataset.map(str::to_string),
+            record,
+        }
+    }
+
+    /// Returns the timing of a single prove of `millis`.
+    pub(crate) fn timing(millis: u64) -> Timing {
+        Timing::new(&[Duration::from_millis(millis)])
+    }
+
+    /// Returns a record of a prove of 12 ms without the optional columns.
+    pub(crate) fn single_record() -> SingleRecord {
+        SingleRecord {
+            prove: timing(12),
+            peak_mem_kb: None,
+            allocs: None,
+            verify: Duration::from_millis(3),
+            verify_ops: VerifyOps::default(),
+            proof_bytes: 1024,
+            triples_per_second: None,
+            witness_gen: None,
+            preprocess: None,
+        }
+    }
+
+    fn batched_record() -> BatchedRecord {
+        BatchedRecord {
+            batch_size: 4,
+            prove: timing(40),
+            amortized_prove: timing(10),
+            single_prove: timing(12),
+        }
+    }
+
+    fn aggregated_record() -> AggregatedRecord {
+        AggregatedRecord {
+            num_circuits: 2,
+            prove: timing(30),
+            verify: Duration::from_millis(5),
+            proof_bytes: 2048,
+            baseline_prove: Duration::from_millis(24),
+            baseline_verify: Duration::from_millis(6),
+            baseline_proof_bytes: 4096,
+        }
+    }
+
+    #[test]
+    fn csv_line_per_column() {
+        let records = [
+            BenchRecord::Single(single_record()),
+            BenchRecord::Batched(batched_record()),
+            BenchRecord::Aggregated(aggregated_record()),
+        ];
+        for record in records {
+            let row = row(OutputFormat::Csv, Some("lubm"), record);
+            let line = FileSink::line(System::HyperPlonk, &row);
+            let values = line.split(", ").count();
+            assert_eq!(values, row.mode().columns().len(), "{line}");
+        }
+
+        let row = row(
+            OutputFormat::Csv,
+            Some("lubm"),
+            BenchRecord::Single(single_record()),
+        );
+        let line = FileSink::line(System::HyperPlonk, &row);
+        let value = |column| {
+            let idx = ProofMode::Single
+                .columns()
+                .iter()
+                .position(|name| *name == column);
+            line.split(", ").nth(idx.unwrap()).unwrap()
+        };
+        assert_eq!(value("prove_ms"), "12");
+        assert_eq!(value("prove_stddev_ms"), "0.0");
+        assert_eq!(value("proof_bytes"), "1024");
+        assert_eq!(value("dataset"), "lubm");
+        // Unavailable columns are left empty, except `peak_mem_kb`.
+        assert_eq!(value("peak_mem_kb"), "0");
+        assert_eq!(value("bytes_allocated"), "");
+        assert_eq!(value("triples_per_second"), "");
+    }
+
+    #[test]
+    fn json_line() {
+        let line = |dataset, record| {
+            FileSink::line(
+                System::HyperPlonk,
+                &row(OutputFormat::Json, dataset, record),
+            )
+        };
+        let single = line(Some("lubm"), BenchRecord::Single(single_record()));
+        assert!(single.starts_with(r#"{"system":"hyperplonk","k":4,"prove_ms":12,"#));
+        assert!(single.contains(r#""peak_mem_kb":null,"#));
+        assert!(single.contains(r#""dataset":"lubm","#));
+        assert_eq!(
+            line(None, BenchRecord::Batched(batched_record())),
+            r#"{"system":"hyperplonk","k":4,"batch_size":4,"prove_ms":40,"amortized_prove_ms":10,"single_prove_ms":12}"#
+        );
+        assert_eq!(
+            line(None, BenchRecord::Timeout),
+            r#"{"system":"hyperplonk","k":4,"timeout":true,"dataset":null}"#
+        );
+        assert_eq!(
+            line(Some("a\"b"), BenchRecord::Interrupted),
+            r#"{"system":"hyperplonk","k":4,"interrupted":true,"dataset":"a\"b"}"#
+        );
+    }
+
+    #[test]
+    fn marker_lines() {
+        let line = |format, dataset, record| {
+            FileSink::line(System::HyperPlonk, &row(format, dataset, record))
+        };
+        assert_eq!(
+            line(OutputFormat::Csv, None, BenchRecord::Timeout),
+            "4, timeout"
+        );
+        assert_eq!(
+            line(OutputFormat::Csv, Some("lubm"), BenchRecord::Interrupted),
+            "4, interrupted, lubm"
+        );
+        assert_eq!(
+            line(OutputFormat::Gnuplot, None, BenchRecord::Interrupted),
+            "# 4 interrupted"
+        );
+        assert_eq!(
+            line(OutputFormat::Gnuplot, Some("lubm"), BenchRecord::Timeout),
+            "# 4 timeout lubm"
+        );
+        assert_eq!(
+            row(OutputFormat::Csv, None, BenchRecord::Timeout).mode(),
+            ProofMode::Single
+        );
+    }
+
+    #[test]
+    fn gnuplot_line_within_stddev() {
+        let record = BatchedRecord {
+            amortized_prove: Timing::new(&[1, 3].map(Duration::from_secs)),
+            ..batched_record()
+        };
+        let row = row(OutputFormat::Gnuplot, None, BenchRecord::Batched(record));
+        assert_eq!(
+            FileSink::line(System::HyperPlonk, &row),
+            "4 2000.000 585.786 3414.214"
+        );
+    }
+
+    #[test]
+    fn file_sink_appends_rows() {
+        let system = System::Plonk;
+        let path = test_output_dir().join(format!("{system}_aggregated"));
+        let row = row(
+            OutputFormat::Csv,
+            None,
+            BenchRecord::Aggregated(aggregated_record()),
+        );
+        let sink = FileSink::default();
+        let written = sink
+            .open(system, ProofMode::Aggregated, OutputFormat::Csv)
+            .and_then(|()| sink.write_row(system, &row))
+            .and_then(|()| sink.write_row(system, &row));
+        let content = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+
+        written.unwrap();
+        let line = FileSink::line(system, &row);
+        assert_eq!(
+            content.unwrap(),
+            format!(
+                "{}\n{line}\n{line}\n",
+                ProofMode::Aggregated.columns().join(",")
+            )
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::proof_system::{in_phase, BenchError, Param, Phase, System};
+use plonkish_backend::{
+    backend::{
+        hyperplonk::HyperPlonk, plonk::Plonk, unihyperplonk::UniHyperPlonk, PlonkishBackend,
+        PlonkishCircuitInfo,
+    },
+    halo2_curves::{bn256::Fr, pairing::MultiMillerLoop},
+    pcs::{
+        multilinear::{MultilinearKzg, MultilinearKzgParam},
+        univariate::{UnivariateKzg, UnivariateKzgParam},
+        PolynomialCommitmentScheme,
+    },
+    Error,
+};
+use std::{fs, path::Path, sync::Once};
+
+/// Backend whose `param` can be read from the structured reference string of
+/// a trusted setup instead of running [`PlonkishBackend::setup`], see
+/// [`BenchOptions::srs_path`].
+///
+/// Backends reading another SRS format, e.g. the transcript of a ceremony,
+/// override [`Self::load_srs`].
+///
+/// [`BenchOptions::srs_path`]: crate::proof_system::BenchOptions::srs_path
+pub trait TrustedSetup: PlonkishBackend<Fr> {
+    /// Reads the `param` for `circuit_info` from the SRS at `path`, which by
+    /// default is the bincode serialized `param` of the backend's PCS.
+    fn load_srs(
+        path: &Path,
+        circuit_info: &PlonkishCircuitInfo<Fr>,
+    ) -> Result<Param<Self>, BenchError> {
+        let _ = circuit_info;
+        let param = bincode::deserialize(&fs::read(path)?)
+            .map_err(|err| Error::Serialization(err.to_string()))?;
+        Ok(param)
+    }
+
+    /// Returns the maximum degree of the polynomials `param` commits to, see
+    /// [`SrsDegree`].
+    fn srs_degree(param: &Param<Self>) -> usize;
+}
+
+impl<Pcs: SrsDegree> TrustedSetup for HyperPlonk<Pcs>
+where
+    Self: PlonkishBackend<Fr, Pcs = Pcs>,
+{
+    fn srs_degree(param: &Pcs::Param) -> usize {
+        Pcs::srs_degree(param)
+    }
+}
+
+impl<Pcs: SrsDegree> TrustedSetup for UniHyperPlonk<Pcs, true>
+where
+    Self: PlonkishBackend<Fr, Pcs = Pcs>,
+{
+    fn srs_degree(param: &Pcs::Param) -> usize {
+        Pcs::srs_degree(param)
+    }
+}
+
+impl<Pcs: SrsDegree> TrustedSetup for Plonk<Pcs>
+where
+    Self: PlonkishBackend<Fr, Pcs = Pcs>,
+{
+    fn srs_degree(param: &Pcs::Param) -> usize {
+        Pcs::srs_degree(param)
+    }
+}
+
+/// PCS whose `param` commits to polynomials up to some degree, which
+/// [`bench_plonkish_backend`] checks against the [`min_srs_degree`] of the
+/// circuit before preprocessing rather than failing somewhere in it.
+///
+/// [`bench_plonkish_backend`]: crate::proof_system::bench_plonkish_backend
+pub trait SrsDegree: PolynomialCommitmentScheme<Fr> {
+    /// Returns the maximum degree of a polynomial `param` commits to, where a
+    /// multilinear polynomial of `2^n` evaluations counts as of degree
+    /// `2^n - 1`, as the univariate one of as many coefficients.
+    fn srs_degree(param: &Self::Param) -> usize;
+}
+
+impl<M: MultiMillerLoop> SrsDegree for MultilinearKzg<M>
+where
+    Self: PolynomialCommitmentScheme<Fr, Param = MultilinearKzgParam<M>>,
+{
+    fn srs_degree(param: &MultilinearKzgParam<M>) -> usize {
+        (1 << param.num_vars()) - 1
+    }
+}
+
+impl<M: MultiMillerLoop> SrsDegree for UnivariateKzg<M>
+where
+    Self: PolynomialCommitmentScheme<Fr, Param = UnivariateKzgParam<M>>,
+{
+    fn srs_degree(param: &UnivariateKzgParam<M>) -> usize {
+        param.degree()
+    }
+}
+
+/// Returns the minimum degree of an SRS for `circuit_info`, whose
+/// polynomials have `2^k` evaluations.
+pub fn min_srs_degree(circuit_info: &PlonkishCircuitInfo<Fr>) -> usize {
+    (1 << circuit_info.k) - 1
+}
+
+/// Fails with [`BenchError::SrsTooSmall`] if `param` of backend `B` doesn't
+/// reach the [`min_srs_degree`] of `circuit_info`, e.g. when read from an SRS
+/// of a smaller `k` or passed for a smaller circuit.
+pub(super) fn check_srs_degree<B: TrustedSetup>(
+    param: &Param<B>,
+    circuit_info: &PlonkishCircuitInfo<Fr>,
+) -> Result<(), BenchError> {
+    let (have, need) = (B::srs_degree(param), min_srs_degree(circuit_info));
+    match have < need {
+        true => Err(BenchError::SrsTooSmall { have, need }),
+        false => Ok(()),
+    }
+}
+
+/// Reads `param` of backend `B` for `circuit_info` from the SRS at `path`,
+/// timed as the setup phase.
+pub(super) fn load_param<B: TrustedSetup>(
+    system: System,
+    k: usize,
+    path: &Path,
+    circuit_info: &PlonkishCircuitInfo<Fr>,
+) -> Result<Param<B>, BenchError> {
+    in_phase(system, Phase::Setup, k, || B::load_srs(path, circuit_info))
+}
+
+/// Warns once per process that `param` comes from [`PlonkishBackend::setup`]
+/// with a known RNG, whose trapdoor anyone replaying the RNG can recover.
+pub(super) fn warn_insecure_setup() {
+    static WARNING: Once = Once::new();
+    WARNING.call_once(|| {
+        eprintln!(
+            "Warning: running setup with a known RNG, which is insecure outside of benchmarks; \
+             pass a trusted-setup SRS to use one instead"
+        )
+    });
+}
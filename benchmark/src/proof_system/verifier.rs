@@ -0,0 +1,372 @@
+use crate::proof_system::BenchError;
+use plonkish_backend::{
+    backend::{
+        hyperplonk::HyperPlonkVerifierParam, plonk::PlonkVerifierParam,
+        unihyperplonk::UniHyperPlonkVerifierParam, PlonkishBackend, PlonkishCircuit,
+    },
+    halo2_curves::bn256::{Bn256, Fr},
+    pcs::{CommitmentChunk, PolynomialCommitmentScheme},
+    util::{
+        arithmetic::{defer_pairings, MultiMillerLoop},
+        izip,
+        test::std_rng,
+        transcript::{InMemoryTranscript, Keccak256Transcript, TranscriptRead, TranscriptWrite},
+        Itertools,
+    },
+    Error,
+};
+use rand::{rngs::OsRng, RngCore};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Cursor, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Verifier param exposing the number of instance values it expects in each
+/// instance polynomial, which [`verify_proof_file`] checks up front since the
+/// backends panic on a mismatch.
+pub trait VerifierParamInstances {
+    fn num_instances(&self) -> &[usize];
+}
+
+impl<Pcs: PolynomialCommitmentScheme<Fr>> VerifierParamInstances
+    for HyperPlonkVerifierParam<Fr, Pcs>
+{
+    fn num_instances(&self) -> &[usize] {
+        HyperPlonkVerifierParam::num_instances(self)
+    }
+}
+
+impl<Pcs: PolynomialCommitmentScheme<Fr>> VerifierParamInstances
+    for UniHyperPlonkVerifierParam<Fr, Pcs>
+{
+    fn num_instances(&self) -> &[usize] {
+        HyperPlonkVerifierParam::num_instances(self)
+    }
+}
+
+impl<Pcs: PolynomialCommitmentScheme<Fr>> VerifierParamInstances for PlonkVerifierParam<Fr, Pcs> {
+    fn num_instances(&self) -> &[usize] {
+        PlonkVerifierParam::num_instances(self)
+    }
+}
+
+/// Verifies the proof at `proof_path` against `instances` with the bincode
+/// serialized `vp` at `vp_path`, without setup, preprocess or prove, e.g. on
+/// a client apart from the prover. Returns whether backend `B` accepts the
+/// proof together with the time [`PlonkishBackend::verify`] took.
+///
+/// Fails with [`BenchError::InstanceMismatch`] if `instances` aren't shaped
+/// as `vp` expects, and with [`BenchError::TruncatedProof`] if the proof ends
+/// before the verifier is done reading it.
+pub fn verify_proof_file<B>(
+    vp_path: &Path,
+    instances: &[Vec<Fr>],
+    proof_path: &Path,
+) -> Result<(bool, Duration), BenchError>
+where
+    B: PlonkishBackend<Fr>,
+    B::VerifierParam: VerifierParamInstances,
+    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    let vp = bincode::deserialize::<B::VerifierParam>(&fs::read(vp_path)?)
+        .map_err(|err| Error::Serialization(err.to_string()))?;
+    let found = instances.iter().map(Vec::len).collect::<Vec<_>>();
+    if found != vp.num_instances() {
+        return Err(BenchError::InstanceMismatch {
+            expected: vp.num_instances().to_vec(),
+            found,
+        });
+    }
+
+    let proof = fs::read(proof_path)?;
+    let mut transcript = Keccak256Transcript::from_proof((), proof.as_slice());
+    let start = Instant::now();
+    let result = B::verify(&vp, instances, &mut transcript, std_rng());
+    let verify_time = start.elapsed();
+    match result {
+        Ok(()) => Ok((true, verify_time)),
+        Err(Error::Transcript(io::ErrorKind::UnexpectedEof, _)) => {
+            Err(BenchError::TruncatedProof {
+                proof_path: proof_path.to_path_buf(),
+                num_bytes: proof.len(),
+            })
+        }
+        Err(_) => Ok((false, verify_time)),
+    }
+}
+
+/// Verifies each of `proofs` against its instances with the same `vp`, e.g.
+/// of many datasets queried by one query circuit shape, returning whether
+/// backend `B` accepts each in order so a caller can tell which failed.
+/// Instances not shaped as `vp` expects, or a proof ending before the
+/// verifier is done reading it, fail only that proof.
+///
+/// The pairing checks of every proof are deferred with [`defer_pairings`] and
+/// checked at once by [`MultiMillerLoop::pairing_checks_hold`], a random
+/// linear combination from [`OsRng`] of them, so the final exponentiation is
+/// paid once rather than per proof. If the combination fails, the proofs
+/// accepted up to their pairings are verified again with
+/// [`verify_serially`] to tell which failed.
+pub fn verify_batched<B>(vp: &B::VerifierParam, proofs: &[(&[Vec<Fr>], &[u8])]) -> Vec<bool>
+where
+    B: PlonkishBackend<Fr>,
+    B::VerifierParam: VerifierParamInstances,
+    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    let (mut accepted, checks): (Vec<_>, Vec<_>) = proofs
+        .iter()
+        .map(|(instances, proof)| defer_pairings::<Bn256, _>(|| verify::<B>(vp, instances, proof)))
+        .unzip();
+    let checks = izip!(&accepted, checks)
+        .filter(|(accepted, _)| **accepted)
+        .flat_map(|(_, checks)| checks)
+        .collect_vec();
+    if !Bn256::pairing_checks_hold(&checks, OsRng) {
+        for (accepted, (instances, proof)) in accepted.iter_mut().zip(proofs) {
+            *accepted = *accepted && verify::<B>(vp, instances, proof);
+        }
+    }
+    accepted
+}
+
+/// Verifies each of `proofs` against its instances with the same `vp` as
+/// [`verify_batched`] does, but each on its own, one after another, including
+/// its pairing checks.
+pub fn verify_serially<B>(vp: &B::VerifierParam, proofs: &[(&[Vec<Fr>], &[u8])]) -> Vec<bool>
+where
+    B: PlonkishBackend<Fr>,
+    B::VerifierParam: VerifierParamInstances,
+    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    proofs
+        .iter()
+        .map(|(instances, proof)| verify::<B>(vp, instances, proof))
+        .collect()
+}
+
+/// Returns whether `B` accepts `proof` of `instances`, reading it from a
+/// fresh transcript, or `false` if `instances` aren't shaped as `vp` expects.
+fn verify<B>(vp: &B::VerifierParam, instances: &[Vec<Fr>], proof: &[u8]) -> bool
+where
+    B: PlonkishBackend<Fr>,
+    B::VerifierParam: VerifierParamInstances,
+    Keccak256Transcript<Cursor<Vec<u8>>>: TranscriptRead<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    if !instances
+        .iter()
+        .map(Vec::len)
+        .eq(vp.num_instances().iter().copied())
+    {
+        return false;
+    }
+    let mut transcript = Keccak256Transcript::from_proof((), proof);
+    B::verify(vp, instances, &mut transcript, std_rng()).is_ok()
+}
+
+/// Proves `circuit` with backend `B`, streaming the proof to a new file at
+/// `proof_path` through a buffered writer as the prover produces it instead
+/// of collecting it in memory first, for a proof only wanted on disk, e.g. to
+/// be checked later by [`verify_proof_file`]. Returns the number of bytes
+/// written.
+///
+/// The benchmarks keep proving into memory, since they verify the proof right
+/// away.
+pub fn prove_to_file<B>(
+    pp: &B::ProverParam,
+    circuit: &impl PlonkishCircuit<Fr>,
+    proof_path: &Path,
+    rng: impl RngCore,
+) -> Result<u64, BenchError>
+where
+    B: PlonkishBackend<Fr>,
+    Keccak256Transcript<BufWriter<File>>: TranscriptWrite<CommitmentChunk<Fr, B::Pcs>, Fr>,
+{
+    let file = File::create(proof_path)?;
+    let mut transcript = Keccak256Transcript::from_stream(BufWriter::new(file));
+    B::prove(pp, circuit, &mut transcript, rng)?;
+    let mut writer = transcript.into_stream();
+    writer.flush()?;
+    Ok(writer.get_ref().metadata()?.len())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::proof_system::{
+        test::vanilla_plonk,
+        verifier::{prove_to_file, verify, verify_batched, verify_proof_file, verify_serially},
+        BenchError,
+    };
+    use plonkish_backend::{
+        backend::{hyperplonk::HyperPlonk, PlonkishBackend, PlonkishCircuit},
+        halo2_curves::bn256::{Bn256, Fr},
+        pcs::multilinear::MultilinearKzg,
+        util::{
+            arithmetic::{defer_pairings, MultiMillerLoop},
+            test::std_rng,
+            transcript::{InMemoryTranscript, Keccak256Transcript},
+        },
+    };
+    use rand::rngs::OsRng;
+    use std::{env, fs, slice, time::Instant};
+
+    type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+
+    /// Instances of a proof with the proof.
+    type Proof = (Vec<Vec<Fr>>, Vec<u8>);
+
+    /// Returns the vp and the instances and proofs of `num_proofs` circuits
+    /// of `2^k` rows of the same shape.
+    fn prove(
+        k: usize,
+        num_proofs: u64,
+    ) -> (<Pb as PlonkishBackend<Fr>>::VerifierParam, Vec<Proof>) {
+        let circuits = (0..num_proofs)
+            .map(|seed| vanilla_plonk(k, seed))
+            .collect::<Vec<_>>();
+        let circuit_info = circuits[0].circuit_info().unwrap();
+        let param = Pb::setup(&circuit_info, std_rng()).unwrap();
+        let (pp, vp) = Pb::preprocess(&param, &circuit_info).unwrap();
+        let proofs = circuits
+            .iter()
+            .map(|circuit| {
+                let mut transcript = Keccak256Transcript::new(());
+                Pb::prove(&pp, circuit, &mut transcript, std_rng()).unwrap();
+                (circuit.instances().to_vec(), transcript.into_proof())
+            })
+            .collect();
+        (vp, proofs)
+    }
+
+    fn borrowed(proofs: &[Proof]) -> Vec<(&[Vec<Fr>], &[u8])> {
+        proofs
+            .iter()
+            .map(|(instances, proof)| (instances.as_slice(), proof.as_slice()))
+            .collect()
+    }
+
+    #[test]
+    fn batched_rejects_only_failing_proofs() {
+        let (vp, mut proofs) = prove(4, 4);
+        assert_eq!(verify_batched::<Pb>(&vp, &borrowed(&proofs)), [true; 4]);
+        assert_eq!(verify_batched::<Pb>(&vp, &[]), [] as [bool; 0]);
+
+        // A proof only failing its pairing checks, to fall back to verifying
+        // serially, with the last two quotients of its opening proof, the
+        // last two points of 64 bytes, swapped.
+        let (instances, proof) = &mut proofs[1];
+        let len = proof.len();
+        let (quotients, last) = proof[len - 128..].split_at_mut(64);
+        quotients.swap_with_slice(last);
+        let (accepted, _) = defer_pairings::<Bn256, _>(|| verify::<Pb>(&vp, instances, proof));
+        assert!(accepted);
+        proofs[2].0.push(Vec::new());
+        let expected = [true, false, false, true];
+        assert_eq!(verify_serially::<Pb>(&vp, &borrowed(&proofs)), expected);
+        assert_eq!(verify_batched::<Pb>(&vp, &borrowed(&proofs)), expected);
+    }
+
+    #[test]
+    fn proof_file_errors() {
+        let (vp, proofs) = prove(4, 1);
+        let (instances, proof) = &proofs[0];
+        let dir = env::temp_dir().join(format!("bench-proof-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let vp_path = dir.join("vp");
+        fs::write(&vp_path, bincode::serialize(&vp).unwrap()).unwrap();
+        let verify_file = |instances: &[Vec<Fr>], proof: &[u8]| {
+            let proof_path = dir.join("proof");
+            fs::write(&proof_path, proof).unwrap();
+            verify_proof_file::<Pb>(&vp_path, instances, &proof_path)
+        };
+
+        let accepted = verify_file(instances, proof);
+        let mut mutated = proof.clone();
+        mutated[0] ^= 1;
+        let rejected = verify_file(instances, &mutated);
+        let truncated = verify_file(instances, &proof[..proof.len() - 1]);
+        let mut extra = instances.clone();
+        extra[0].push(Fr::from(0));
+        let mismatched = verify_file(&extra, proof);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(accepted, Ok((true, _))));
+        assert!(matches!(rejected, Ok((false, _))));
+        assert!(matches!(
+            truncated,
+            Err(BenchError::TruncatedProof { proof_path, num_bytes })
+                if proof_path == dir.join("proof") && num_bytes == proof.len() - 1
+        ));
+        assert!(matches!(
+            mismatched,
+            Err(BenchError::InstanceMismatch { expected, found })
+                if expected == [instances[0].len()] && found == [instances[0].len() + 1]
+        ));
+    }
+
+    #[test]
+    fn prove_to_file_as_in_memory() {
+        let circuit = vanilla_plonk(4, 0);
+        let circuit_info = circuit.circuit_info().unwrap();
+        let param = Pb::setup(&circuit_info, std_rng()).unwrap();
+        let (pp, vp) = Pb::preprocess(&param, &circuit_info).unwrap();
+        let proof = {
+            let mut transcript = Keccak256Transcript::new(());
+            Pb::prove(&pp, &circuit, &mut transcript, std_rng()).unwrap();
+            transcript.into_proof()
+        };
+        let dir = env::temp_dir().join(format!("bench-prove-to-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let [vp_path, proof_path] = ["vp", "proof"].map(|name| dir.join(name));
+        fs::write(&vp_path, bincode::serialize(&vp).unwrap()).unwrap();
+
+        let num_bytes = prove_to_file::<Pb>(&pp, &circuit, &proof_path, std_rng());
+        let written = fs::read(&proof_path);
+        let verified = verify_proof_file::<Pb>(&vp_path, circuit.instances(), &proof_path);
+        let missing_dir = prove_to_file::<Pb>(&pp, &circuit, &dir.join("a/proof"), std_rng());
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Streamed with the same rng, the proof is the one proved in memory.
+        assert_eq!(num_bytes.unwrap(), proof.len() as u64);
+        assert_eq!(written.unwrap(), proof);
+        assert!(matches!(verified, Ok((true, _))));
+        assert!(matches!(missing_dir, Err(BenchError::Io(_))));
+    }
+
+    /// Times [`verify_batched`] against [`verify_serially`], and the pairing
+    /// checks they defer checked at once against each on its own, run with
+    /// `cargo test --release -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_batched_against_serial() {
+        let (vp, proofs) = prove(10, 16);
+        let proofs = borrowed(&proofs);
+        let time = |verify: &dyn Fn() -> bool| {
+            let start = Instant::now();
+            assert!(verify());
+            start.elapsed()
+        };
+        let accepted = |accepted: Vec<bool>| accepted.into_iter().all(|accepted| accepted);
+        let serial = time(&|| accepted(verify_serially::<Pb>(&vp, &proofs)));
+        let batched = time(&|| accepted(verify_batched::<Pb>(&vp, &proofs)));
+        println!("verify: serial {serial:?}, batched {batched:?}");
+
+        let checks = proofs
+            .iter()
+            .flat_map(|(instances, proof)| {
+                defer_pairings::<Bn256, _>(|| verify::<Pb>(&vp, instances, proof)).1
+            })
+            .collect::<Vec<_>>();
+        let serial = time(&|| {
+            checks
+                .iter()
+                .all(|check| Bn256::pairing_checks_hold(slice::from_ref(check), OsRng))
+        });
+        let batched = time(&|| Bn256::pairing_checks_hold(&checks, OsRng));
+        println!(
+            "{} pairing checks: serial {serial:?}, batched {batched:?}",
+            checks.len()
+        );
+    }
+}
@@ -13,6 +13,7 @@ use rand::RngCore;
 use std::{collections::BTreeSet, fmt::Debug};
 
 pub mod hyperplonk;
+pub mod plonk;
 pub mod unihyperplonk;
 
 pub trait PlonkishBackend<F: Field>: Clone + Debug {
@@ -152,6 +153,7 @@ mod mock {
         Error,
     };
 
+    #[derive(Clone)]
     pub(crate) struct MockCircuit<F> {
         instances: Vec<Vec<F>>,
         witnesses: Vec<Vec<F>>,
@@ -194,11 +196,12 @@ pub(crate) mod test {
         util::{
             arithmetic::PrimeField,
             end_timer, start_timer,
-            test::seeded_std_rng,
+            test::{rand_idx, seeded_std_rng, std_rng},
             transcript::{InMemoryTranscript, TranscriptRead, TranscriptWrite},
             DeserializeOwned, Serialize,
         },
     };
+    use rand::RngCore;
     use std::{hash::Hash, ops::Range};
 
     pub fn run_plonkish_backend<F, Pb, T, C>(
@@ -241,4 +244,56 @@ pub(crate) mod test {
             end_timer(timer);
         }
     }
+
+    /// Proves the circuit of `circuit_fn` at `num_vars` and asserts that each
+    /// of `num_mutations` copies of the proof, with a random bit flipped or a
+    /// random byte replaced, is rejected, all from a fixed seed.
+    ///
+    /// Every byte of a proof of `T` is read by the verifier, and a field
+    /// element or point that is non-canonical after a mutation fails to be
+    /// read, so no mutation lands in unused padding. A mutation that is
+    /// accepted isn't expected and is a soundness bug to investigate.
+    pub fn run_mutated_proofs<F, Pb, T, C>(
+        num_vars: usize,
+        num_mutations: usize,
+        circuit_fn: impl Fn(usize) -> (PlonkishCircuitInfo<F>, C),
+    ) where
+        F: PrimeField + Hash + Serialize + DeserializeOwned,
+        Pb: PlonkishBackend<F>,
+        T: TranscriptRead<<Pb::Pcs as PolynomialCommitmentScheme<F>>::CommitmentChunk, F>
+            + TranscriptWrite<<Pb::Pcs as PolynomialCommitmentScheme<F>>::CommitmentChunk, F>
+            + InMemoryTranscript<Param = ()>,
+        C: PlonkishCircuit<F>,
+    {
+        let (circuit_info, circuit) = circuit_fn(num_vars);
+        let instances = circuit.instances();
+        let param = Pb::setup(&circuit_info, std_rng()).unwrap();
+        let (pp, vp) = Pb::preprocess(&param, &circuit_info).unwrap();
+        let proof = {
+            let mut transcript = T::new(());
+            Pb::prove(&pp, &circuit, &mut transcript, std_rng()).unwrap();
+            transcript.into_proof()
+        };
+        let verify = |proof: &[u8]| {
+            let mut transcript = T::from_proof((), proof);
+            Pb::verify(&vp, instances, &mut transcript, std_rng())
+        };
+        assert_eq!(verify(&proof), Ok(()));
+
+        let mut rng = std_rng();
+        for _ in 0..num_mutations {
+            let mut mutated = proof.clone();
+            let idx = rand_idx(0..proof.len(), &mut rng);
+            match rng.next_u32() % 2 {
+                0 => mutated[idx] ^= 1 << (rng.next_u32() % 8),
+                _ => mutated[idx] = mutated[idx].wrapping_add(1 + (rng.next_u32() % 255) as u8),
+            }
+            assert!(
+                verify(&mutated).is_err(),
+                "mutation of byte {idx} of {} to {:#04x} accepted",
+                proof.len(),
+                mutated[idx]
+            );
+        }
+    }
 }
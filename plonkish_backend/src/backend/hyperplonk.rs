@@ -10,7 +10,7 @@ use crate::{
         },
         PlonkishBackend, PlonkishCircuit, PlonkishCircuitInfo, WitnessEncoding,
     },
-    pcs::PolynomialCommitmentScheme,
+    pcs::{Evaluation, PolynomialCommitmentScheme},
     poly::multilinear::MultilinearPolynomial,
     util::{
         arithmetic::{powers, PrimeField},
@@ -76,6 +76,17 @@ where
     pub(crate) permutation_comms: Vec<(usize, Pcs::Commitment)>,
 }
 
+impl<F, Pcs> HyperPlonkVerifierParam<F, Pcs>
+where
+    F: PrimeField,
+    Pcs: PolynomialCommitmentScheme<F>,
+{
+    /// Returns the number of instance values in each instance polynomial.
+    pub fn num_instances(&self) -> &[usize] {
+        &self.num_instances
+    }
+}
+
 impl<F, Pcs> PlonkishBackend<F> for HyperPlonk<Pcs>
 where
     F: PrimeField + Hash + Serialize + DeserializeOwned,
@@ -113,198 +124,456 @@ where
         transcript: &mut impl TranscriptWrite<Pcs::CommitmentChunk, F>,
         _: impl RngCore,
     ) -> Result<(), Error> {
-        let instance_polys = {
-            let instances = circuit.instances();
-            for (num_instances, instances) in pp.num_instances.iter().zip_eq(instances) {
-                assert_eq!(instances.len(), *num_instances);
-                for instance in instances.iter() {
-                    transcript.common_field_element(instance)?;
-                }
-            }
-            instance_polys::<_, BinaryField>(pp.num_vars, instances)
-        };
-
-        // Round 0..n
+        let opening = prove_until_opening(pp, circuit, transcript)?;
 
-        let mut witness_polys = Vec::with_capacity(pp.num_witness_polys.iter().sum());
-        let mut witness_comms = Vec::with_capacity(witness_polys.len());
-        let mut challenges = Vec::with_capacity(pp.num_challenges.iter().sum::<usize>() + 4);
-        for (round, (num_witness_polys, num_challenges)) in pp
-            .num_witness_polys
-            .iter()
-            .zip_eq(pp.num_challenges.iter())
-            .enumerate()
-        {
-            let timer = start_timer(|| format!("witness_collector-{round}"));
-            let polys = circuit
-                .synthesize(round, &challenges)?
-                .into_iter()
-                .map(MultilinearPolynomial::new)
-                .collect_vec();
-            assert_eq!(polys.len(), *num_witness_polys);
-            end_timer(timer);
-
-            witness_comms.extend(Pcs::batch_commit_and_write(&pp.pcs, &polys, transcript)?);
-            witness_polys.extend(polys);
-            challenges.extend(transcript.squeeze_challenges(*num_challenges));
-        }
-        let polys = chain![&instance_polys, &pp.preprocess_polys, &witness_polys].collect_vec();
+        // PCS open
 
-        // Round n
+        let dummy_comm = Pcs::Commitment::default();
+        let timer = start_timer(|| format!("pcs_batch_open-{}", opening.evals.len()));
+        Pcs::batch_open(
+            &pp.pcs,
+            opening.polys(pp),
+            opening.comms(pp, &dummy_comm),
+            &opening.points,
+            &opening.evals,
+            transcript,
+        )?;
+        end_timer(timer);
 
-        let beta = transcript.squeeze_challenge();
+        Ok(())
+    }
 
-        let timer = start_timer(|| format!("lookup_compressed_polys-{}", pp.lookups.len()));
-        let lookup_compressed_polys = {
-            let max_lookup_width = pp.lookups.iter().map(Vec::len).max().unwrap_or_default();
-            let betas = powers(beta).take(max_lookup_width).collect_vec();
-            lookup_compressed_polys::<_, BinaryField>(&pp.lookups, &polys, &challenges, &betas)
-        };
-        end_timer(timer);
+    fn verify(
+        vp: &Self::VerifierParam,
+        instances: &[Vec<F>],
+        transcript: &mut impl TranscriptRead<Pcs::CommitmentChunk, F>,
+        _: impl RngCore,
+    ) -> Result<(), Error> {
+        let opening = verify_until_opening(vp, instances, transcript)?;
 
-        let timer = start_timer(|| format!("lookup_m_polys-{}", pp.lookups.len()));
-        let lookup_m_polys = lookup_m_polys(&lookup_compressed_polys)?;
-        end_timer(timer);
+        // PCS verify
 
-        let lookup_m_comms = Pcs::batch_commit_and_write(&pp.pcs, &lookup_m_polys, transcript)?;
+        let dummy_comm = Pcs::Commitment::default();
+        Pcs::batch_verify(
+            &vp.pcs,
+            opening.comms(vp, &dummy_comm),
+            &opening.points,
+            &opening.evals,
+            transcript,
+        )?;
 
-        // Round n+1
+        Ok(())
+    }
+}
 
-        let gamma = transcript.squeeze_challenge();
+impl<Pcs> HyperPlonk<Pcs> {
+    /// Proves `circuits`, each preprocessed into the `pp` of same index, one
+    /// after the other in `transcript` but with a single PCS opening of the
+    /// polynomials of all of them, so verifying them takes one
+    /// [`PolynomialCommitmentScheme::batch_verify`] instead of one per
+    /// circuit. The circuits are expected to be preprocessed from the same
+    /// `param` at the same `k`, which also has to fit the batch of all their
+    /// polynomials for a PCS whose setup depends on the batch size.
+    pub fn prove_aggregated<F>(
+        pps: &[HyperPlonkProverParam<F, Pcs>],
+        circuits: &[impl PlonkishCircuit<F>],
+        transcript: &mut impl TranscriptWrite<Pcs::CommitmentChunk, F>,
+    ) -> Result<(), Error>
+    where
+        F: PrimeField + Hash,
+        Pcs: PolynomialCommitmentScheme<F, Polynomial = MultilinearPolynomial<F>>,
+    {
+        check_aggregated(pps.iter().map(|pp| pp.num_vars), circuits.len())?;
+        let openings = pps
+            .iter()
+            .zip(circuits)
+            .map(|(pp, circuit)| prove_until_opening(pp, circuit, transcript))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let timer = start_timer(|| format!("lookup_h_polys-{}", pp.lookups.len()));
-        let lookup_h_polys = lookup_h_polys(&lookup_compressed_polys, &lookup_m_polys, &gamma);
-        end_timer(timer);
+        // PCS open
 
-        let timer = start_timer(|| format!("permutation_z_polys-{}", pp.permutation_polys.len()));
-        let permutation_z_polys = permutation_z_polys::<_, BinaryField>(
-            pp.num_permutation_z_polys,
-            &pp.permutation_polys,
-            &polys,
-            &beta,
-            &gamma,
+        let dummy_comm = Pcs::Commitment::default();
+        let polys = pps
+            .iter()
+            .zip(&openings)
+            .flat_map(|(pp, opening)| opening.polys(pp))
+            .collect_vec();
+        let comms = pps
+            .iter()
+            .zip(&openings)
+            .flat_map(|(pp, opening)| opening.comms(pp, &dummy_comm))
+            .collect_vec();
+        let (points, evals) = aggregated_evals(
+            openings
+                .iter()
+                .zip(pps)
+                .map(|(opening, pp)| (opening.num_polys(pp), &opening.points, &opening.evals)),
         );
+        let timer = start_timer(|| format!("pcs_batch_open-{}", evals.len()));
+        Pcs::batch_open(&pps[0].pcs, polys, comms, &points, &evals, transcript)?;
         end_timer(timer);
 
-        let lookup_h_permutation_z_polys =
-            chain![lookup_h_polys.iter(), permutation_z_polys.iter()].collect_vec();
-        let lookup_h_permutation_z_comms =
-            Pcs::batch_commit_and_write(&pp.pcs, lookup_h_permutation_z_polys.clone(), transcript)?;
+        Ok(())
+    }
 
-        // Round n+2
+    /// Verifies the proof of [`Self::prove_aggregated`], with the `vp` and
+    /// instances of each circuit.
+    pub fn verify_aggregated<F>(
+        vps: &[HyperPlonkVerifierParam<F, Pcs>],
+        instances: &[&[Vec<F>]],
+        transcript: &mut impl TranscriptRead<Pcs::CommitmentChunk, F>,
+    ) -> Result<(), Error>
+    where
+        F: PrimeField,
+        Pcs: PolynomialCommitmentScheme<F, Polynomial = MultilinearPolynomial<F>>,
+    {
+        check_aggregated(vps.iter().map(|vp| vp.num_vars), instances.len())?;
+        let openings = vps
+            .iter()
+            .zip(instances)
+            .map(|(vp, instances)| verify_until_opening(vp, instances, transcript))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let alpha = transcript.squeeze_challenge();
-        let y = transcript.squeeze_challenges(pp.num_vars);
+        // PCS verify
+
+        let dummy_comm = Pcs::Commitment::default();
+        let comms = vps
+            .iter()
+            .zip(&openings)
+            .flat_map(|(vp, opening)| opening.comms(vp, &dummy_comm))
+            .collect_vec();
+        let (points, evals) = aggregated_evals(
+            openings
+                .iter()
+                .zip(vps)
+                .map(|(opening, vp)| (opening.num_polys(vp), &opening.points, &opening.evals)),
+        );
+        Pcs::batch_verify(&vps[0].pcs, comms, &points, &evals, transcript)?;
 
-        let polys = chain![
-            polys,
+        Ok(())
+    }
+}
+
+/// Polynomials of a proof besides the preprocessed ones of its `pp`, with
+/// their commitments and the evaluations to open, as they are right before
+/// the PCS opening.
+struct ProverOpening<F: PrimeField, Pcs: PolynomialCommitmentScheme<F>> {
+    instance_polys: Vec<MultilinearPolynomial<F>>,
+    witness_polys: Vec<MultilinearPolynomial<F>>,
+    witness_comms: Vec<Pcs::Commitment>,
+    lookup_m_polys: Vec<MultilinearPolynomial<F>>,
+    lookup_m_comms: Vec<Pcs::Commitment>,
+    lookup_h_permutation_z_polys: Vec<MultilinearPolynomial<F>>,
+    lookup_h_permutation_z_comms: Vec<Pcs::Commitment>,
+    points: Vec<Vec<F>>,
+    evals: Vec<Evaluation<F>>,
+}
+
+impl<F, Pcs> ProverOpening<F, Pcs>
+where
+    F: PrimeField,
+    Pcs: PolynomialCommitmentScheme<F, Polynomial = MultilinearPolynomial<F>>,
+{
+    /// Returns the polynomials in the order `evals` refers to them.
+    fn polys<'a>(
+        &'a self,
+        pp: &'a HyperPlonkProverParam<F, Pcs>,
+    ) -> impl Iterator<Item = &'a MultilinearPolynomial<F>> {
+        chain![
+            &self.instance_polys,
+            &pp.preprocess_polys,
+            &self.witness_polys,
             pp.permutation_polys.iter().map(|(_, poly)| poly),
-            lookup_m_polys.iter(),
-            lookup_h_permutation_z_polys,
+            &self.lookup_m_polys,
+            &self.lookup_h_permutation_z_polys,
         ]
-        .collect_vec();
-        challenges.extend([beta, gamma, alpha]);
-        let (points, evals) = prove_zero_check(
-            pp.num_instances.len(),
-            &pp.expression,
-            &polys,
-            challenges,
-            y,
-            transcript,
-        )?;
-
-        // PCS open
+    }
 
-        let dummy_comm = Pcs::Commitment::default();
-        let comms = chain![
-            iter::repeat(&dummy_comm).take(pp.num_instances.len()),
+    /// Returns the commitments of [`Self::polys`], with `dummy_comm` for the
+    /// instance polynomials the verifier evaluates itself.
+    fn comms<'a>(
+        &'a self,
+        pp: &'a HyperPlonkProverParam<F, Pcs>,
+        dummy_comm: &'a Pcs::Commitment,
+    ) -> impl Iterator<Item = &'a Pcs::Commitment> {
+        chain![
+            iter::repeat(dummy_comm).take(pp.num_instances.len()),
             &pp.preprocess_comms,
-            &witness_comms,
+            &self.witness_comms,
             &pp.permutation_comms,
-            &lookup_m_comms,
-            &lookup_h_permutation_z_comms,
+            &self.lookup_m_comms,
+            &self.lookup_h_permutation_z_comms,
         ]
-        .collect_vec();
-        let timer = start_timer(|| format!("pcs_batch_open-{}", evals.len()));
-        Pcs::batch_open(&pp.pcs, polys, comms, &points, &evals, transcript)?;
-        end_timer(timer);
+    }
 
-        Ok(())
+    fn num_polys(&self, pp: &HyperPlonkProverParam<F, Pcs>) -> usize {
+        self.polys(pp).count()
     }
+}
 
-    fn verify(
-        vp: &Self::VerifierParam,
-        instances: &[Vec<F>],
-        transcript: &mut impl TranscriptRead<Pcs::CommitmentChunk, F>,
-        _: impl RngCore,
-    ) -> Result<(), Error> {
-        for (num_instances, instances) in vp.num_instances.iter().zip_eq(instances) {
+/// Commitments read by the verifier and the evaluations to verify, as they
+/// are right before the PCS verification.
+struct VerifierOpening<F: PrimeField, Pcs: PolynomialCommitmentScheme<F>> {
+    witness_comms: Vec<Pcs::Commitment>,
+    lookup_m_comms: Vec<Pcs::Commitment>,
+    lookup_h_permutation_z_comms: Vec<Pcs::Commitment>,
+    points: Vec<Vec<F>>,
+    evals: Vec<Evaluation<F>>,
+}
+
+impl<F, Pcs> VerifierOpening<F, Pcs>
+where
+    F: PrimeField,
+    Pcs: PolynomialCommitmentScheme<F>,
+{
+    /// Returns the commitments in the order `evals` refers to them, with
+    /// `dummy_comm` for the instance polynomials.
+    fn comms<'a>(
+        &'a self,
+        vp: &'a HyperPlonkVerifierParam<F, Pcs>,
+        dummy_comm: &'a Pcs::Commitment,
+    ) -> impl Iterator<Item = &'a Pcs::Commitment> {
+        chain![
+            iter::repeat(dummy_comm).take(vp.num_instances.len()),
+            &vp.preprocess_comms,
+            &self.witness_comms,
+            vp.permutation_comms.iter().map(|(_, comm)| comm),
+            &self.lookup_m_comms,
+            &self.lookup_h_permutation_z_comms,
+        ]
+    }
+
+    fn num_polys(&self, vp: &HyperPlonkVerifierParam<F, Pcs>) -> usize {
+        self.comms(vp, &Pcs::Commitment::default()).count()
+    }
+}
+
+fn prove_until_opening<F, Pcs>(
+    pp: &HyperPlonkProverParam<F, Pcs>,
+    circuit: &impl PlonkishCircuit<F>,
+    transcript: &mut impl TranscriptWrite<Pcs::CommitmentChunk, F>,
+) -> Result<ProverOpening<F, Pcs>, Error>
+where
+    F: PrimeField + Hash,
+    Pcs: PolynomialCommitmentScheme<F, Polynomial = MultilinearPolynomial<F>>,
+{
+    let instance_polys = {
+        let instances = circuit.instances();
+        for (num_instances, instances) in pp.num_instances.iter().zip_eq(instances) {
             assert_eq!(instances.len(), *num_instances);
             for instance in instances.iter() {
                 transcript.common_field_element(instance)?;
             }
         }
+        instance_polys::<_, BinaryField>(pp.num_vars, instances)
+    };
+
+    // Round 0..n
+
+    let mut witness_polys = Vec::with_capacity(pp.num_witness_polys.iter().sum());
+    let mut witness_comms = Vec::with_capacity(witness_polys.len());
+    let mut challenges = Vec::with_capacity(pp.num_challenges.iter().sum::<usize>() + 4);
+    for (round, (num_witness_polys, num_challenges)) in pp
+        .num_witness_polys
+        .iter()
+        .zip_eq(pp.num_challenges.iter())
+        .enumerate()
+    {
+        let timer = start_timer(|| format!("witness_collector-{round}"));
+        let polys = circuit
+            .synthesize(round, &challenges)?
+            .into_iter()
+            .map(MultilinearPolynomial::new)
+            .collect_vec();
+        assert_eq!(polys.len(), *num_witness_polys);
+        end_timer(timer);
+
+        witness_comms.extend(Pcs::batch_commit_and_write(&pp.pcs, &polys, transcript)?);
+        witness_polys.extend(polys);
+        challenges.extend(transcript.squeeze_challenges(*num_challenges));
+    }
+    let polys = chain![&instance_polys, &pp.preprocess_polys, &witness_polys].collect_vec();
+
+    // Round n
 
-        // Round 0..n
+    let beta = transcript.squeeze_challenge();
+
+    let timer = start_timer(|| format!("lookup_compressed_polys-{}", pp.lookups.len()));
+    let lookup_compressed_polys = {
+        let max_lookup_width = pp.lookups.iter().map(Vec::len).max().unwrap_or_default();
+        let betas = powers(beta).take(max_lookup_width).collect_vec();
+        lookup_compressed_polys::<_, BinaryField>(&pp.lookups, &polys, &challenges, &betas)
+    };
+    end_timer(timer);
+
+    let timer = start_timer(|| format!("lookup_m_polys-{}", pp.lookups.len()));
+    let lookup_m_polys = lookup_m_polys(&lookup_compressed_polys)?;
+    end_timer(timer);
+
+    let lookup_m_comms = Pcs::batch_commit_and_write(&pp.pcs, &lookup_m_polys, transcript)?;
+
+    // Round n+1
+
+    let gamma = transcript.squeeze_challenge();
+
+    let timer = start_timer(|| format!("lookup_h_polys-{}", pp.lookups.len()));
+    let lookup_h_polys = lookup_h_polys(&lookup_compressed_polys, &lookup_m_polys, &gamma);
+    end_timer(timer);
+
+    let timer = start_timer(|| format!("permutation_z_polys-{}", pp.permutation_polys.len()));
+    let permutation_z_polys = permutation_z_polys::<_, BinaryField>(
+        pp.num_permutation_z_polys,
+        &pp.permutation_polys,
+        &polys,
+        &beta,
+        &gamma,
+    );
+    end_timer(timer);
+
+    let lookup_h_permutation_z_polys = chain![lookup_h_polys, permutation_z_polys].collect_vec();
+    let lookup_h_permutation_z_comms =
+        Pcs::batch_commit_and_write(&pp.pcs, &lookup_h_permutation_z_polys, transcript)?;
+
+    // Round n+2
+
+    let alpha = transcript.squeeze_challenge();
+    let y = transcript.squeeze_challenges(pp.num_vars);
+
+    let mut opening = ProverOpening {
+        instance_polys,
+        witness_polys,
+        witness_comms,
+        lookup_m_polys,
+        lookup_m_comms,
+        lookup_h_permutation_z_polys,
+        lookup_h_permutation_z_comms,
+        points: Vec::new(),
+        evals: Vec::new(),
+    };
+    challenges.extend([beta, gamma, alpha]);
+    (opening.points, opening.evals) = prove_zero_check(
+        pp.num_instances.len(),
+        &pp.expression,
+        &opening.polys(pp).collect_vec(),
+        challenges,
+        y,
+        transcript,
+    )?;
+
+    Ok(opening)
+}
 
-        let mut witness_comms = Vec::with_capacity(vp.num_witness_polys.iter().sum());
-        let mut challenges = Vec::with_capacity(vp.num_challenges.iter().sum::<usize>() + 4);
-        for (num_polys, num_challenges) in
-            vp.num_witness_polys.iter().zip_eq(vp.num_challenges.iter())
-        {
-            witness_comms.extend(Pcs::read_commitments(&vp.pcs, *num_polys, transcript)?);
-            challenges.extend(transcript.squeeze_challenges(*num_challenges));
+fn verify_until_opening<F, Pcs>(
+    vp: &HyperPlonkVerifierParam<F, Pcs>,
+    instances: &[Vec<F>],
+    transcript: &mut impl TranscriptRead<Pcs::CommitmentChunk, F>,
+) -> Result<VerifierOpening<F, Pcs>, Error>
+where
+    F: PrimeField,
+    Pcs: PolynomialCommitmentScheme<F, Polynomial = MultilinearPolynomial<F>>,
+{
+    for (num_instances, instances) in vp.num_instances.iter().zip_eq(instances) {
+        assert_eq!(instances.len(), *num_instances);
+        for instance in instances.iter() {
+            transcript.common_field_element(instance)?;
         }
+    }
 
-        // Round n
+    // Round 0..n
 
-        let beta = transcript.squeeze_challenge();
+    let mut witness_comms = Vec::with_capacity(vp.num_witness_polys.iter().sum());
+    let mut challenges = Vec::with_capacity(vp.num_challenges.iter().sum::<usize>() + 4);
+    for (num_polys, num_challenges) in vp.num_witness_polys.iter().zip_eq(vp.num_challenges.iter())
+    {
+        witness_comms.extend(Pcs::read_commitments(&vp.pcs, *num_polys, transcript)?);
+        challenges.extend(transcript.squeeze_challenges(*num_challenges));
+    }
 
-        let lookup_m_comms = Pcs::read_commitments(&vp.pcs, vp.num_lookups, transcript)?;
+    // Round n
 
-        // Round n+1
+    let beta = transcript.squeeze_challenge();
 
-        let gamma = transcript.squeeze_challenge();
+    let lookup_m_comms = Pcs::read_commitments(&vp.pcs, vp.num_lookups, transcript)?;
 
-        let lookup_h_permutation_z_comms = Pcs::read_commitments(
-            &vp.pcs,
-            vp.num_lookups + vp.num_permutation_z_polys,
-            transcript,
-        )?;
+    // Round n+1
 
-        // Round n+2
+    let gamma = transcript.squeeze_challenge();
 
-        let alpha = transcript.squeeze_challenge();
-        let y = transcript.squeeze_challenges(vp.num_vars);
+    let lookup_h_permutation_z_comms = Pcs::read_commitments(
+        &vp.pcs,
+        vp.num_lookups + vp.num_permutation_z_polys,
+        transcript,
+    )?;
 
-        challenges.extend([beta, gamma, alpha]);
-        let (points, evals) = verify_zero_check(
-            vp.num_vars,
-            &vp.expression,
-            instances,
-            &challenges,
-            &y,
-            transcript,
-        )?;
+    // Round n+2
 
-        // PCS verify
+    let alpha = transcript.squeeze_challenge();
+    let y = transcript.squeeze_challenges(vp.num_vars);
 
-        let dummy_comm = Pcs::Commitment::default();
-        let comms = chain![
-            iter::repeat(&dummy_comm).take(vp.num_instances.len()),
-            &vp.preprocess_comms,
-            &witness_comms,
-            vp.permutation_comms.iter().map(|(_, comm)| comm),
-            &lookup_m_comms,
-            &lookup_h_permutation_z_comms,
-        ]
-        .collect_vec();
-        Pcs::batch_verify(&vp.pcs, comms, &points, &evals, transcript)?;
+    challenges.extend([beta, gamma, alpha]);
+    let (points, evals) = verify_zero_check(
+        vp.num_vars,
+        &vp.expression,
+        instances,
+        &challenges,
+        &y,
+        transcript,
+    )?;
 
-        Ok(())
+    Ok(VerifierOpening {
+        witness_comms,
+        lookup_m_comms,
+        lookup_h_permutation_z_comms,
+        points,
+        evals,
+    })
+}
+
+/// Fails unless there is a circuit to aggregate, as many as params, and all
+/// of the same `num_vars`.
+fn check_aggregated(
+    num_vars: impl ExactSizeIterator<Item = usize>,
+    num_circuits: usize,
+) -> Result<(), Error> {
+    if num_vars.len() != num_circuits {
+        return Err(Error::InvalidSnark(format!(
+            "{} params for {num_circuits} aggregated circuits",
+            num_vars.len()
+        )));
+    }
+    match num_vars.dedup().collect_vec()[..] {
+        [_] => Ok(()),
+        [] => Err(Error::InvalidSnark("no circuits to aggregate".to_string())),
+        _ => Err(Error::InvalidSnark(
+            "aggregated circuits differ in size".to_string(),
+        )),
     }
 }
 
+/// Concatenates the points and evaluations of several openings, given their
+/// number of polynomials, shifting each evaluation to the polynomials and
+/// points of its own opening.
+fn aggregated_evals<'a, F: PrimeField>(
+    openings: impl IntoIterator<Item = (usize, &'a Vec<Vec<F>>, &'a Vec<Evaluation<F>>)>,
+) -> (Vec<Vec<F>>, Vec<Evaluation<F>>) {
+    let mut num_polys = 0;
+    let mut points = Vec::new();
+    let mut evals = Vec::new();
+    for (num_opening_polys, opening_points, opening_evals) in openings {
+        let point_offset = points.len();
+        evals.extend(opening_evals.iter().map(|eval| {
+            Evaluation::new(
+                num_polys + eval.poly(),
+                point_offset + eval.point(),
+                *eval.value(),
+            )
+        }));
+        points.extend(opening_points.iter().cloned());
+        num_polys += num_opening_polys;
+    }
+    (points, evals)
+}
+
 impl<Pcs> WitnessEncoding for HyperPlonk<Pcs> {
     fn row_mapping(k: usize) -> Vec<usize> {
         BinaryField::new(k).usable_indices()
@@ -319,7 +588,8 @@ mod test {
                 util::{rand_vanilla_plonk_circuit, rand_vanilla_plonk_w_lookup_circuit},
                 HyperPlonk,
             },
-            test::run_plonkish_backend,
+            test::{run_mutated_proofs, run_plonkish_backend},
+            PlonkishBackend, PlonkishCircuit,
         },
         pcs::{
             multilinear::{
@@ -329,14 +599,18 @@ mod test {
             univariate::UnivariateKzg,
         },
         util::{
-            code::BrakedownSpec6, expression::rotate::BinaryField, hash::Keccak256,
-            test::seeded_std_rng, transcript::Keccak256Transcript,
+            code::BrakedownSpec6,
+            expression::rotate::BinaryField,
+            hash::Keccak256,
+            test::{seeded_std_rng, std_rng},
+            transcript::{InMemoryTranscript, Keccak256Transcript},
         },
     };
     use halo2_curves::{
         bn256::{self, Bn256},
         grumpkin,
     };
+    use rand::{rngs::StdRng, SeedableRng};
 
     macro_rules! tests {
         ($suffix:ident, $pcs:ty, $num_vars_range:expr) => {
@@ -367,4 +641,57 @@ mod test {
     tests!(kzg, MultilinearKzg<Bn256>);
     tests!(gemini_kzg, Gemini<UnivariateKzg<Bn256>>);
     tests!(zeromorph_kzg, Zeromorph<UnivariateKzg<Bn256>>);
+
+    #[test]
+    fn aggregated_w_kzg() {
+        type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+        // Same preprocess RNG for the same circuit info, witnesses of their own.
+        let (circuit_infos, circuits): (Vec<_>, Vec<_>) = (0..3)
+            .map(|seed| {
+                rand_vanilla_plonk_circuit::<_, BinaryField>(
+                    6,
+                    seeded_std_rng(),
+                    StdRng::seed_from_u64(seed),
+                )
+            })
+            .unzip();
+        let param = Pb::setup(&circuit_infos[0], seeded_std_rng()).unwrap();
+        let (pps, vps): (Vec<_>, Vec<_>) = circuit_infos
+            .iter()
+            .map(|circuit_info| Pb::preprocess(&param, circuit_info).unwrap())
+            .unzip();
+
+        let proof = {
+            let mut transcript = Keccak256Transcript::new(());
+            Pb::prove_aggregated(&pps, &circuits, &mut transcript).unwrap();
+            transcript.into_proof()
+        };
+        let verify = |instances: &[&[Vec<bn256::Fr>]]| {
+            let mut transcript = Keccak256Transcript::from_proof((), proof.as_slice());
+            Pb::verify_aggregated(&vps, instances, &mut transcript)
+        };
+        let instances = circuits
+            .iter()
+            .map(PlonkishCircuit::instances)
+            .collect::<Vec<_>>();
+        assert_eq!(verify(&instances), Ok(()));
+        assert!(verify(&[instances[1], instances[0], instances[2]]).is_err());
+
+        // One opening for all of them is shorter than a proof per circuit.
+        let proof_sizes = pps.iter().zip(&circuits).map(|(pp, circuit)| {
+            let mut transcript = Keccak256Transcript::new(());
+            Pb::prove(pp, circuit, &mut transcript, seeded_std_rng()).unwrap();
+            transcript.into_proof().len()
+        });
+        assert!(proof.len() < proof_sizes.sum());
+    }
+
+    #[test]
+    fn mutated_proof_w_kzg() {
+        run_mutated_proofs::<_, HyperPlonk<MultilinearKzg<Bn256>>, Keccak256Transcript<_>, _>(
+            8,
+            256,
+            |num_vars| rand_vanilla_plonk_circuit::<_, BinaryField>(num_vars, std_rng(), std_rng()),
+        );
+    }
 }
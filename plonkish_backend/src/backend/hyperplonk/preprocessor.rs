@@ -141,7 +141,7 @@ pub(crate) fn compose<F: PrimeField>(
     (num_permutation_z_polys, expression)
 }
 
-pub(super) fn max_degree<F: PrimeField>(
+pub(crate) fn max_degree<F: PrimeField>(
     circuit_info: &PlonkishCircuitInfo<F>,
     lookup_constraints: Option<&[Expression<F>]>,
 ) -> usize {
@@ -159,7 +159,7 @@ pub(super) fn max_degree<F: PrimeField>(
     .unwrap()
 }
 
-pub(super) fn lookup_constraints<F: PrimeField>(
+pub(crate) fn lookup_constraints<F: PrimeField>(
     circuit_info: &PlonkishCircuitInfo<F>,
     beta: &Expression<F>,
     gamma: &Expression<F>,
@@ -102,7 +102,7 @@ pub fn rand_vanilla_plonk_circuit<F: PrimeField, R: Rotatable + From<usize>>(
     num_vars: usize,
     mut preprocess_rng: impl RngCore,
     mut witness_rng: impl RngCore,
-) -> (PlonkishCircuitInfo<F>, impl PlonkishCircuit<F>) {
+) -> (PlonkishCircuitInfo<F>, impl PlonkishCircuit<F> + Clone) {
     let size = 1 << num_vars;
     let mut polys = [(); 9].map(|_| vec![F::ZERO; size]);
 
@@ -210,7 +210,7 @@ pub fn rand_vanilla_plonk_w_lookup_circuit<F: PrimeField + Hash, R: Rotatable +
     num_vars: usize,
     mut preprocess_rng: impl RngCore,
     mut witness_rng: impl RngCore,
-) -> (PlonkishCircuitInfo<F>, impl PlonkishCircuit<F>) {
+) -> (PlonkishCircuitInfo<F>, impl PlonkishCircuit<F> + Clone) {
     let size = 1 << num_vars;
     let mut polys = [(); 13].map(|_| vec![F::ZERO; size]);
 
@@ -0,0 +1,563 @@
+//! Classic PLONK over a univariate PCS.
+//!
+//! It shares the circuit semantics of [`HyperPlonk`], except that row `i` is
+//! the point `omega^i` of the size `2^k` multiplicative subgroup, so the
+//! constraints are checked by the divisibility of their random linear
+//! combination by the vanishing poly of the subgroup, instead of a zero-check.
+//! The lookup argument is the same logarithmic derivative one, whose sum is
+//! checked with a running sum poly over the cycle of rows. Circuits with
+//! [`CommonPolynomial::Identity`] are rejected since it evaluates to the row
+//! index in [`HyperPlonk`] but to `omega^i` here.
+//!
+//! [`HyperPlonk`]: crate::backend::hyperplonk::HyperPlonk
+//! [`CommonPolynomial::Identity`]: crate::util::expression::CommonPolynomial::Identity
+
+use crate::{
+    backend::{
+        hyperplonk::{
+            prover::{instance_polys, lookup_compressed_polys, lookup_h_polys, lookup_m_polys},
+            verifier::pcs_query,
+        },
+        plonk::{
+            preprocessor::{
+                batch_size, compose, num_quotient_chunks, permutation_polys, uses_identity,
+            },
+            prover::{lookup_phi_polys, permutation_z_polys, quotient_poly},
+            verifier::{instance_evals, points},
+        },
+        PlonkishBackend, PlonkishCircuit, PlonkishCircuitInfo, WitnessEncoding,
+    },
+    pcs::{Evaluation, PolynomialCommitmentScheme},
+    poly::{multilinear::MultilinearPolynomial, univariate::UnivariatePolynomial},
+    util::{
+        arithmetic::{powers, WithSmallOrderMulGroup},
+        chain, end_timer,
+        expression::{
+            evaluator::quotient::Radix2Domain,
+            rotate::{Lexical, Rotatable},
+            Expression, Rotation,
+        },
+        izip, start_timer,
+        transcript::{TranscriptRead, TranscriptWrite},
+        Deserialize, DeserializeOwned, Itertools, Serialize,
+    },
+    Error,
+};
+use rand::RngCore;
+use std::{fmt::Debug, hash::Hash, marker::PhantomData};
+
+pub(crate) mod preprocessor;
+pub(crate) mod prover;
+pub(crate) mod verifier;
+
+#[derive(Clone, Debug)]
+pub struct Plonk<Pcs>(PhantomData<Pcs>);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(serialize = "F: Serialize", deserialize = "F: DeserializeOwned"))]
+pub struct PlonkProverParam<F, Pcs>
+where
+    F: WithSmallOrderMulGroup<3>,
+    Pcs: PolynomialCommitmentScheme<F>,
+{
+    pub(crate) pcs: Pcs::ProverParam,
+    pub(crate) num_instances: Vec<usize>,
+    pub(crate) num_witness_polys: Vec<usize>,
+    pub(crate) num_challenges: Vec<usize>,
+    pub(crate) lookups: Vec<Vec<(Expression<F>, Expression<F>)>>,
+    pub(crate) num_permutation_z_polys: usize,
+    pub(crate) k: usize,
+    pub(crate) expression: Expression<F>,
+    pub(crate) preprocess_polys: Vec<MultilinearPolynomial<F>>,
+    pub(crate) preprocess_monomials: Vec<UnivariatePolynomial<F>>,
+    pub(crate) preprocess_comms: Vec<Pcs::Commitment>,
+    pub(crate) permutation_polys: Vec<(usize, MultilinearPolynomial<F>)>,
+    pub(crate) permutation_monomials: Vec<UnivariatePolynomial<F>>,
+    pub(crate) permutation_comms: Vec<Pcs::Commitment>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(serialize = "F: Serialize", deserialize = "F: DeserializeOwned"))]
+pub struct PlonkVerifierParam<F, Pcs>
+where
+    F: WithSmallOrderMulGroup<3>,
+    Pcs: PolynomialCommitmentScheme<F>,
+{
+    pub(crate) pcs: Pcs::VerifierParam,
+    pub(crate) num_instances: Vec<usize>,
+    pub(crate) num_witness_polys: Vec<usize>,
+    pub(crate) num_challenges: Vec<usize>,
+    pub(crate) num_lookups: usize,
+    pub(crate) num_permutation_z_polys: usize,
+    pub(crate) k: usize,
+    pub(crate) expression: Expression<F>,
+    pub(crate) preprocess_comms: Vec<Pcs::Commitment>,
+    pub(crate) permutation_comms: Vec<Pcs::Commitment>,
+}
+
+impl<F, Pcs> PlonkVerifierParam<F, Pcs>
+where
+    F: WithSmallOrderMulGroup<3>,
+    Pcs: PolynomialCommitmentScheme<F>,
+{
+    /// Returns the number of instance values in each instance polynomial.
+    pub fn num_instances(&self) -> &[usize] {
+        &self.num_instances
+    }
+}
+
+impl<F, Pcs> PlonkishBackend<F> for Plonk<Pcs>
+where
+    F: WithSmallOrderMulGroup<3> + Hash + Serialize + DeserializeOwned,
+    Pcs: PolynomialCommitmentScheme<F, Polynomial = UnivariatePolynomial<F>>,
+{
+    type Pcs = Pcs;
+    type ProverParam = PlonkProverParam<F, Pcs>;
+    type VerifierParam = PlonkVerifierParam<F, Pcs>;
+
+    fn setup(
+        circuit_info: &PlonkishCircuitInfo<F>,
+        rng: impl RngCore,
+    ) -> Result<Pcs::Param, Error> {
+        assert!(circuit_info.is_well_formed());
+
+        let poly_size = 1 << circuit_info.k;
+        let batch_size = batch_size(circuit_info);
+        Pcs::setup(poly_size, batch_size, rng)
+    }
+
+    fn preprocess(
+        param: &Pcs::Param,
+        circuit_info: &PlonkishCircuitInfo<F>,
+    ) -> Result<(Self::ProverParam, Self::VerifierParam), Error> {
+        assert!(circuit_info.is_well_formed());
+        if uses_identity(circuit_info) {
+            return Err(Error::InvalidSnark(
+                "Plonk doesn't support circuit using identity".to_string(),
+            ));
+        }
+
+        let k = circuit_info.k;
+        let poly_size = 1 << k;
+        let batch_size = batch_size(circuit_info);
+        let (pcs_pp, pcs_vp) = Pcs::trim(param, poly_size, batch_size)?;
+
+        let (num_permutation_z_polys, expression) = compose(circuit_info);
+        let domain = Radix2Domain::new(k, expression.degree());
+
+        // Compute preprocesses comms
+        let preprocess_monomials = monomials(&domain, &circuit_info.preprocess_polys);
+        let preprocess_comms = Pcs::batch_commit(&pcs_pp, &preprocess_monomials)?;
+
+        // Compute permutation polys and comms
+        let permutation_polys = permutation_polys(
+            k,
+            &circuit_info.permutation_polys(),
+            &circuit_info.permutations,
+        );
+        let permutation_monomials = monomials(&domain, &permutation_polys);
+        let permutation_comms = Pcs::batch_commit(&pcs_pp, &permutation_monomials)?;
+
+        let vp = PlonkVerifierParam {
+            pcs: pcs_vp,
+            num_instances: circuit_info.num_instances.clone(),
+            num_witness_polys: circuit_info.num_witness_polys.clone(),
+            num_challenges: circuit_info.num_challenges.clone(),
+            num_lookups: circuit_info.lookups.len(),
+            num_permutation_z_polys,
+            k,
+            expression: expression.clone(),
+            preprocess_comms: preprocess_comms.clone(),
+            permutation_comms: permutation_comms.clone(),
+        };
+        let pp = PlonkProverParam {
+            pcs: pcs_pp,
+            num_instances: circuit_info.num_instances.clone(),
+            num_witness_polys: circuit_info.num_witness_polys.clone(),
+            num_challenges: circuit_info.num_challenges.clone(),
+            lookups: circuit_info.lookups.clone(),
+            num_permutation_z_polys,
+            k,
+            expression,
+            preprocess_polys: circuit_info
+                .preprocess_polys
+                .iter()
+                .cloned()
+                .map(MultilinearPolynomial::new)
+                .collect(),
+            preprocess_monomials,
+            preprocess_comms,
+            permutation_polys: circuit_info
+                .permutation_polys()
+                .into_iter()
+                .zip(
+                    permutation_polys
+                        .into_iter()
+                        .map(MultilinearPolynomial::new),
+                )
+                .collect(),
+            permutation_monomials,
+            permutation_comms,
+        };
+        Ok((pp, vp))
+    }
+
+    fn prove(
+        pp: &Self::ProverParam,
+        circuit: &impl PlonkishCircuit<F>,
+        transcript: &mut impl TranscriptWrite<Pcs::CommitmentChunk, F>,
+        _: impl RngCore,
+    ) -> Result<(), Error> {
+        let domain = Radix2Domain::new(pp.k, pp.expression.degree());
+
+        let instance_polys = {
+            let instances = circuit.instances();
+            for (num_instances, instances) in pp.num_instances.iter().zip_eq(instances) {
+                assert_eq!(instances.len(), *num_instances);
+                for instance in instances.iter() {
+                    transcript.common_field_element(instance)?;
+                }
+            }
+            instance_polys::<_, Lexical>(pp.k, instances)
+        };
+
+        // Round 0..n
+
+        let mut witness_polys = Vec::with_capacity(pp.num_witness_polys.iter().sum());
+        let mut witness_monomials = Vec::with_capacity(witness_polys.len());
+        let mut witness_comms = Vec::with_capacity(witness_polys.len());
+        let mut challenges = Vec::with_capacity(pp.num_challenges.iter().sum::<usize>() + 3);
+        for (round, (num_witness_polys, num_challenges)) in pp
+            .num_witness_polys
+            .iter()
+            .zip_eq(pp.num_challenges.iter())
+            .enumerate()
+        {
+            let timer = start_timer(|| format!("witness_collector-{round}"));
+            let polys = circuit.synthesize(round, &challenges)?;
+            assert_eq!(polys.len(), *num_witness_polys);
+            end_timer(timer);
+
+            let monomials = monomials(&domain, &polys);
+            witness_comms.extend(Pcs::batch_commit_and_write(
+                &pp.pcs, &monomials, transcript,
+            )?);
+            witness_polys.extend(polys.into_iter().map(MultilinearPolynomial::new));
+            witness_monomials.extend(monomials);
+            challenges.extend(transcript.squeeze_challenges(*num_challenges));
+        }
+        let polys = chain![&instance_polys, &pp.preprocess_polys, &witness_polys].collect_vec();
+
+        // Round n
+
+        let beta = transcript.squeeze_challenge();
+
+        let timer = start_timer(|| format!("lookup_compressed_polys-{}", pp.lookups.len()));
+        let lookup_compressed_polys = {
+            let max_lookup_width = pp.lookups.iter().map(Vec::len).max().unwrap_or_default();
+            let betas = powers(beta).take(max_lookup_width).collect_vec();
+            lookup_compressed_polys::<_, Lexical>(&pp.lookups, &polys, &challenges, &betas)
+        };
+        end_timer(timer);
+
+        let timer = start_timer(|| format!("lookup_m_polys-{}", pp.lookups.len()));
+        let lookup_m_polys = lookup_m_polys(&lookup_compressed_polys)?;
+        end_timer(timer);
+
+        let lookup_m_monomials = monomials(&domain, lookup_m_polys.iter().map(|poly| poly.evals()));
+        let lookup_m_comms = Pcs::batch_commit_and_write(&pp.pcs, &lookup_m_monomials, transcript)?;
+
+        // Round n+1
+
+        let gamma = transcript.squeeze_challenge();
+
+        let timer = start_timer(|| format!("lookup_h_polys-{}", pp.lookups.len()));
+        let lookup_h_polys = lookup_h_polys(&lookup_compressed_polys, &lookup_m_polys, &gamma);
+        let lookup_phi_polys = lookup_phi_polys(&lookup_h_polys);
+        end_timer(timer);
+
+        let timer = start_timer(|| format!("permutation_z_polys-{}", pp.permutation_polys.len()));
+        let permutation_z_polys = permutation_z_polys(
+            pp.num_permutation_z_polys,
+            &pp.permutation_polys,
+            &polys,
+            &beta,
+            &gamma,
+        );
+        end_timer(timer);
+
+        let lookup_h_phi_permutation_z_monomials = monomials(
+            &domain,
+            chain![&lookup_h_polys, &lookup_phi_polys, &permutation_z_polys]
+                .map(MultilinearPolynomial::evals),
+        );
+        let lookup_h_phi_permutation_z_comms = Pcs::batch_commit_and_write(
+            &pp.pcs,
+            &lookup_h_phi_permutation_z_monomials,
+            transcript,
+        )?;
+
+        // Round n+2
+
+        let alpha = transcript.squeeze_challenge();
+        challenges.extend([beta, gamma, alpha]);
+
+        let instance_monomials = monomials(&domain, instance_polys.iter().map(|poly| poly.evals()));
+        let monomials = chain![
+            &instance_monomials,
+            &pp.preprocess_monomials,
+            &witness_monomials,
+            &pp.permutation_monomials,
+            &lookup_m_monomials,
+            &lookup_h_phi_permutation_z_monomials,
+        ]
+        .collect_vec();
+
+        let expression = pp
+            .expression
+            .simplified(Some(&challenges))
+            .unwrap_or_else(Expression::zero);
+        let quotient_chunks = {
+            let polys = monomials.iter().map(|poly| poly.coeffs()).collect_vec();
+            let mut quotient = quotient_poly(&domain, &expression, &polys);
+            let num_chunks = num_quotient_chunks(&pp.expression);
+            if cfg!(feature = "sanity-check") {
+                assert!(!quotient[num_chunks * domain.n()..]
+                    .iter()
+                    .any(|v| *v != F::ZERO));
+            }
+            quotient.resize(num_chunks * domain.n(), F::ZERO);
+            quotient
+                .chunks(domain.n())
+                .map(|chunk| UnivariatePolynomial::monomial(chunk.to_vec()))
+                .collect_vec()
+        };
+        let quotient_comms = Pcs::batch_commit_and_write(&pp.pcs, &quotient_chunks, transcript)?;
+
+        // Round n+3
+
+        let x = transcript.squeeze_challenge();
+
+        let pcs_query = pcs_query(&pp.expression, pp.num_instances.len());
+        let (points, point_idx) = points(&domain, &pcs_query, x);
+        let evals = chain![
+            pcs_query.iter().map(|query| {
+                let point = domain.rotate_point(x, query.rotation());
+                let poly = query.poly() - pp.num_instances.len();
+                (
+                    poly,
+                    point_idx[&query.rotation()],
+                    monomials[query.poly()].evaluate(&point),
+                )
+            }),
+            (monomials.len() - pp.num_instances.len()..)
+                .zip(&quotient_chunks)
+                .map(|(poly, chunk)| (poly, point_idx[&Rotation::cur()], chunk.evaluate(&x))),
+        ]
+        .map(|(poly, point, eval)| Evaluation::new(poly, point, eval))
+        .collect_vec();
+        transcript.write_field_elements(evals.iter().map(Evaluation::value))?;
+
+        let polys = chain![
+            monomials[pp.num_instances.len()..].iter().copied(),
+            &quotient_chunks
+        ];
+        let comms = chain![
+            &pp.preprocess_comms,
+            &witness_comms,
+            &pp.permutation_comms,
+            &lookup_m_comms,
+            &lookup_h_phi_permutation_z_comms,
+            &quotient_comms,
+        ];
+        let _timer = start_timer(|| format!("pcs_batch_open-{}", evals.len()));
+        Pcs::batch_open(&pp.pcs, polys, comms, &points, &evals, transcript)
+    }
+
+    fn verify(
+        vp: &Self::VerifierParam,
+        instances: &[Vec<F>],
+        transcript: &mut impl TranscriptRead<Pcs::CommitmentChunk, F>,
+        _: impl RngCore,
+    ) -> Result<(), Error> {
+        let domain = Radix2Domain::new(vp.k, vp.expression.degree());
+
+        for (num_instances, instances) in vp.num_instances.iter().zip_eq(instances) {
+            assert_eq!(instances.len(), *num_instances);
+            for instance in instances.iter() {
+                transcript.common_field_element(instance)?;
+            }
+        }
+
+        // Round 0..n
+
+        let mut witness_comms = Vec::with_capacity(vp.num_witness_polys.iter().sum());
+        let mut challenges = Vec::with_capacity(vp.num_challenges.iter().sum::<usize>() + 3);
+        for (num_polys, num_challenges) in
+            vp.num_witness_polys.iter().zip_eq(vp.num_challenges.iter())
+        {
+            witness_comms.extend(Pcs::read_commitments(&vp.pcs, *num_polys, transcript)?);
+            challenges.extend(transcript.squeeze_challenges(*num_challenges));
+        }
+
+        // Round n
+
+        let beta = transcript.squeeze_challenge();
+
+        let lookup_m_comms = Pcs::read_commitments(&vp.pcs, vp.num_lookups, transcript)?;
+
+        // Round n+1
+
+        let gamma = transcript.squeeze_challenge();
+
+        let lookup_h_phi_permutation_z_comms = Pcs::read_commitments(
+            &vp.pcs,
+            2 * vp.num_lookups + vp.num_permutation_z_polys,
+            transcript,
+        )?;
+
+        // Round n+2
+
+        let alpha = transcript.squeeze_challenge();
+        challenges.extend([beta, gamma, alpha]);
+
+        let num_chunks = num_quotient_chunks(&vp.expression);
+        let quotient_comms = Pcs::read_commitments(&vp.pcs, num_chunks, transcript)?;
+
+        // Round n+3
+
+        let x = transcript.squeeze_challenge();
+
+        let pcs_query = pcs_query(&vp.expression, vp.num_instances.len());
+        let (points, point_idx) = points(&domain, &pcs_query, x);
+        let query_evals = transcript.read_field_elements(pcs_query.len())?;
+        let quotient_evals = transcript.read_field_elements(num_chunks)?;
+
+        let constraint_eval = {
+            let evals = chain![
+                izip!(pcs_query.iter().copied(), query_evals.iter().copied()),
+                instance_evals(&domain, &vp.expression, instances, x),
+            ]
+            .collect();
+            domain.evaluate(&vp.expression, &evals, &challenges, x)
+        };
+        let quotient_eval = {
+            let x_to_n = x.pow_vartime([domain.n() as u64]);
+            let eval = powers(x_to_n)
+                .zip(&quotient_evals)
+                .map(|(scalar, eval)| scalar * eval)
+                .fold(F::ZERO, |acc, eval| acc + eval);
+            (x_to_n - F::ONE) * eval
+        };
+        if constraint_eval != quotient_eval {
+            return Err(Error::InvalidSnark(
+                "Unmatched between constraint evaluation and quotient evaluation".to_string(),
+            ));
+        }
+
+        let comms = chain![
+            &vp.preprocess_comms,
+            &witness_comms,
+            &vp.permutation_comms,
+            &lookup_m_comms,
+            &lookup_h_phi_permutation_z_comms,
+        ];
+        let num_polys = comms.clone().count();
+        let evals = chain![
+            izip!(&pcs_query, query_evals).map(|(query, eval)| {
+                let poly = query.poly() - vp.num_instances.len();
+                (poly, point_idx[&query.rotation()], eval)
+            }),
+            (num_polys..).zip(quotient_evals).map(|(poly, eval)| (
+                poly,
+                point_idx[&Rotation::cur()],
+                eval
+            )),
+        ]
+        .map(|(poly, point, eval)| Evaluation::new(poly, point, eval))
+        .collect_vec();
+        Pcs::batch_verify(
+            &vp.pcs,
+            comms.chain(&quotient_comms),
+            &points,
+            &evals,
+            transcript,
+        )
+    }
+}
+
+impl<Pcs> WitnessEncoding for Plonk<Pcs> {
+    fn row_mapping(k: usize) -> Vec<usize> {
+        Lexical::new(k).usable_indices()
+    }
+}
+
+fn monomials<F: WithSmallOrderMulGroup<3>>(
+    domain: &Radix2Domain<F>,
+    polys: impl IntoIterator<Item = impl AsRef<[F]>>,
+) -> Vec<UnivariatePolynomial<F>> {
+    polys
+        .into_iter()
+        .map(|poly| domain.lagrange_to_monomial(poly.as_ref().into()))
+        .map(UnivariatePolynomial::monomial)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        backend::{
+            hyperplonk::util::{rand_vanilla_plonk_circuit, rand_vanilla_plonk_w_lookup_circuit},
+            plonk::Plonk,
+            test::{run_mutated_proofs, run_plonkish_backend},
+        },
+        pcs::univariate::UnivariateKzg,
+        util::{
+            expression::rotate::Lexical, test::seeded_std_rng, transcript::Keccak256Transcript,
+        },
+    };
+    use halo2_curves::bn256::{Bn256, Fr};
+
+    #[test]
+    fn vanilla_plonk_w_kzg() {
+        run_plonkish_backend::<_, Plonk<UnivariateKzg<Bn256>>, Keccak256Transcript<_>, _>(
+            2..16,
+            |num_vars| {
+                rand_vanilla_plonk_circuit::<Fr, Lexical>(
+                    num_vars,
+                    seeded_std_rng(),
+                    seeded_std_rng(),
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn vanilla_plonk_w_lookup_w_kzg() {
+        run_plonkish_backend::<_, Plonk<UnivariateKzg<Bn256>>, Keccak256Transcript<_>, _>(
+            2..16,
+            |num_vars| {
+                rand_vanilla_plonk_w_lookup_circuit::<Fr, Lexical>(
+                    num_vars,
+                    seeded_std_rng(),
+                    seeded_std_rng(),
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn mutated_proof_w_kzg() {
+        run_mutated_proofs::<_, Plonk<UnivariateKzg<Bn256>>, Keccak256Transcript<_>, _>(
+            6,
+            32,
+            |num_vars| {
+                rand_vanilla_plonk_w_lookup_circuit::<Fr, Lexical>(
+                    num_vars,
+                    seeded_std_rng(),
+                    seeded_std_rng(),
+                )
+            },
+        );
+    }
+}
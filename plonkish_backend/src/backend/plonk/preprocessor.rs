@@ -0,0 +1,186 @@
+use crate::{
+    backend::{
+        hyperplonk::preprocessor::{lookup_constraints, max_degree},
+        PlonkishCircuitInfo,
+    },
+    util::{
+        arithmetic::{div_ceil, powers, root_of_unity, PrimeField},
+        chain,
+        expression::{CommonPolynomial, Expression, Query, Rotation},
+        Itertools,
+    },
+};
+use std::{array, mem};
+
+pub(super) fn batch_size<F: PrimeField>(circuit_info: &PlonkishCircuitInfo<F>) -> usize {
+    let num_lookups = circuit_info.lookups.len();
+    let num_permutation_polys = circuit_info.permutation_polys().len();
+    let (_, expression) = compose(circuit_info);
+    chain![
+        [circuit_info.preprocess_polys.len() + num_permutation_polys],
+        circuit_info.num_witness_polys.clone(),
+        [num_lookups],
+        [3 * num_lookups + div_ceil(num_permutation_polys, max_degree(circuit_info, None) - 1)],
+        [num_quotient_chunks(&expression)],
+    ]
+    .sum()
+}
+
+pub(super) fn num_quotient_chunks<F: Clone>(expression: &Expression<F>) -> usize {
+    expression.degree().max(2) - 1
+}
+
+pub(super) fn uses_identity<F: Clone>(circuit_info: &PlonkishCircuitInfo<F>) -> bool {
+    circuit_info.expressions().any(|expression| {
+        expression.evaluate(
+            &|_| false,
+            &|common_poly| matches!(common_poly, CommonPolynomial::Identity),
+            &|_| false,
+            &|_| false,
+            &|value| value,
+            &|lhs, rhs| lhs || rhs,
+            &|lhs, rhs| lhs || rhs,
+            &|value, _| value,
+        )
+    })
+}
+
+pub(super) fn compose<F: PrimeField>(
+    circuit_info: &PlonkishCircuitInfo<F>,
+) -> (usize, Expression<F>) {
+    let challenge_offset = circuit_info.num_challenges.iter().sum::<usize>();
+    let [beta, gamma, alpha] =
+        &array::from_fn(|idx| Expression::<F>::Challenge(challenge_offset + idx));
+
+    let (lookup_constraints, _) = lookup_constraints(circuit_info, beta, gamma);
+    let lookup_sum_constraints = lookup_sum_constraints(circuit_info);
+
+    let max_degree = max_degree(circuit_info, Some(&lookup_constraints));
+    let (num_permutation_z_polys, permutation_constraints) = permutation_constraints(
+        circuit_info,
+        max_degree,
+        beta,
+        gamma,
+        3 * circuit_info.lookups.len(),
+    );
+
+    let expression = {
+        let constraints = chain![
+            circuit_info.constraints.iter(),
+            lookup_constraints.iter(),
+            lookup_sum_constraints.iter(),
+            permutation_constraints.iter(),
+        ]
+        .collect_vec();
+        Expression::distribute_powers(constraints, alpha)
+    };
+
+    (num_permutation_z_polys, expression)
+}
+
+// Each lookup has a running sum `phi` of its `h` over the cycle of rows, which
+// only closes when `h` sums to zero.
+pub(super) fn lookup_sum_constraints<F: PrimeField>(
+    circuit_info: &PlonkishCircuitInfo<F>,
+) -> Vec<Expression<F>> {
+    let num_lookups = circuit_info.lookups.len();
+    let h_offset = circuit_info.num_poly() + circuit_info.permutation_polys().len() + num_lookups;
+    let phi_offset = h_offset + num_lookups;
+    (h_offset..)
+        .zip(phi_offset..)
+        .take(num_lookups)
+        .map(|(h, phi)| {
+            let h = Expression::<F>::Polynomial(Query::new(h, Rotation::cur()));
+            let [phi, phi_next] = [Rotation::cur(), Rotation::next()]
+                .map(|rotation| Expression::<F>::Polynomial(Query::new(phi, rotation)));
+            phi_next - phi - h
+        })
+        .collect()
+}
+
+// Same as `hyperplonk::preprocessor::permutation_constraints` except the
+// identity of row `i` of the `j`-th permutation poly is `delta^j * omega^i`.
+pub(super) fn permutation_constraints<F: PrimeField>(
+    circuit_info: &PlonkishCircuitInfo<F>,
+    max_degree: usize,
+    beta: &Expression<F>,
+    gamma: &Expression<F>,
+    num_builtin_witness_polys: usize,
+) -> (usize, Vec<Expression<F>>) {
+    let permutation_polys = circuit_info.permutation_polys();
+    let chunk_size = max_degree - 1;
+    let num_chunks = div_ceil(permutation_polys.len(), chunk_size);
+    let permutation_offset = circuit_info.num_poly();
+    let z_offset = permutation_offset + permutation_polys.len() + num_builtin_witness_polys;
+    let polys = permutation_polys
+        .iter()
+        .map(|idx| Expression::Polynomial(Query::new(*idx, Rotation::cur())))
+        .collect_vec();
+    let ids = powers(F::DELTA)
+        .take(polys.len())
+        .map(|delta| Expression::Constant(delta) * Expression::identity())
+        .collect_vec();
+    let permutations = (permutation_offset..)
+        .map(|idx| Expression::Polynomial(Query::new(idx, Rotation::cur())))
+        .take(permutation_polys.len())
+        .collect_vec();
+    let zs = (z_offset..)
+        .map(|idx| Expression::Polynomial(Query::new(idx, Rotation::cur())))
+        .take(num_chunks)
+        .collect_vec();
+    let z_0_next = Expression::<F>::Polynomial(Query::new(z_offset, Rotation::next()));
+    let l_0 = &Expression::<F>::lagrange(0);
+    let one = &Expression::one();
+    let constraints = chain![
+        zs.first().map(|z_0| l_0 * (z_0 - one)),
+        polys
+            .chunks(chunk_size)
+            .zip(ids.chunks(chunk_size))
+            .zip(permutations.chunks(chunk_size))
+            .zip(zs.iter())
+            .zip(zs.iter().skip(1).chain([&z_0_next]))
+            .map(|((((polys, ids), permutations), z_lhs), z_rhs)| {
+                z_lhs
+                    * polys
+                        .iter()
+                        .zip(ids)
+                        .map(|(poly, id)| poly + beta * id + gamma)
+                        .product::<Expression<_>>()
+                    - z_rhs
+                        * polys
+                            .iter()
+                            .zip(permutations)
+                            .map(|(poly, permutation)| poly + beta * permutation + gamma)
+                            .product::<Expression<_>>()
+            }),
+    ]
+    .collect();
+    (num_chunks, constraints)
+}
+
+pub(super) fn permutation_polys<F: PrimeField>(
+    k: usize,
+    permutation_polys: &[usize],
+    cycles: &[Vec<(usize, usize)>],
+) -> Vec<Vec<F>> {
+    let poly_index = {
+        let mut poly_index = vec![0; permutation_polys.last().map(|poly| 1 + poly).unwrap_or(0)];
+        for (idx, poly) in permutation_polys.iter().enumerate() {
+            poly_index[*poly] = idx;
+        }
+        poly_index
+    };
+    let omegas = powers(root_of_unity::<F>(k)).take(1 << k).collect_vec();
+    let mut permutations = powers(F::DELTA)
+        .take(permutation_polys.len())
+        .map(|delta| omegas.iter().map(|omega| delta * omega).collect_vec())
+        .collect_vec();
+    for cycle in cycles.iter() {
+        let (i0, j0) = cycle[0];
+        let mut last = permutations[poly_index[i0]][j0];
+        for &(i, j) in cycle.iter().cycle().skip(1).take(cycle.len()) {
+            mem::swap(&mut permutations[poly_index[i]][j], &mut last);
+        }
+    }
+    permutations
+}
@@ -0,0 +1,166 @@
+use crate::{
+    poly::multilinear::MultilinearPolynomial,
+    util::{
+        arithmetic::{
+            div_ceil, powers, root_of_unity, BatchInvert, PrimeField, WithSmallOrderMulGroup,
+        },
+        end_timer,
+        expression::{
+            evaluator::quotient::{QuotientEvaluator, Radix2Domain},
+            Expression,
+        },
+        izip,
+        parallel::parallelize,
+        start_timer, Itertools,
+    },
+};
+use std::borrow::{Borrow, Cow};
+
+pub(super) fn lookup_phi_polys<F: PrimeField>(
+    h_polys: &[MultilinearPolynomial<F>],
+) -> Vec<MultilinearPolynomial<F>> {
+    h_polys
+        .iter()
+        .map(|h_poly| {
+            let mut phi = vec![F::ZERO; h_poly.evals().len()];
+            for idx in 1..phi.len() {
+                phi[idx] = phi[idx - 1] + h_poly[idx - 1];
+            }
+
+            if cfg!(feature = "sanity-check") {
+                assert_eq!(
+                    *phi.last().unwrap() + h_poly.evals().last().unwrap(),
+                    F::ZERO
+                );
+            }
+
+            MultilinearPolynomial::new(phi)
+        })
+        .collect()
+}
+
+// Same as `hyperplonk::prover::permutation_z_polys` except the identity of row
+// `i` of the `j`-th permutation poly is `delta^j * omega^i`.
+pub(super) fn permutation_z_polys<F: PrimeField>(
+    num_chunks: usize,
+    permutation_polys: &[(usize, MultilinearPolynomial<F>)],
+    polys: &[impl Borrow<MultilinearPolynomial<F>>],
+    beta: &F,
+    gamma: &F,
+) -> Vec<MultilinearPolynomial<F>> {
+    if permutation_polys.is_empty() {
+        return Vec::new();
+    }
+
+    let chunk_size = div_ceil(permutation_polys.len(), num_chunks);
+    let polys = polys.iter().map(Borrow::borrow).collect_vec();
+    let num_vars = polys[0].num_vars();
+    let omega = root_of_unity::<F>(num_vars);
+
+    let timer = start_timer(|| "products");
+    let products = permutation_polys
+        .chunks(chunk_size)
+        .zip(powers(F::DELTA.pow_vartime([chunk_size as u64])))
+        .map(|(permutation_polys, delta_offset)| {
+            let mut product = vec![F::ONE; 1 << num_vars];
+
+            for (poly, permutation_poly) in permutation_polys.iter() {
+                parallelize(&mut product, |(product, start)| {
+                    for ((product, value), permutation) in product
+                        .iter_mut()
+                        .zip(polys[*poly][start..].iter())
+                        .zip(permutation_poly[start..].iter())
+                    {
+                        *product *= (*beta * permutation) + gamma + value;
+                    }
+                });
+            }
+
+            parallelize(&mut product, |(product, _)| {
+                product.batch_invert();
+            });
+
+            for ((poly, _), delta) in permutation_polys.iter().zip(powers(F::DELTA)) {
+                let beta_delta = *beta * delta_offset * delta;
+                parallelize(&mut product, |(product, start)| {
+                    let mut beta_id = beta_delta * omega.pow_vartime([start as u64]);
+                    for (product, value) in product.iter_mut().zip(polys[*poly][start..].iter()) {
+                        *product *= beta_id + gamma + value;
+                        beta_id *= omega;
+                    }
+                });
+            }
+
+            product
+        })
+        .collect_vec();
+    end_timer(timer);
+
+    let _timer = start_timer(|| "z_polys");
+    let mut z = vec![vec![F::ZERO; 1 << num_vars]; num_chunks];
+
+    z[0][0] = F::ONE;
+    for chunk_idx in 1..num_chunks {
+        z[chunk_idx][0] = z[chunk_idx - 1][0] * products[chunk_idx - 1][0];
+    }
+    for idx in 1..1 << num_vars {
+        z[0][idx] = z[num_chunks - 1][idx - 1] * products[num_chunks - 1][idx - 1];
+        for chunk_idx in 1..num_chunks {
+            z[chunk_idx][idx] = z[chunk_idx - 1][idx] * products[chunk_idx - 1][idx];
+        }
+    }
+
+    if cfg!(feature = "sanity-check") {
+        let last_idx = (1 << num_vars) - 1;
+        assert_eq!(
+            z.last().unwrap()[last_idx] * products.last().unwrap()[last_idx],
+            F::ONE
+        );
+    }
+
+    z.into_iter().map(MultilinearPolynomial::new).collect()
+}
+
+// Returns the monomial coefficients of the quotient of `expression` by the
+// vanishing poly of the size `domain.n()` subgroup, given the polys in
+// monomial basis.
+pub(super) fn quotient_poly<F: WithSmallOrderMulGroup<3>>(
+    domain: &Radix2Domain<F>,
+    expression: &Expression<F>,
+    polys: &[&[F]],
+) -> Vec<F> {
+    let timer = start_timer(|| format!("extended_polys-{}", polys.len()));
+    let polys = polys
+        .iter()
+        .map(|poly| domain.monomial_to_extended_lagrange(Cow::Borrowed(poly)))
+        .collect_vec();
+    let lagranges = expression
+        .used_langrange()
+        .into_iter()
+        .map(|i| {
+            let mut lagrange = vec![F::ZERO; domain.n()];
+            lagrange[i.rem_euclid(domain.n() as i32) as usize] = F::ONE;
+            (i, domain.lagrange_to_extended_lagrange(lagrange.into()))
+        })
+        .collect_vec();
+    end_timer(timer);
+
+    let timer = start_timer(|| "quotient");
+    let ev = QuotientEvaluator::new(
+        domain,
+        expression,
+        lagranges
+            .iter()
+            .map(|(i, lagrange)| (*i, lagrange.as_slice()))
+            .collect(),
+        polys.iter().map(Vec::as_slice),
+    );
+    let mut q = vec![F::ZERO; domain.extended_n()];
+    parallelize(&mut q, |(q, start)| {
+        let mut cache = ev.cache();
+        izip!(q, start..).for_each(|(q, row)| ev.evaluate(q, &mut cache, row));
+    });
+    end_timer(timer);
+
+    domain.extended_lagrange_to_monomial(q.into())
+}
@@ -0,0 +1,59 @@
+use crate::util::{
+    arithmetic::{inner_product, BatchInvert, WithSmallOrderMulGroup},
+    expression::{evaluator::quotient::Radix2Domain, Expression, Query, Rotation},
+    Itertools,
+};
+use std::collections::{BTreeSet, HashMap};
+
+pub(super) fn instance_evals<F: WithSmallOrderMulGroup<3>>(
+    domain: &Radix2Domain<F>,
+    expression: &Expression<F>,
+    instances: &[Vec<F>],
+    x: F,
+) -> Vec<(Query, F)> {
+    let mut instance_query = expression.used_query();
+    instance_query.retain(|query| query.poly() < instances.len());
+
+    let max_instance_len = instances.iter().map(Vec::len).max().unwrap_or_default();
+    let omegas = (0..max_instance_len as i32)
+        .map(|i| domain.rotate_point(F::ONE, Rotation(i)))
+        .collect_vec();
+
+    instance_query
+        .into_iter()
+        .map(|query| {
+            let point = domain.rotate_point(x, query.rotation());
+            let lagrange_evals = {
+                let common = (point.pow_vartime([domain.n() as u64]) - F::ONE) * domain.n_inv();
+                let mut denoms = omegas.iter().map(|omega| point - omega).collect_vec();
+                denoms.batch_invert();
+                omegas
+                    .iter()
+                    .zip(denoms)
+                    .map(|(omega, denom)| common * omega * denom)
+                    .collect_vec()
+            };
+            let instances = &instances[query.poly()];
+            let eval = inner_product(instances, &lagrange_evals[..instances.len()]);
+            (query, eval)
+        })
+        .collect()
+}
+
+pub(super) fn points<F: WithSmallOrderMulGroup<3>>(
+    domain: &Radix2Domain<F>,
+    pcs_query: &BTreeSet<Query>,
+    x: F,
+) -> (Vec<F>, HashMap<Rotation, usize>) {
+    let rotations = pcs_query
+        .iter()
+        .map(Query::rotation)
+        .chain([Rotation::cur()])
+        .collect::<BTreeSet<_>>();
+    let points = rotations
+        .iter()
+        .map(|rotation| domain.rotate_point(x, *rotation))
+        .collect();
+    let point_idx = rotations.into_iter().zip(0..).collect();
+    (points, point_idx)
+}
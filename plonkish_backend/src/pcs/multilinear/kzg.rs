@@ -17,7 +17,7 @@ use crate::{
     Error,
 };
 use rand::RngCore;
-use std::{iter, marker::PhantomData, ops::Neg, slice};
+use std::{iter, marker::PhantomData, slice};
 
 #[derive(Clone, Debug)]
 pub struct MultilinearKzg<M: MultiMillerLoop>(PhantomData<M>);
@@ -327,23 +327,11 @@ where
 
         let quotients = transcript.read_commitments(point.len())?;
 
-        let window_size = window_size(point.len());
-        let window_table = window_table(window_size, vp.g2);
-        let rhs = chain![
-            [vp.g2.neg()],
-            vp.ss(point.len())
-                .iter()
-                .cloned()
-                .zip_eq(fixed_base_msm(window_size, &window_table, point))
-                .map(|(s_i, x_i)| (s_i - x_i.into()).into()),
-        ]
-        .map_into()
-        .collect_vec();
-        let lhs = chain![
-            [(comm.0.to_curve() - vp.g1 * eval).into()],
-            quotients.iter().cloned()
-        ]
-        .collect_vec();
+        // Since e(q_i, s_i - x_i * g2) = e(q_i, s_i) * e(x_i * q_i, -g2), the
+        // pairings are over the fixed points of `vp` only.
+        let c = (variable_base_msm(point, &quotients) + comm.0 - vp.g1 * eval).into();
+        let lhs = chain![[c], quotients].collect_vec();
+        let rhs = chain![[-vp.g2], vp.ss(point.len()).iter().copied()].collect_vec();
         M::pairings_product_is_identity(&lhs.iter().zip_eq(rhs.iter()).collect_vec())
             .then_some(())
             .ok_or_else(|| Error::InvalidPcsOpen("Invalid multilinear KZG open".to_string()))
@@ -242,8 +242,8 @@ where
         let pi = transcript.read_commitment()?;
 
         M::pairings_product_is_identity(&[
-            (&c, &(-vp.s_offset_g2).into()),
-            (&pi, &(vp.s_g2() - (vp.g2() * x).into()).to_affine().into()),
+            (&c, &-vp.s_offset_g2),
+            (&pi, &(vp.s_g2() - (vp.g2() * x).into()).to_affine()),
         ])
         .then_some(())
         .ok_or_else(|| Error::InvalidPcsOpen("Invalid Zeromorph KZG open".to_string()))
@@ -355,7 +355,7 @@ where
     ) -> Result<(), Error> {
         let pi = transcript.read_commitment()?;
         let c = (pi * point + comm.0 - vp.g1 * eval).into();
-        M::pairings_product_is_identity(&[(&c, &(-vp.g2).into()), (&pi, &vp.s_g2.into())])
+        M::pairings_product_is_identity(&[(&c, &-vp.g2), (&pi, &vp.s_g2)])
             .then_some(())
             .ok_or_else(|| Error::InvalidPcsOpen("Invalid univariate KZG open".to_string()))
     }
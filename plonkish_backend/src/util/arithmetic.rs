@@ -1,11 +1,14 @@
 use crate::util::{izip_eq, parallel::parallelize, BigUint, Itertools};
 use halo2_curves::{
-    bn256, grumpkin,
+    bn256,
+    group::GroupEncoding,
+    grumpkin,
     pairing::{self, MillerLoopResult},
     pasta::{pallas, vesta},
 };
 use num_integer::Integer;
-use std::{borrow::Borrow, fmt::Debug, iter};
+use rand::RngCore;
+use std::{any::Any, borrow::Borrow, cell::RefCell, collections::HashMap, fmt::Debug, iter};
 
 mod fft;
 mod msm;
@@ -23,18 +26,125 @@ pub use halo2_curves::{
     },
     Coordinates, CurveAffine, CurveExt,
 };
-pub use msm::{fixed_base_msm, variable_base_msm, window_size, window_table, Msm};
+pub use msm::{
+    count_msms, fixed_base_msm, variable_base_msm, window_size, window_table, Msm, MsmCounts,
+};
+
+/// Terms of a deferred check that the product of their pairings is the
+/// identity, see [`defer_pairings`].
+pub type PairingCheck<M> = Vec<(
+    <M as pairing::Engine>::G1Affine,
+    <M as pairing::Engine>::G2Affine,
+)>;
+
+thread_local! {
+    /// Checks deferred by the innermost [`defer_pairings`] running on this
+    /// thread, a `Vec<PairingCheck<M>>` of its engine `M`.
+    static DEFERRED_PAIRINGS: RefCell<Option<Box<dyn Any>>> = RefCell::new(None);
+}
+
+pub trait MultiMillerLoop: pairing::MultiMillerLoop + Debug + Sync + 'static {
+    /// Returns whether the product of the pairings of `terms` is the identity.
+    /// Within [`defer_pairings`] of `Self`, the check is deferred instead and
+    /// `true` is returned.
+    fn pairings_product_is_identity(terms: &[(&Self::G1Affine, &Self::G2Affine)]) -> bool {
+        let deferred = DEFERRED_PAIRINGS.with(|deferred| {
+            let mut deferred = deferred.borrow_mut();
+            match deferred
+                .as_mut()
+                .and_then(|checks| checks.downcast_mut::<Vec<PairingCheck<Self>>>())
+            {
+                Some(checks) => {
+                    checks.push(terms.iter().map(|(g1, g2)| (**g1, **g2)).collect());
+                    true
+                }
+                None => false,
+            }
+        });
+        if deferred {
+            return true;
+        }
+        let prepared = terms
+            .iter()
+            .map(|(_, g2)| Self::G2Prepared::from(**g2))
+            .collect_vec();
+        let terms = izip_eq!(terms, &prepared)
+            .map(|((g1, _), g2)| (*g1, g2))
+            .collect_vec();
+        Self::multi_miller_loop(&terms)
+            .final_exponentiation()
+            .is_identity()
+            .into()
+    }
 
-pub trait MultiMillerLoop: pairing::MultiMillerLoop + Debug + Sync {
-    fn pairings_product_is_identity(terms: &[(&Self::G1Affine, &Self::G2Prepared)]) -> bool {
-        Self::multi_miller_loop(terms)
+    /// Returns whether every one of `checks` holds, with a single multi Miller
+    /// loop and final exponentiation, of a random linear combination of them:
+    /// the terms of each check but the first are scaled by a random 128-bit
+    /// scalar of that check from `rng`, and the terms of every check with the
+    /// same `G2` point are summed into one pairing. A failing check makes the
+    /// combination the identity with probability at most `2^-128`.
+    ///
+    /// `rng` must be unpredictable to whoever made the checks, e.g. the
+    /// prover of the verified proofs. Checks over the same few `G2` points,
+    /// as of KZG openings with the same verifier param, take about as many
+    /// pairings together as one of them alone.
+    fn pairing_checks_hold(checks: &[PairingCheck<Self>], mut rng: impl RngCore) -> bool {
+        let mut groups =
+            HashMap::<_, (Self::G2Affine, Vec<Self::Scalar>, Vec<Self::G1Affine>)>::new();
+        for (idx, check) in checks.iter().enumerate() {
+            let scalar = if idx == 0 {
+                Self::Scalar::ONE
+            } else {
+                Self::Scalar::from_u128((rng.next_u64() as u128) << 64 | rng.next_u64() as u128)
+            };
+            for (g1, g2) in check {
+                let (_, scalars, bases) = groups
+                    .entry(g2.to_bytes().as_ref().to_vec())
+                    .or_insert_with(|| (*g2, Vec::new(), Vec::new()));
+                scalars.push(scalar);
+                bases.push(*g1);
+            }
+        }
+        let (lhs, rhs): (Vec<Self::G1Affine>, Vec<Self::G2Affine>) = groups
+            .into_values()
+            .map(|(g2, scalars, bases)| (variable_base_msm(&scalars, &bases).to_affine(), g2))
+            .unzip();
+        let rhs = rhs.into_iter().map(Self::G2Prepared::from).collect_vec();
+        Self::multi_miller_loop(&izip_eq!(&lhs, &rhs).collect_vec())
             .final_exponentiation()
             .is_identity()
             .into()
     }
 }
 
-impl<M> MultiMillerLoop for M where M: pairing::MultiMillerLoop + Debug + Sync {}
+impl<M> MultiMillerLoop for M where M: pairing::MultiMillerLoop + Debug + Sync + 'static {}
+
+/// Runs `f` with the checks of [`MultiMillerLoop::pairings_product_is_identity`]
+/// of `M` on this thread deferred, returning the output of `f` with the
+/// deferred checks, e.g. to verify many proofs and check all of their
+/// pairings at once with [`MultiMillerLoop::pairing_checks_hold`]. Since each
+/// deferred check is taken to hold within `f`, an output of `f` relying on
+/// them holds only if the returned checks do.
+pub fn defer_pairings<M: MultiMillerLoop, T>(f: impl FnOnce() -> T) -> (T, Vec<PairingCheck<M>>) {
+    /// Restores the checks deferred before, even if `f` panics, so no check
+    /// is deferred past `defer_pairings`.
+    struct Restore(Option<Option<Box<dyn Any>>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take().unwrap();
+            DEFERRED_PAIRINGS.with(|deferred| *deferred.borrow_mut() = previous);
+        }
+    }
+
+    let checks: Box<dyn Any> = Box::<Vec<PairingCheck<M>>>::default();
+    let previous = DEFERRED_PAIRINGS.with(|deferred| deferred.replace(Some(checks)));
+    let restore = Restore(Some(previous));
+    let output = f();
+    let checks = DEFERRED_PAIRINGS.with(|deferred| deferred.take()).unwrap();
+    drop(restore);
+    (output, *checks.downcast().unwrap())
+}
 
 pub trait TwoChainCurve: CurveAffine {
     type Secondary: TwoChainCurve<ScalarExt = Self::Base, Base = Self::ScalarExt, Secondary = Self>;
@@ -229,11 +339,54 @@ pub fn batch_projective_to_affine<C: CurveAffine>(projectives: &[C::Curve]) -> V
 
 #[cfg(test)]
 mod test {
-    use crate::util::arithmetic;
+    use crate::util::{
+        arithmetic::{
+            self, count_msms, defer_pairings, variable_base_msm, MsmCounts, MultiMillerLoop,
+        },
+        test::std_rng,
+    };
     use halo2_curves::bn256;
 
     #[test]
     fn field_size() {
         assert_eq!(arithmetic::field_size::<bn256::Fr>(), 254);
     }
+
+    #[test]
+    fn deferred_pairings() {
+        let (g1, g2) = (bn256::G1Affine::generator(), bn256::G2Affine::generator());
+        let check = |g1s: [bn256::G1Affine; 2]| {
+            bn256::Bn256::pairings_product_is_identity(&[(&g1s[0], &g2), (&g1s[1], &g2)])
+        };
+        let (holds, fails) = ([g1, -g1], [g1, g1]);
+        assert!(check(holds) && !check(fails));
+
+        let (deferred, checks) = defer_pairings::<bn256::Bn256, _>(|| check(fails));
+        assert!(deferred && checks.len() == 1);
+        assert!(!bn256::Bn256::pairing_checks_hold(&checks, std_rng()));
+        // Nothing is deferred past it.
+        assert!(!check(fails));
+
+        let (_, checks) = defer_pairings::<bn256::Bn256, _>(|| (check(holds), check(holds)));
+        assert!(bn256::Bn256::pairing_checks_hold(&checks, std_rng()));
+        let (_, checks) = defer_pairings::<bn256::Bn256, _>(|| (check(holds), check(fails)));
+        assert!(!bn256::Bn256::pairing_checks_hold(&checks, std_rng()));
+        assert!(bn256::Bn256::pairing_checks_hold(&[], std_rng()));
+    }
+
+    #[test]
+    fn counted_msms() {
+        let bases = [bn256::G1Affine::generator(); 3];
+        let scalars = [bn256::Fr::one(); 3];
+        let msm = |n: usize| variable_base_msm(&scalars[..n], &bases[..n]);
+        let ((_, inner), outer) = count_msms(|| {
+            msm(1);
+            count_msms(|| msm(3))
+        });
+        assert_eq!(inner, MsmCounts { msms: 1, bases: 3 });
+        assert_eq!(outer, MsmCounts { msms: 2, bases: 4 });
+        // Nothing is counted past it.
+        msm(2);
+        assert_eq!(count_msms(|| ()).1, MsmCounts::default());
+    }
 }
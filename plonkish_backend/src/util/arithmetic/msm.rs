@@ -8,6 +8,7 @@ use crate::{
     },
 };
 use std::{
+    cell::Cell,
     iter::Sum,
     mem::size_of,
     ops::{Add, Mul, Neg, Sub},
@@ -88,6 +89,38 @@ pub fn fixed_base_msm<'a, C: CurveAffine>(
     outputs
 }
 
+thread_local! {
+    /// Counts of the innermost [`count_msms`] running on this thread.
+    static MSM_COUNTS: Cell<Option<MsmCounts>> = Cell::new(None);
+}
+
+/// Number of calls of [`variable_base_msm`] and their total number of bases,
+/// see [`count_msms`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MsmCounts {
+    pub msms: usize,
+    pub bases: usize,
+}
+
+/// Runs `f`, returning its output with the [`MsmCounts`] of the calls of
+/// [`variable_base_msm`] on this thread within, e.g. as a machine independent
+/// proxy of the group work of a verifier. Fixed base MSMs and single scalar
+/// multiplications aren't counted.
+pub fn count_msms<T>(f: impl FnOnce() -> T) -> (T, MsmCounts) {
+    let previous = MSM_COUNTS.with(|counts| counts.replace(Some(MsmCounts::default())));
+    let output = f();
+    let counts = MSM_COUNTS.with(|counts| counts.replace(previous)).unwrap();
+    if let Some(previous) = previous {
+        MSM_COUNTS.with(|outer| {
+            outer.set(Some(MsmCounts {
+                msms: previous.msms + counts.msms,
+                bases: previous.bases + counts.bases,
+            }))
+        });
+    }
+    (output, counts)
+}
+
 // Copy from https://github.com/zcash/halo2/blob/main/halo2_proofs/src/arithmetic.rs
 pub fn variable_base_msm<'a, 'b, C: CurveAffine>(
     scalars: impl IntoIterator<Item = &'a C::Scalar>,
@@ -96,11 +129,21 @@ pub fn variable_base_msm<'a, 'b, C: CurveAffine>(
     let scalars = scalars.into_iter().collect_vec();
     let bases = bases.into_iter().collect_vec();
     assert_eq!(scalars.len(), bases.len());
+    MSM_COUNTS.with(|counts| {
+        if let Some(MsmCounts { msms, bases }) = counts.get() {
+            counts.set(Some(MsmCounts {
+                msms: msms + 1,
+                bases: bases + scalars.len(),
+            }))
+        }
+    });
 
     let _timer = start_timer(|| format!("variable_base_msm-{}", scalars.len()));
 
     let num_threads = num_threads();
-    if scalars.len() <= num_threads {
+    if scalars.is_empty() {
+        return C::Curve::identity();
+    } else if scalars.len() <= num_threads {
         let mut result = C::Curve::identity();
         variable_base_msm_serial(&scalars, &bases, &mut result);
         return result;
@@ -1,8 +1,8 @@
 #[cfg(feature = "timer")]
-pub use enabled::{end_timer, start_timer, start_unit_timer};
+pub use enabled::{end_timer, set_timers_quiet, start_timer, start_unit_timer};
 
 #[cfg(not(feature = "timer"))]
-pub use disabled::{end_timer, start_timer, start_unit_timer};
+pub use disabled::{end_timer, set_timers_quiet, start_timer, start_unit_timer};
 
 #[cfg(feature = "timer")]
 mod enabled {
@@ -10,6 +10,17 @@ mod enabled {
     use std::sync::atomic::{AtomicBool, Ordering};
 
     static IN_UNIT: AtomicBool = AtomicBool::new(false);
+    static QUIET: AtomicBool = AtomicBool::new(false);
+
+    /// Stops every timer started afterwards from printing, or lets them print
+    /// again, e.g. to keep a long run's output readable.
+    pub fn set_timers_quiet(quiet: bool) {
+        QUIET.store(quiet, Ordering::Relaxed);
+    }
+
+    fn is_quiet() -> bool {
+        QUIET.load(Ordering::Relaxed)
+    }
 
     pub struct TimerInfo {
         pub inner: Option<InnerTimerInfo>,
@@ -18,7 +29,8 @@ mod enabled {
 
     pub fn start_timer<T: ToString>(_msg: impl Fn() -> T) -> TimerInfo {
         TimerInfo {
-            inner: (!IN_UNIT.load(Ordering::Relaxed)).then(|| start_timer!(|| _msg().to_string())),
+            inner: (!IN_UNIT.load(Ordering::Relaxed) && !is_quiet())
+                .then(|| start_timer!(|| _msg().to_string())),
             unit: false,
         }
     }
@@ -31,7 +43,7 @@ mod enabled {
             panic!("Unit timer should not be nested");
         }
         TimerInfo {
-            inner: Some(start_timer!(|| _msg().to_string())),
+            inner: (!is_quiet()).then(|| start_timer!(|| _msg().to_string())),
             unit: true,
         }
     }
@@ -49,11 +61,12 @@ mod enabled {
     fn end_timer_inner(info: &mut TimerInfo) {
         if let Some(inner) = info.inner.take() {
             end_timer!(inner);
-            if info.unit {
-                IN_UNIT
-                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |_| Some(false))
-                    .unwrap();
-            }
+        }
+        if info.unit {
+            info.unit = false;
+            IN_UNIT
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |_| Some(false))
+                .unwrap();
         }
     }
 }
@@ -62,6 +75,8 @@ mod enabled {
 mod disabled {
     pub struct TimerInfo;
 
+    pub fn set_timers_quiet(_: bool) {}
+
     pub fn start_timer<T: ToString>(_: impl Fn() -> T) -> TimerInfo {
         TimerInfo
     }
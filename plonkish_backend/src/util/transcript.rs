@@ -104,6 +104,22 @@ pub struct FiatShamirTranscript<H, S> {
     stream: S,
 }
 
+impl<H: Hash, S> FiatShamirTranscript<H, S> {
+    /// Returns a transcript reading from or writing to `stream`, e.g. a
+    /// buffered file the proof is written to as it's produced instead of
+    /// being kept in memory as by [`InMemoryTranscript`].
+    pub fn from_stream(stream: S) -> Self {
+        Self {
+            state: H::default(),
+            stream,
+        }
+    }
+
+    pub fn into_stream(self) -> S {
+        self.stream
+    }
+}
+
 impl<H: Hash> InMemoryTranscript for FiatShamirTranscript<H, Cursor<Vec<u8>>> {
     type Param = ();
 
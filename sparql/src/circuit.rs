@@ -0,0 +1,3934 @@
+//! Circuits proving the solutions of a [`SelectQuery`] over an [`RdfGraph`]
+//! under its [`MerkleTripleSet::root`], compiled by [`compile_bgp`] and its
+//! variants.
+//!
+//! # Semantics
+//!
+//! Every triple matched by a solution is proven to be a leaf under the root,
+//! with bindings of shared variables copy constrained, so the verifier learns
+//! nothing of the dataset beyond its root and the results. Filtered bindings
+//! are opened as integers by [`integer_encoding`], and each comparison is
+//! proven by range checking the difference to [`NUM_FILTER_BITS`] bits, or
+//! those of [`compile_bgp_with_value_bits`]. The circuit proves each reported
+//! solution is a solution, but not that every solution is reported, except
+//! for the [empty results](#empty-results) of a single pattern.
+//!
+//! ## GRAPH
+//!
+//! A triple pattern in a `GRAPH` clause opens its triple as a quad, whose
+//! [`Domain::Quad`] leaf binds the graph name to its constant or variable as
+//! any term, while any other pattern opens a [`Domain::Triple`] leaf, which
+//! only triples of the default graph have.
+//!
+//! ## Counts
+//!
+//! With a [`Count`] projection, the only result is the number `n` of distinct
+//! values of the counted variable, proven by one solution per value with the
+//! values constrained pairwise distinct. As above, the proof then attests that
+//! at least `n` distinct values match, so `n` is a lower bound, which
+//! [`InstanceLayout::count_lower_bound`] marks.
+//!
+//! ## OPTIONAL
+//!
+//! With an `OPTIONAL` block, each solution witnesses a boolean `present`, and
+//! the Merkle membership and bindings of each optional pattern are only
+//! constrained if it's `1`. The variables only bound by the block are bound to
+//! `present` times the matched term, which is [`NULL_ENCODING`] if it's `0`.
+//! As above, the proof doesn't attest that an unmatched block has no match.
+//!
+//! ## UNION
+//!
+//! With a `UNION` of two branches, each solution witnesses a boolean `branch`
+//! selecting the right one, and each pattern of a branch is constrained as the
+//! optional ones under its selector. A variable only bound by the branches is
+//! bound to the sum of the selected term of each branch binding it, so it's
+//! [`NULL_ENCODING`] if the selected branch doesn't.
+//!
+//! ## FILTER NOT EXISTS
+//!
+//! With `FILTER NOT EXISTS` of a single pattern, whose variables all have to
+//! be bound by the required patterns, the leaf the pattern opens per solution
+//! is proven absent from the dataset by the [`NonMembershipProof`] of its
+//! neighbors. The [`MerkleTripleSet`] sorts its leaves as integers and pads
+//! them with [`PADDING_LEAF`], so the leaves at two adjacent indices below
+//! and above the leaf, or the first or last one with `0` or the modulus minus
+//! one as bound, leave no room for it. This relies on the root being the one
+//! of a sorted set, which the verifier has to trust of the dataset root
+//! anyway. Each solution then costs two Merkle paths with their indices, one
+//! or two hashes and three limbs and comparisons.
+//!
+//! ## GROUP BY
+//!
+//! With `GROUP BY ?k` and a [`Count`] of `?x`, the solutions are deduplicated
+//! by the encodings of `?k` and `?x`, sorted by them as integers and split
+//! into groups of equal `?k`, whose results are `?k` and the count. Within a
+//! group the key cells are copy constrained equal and the values of `?x`
+//! strictly increasing, while the keys of consecutive groups are strictly
+//! increasing, so each group is of a distinct key and counts distinct values.
+//! The boundaries of the groups are fixed by the count of each, which the
+//! [`QueryCircuitShape`] is then of. As for a single count, the proof attests
+//! that at least the count of each group matches. The sort costs the limbs
+//! and a comparison of about `2·128 + 4` rows each per solution, and another
+//! limbs and two comparisons per group, so about `2·(2·128 + 4)` rows per
+//! counted value against the quadratic distinctness constraints of a
+//! [`Count`] without `GROUP BY`, which also isn't bounded by [`MAX_COUNT`].
+//!
+//! ## DISTINCT
+//!
+//! With `SELECT DISTINCT`, the results are sorted by the key of their
+//! projected encodings, which is the only one for a single projected variable
+//! and otherwise chains [`hash_node`] over them. Each key is decomposed into
+//! a 128 bit and a 126 bit limb, and consecutive keys are constrained strictly
+//! increasing by range checking the limbs of their difference, with the last
+//! key below the modulus so the limbs are canonical. That costs about
+//! `2·(2·128 + 4)` rows per result plus `m - 1` hashes for `m` projected
+//! variables, so the results are pairwise distinct without the quadratic
+//! constraints of a [`Count`].
+//!
+//! ## BIND
+//!
+//! With `BIND(?a op ?b AS ?c)` of `+`, `-` or `*`, each variable operand is
+//! opened as an integer as in filters, `?c` is bound to the
+//! [`integer_encoding`] of the result and can be projected, filtered and so
+//! on as any other variable. Operands and results are `i64`, so the result is
+//! range checked to 64 bits shifted by `2^63`, or the value bits of
+//! [`compile_bgp_with_value_bits`], and as the operands are `i64` too a
+//! product stays below `2^126` and never wraps the field. A solution
+//! whose result is out of range isn't one of [`evaluate`], as for an operand
+//! that isn't an integer literal.
+//!
+//! ## String filters
+//!
+//! With `FILTER(?x = "s")` of an `xsd:string` literal `s` of at most
+//! [`MAX_STRING_LEN`] bytes, the binding of `?x` is opened as the
+//! [`string_encoding`] of limbs constrained equal to the length and limbs of
+//! `s`, so it's the encoding of `s` and of no other string. A longer `s`
+//! fails with [`CompileError::StringTooLong`].
+//!
+//! ## Boolean filters
+//!
+//! With a boolean `FILTER` of `!`, `&&` and `||` over comparisons, each
+//! comparison is evaluated to a boolean cell, by the inverse of the
+//! difference for `=` and `!=` and by range checking either the difference
+//! or its complement for an ordering, which are combined by products and
+//! complements, and the result is constrained to `1`. A top-level `&&` is
+//! split into filters instead. A binding that isn't a literal of the
+//! datatype of its comparison fails the whole filter rather than being a
+//! SPARQL error `||` could absorb, see [`FilterCondition`].
+//!
+//! ## VALUES
+//!
+//! With `VALUES`, the binding of its variable in each solution is constrained
+//! to be one of the encodings of its terms, which are constants hashed into
+//! the [`query_commitment`], by a product of differences of one row per term.
+//!
+//! ## ORDER BY
+//!
+//! With `ORDER BY ?x` of a projected `?x`, the results are sorted by the
+//! encoding of `?x` ascending, constrained as the keys of `SELECT DISTINCT`
+//! but non-decreasing, which `SELECT DISTINCT ?x` already is. Other
+//! conditions aren't supported yet.
+//!
+//! ## LIMIT
+//!
+//! With `LIMIT n`, only the first `n` solutions in the order above are
+//! proven, which are the top `n` with `ORDER BY`, and the `n` results are
+//! padded past them with [`NULL_ENCODING`], see [`InstanceLayout`]. Padding
+//! isn't a solution, so it's neither ordered nor counted by a [`Count`],
+//! whose single result is only dropped by `LIMIT 0`.
+//!
+//! ## Empty results
+//!
+//! A query without solutions over a dataset compiles to a circuit of no
+//! results, `n` results of [`NULL_ENCODING`] under `LIMIT n`, or the count
+//! `0` with a [`Count`]. For a single triple pattern without `UNION`,
+//! filters, `VALUES`, `BIND` or `FILTER NOT EXISTS`, the proof attests that
+//! nothing matches by opening every leaf of the tree as padding, a triple or
+//! a quad with its terms, hashing them into the root, and constraining each
+//! one the pattern could match to differ from it in a constant or a repeated
+//! variable. The leaves are sorted by their hashes, so the ones of a pattern
+//! aren't a range of adjacent leaves whose neighbors a [`NonMembershipProof`]
+//! could bound, and the scan costs two hashes, a node and about 30 rows per
+//! leaf. Any other query without solutions fails with
+//! [`CompileError::UnsupportedEmpty`], except for a [`Count`] without
+//! `GROUP BY`, whose `0` is a lower bound anyway, and for `LIMIT 0`.
+//!
+//! [`Count`]: crate::query::Count
+//! [`NonMembershipProof`]: crate::merkle::NonMembershipProof
+//! [`string_encoding`]: crate::hash::string_encoding
+
+use crate::{
+    eval::{evaluate, matched_subgraph, Solution},
+    hash::{
+        hash3, hash_node, i64_to_field, integer_encoding, string_limbs, variable_encoding, Domain,
+        HashChoice, MAX_STRING_LEN,
+    },
+    merkle::{MerklePath, MerkleTripleSet, SubsetProof, PADDING_LEAF},
+    query::{
+        ArithOp, CompareOp, FilterCondition, FilterDatatype, FilterExpr, Operand, OrderCondition,
+        Pattern, SelectQuery, TermPattern, TriplePattern, Variable,
+    },
+    rdf::{RdfGraph, Term},
+};
+use plonkish_backend::{
+    backend::{PlonkishCircuit, PlonkishCircuitInfo, WitnessEncoding},
+    halo2_curves::bn256::Fr,
+    util::{
+        arithmetic::{Field, PrimeField},
+        chain, Itertools,
+    },
+    Error,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt::{self, Display},
+    iter,
+    sync::Arc,
+};
+
+mod artifact;
+mod builder;
+mod cache;
+mod check;
+mod estimate;
+mod instance;
+mod shape;
+
+use builder::{limb_values, Cell, CircuitBuilder, NUM_FIXED_POLYS, NUM_WITNESS_POLYS};
+
+pub use artifact::{load_circuit, save_circuit, CircuitFileError, CIRCUIT_FORMAT_VERSION};
+pub use cache::{CircuitCache, CircuitKey};
+pub use check::{check_satisfied, ConstraintViolation};
+pub use estimate::{
+    estimate_circuit_size, estimate_circuit_size_with_solutions,
+    estimate_circuit_size_with_strategy, triple_capacity, CircuitSizeEstimate,
+};
+pub use instance::{
+    decode_instances, format_instances, results_to_bindings, DecodeError, InstanceLayout,
+    QueryPublicInputs, TermDictionary,
+};
+pub use shape::{QueryCircuitShape, Witness, WitnessError};
+
+/// Default number of bits of the integers of filters and `BIND`, which
+/// covers any `i64`, see [`compile_bgp_with_value_bits`].
+pub const NUM_FILTER_BITS: usize = 64;
+
+/// Encoding of a projected variable left unbound by an unmatched `OPTIONAL`
+/// block, which no [`Term::encode`] hits but with negligible probability.
+pub const NULL_ENCODING: Fr = Fr::ZERO;
+
+/// Maximum count of a [`Count`] projection, bounding the quadratic number of
+/// distinctness constraints.
+///
+/// [`Count`]: crate::query::Count
+pub const MAX_COUNT: usize = 1 << 8;
+
+/// How the circuit proves the triple matched by each pattern of a solution is
+/// in the dataset, see [`compile_bgp_with_strategy`].
+///
+/// A Merkle path costs `depth` node hashes per matched triple, while a lookup
+/// costs a row per matched triple on top of laying out the `2^depth` leaves
+/// as a table once and hashing them into the root by `2^depth - 1` node
+/// hashes. The lookup is cheaper in rows once the matched triples exceed
+/// about `2^depth / depth`, e.g. 11 matches over 64 triples or 640 over 8192,
+/// though the lookup argument adds its own commitments to the prove time, so
+/// the crossover in prove time is best measured by benchmarking both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchStrategy {
+    /// A [`MerklePath`] from each matched leaf to the dataset root.
+    #[default]
+    Merkle,
+    /// A lookup of each matched leaf into a table of every leaf of the
+    /// [`MerkleTripleSet`], whose root is hashed in-circuit from the table.
+    Lookup,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompileError {
+    /// `UNION` of other than two branches, which isn't supported yet.
+    UnsupportedUnion { num_branches: usize },
+    /// Variable of the `OPTIONAL` block bound by only one branch of the
+    /// `UNION`, which the block could bind otherwise, which isn't supported
+    /// yet.
+    UnsupportedOptionalVariable(Variable),
+    /// Projected, filtered or counted variable not appearing in any triple
+    /// pattern, or operand of a `BIND` not bound by the required patterns or
+    /// an earlier `BIND`.
+    UnboundVariable(Variable),
+    /// Count of distinct values exceeding [`MAX_COUNT`].
+    CountOverflow { count: usize },
+    /// Number of solutions differing from the one of the shape, or solutions
+    /// over the dataset of a query given none, whose
+    /// [empty result](crate::circuit#empty-results) would be proven.
+    NumSolutions { expected: usize, found: usize },
+    /// Counts of the groups of `GROUP BY` differing from the ones of the
+    /// shape.
+    GroupCounts {
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    /// Merkle depth exceeding the one of the shape.
+    Depth { max: usize, found: usize },
+    /// Number of triples of the dataset exceeding the [`triple_capacity`] of
+    /// the circuit at `k`.
+    CapacityExceeded { have: usize, max: usize, k: usize },
+    /// `ORDER BY` other than a single ascending condition, or with `DISTINCT`
+    /// of several variables, which isn't supported yet.
+    UnsupportedOrder { reason: &'static str },
+    /// Variable of `ORDER BY` that isn't projected.
+    UnprojectedOrderVariable(Variable),
+    /// Hash of the [`MerkleTripleSet`] that isn't
+    /// [`HashChoice::is_supported_in_circuit`], whose root the circuit
+    /// couldn't reach.
+    UnsupportedHash(HashChoice),
+    /// `FILTER NOT EXISTS` of several patterns, of a variable not bound by
+    /// the required patterns, or over a disclosed subset by
+    /// [`compile_bgp_over_subset`], which isn't supported yet.
+    UnsupportedNotExists { reason: &'static str },
+    /// String of a [`StringFilter`] longer than the [`MAX_STRING_LEN`] bytes
+    /// the circuit can compare.
+    ///
+    /// [`StringFilter`]: crate::query::StringFilter
+    StringTooLong { length: usize, max: usize },
+    /// Bits of integers outside `1..=64`, see [`compile_bgp_with_value_bits`].
+    UnsupportedValueBits { value_bits: usize },
+    /// Integer of a filter or `BIND`, of the query or a solution, that isn't
+    /// a signed integer of `value_bits` bits.
+    ValueOutOfRange { value: i64, value_bits: usize },
+    /// No solutions of a query other than a single triple pattern without
+    /// `UNION`, filters, `VALUES`, `BIND` or `FILTER NOT EXISTS`, or over a
+    /// disclosed subset by [`compile_bgp_over_subset`], whose
+    /// [empty result](crate::circuit#empty-results) the circuit can't prove.
+    UnsupportedEmpty { reason: &'static str },
+    /// Solution at `idx` of [`compile_bgp_with_solutions`] that isn't one of
+    /// [`evaluate`] of the query over the dataset.
+    NotASolution { idx: usize },
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::UnsupportedUnion { num_branches } => {
+                write!(f, "union of {num_branches} branches is not supported")
+            }
+            CompileError::UnsupportedOptionalVariable(variable) => write!(
+                f,
+                "variable {variable} of OPTIONAL bound by only one branch of UNION is not supported"
+            ),
+            CompileError::UnboundVariable(variable) => {
+                write!(f, "variable {variable} is not bound by any pattern")
+            }
+            CompileError::CountOverflow { count } => {
+                write!(f, "count {count} exceeds maximum {MAX_COUNT}")
+            }
+            CompileError::NumSolutions { expected, found } => {
+                write!(f, "expected {expected} solutions, found {found}")
+            }
+            CompileError::GroupCounts { expected, found } => {
+                write!(
+                    f,
+                    "expected groups of {expected:?} solutions, found {found:?}"
+                )
+            }
+            CompileError::Depth { max, found } => {
+                write!(f, "merkle depth {found} exceeds maximum {max}")
+            }
+            CompileError::CapacityExceeded { have, max, k } => write!(
+                f,
+                "{have} triples exceed the capacity of {max} at k = {k}, where each doubling \
+                 costs a Merkle level per pattern of each solution out of the 2^{k} rows, \
+                 so increase k"
+            ),
+            CompileError::UnsupportedOrder { reason } => {
+                write!(f, "ORDER BY {reason} is not supported")
+            }
+            CompileError::UnprojectedOrderVariable(variable) => {
+                write!(f, "variable {variable} of ORDER BY is not projected")
+            }
+            CompileError::UnsupportedHash(hash) => write!(
+                f,
+                "{hash} is not supported in-circuit, so the Merkle root couldn't be proven, \
+                 use {} instead",
+                HashChoice::Poseidon
+            ),
+            CompileError::UnsupportedNotExists { reason } => {
+                write!(f, "FILTER NOT EXISTS {reason} is not supported")
+            }
+            CompileError::StringTooLong { length, max } => write!(
+                f,
+                "string literal of {length} bytes in FILTER exceeds the bound of {max} bytes"
+            ),
+            CompileError::UnsupportedValueBits { value_bits } => {
+                write!(f, "value bits {value_bits} are not in 1..=64")
+            }
+            CompileError::ValueOutOfRange { value, value_bits } => {
+                write!(f, "integer {value} does not fit {value_bits} signed bits")
+            }
+            CompileError::UnsupportedEmpty { reason } => {
+                write!(f, "empty result {reason} is not supported")
+            }
+            CompileError::NotASolution { idx } => {
+                write!(
+                    f,
+                    "solution {idx} is not a solution of the query over the dataset"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Circuit proving the solutions of a [`SelectQuery`] over an [`RdfGraph`],
+/// pairing a [`QueryCircuitShape`] with a [`Witness`] of it.
+#[derive(Clone, Debug)]
+pub struct QueryCircuit {
+    shape: Arc<QueryCircuitShape>,
+    witness: Witness,
+}
+
+impl QueryCircuit {
+    /// Pairs `shape` with `witness`, which has to be given by
+    /// [`QueryCircuitShape::witness_for`] of the same `shape`.
+    pub fn new(shape: Arc<QueryCircuitShape>, witness: Witness) -> Self {
+        assert_eq!(witness.instances[0].len(), shape.layout.num_instances());
+        Self { shape, witness }
+    }
+
+    pub fn shape(&self) -> &Arc<QueryCircuitShape> {
+        &self.shape
+    }
+
+    pub fn witness(&self) -> &Witness {
+        &self.witness
+    }
+
+    pub fn instance_layout(&self) -> &InstanceLayout {
+        self.shape.instance_layout()
+    }
+}
+
+impl PlonkishCircuit<Fr> for QueryCircuit {
+    fn circuit_info_without_preprocess(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+        let circuit_info = self.shape.circuit_info();
+        let n = 1 << circuit_info.k;
+        Ok(PlonkishCircuitInfo {
+            preprocess_polys: vec![vec![Fr::ZERO; n]; NUM_FIXED_POLYS],
+            permutations: (0..NUM_WITNESS_POLYS)
+                .map(|column| vec![(builder::witness_poly(column), 1)])
+                .collect(),
+            ..circuit_info.clone()
+        })
+    }
+
+    fn circuit_info(&self) -> Result<PlonkishCircuitInfo<Fr>, Error> {
+        Ok(self.shape.circuit_info().clone())
+    }
+
+    fn instances(&self) -> &[Vec<Fr>] {
+        &self.witness.instances
+    }
+
+    fn synthesize(&self, round: usize, challenges: &[Fr]) -> Result<Vec<Vec<Fr>>, Error> {
+        assert!(round == 0 && challenges.is_empty());
+        Ok(self.witness.witness_polys.clone())
+    }
+}
+
+/// Returns the commitment to `query` exposed as first instance by
+/// [`compile_bgp`], which chains [`hash_node`] over the [`Domain::Pattern`]
+/// hash of each triple pattern, the [`Domain::Union`] hash of each `UNION`
+/// branch header and pattern, the [`Domain::Optional`] hash of each pattern
+/// of the `OPTIONAL` block, the [`Domain::Filter`] hash of each filter as
+/// `(variable, op, value)`, the projected variables, the
+/// [`Domain::Aggregate`] hash of the counted variable as `(variable, 0, 0)` if
+/// any, the [`Domain::Distinct`] hash of `(0, 0, 0)` if the query is
+/// `SELECT DISTINCT`, the [`Domain::Values`] hash of the header and each
+/// term of the `VALUES` if any, the [`Domain::Order`] hash of each
+/// condition of `ORDER BY` as `(variable, descending, 0)`, the
+/// [`Domain::Limit`] hash of `(n, 0, 0)` for `LIMIT n` if any, the
+/// [`Domain::NotExists`] hash of each pattern of the `FILTER NOT EXISTS`
+/// block, then the [`Domain::Graph`] hash of each pattern in a `GRAPH` clause
+/// as `(pattern, graph, 0)`, with `pattern` its index among the patterns in
+/// the order above, so a query without either commits as before, then the
+/// [`Domain::Bind`] hashes of each `BIND`, the [`Domain::StringFilter`]
+/// hash of each string filter as `(variable, value, 0)` and the
+/// [`Domain::Group`] hash of the `GROUP BY` variable as `(variable, 0, 0)` if
+/// any, then the [`Domain::Condition`] hashes of each boolean filter
+/// condition in postfix order. IRIs are
+/// encoded by [`Term::encode`], variables by [`variable_encoding`] of their
+/// index in [`SelectQuery::variables`], followed by the ones only in the
+/// `FILTER NOT EXISTS` block, and operators by their discriminant.
+///
+/// A verifier compares the first instance to the commitment of the query it
+/// expects, e.g. by [`QueryPublicInputs::matches_query`], which holds for any
+/// query equivalent up to what's canonicalized: parsing drops whitespace,
+/// expands prefixed names and `a`, and orients filters, while variables are
+/// numbered by first appearance so consistently renaming them is fine,
+/// filters, string filters and the terms of `VALUES` are hashed sorted by
+/// encoding without
+/// duplicates, and `DISTINCT` is dropped for a [`Count`]. Triple patterns keep
+/// their order, which numbers the variables and lays out the circuit, so one
+/// reordered by [`SelectQuery::with_pattern_order`] commits differently.
+///
+/// [`Count`]: crate::query::Count
+pub fn query_commitment(query: &SelectQuery) -> Fr {
+    let QueryEncoding {
+        patterns,
+        union,
+        optional,
+        filters,
+        projection,
+        count,
+        distinct,
+        values,
+        order,
+        limit,
+        not_exists,
+        graphs,
+        binds,
+        string_filters,
+        group,
+        conditions,
+    } = QueryEncoding::new(query);
+    let acc = patterns.into_iter().fold(Fr::ZERO, |acc, pattern| {
+        hash_node(acc, hash3(pattern, Domain::Pattern))
+    });
+    let acc = union.into_iter().fold(acc, |acc, pattern| {
+        hash_node(acc, hash3(pattern, Domain::Union))
+    });
+    let acc = optional.into_iter().fold(acc, |acc, pattern| {
+        hash_node(acc, hash3(pattern, Domain::Optional))
+    });
+    let acc = filters.into_iter().fold(acc, |acc, filter| {
+        hash_node(acc, hash3(filter, Domain::Filter))
+    });
+    let acc = projection.into_iter().fold(acc, hash_node);
+    let acc = count.into_iter().fold(acc, |acc, count| {
+        hash_node(acc, hash3(count, Domain::Aggregate))
+    });
+    let acc = distinct.into_iter().fold(acc, |acc, distinct| {
+        hash_node(acc, hash3(distinct, Domain::Distinct))
+    });
+    let acc = values.into_iter().fold(acc, |acc, values| {
+        hash_node(acc, hash3(values, Domain::Values))
+    });
+    let acc = order.into_iter().fold(acc, |acc, condition| {
+        hash_node(acc, hash3(condition, Domain::Order))
+    });
+    let acc = limit.into_iter().fold(acc, |acc, limit| {
+        hash_node(acc, hash3(limit, Domain::Limit))
+    });
+    let acc = not_exists.into_iter().fold(acc, |acc, pattern| {
+        hash_node(acc, hash3(pattern, Domain::NotExists))
+    });
+    let acc = graphs.into_iter().fold(acc, |acc, graph| {
+        hash_node(acc, hash3(graph, Domain::Graph))
+    });
+    let acc = binds
+        .into_iter()
+        .fold(acc, |acc, bind| hash_node(acc, hash3(bind, Domain::Bind)));
+    let acc = string_filters.into_iter().fold(acc, |acc, filter| {
+        hash_node(acc, hash3(filter, Domain::StringFilter))
+    });
+    let acc = group.into_iter().fold(acc, |acc, group| {
+        hash_node(acc, hash3(group, Domain::Group))
+    });
+    conditions.into_iter().fold(acc, |acc, node| {
+        hash_node(acc, hash3(node, Domain::Condition))
+    })
+}
+
+struct QueryEncoding {
+    patterns: Vec<[Fr; 3]>,
+    union: Vec<[Fr; 3]>,
+    optional: Vec<[Fr; 3]>,
+    filters: Vec<[Fr; 3]>,
+    projection: Vec<Fr>,
+    count: Option<[Fr; 3]>,
+    distinct: Option<[Fr; 3]>,
+    values: Vec<[Fr; 3]>,
+    order: Vec<[Fr; 3]>,
+    limit: Option<[Fr; 3]>,
+    not_exists: Vec<[Fr; 3]>,
+    graphs: Vec<[Fr; 3]>,
+    binds: Vec<[Fr; 3]>,
+    string_filters: Vec<[Fr; 3]>,
+    group: Option<[Fr; 3]>,
+    conditions: Vec<[Fr; 3]>,
+}
+
+impl QueryEncoding {
+    fn new(query: &SelectQuery) -> Self {
+        let mut variables = query.variables_with_binds();
+        for variable in query.not_exists.iter().flat_map(TriplePattern::variables) {
+            if !variables.contains(&variable) {
+                variables.push(variable);
+            }
+        }
+        let var_encoding = |variable: &Variable| {
+            let idx = variables.iter().position(|v| *v == variable);
+            variable_encoding(idx.expect("variable to be bound by a pattern"))
+        };
+        let pattern_encoding = |pattern: &TriplePattern| {
+            pattern.terms().map(|term| match term {
+                TermPattern::Variable(variable) => var_encoding(variable),
+                _ => constant_encoding(term).unwrap(),
+            })
+        };
+        let patterns = query.patterns.iter().map(pattern_encoding).collect();
+        let union = query
+            .union
+            .iter()
+            .flat_map(Pattern::branches)
+            .enumerate()
+            .flat_map(|(idx, branch)| {
+                let header = [
+                    Fr::from(idx as u64),
+                    Fr::from(branch.len() as u64),
+                    Fr::ZERO,
+                ];
+                iter::once(header).chain(branch.iter().map(pattern_encoding))
+            })
+            .collect();
+        let optional = query.optional.iter().map(pattern_encoding).collect();
+        // Filters are a conjunction and the terms of `VALUES` a set, so both
+        // are hashed in order of their encodings without duplicates.
+        let filters = query
+            .filters
+            .iter()
+            .map(|filter| {
+                [
+                    var_encoding(&filter.variable),
+                    filter_op_encoding(filter),
+                    i64_to_field(filter.value),
+                ]
+            })
+            .sorted_by_cached_key(|filter| filter.map(|value| value.to_repr()))
+            .dedup()
+            .collect();
+        let projection = query.projection.iter().map(var_encoding).collect();
+        let count = query
+            .count
+            .as_ref()
+            .map(|count| [var_encoding(&count.variable), Fr::ZERO, Fr::ZERO]);
+        // A count is of distinct values already.
+        let distinct = (query.distinct && query.count.is_none()).then_some([Fr::ZERO; 3]);
+        let values = query
+            .values
+            .iter()
+            .flat_map(|values| {
+                let terms = values
+                    .terms
+                    .iter()
+                    .map(|term| constant_encoding(term).unwrap())
+                    .sorted_by_cached_key(PrimeField::to_repr)
+                    .dedup()
+                    .collect_vec();
+                let header = [
+                    var_encoding(&values.variable),
+                    Fr::from(terms.len() as u64),
+                    Fr::ZERO,
+                ];
+                let terms = terms.into_iter().map(|term| [term, Fr::ZERO, Fr::ZERO]);
+                iter::once(header).chain(terms)
+            })
+            .collect();
+        let order = query
+            .order_by
+            .iter()
+            .map(|condition| {
+                [
+                    var_encoding(&condition.variable),
+                    Fr::from(condition.descending as u64),
+                    Fr::ZERO,
+                ]
+            })
+            .collect();
+        let limit = query
+            .limit
+            .map(|limit| [Fr::from(limit as u64), Fr::ZERO, Fr::ZERO]);
+        let not_exists = query.not_exists.iter().map(pattern_encoding).collect();
+        let graphs = chain![
+            &query.patterns,
+            query.union.iter().flat_map(Pattern::branches).flatten(),
+            &query.optional,
+            &query.not_exists,
+        ]
+        .enumerate()
+        .filter_map(|(idx, pattern)| {
+            let graph = match pattern.graph.as_ref()? {
+                TermPattern::Variable(variable) => var_encoding(variable),
+                graph => constant_encoding(graph).unwrap(),
+            };
+            Some([Fr::from(idx as u64), graph, Fr::ZERO])
+        })
+        .collect();
+        let operand_encoding = |operand: &Operand| match operand {
+            Operand::Variable(variable) => var_encoding(variable),
+            Operand::Integer(value) => integer_encoding(*value),
+        };
+        let binds = query
+            .binds
+            .iter()
+            .flat_map(|bind| {
+                [
+                    [
+                        var_encoding(&bind.variable),
+                        Fr::from(bind.op as u64),
+                        Fr::ZERO,
+                    ],
+                    [
+                        operand_encoding(&bind.lhs),
+                        operand_encoding(&bind.rhs),
+                        Fr::ZERO,
+                    ],
+                ]
+            })
+            .collect();
+        // Strings too long to compile are still committed to by their term
+        // encoding.
+        let string_filters = query
+            .string_filters
+            .iter()
+            .map(|filter| {
+                let value = Term::Literal(filter.literal()).encode();
+                [var_encoding(&filter.variable), value, Fr::ZERO]
+            })
+            .sorted_by_cached_key(|filter| filter.map(|value| value.to_repr()))
+            .dedup()
+            .collect();
+        let group = query
+            .group_by
+            .as_ref()
+            .map(|variable| [var_encoding(variable), Fr::ZERO, Fr::ZERO]);
+        fn postfix(
+            condition: &FilterCondition,
+            var_encoding: &impl Fn(&Variable) -> Fr,
+            nodes: &mut Vec<[Fr; 3]>,
+        ) {
+            let connective = |n: u64| [Fr::ZERO, Fr::from(n), Fr::ZERO];
+            match condition {
+                FilterCondition::Compare(filter) => nodes.push([
+                    var_encoding(&filter.variable),
+                    filter_op_encoding(filter),
+                    i64_to_field(filter.value),
+                ]),
+                FilterCondition::Not(condition) => {
+                    postfix(condition, var_encoding, nodes);
+                    nodes.push(connective(6));
+                }
+                FilterCondition::And(lhs, rhs) | FilterCondition::Or(lhs, rhs) => {
+                    postfix(lhs, var_encoding, nodes);
+                    postfix(rhs, var_encoding, nodes);
+                    let n = match condition {
+                        FilterCondition::And(..) => 7,
+                        _ => 8,
+                    };
+                    nodes.push(connective(n));
+                }
+            }
+        }
+        let mut conditions = Vec::new();
+        for condition in query.conditions.iter() {
+            postfix(condition, &var_encoding, &mut conditions);
+        }
+        Self {
+            patterns,
+            union,
+            optional,
+            filters,
+            projection,
+            count,
+            distinct,
+            values,
+            order,
+            limit,
+            not_exists,
+            graphs,
+            binds,
+            string_filters,
+            group,
+            conditions,
+        }
+    }
+}
+
+/// Compiles the basic graph pattern of `query` over `dataset` into a circuit
+/// laid out for the row mapping of `E`, whose instances are the
+/// [`query_commitment`], the [`MerkleTripleSet::root`] of `dataset`, then the
+/// encoding of each projected variable of each solution, as described by
+/// [`InstanceLayout`].
+///
+/// The circuit proves each reported solution is a solution, but not that
+/// every solution is reported, except for the empty result of a single
+/// pattern, and a query it can't constrain fails with a [`CompileError`].
+/// How each feature of the query is constrained is described in the
+/// [module documentation](crate::circuit#semantics).
+pub fn compile_bgp<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+) -> Result<QueryCircuit, CompileError> {
+    compile_bgp_with_strategy::<E>(query, dataset, MatchStrategy::Merkle)
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp`], but with the integers
+/// of filters and `BIND` signed integers of `value_bits` bits rather than
+/// [`NUM_FILTER_BITS`], so each comparison and result costs a row per bit.
+///
+/// The filter values and integer operands of `query`, and every integer its
+/// filters and `BIND`s open or compute per solution, have to fit, or it fails
+/// with [`CompileError::ValueOutOfRange`], as does a witness of a dataset by
+/// [`QueryCircuitShape::witness_for`]. `value_bits` has to be in `1..=64`.
+pub fn compile_bgp_with_value_bits<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    value_bits: usize,
+) -> Result<QueryCircuit, CompileError> {
+    let solutions = evaluate(query, dataset).collect();
+    let (shape, witness) = compile::<E>(
+        query,
+        dataset,
+        None,
+        None,
+        MatchStrategy::Merkle,
+        value_bits,
+        solutions,
+    )?;
+    Ok(QueryCircuit::new(Arc::new(shape), witness))
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp`], whose root is the one
+/// of [`MerkleTripleSet::new_with_hash`] with `hash`, failing with
+/// [`CompileError::UnsupportedHash`] for a hash the circuit doesn't
+/// constrain rather than proving a root other than the one of `hash`.
+pub fn compile_bgp_with_hash<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    hash: HashChoice,
+) -> Result<QueryCircuit, CompileError> {
+    if !hash.is_supported_in_circuit() {
+        return Err(CompileError::UnsupportedHash(hash));
+    }
+    compile_bgp::<E>(query, dataset)
+}
+
+/// Compiles `query` over the [`matched_subgraph`] of `dataset` rather than
+/// all of it as [`compile_bgp`], and returns the circuit with the
+/// [`SubsetProof`] of the subgraph in the [`MerkleTripleSet`] of `dataset`,
+/// e.g. to only commit to the triples relevant to `query` of a large
+/// dataset, whose Merkle paths are then only as deep as the subgraph needs.
+///
+/// The [`InstanceLayout::DATASET_ROOT`] of the circuit is the root of the
+/// subgraph, and the circuit doesn't prove it's contained in `dataset`. A
+/// verifier of the root of `dataset` has to check the [`SubsetProof`]
+/// against both roots off-circuit, learning the leaves of the subgraph and
+/// their indices in `dataset`, or else trust the data owner that the
+/// subgraph is one of `dataset`. A `FILTER NOT EXISTS` would only prove a
+/// triple absent from the subgraph, so it fails with
+/// [`CompileError::UnsupportedNotExists`], as does an empty result with
+/// [`CompileError::UnsupportedEmpty`].
+pub fn compile_bgp_over_subset<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+) -> Result<(QueryCircuit, SubsetProof), CompileError> {
+    if !query.not_exists.is_empty() {
+        return Err(CompileError::UnsupportedNotExists {
+            reason: "over a disclosed subset",
+        });
+    }
+    let scanned = scanned_pattern(query).is_some() && query.limit != Some(0);
+    if scanned && evaluate(query, dataset).next().is_none() {
+        return Err(CompileError::UnsupportedEmpty {
+            reason: "over a disclosed subset",
+        });
+    }
+    let subgraph = matched_subgraph(query, dataset);
+    let circuit = compile_bgp::<E>(query, &subgraph)?;
+    let proof = MerkleTripleSet::from_graph(dataset)
+        .prove_subset(&MerkleTripleSet::from_graph(&subgraph))
+        .expect("subgraph to be of the dataset");
+    Ok((circuit, proof))
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp`], but failing up front
+/// with [`CompileError::CapacityExceeded`] if `dataset` has more triples than
+/// the [`triple_capacity`] of the circuit at `k`, instead of the backend
+/// failing on a circuit outgrowing `2^k` rows.
+pub fn compile_bgp_within_k<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    k: usize,
+) -> Result<QueryCircuit, CompileError> {
+    let solutions = evaluate(query, dataset).collect::<Vec<_>>();
+    let max = triple_capacity(query, solutions.len(), k);
+    if dataset.len() > max {
+        return Err(CompileError::CapacityExceeded {
+            have: dataset.len(),
+            max,
+            k,
+        });
+    }
+    let (shape, witness) = compile::<E>(
+        query,
+        dataset,
+        None,
+        None,
+        MatchStrategy::Merkle,
+        NUM_FILTER_BITS,
+        solutions,
+    )?;
+    Ok(QueryCircuit::new(Arc::new(shape), witness))
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp`], but padded to `2^k`
+/// rows and a [`MerkleTripleSet`] of the [`triple_capacity`] at `k`, failing
+/// with [`CompileError::CapacityExceeded`] if `dataset` has more triples.
+///
+/// The rows only depend on the query, the depth of the tree and the number of
+/// solutions, or the count of each group, so every dataset up to the capacity
+/// with as many solutions compiles to the same [`PlonkishCircuitInfo`], which
+/// is then preprocessed once and proven over each, e.g. of a batch.
+pub fn compile_to_k<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    k: usize,
+) -> Result<QueryCircuit, CompileError> {
+    let solutions = evaluate(query, dataset).collect::<Vec<_>>();
+    let max = triple_capacity(query, solutions.len(), k);
+    if max == 0 || dataset.len() > max {
+        return Err(CompileError::CapacityExceeded {
+            have: dataset.len(),
+            max,
+            k,
+        });
+    }
+    let padding = Padding {
+        k,
+        depth: max.trailing_zeros() as usize,
+    };
+    let (shape, witness) = compile::<E>(
+        query,
+        dataset,
+        None,
+        Some(padding),
+        MatchStrategy::Merkle,
+        NUM_FILTER_BITS,
+        solutions,
+    )?;
+    Ok(QueryCircuit::new(Arc::new(shape), witness))
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp`], but proving only
+/// `solutions`, e.g. one or a batch of those of [`evaluate`]. Each has to be
+/// one of [`evaluate`] of `query` over `dataset`, which is evaluated again to
+/// check them, or it fails with [`CompileError::NotASolution`], so a solution
+/// of another query or dataset or one built by the caller never reaches the
+/// circuit.
+pub fn compile_bgp_with_solutions<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    solutions: impl IntoIterator<Item = Solution>,
+) -> Result<QueryCircuit, CompileError> {
+    let solutions = solutions.into_iter().collect::<Vec<_>>();
+    let evaluated = evaluate(query, dataset).collect::<HashSet<_>>();
+    if let Some(idx) = solutions
+        .iter()
+        .position(|solution| !evaluated.contains(solution))
+    {
+        return Err(CompileError::NotASolution { idx });
+    }
+    let (shape, witness) = compile::<E>(
+        query,
+        dataset,
+        None,
+        None,
+        MatchStrategy::Merkle,
+        NUM_FILTER_BITS,
+        solutions,
+    )?;
+    Ok(QueryCircuit::new(Arc::new(shape), witness))
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp`], proving each matched
+/// triple in the dataset by `strategy` rather than always by its
+/// [`MerklePath`], see [`MatchStrategy`] for which is cheaper. The lookup
+/// table of [`MatchStrategy::Lookup`] is constrained to hash into the
+/// [`InstanceLayout::DATASET_ROOT`], so either proves the same statement.
+/// `FILTER NOT EXISTS` proves the neighbors of an absent triple by their
+/// paths either way, as their indices are part of the proof.
+pub fn compile_bgp_with_strategy<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    strategy: MatchStrategy,
+) -> Result<QueryCircuit, CompileError> {
+    let solutions = evaluate(query, dataset).collect();
+    let (shape, witness) = compile::<E>(
+        query,
+        dataset,
+        None,
+        None,
+        strategy,
+        NUM_FILTER_BITS,
+        solutions,
+    )?;
+    Ok(QueryCircuit::new(Arc::new(shape), witness))
+}
+
+/// `k` and Merkle depth a circuit is padded to by [`compile_to_k`].
+#[derive(Clone, Copy, Debug)]
+struct Padding {
+    k: usize,
+    depth: usize,
+}
+
+/// Compiles `query` over `dataset` as [`compile_bgp_with_solutions`] by
+/// `strategy`, into the number of solutions, Merkle depth and `k` of `shape`
+/// if given, whose strategy and value bits `strategy` and `value_bits` then
+/// are, or else padded by `padding` if given.
+fn compile<E: WitnessEncoding>(
+    query: &SelectQuery,
+    dataset: &RdfGraph,
+    shape: Option<&QueryCircuitShape>,
+    padding: Option<Padding>,
+    strategy: MatchStrategy,
+    value_bits: usize,
+    mut solutions: Vec<Solution>,
+) -> Result<(QueryCircuitShape, Witness), CompileError> {
+    let branches = query
+        .union
+        .as_ref()
+        .map(|union| match union.branches()[..] {
+            [left, right] => Ok([left, right]),
+            ref branches => Err(CompileError::UnsupportedUnion {
+                num_branches: branches.len(),
+            }),
+        })
+        .transpose()?;
+    if let Some([left, right]) = branches {
+        fn bound(patterns: &[TriplePattern]) -> HashSet<&Variable> {
+            patterns.iter().flat_map(TriplePattern::variables).collect()
+        }
+        let (required, left, right) = (bound(&query.patterns), bound(left), bound(right));
+        let conditional = query
+            .optional
+            .iter()
+            .flat_map(TriplePattern::variables)
+            .find(|v| !required.contains(v) && left.contains(v) != right.contains(v));
+        if let Some(variable) = conditional {
+            return Err(CompileError::UnsupportedOptionalVariable(variable.clone()));
+        }
+    }
+    let variables = query.variables_with_binds();
+    let var_idx = |variable: &Variable| {
+        variables
+            .iter()
+            .position(|v| *v == variable)
+            .ok_or_else(|| CompileError::UnboundVariable(variable.clone()))
+    };
+    let num_required = query.num_required_variables();
+    let not_exists = match &query.not_exists[..] {
+        [] => None,
+        [pattern] => {
+            let unbound = pattern
+                .variables()
+                .any(|variable| !variables[..num_required].contains(&variable));
+            if unbound {
+                return Err(CompileError::UnsupportedNotExists {
+                    reason: "with a variable not bound by the required patterns",
+                });
+            }
+            Some(pattern)
+        }
+        _ => {
+            return Err(CompileError::UnsupportedNotExists {
+                reason: "of several patterns",
+            })
+        }
+    };
+    // Operands of a BIND have to be bound by every solution, so by the required
+    // patterns or an earlier BIND.
+    let num_bound = variables.len() - query.binds.len();
+    let binds = query
+        .binds
+        .iter()
+        .enumerate()
+        .map(|(idx, bind)| {
+            let operand = |operand: &Operand| match operand {
+                Operand::Variable(variable) => {
+                    let var = var_idx(variable)?;
+                    match var < num_required || (num_bound..num_bound + idx).contains(&var) {
+                        true => Ok(Ok(var)),
+                        false => Err(CompileError::UnboundVariable(variable.clone())),
+                    }
+                }
+                Operand::Integer(value) => Ok(Err(*value)),
+            };
+            Ok((operand(&bind.lhs)?, bind.op, operand(&bind.rhs)?))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let projection = query
+        .projection
+        .iter()
+        .map(var_idx)
+        .collect::<Result<Vec<_>, _>>()?;
+    let filters = query
+        .filters
+        .iter()
+        .map(|filter| Ok((filter, var_idx(&filter.variable)?)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let conditions = query
+        .conditions
+        .iter()
+        .map(|condition| {
+            let vars = condition
+                .comparisons()
+                .into_iter()
+                .map(|filter| Ok((var_idx(&filter.variable)?, filter.datatype)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((condition, vars))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let string_filters = query
+        .string_filters
+        .iter()
+        .map(|filter| {
+            let limbs = string_limbs(&filter.value).ok_or(CompileError::StringTooLong {
+                length: filter.value.len(),
+                max: MAX_STRING_LEN,
+            })?;
+            Ok((limbs, var_idx(&filter.variable)?))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let counted = query
+        .count
+        .as_ref()
+        .map(|count| var_idx(&count.variable))
+        .transpose()?;
+    let grouped = query.group_by.as_ref().map(var_idx).transpose()?;
+    let values = query
+        .values
+        .as_ref()
+        .map(|values| {
+            let encodings = values
+                .terms
+                .iter()
+                .map(|term| constant_encoding(term).unwrap());
+            Ok((var_idx(&values.variable)?, encodings.collect::<Vec<_>>()))
+        })
+        .transpose()?;
+
+    let distinct = query.distinct && counted.is_none();
+    let ordered = match &query.order_by[..] {
+        [] => None,
+        [OrderCondition {
+            variable,
+            descending: false,
+        }] => {
+            var_idx(variable)?;
+            let offset = query.projection.iter().position(|v| v == variable);
+            let offset =
+                offset.ok_or_else(|| CompileError::UnprojectedOrderVariable(variable.clone()))?;
+            if distinct && projection.len() > 1 {
+                return Err(CompileError::UnsupportedOrder {
+                    reason: "with DISTINCT of several variables",
+                });
+            }
+            Some(offset)
+        }
+        [_] => {
+            return Err(CompileError::UnsupportedOrder {
+                reason: "descending",
+            })
+        }
+        _ => {
+            return Err(CompileError::UnsupportedOrder {
+                reason: "of several conditions",
+            })
+        }
+    };
+
+    let projected_encodings = |solution: &Solution| {
+        projection
+            .iter()
+            .map(|var| solution.encoding(*var, dataset).unwrap_or(NULL_ENCODING))
+            .collect::<Vec<_>>()
+    };
+    if distinct {
+        solutions.sort_by_cached_key(|solution| {
+            let mut repr = distinct_key(projected_encodings(solution)).to_repr();
+            repr.as_mut().reverse();
+            repr
+        });
+    }
+    if let Some(offset) = ordered {
+        solutions.sort_by_cached_key(|solution| {
+            let mut repr = projected_encodings(solution)[offset].to_repr();
+            repr.as_mut().reverse();
+            repr
+        });
+    }
+    let group_key = |solution: &Solution| {
+        grouped.map(|key| solution.encoding(key, dataset).unwrap_or(NULL_ENCODING))
+    };
+    let mut group_counts = Vec::new();
+    if let Some(var) = counted {
+        let mut values = HashSet::new();
+        solutions.retain(|solution| {
+            let value = solution.encoding(var, dataset).map(|value| value.to_repr());
+            let key = group_key(solution).map(|key| key.to_repr());
+            value.is_some() && values.insert((key, value))
+        });
+        // `LIMIT` of a count limits its single result.
+        if query.limit == Some(0) {
+            solutions.clear();
+        }
+        if grouped.is_some() {
+            solutions.sort_by_cached_key(|solution| {
+                let key = group_key(solution).unwrap();
+                [key, solution.encoding(var, dataset).unwrap()].map(|value| {
+                    let mut repr = value.to_repr();
+                    repr.as_mut().reverse();
+                    repr
+                })
+            });
+            group_counts = solutions
+                .iter()
+                .map(|solution| group_key(solution).unwrap())
+                .dedup_with_count()
+                .map(|(count, _)| count)
+                .collect();
+        } else if solutions.len() > MAX_COUNT {
+            return Err(CompileError::CountOverflow {
+                count: solutions.len(),
+            });
+        }
+    } else if let Some(limit) = query.limit {
+        solutions.truncate(limit);
+    }
+    if !(1..=NUM_FILTER_BITS).contains(&value_bits) {
+        return Err(CompileError::UnsupportedValueBits { value_bits });
+    }
+    let check_value = |value: i64| {
+        let bound = 1i128 << (value_bits - 1);
+        match (-bound..bound).contains(&(value as i128)) {
+            true => Ok(()),
+            false => Err(CompileError::ValueOutOfRange { value, value_bits }),
+        }
+    };
+    let compared = filters
+        .iter()
+        .map(|(filter, var)| (filter.value, (*var, filter.datatype)))
+        .chain(conditions.iter().flat_map(|(condition, vars)| {
+            let comparisons = condition.comparisons().into_iter();
+            comparisons
+                .map(|filter| filter.value)
+                .zip(vars.iter().copied())
+        }))
+        .collect::<Vec<_>>();
+    let mut opened = compared.iter().map(|(_, var)| *var).collect::<Vec<_>>();
+    for (idx, (lhs, _, rhs)) in binds.iter().enumerate() {
+        for operand in [lhs, rhs] {
+            match *operand {
+                Ok(var) => opened.push((var, FilterDatatype::Integer)),
+                Err(value) => check_value(value)?,
+            }
+        }
+        opened.push((num_bound + idx, FilterDatatype::Integer));
+    }
+    for (value, _) in compared.iter() {
+        check_value(*value)?;
+    }
+    for solution in solutions.iter() {
+        for (var, datatype) in opened.iter() {
+            check_value(solution.value(*var, dataset, *datatype).unwrap())?;
+        }
+    }
+    let mut set = MerkleTripleSet::from_graph(dataset);
+    if let Some(shape) = shape {
+        if solutions.len() != shape.num_solutions {
+            return Err(CompileError::NumSolutions {
+                expected: shape.num_solutions,
+                found: solutions.len(),
+            });
+        }
+        if group_counts != shape.group_counts {
+            return Err(CompileError::GroupCounts {
+                expected: shape.group_counts.clone(),
+                found: group_counts,
+            });
+        }
+        let depth = set.depth();
+        set = set.with_depth(shape.depth).ok_or(CompileError::Depth {
+            max: shape.depth,
+            found: depth,
+        })?;
+    }
+    if let Some(Padding { depth: max, .. }) = padding {
+        let depth = set.depth();
+        set = set
+            .with_depth(max)
+            .ok_or(CompileError::Depth { max, found: depth })?;
+    }
+    // Without solutions, the circuit proves none match by a scan of every leaf
+    // if it can. A count without `GROUP BY` is of `0` anyway, which is a lower
+    // bound of any count, as are the results of `LIMIT 0`.
+    let scanned = match solutions.is_empty() && query.limit != Some(0) {
+        true => match scanned_pattern(query) {
+            Some(pattern) => {
+                let found = evaluate(query, dataset).count();
+                if found > 0 {
+                    return Err(CompileError::NumSolutions { expected: 0, found });
+                }
+                Some(pattern)
+            }
+            None if counted.is_some() && grouped.is_none() => None,
+            None => {
+                return Err(CompileError::UnsupportedEmpty {
+                    reason: "of other than a single triple pattern without UNION, filters, \
+                             VALUES, BIND or FILTER NOT EXISTS",
+                })
+            }
+        },
+        false => None,
+    };
+    let layout = match grouped {
+        Some(_) => InstanceLayout::new(query, group_counts.len()),
+        None => InstanceLayout::new(query, solutions.len()),
+    };
+    let mut instances = vec![Fr::ZERO; layout.num_instances()];
+    instances[InstanceLayout::QUERY_COMMITMENT] = query_commitment(query);
+    instances[InstanceLayout::DATASET_ROOT] = set.root();
+    match (counted, grouped) {
+        (Some(_), Some(_)) => {
+            let mut start = 0;
+            for (group, count) in group_counts.iter().enumerate() {
+                instances[layout.result(group, 0)] = group_key(&solutions[start]).unwrap();
+                instances[layout.result(group, 1)] = Fr::from(*count as u64);
+                start += count;
+            }
+        }
+        (Some(_), None) if layout.num_solutions == 0 => {}
+        (Some(_), None) => instances[layout.result(0, 0)] = Fr::from(solutions.len() as u64),
+        (None, _) => {
+            for idx in 0..layout.num_solutions {
+                let encodings = match solutions.get(idx) {
+                    Some(solution) => projected_encodings(solution),
+                    None => vec![NULL_ENCODING; layout.num_projected],
+                };
+                for (offset, encoding) in encodings.into_iter().enumerate() {
+                    instances[layout.result(idx, offset)] = encoding;
+                }
+            }
+        }
+    }
+    let mut builder = CircuitBuilder::new(instances);
+
+    let encoding = QueryEncoding::new(query);
+    let zero = builder.constant(Fr::ZERO);
+    let mut acc = zero;
+    for (values, domain) in chain![
+        encoding
+            .patterns
+            .into_iter()
+            .map(|values| (values, Domain::Pattern)),
+        encoding
+            .union
+            .into_iter()
+            .map(|values| (values, Domain::Union)),
+        encoding
+            .optional
+            .into_iter()
+            .map(|values| (values, Domain::Optional)),
+        encoding
+            .filters
+            .into_iter()
+            .map(|values| (values, Domain::Filter)),
+    ] {
+        let inputs = values.map(|value| builder.constant(value));
+        let hash = builder.hash3(inputs, domain);
+        acc = builder.hash_node(acc, hash);
+    }
+    for value in encoding.projection {
+        let variable = builder.constant(value);
+        acc = builder.hash_node(acc, variable);
+    }
+    if let Some(values) = encoding.count {
+        let inputs = values.map(|value| builder.constant(value));
+        let hash = builder.hash3(inputs, Domain::Aggregate);
+        acc = builder.hash_node(acc, hash);
+    }
+    if let Some(values) = encoding.distinct {
+        let inputs = values.map(|value| builder.constant(value));
+        let hash = builder.hash3(inputs, Domain::Distinct);
+        acc = builder.hash_node(acc, hash);
+    }
+    for (values, domain) in chain![
+        encoding
+            .values
+            .into_iter()
+            .map(|values| (values, Domain::Values)),
+        encoding
+            .order
+            .into_iter()
+            .map(|values| (values, Domain::Order)),
+        encoding
+            .limit
+            .into_iter()
+            .map(|values| (values, Domain::Limit)),
+        encoding
+            .not_exists
+            .into_iter()
+            .map(|values| (values, Domain::NotExists)),
+        encoding
+            .graphs
+            .into_iter()
+            .map(|values| (values, Domain::Graph)),
+        encoding
+            .binds
+            .into_iter()
+            .map(|values| (values, Domain::Bind)),
+        encoding
+            .string_filters
+            .into_iter()
+            .map(|values| (values, Domain::StringFilter)),
+        encoding
+            .group
+            .into_iter()
+            .map(|values| (values, Domain::Group)),
+        encoding
+            .conditions
+            .into_iter()
+            .map(|values| (values, Domain::Condition)),
+    ] {
+        let inputs = values.map(|value| builder.constant(value));
+        let hash = builder.hash3(inputs, domain);
+        acc = builder.hash_node(acc, hash);
+    }
+    let commitment = builder.instance(InstanceLayout::QUERY_COMMITMENT);
+    builder.copy(acc, commitment);
+    let root = builder.instance(InstanceLayout::DATASET_ROOT);
+    if strategy == MatchStrategy::Lookup {
+        let leaves = builder.table(set.padded_leaves());
+        let table_root = builder.merkle_tree_root(&leaves);
+        builder.copy(table_root, root);
+    }
+    if let Some(pattern) = scanned {
+        assert_no_match(&mut builder, pattern, dataset, &set);
+    }
+
+    let mut counted_cells = Vec::new();
+    let mut key_cells = Vec::new();
+    for (idx, solution) in solutions.iter().enumerate() {
+        let mut bindings = vec![None::<Cell>; variables.len()];
+        for (pattern, triple) in query.patterns.iter().zip(solution.triples.iter()) {
+            let (values, graph_name) = quad_values(dataset, *triple);
+            let (cells, leaf) = pattern_leaf(&mut builder, pattern, values, graph_name);
+            match strategy {
+                MatchStrategy::Merkle => {
+                    let (triple, graph_name) = dataset.quad(*triple);
+                    let path = set
+                        .prove_quad_membership(&triple, graph_name.as_ref())
+                        .unwrap();
+                    let leaf_root = builder.merkle_root(leaf, &path);
+                    builder.copy(leaf_root, root);
+                }
+                MatchStrategy::Lookup => builder.lookup(leaf),
+            }
+            for (term, cell) in pattern.graph_and_terms().zip(cells) {
+                match term {
+                    TermPattern::Variable(variable) => {
+                        let var = variables.iter().position(|v| *v == variable).unwrap();
+                        match bindings[var] {
+                            Some(binding) => builder.copy(binding, cell),
+                            None => bindings[var] = Some(cell),
+                        }
+                    }
+                    _ => {
+                        let constant = builder.constant(constant_encoding(term).unwrap());
+                        builder.copy(constant, cell);
+                    }
+                }
+            }
+        }
+        if let Some(branches) = branches {
+            let (selected, _) = solution.union.as_ref().unwrap();
+            let right = builder.boolean(*selected == 1);
+            let left = builder.affine(right, -Fr::ONE, Fr::ONE);
+            let mut selected_terms = vec![Vec::new(); variables.len()];
+            for (idx, (selector, patterns)) in [left, right].into_iter().zip(branches).enumerate() {
+                let triples = match &solution.union {
+                    Some((selected, triples)) if *selected == idx => Some(&triples[..]),
+                    _ => None,
+                };
+                let new_bindings = guarded_patterns(
+                    &mut builder,
+                    selector,
+                    patterns,
+                    triples,
+                    &variables,
+                    &bindings,
+                    dataset,
+                    &set,
+                    strategy,
+                );
+                for (var, cell) in new_bindings.into_iter().enumerate() {
+                    if let Some(cell) = cell {
+                        selected_terms[var].push(builder.mul(selector, cell));
+                    }
+                }
+            }
+            for (binding, terms) in bindings.iter_mut().zip(selected_terms) {
+                match terms[..] {
+                    [] => {}
+                    [term] => *binding = Some(term),
+                    [lhs, rhs] => *binding = Some(builder.add(lhs, rhs)),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        if !query.optional.is_empty() {
+            let present = builder.boolean(solution.optional.is_some());
+            let new_bindings = guarded_patterns(
+                &mut builder,
+                present,
+                &query.optional,
+                solution.optional.as_deref(),
+                &variables,
+                &bindings,
+                dataset,
+                &set,
+                strategy,
+            );
+            for (binding, cell) in bindings.iter_mut().zip(new_bindings) {
+                if let Some(cell) = cell {
+                    *binding = Some(builder.mul(present, cell));
+                }
+            }
+        }
+        let mut bind_values = Vec::with_capacity(binds.len());
+        for (idx, (lhs, op, rhs)) in binds.iter().enumerate() {
+            let operand =
+                |builder: &mut CircuitBuilder, operand: &Result<usize, i64>| match *operand {
+                    Ok(var) if var >= num_bound => bind_values[var - num_bound],
+                    Ok(var) => {
+                        let value = solution.integer(var, dataset).unwrap();
+                        let binding = bindings[var].unwrap();
+                        open_value(builder, zero, binding, FilterDatatype::Integer, value)
+                    }
+                    Err(value) => builder.constant(i64_to_field(value)),
+                };
+            let lhs = operand(&mut builder, lhs);
+            let rhs = operand(&mut builder, rhs);
+            let value = match op {
+                ArithOp::Add => builder.add(lhs, rhs),
+                ArithOp::Sub => builder.sub(lhs, rhs),
+                ArithOp::Mul => builder.mul(lhs, rhs),
+            };
+            // Operands are `i64`, so the product is below `2^126` in absolute
+            // value and never wraps, and the result is an `i64` iff shifting
+            // it by `2^63` fits 64 bits.
+            let shifted = builder.affine(value, Fr::ONE, Fr::from(1u64 << (value_bits - 1)));
+            builder.range_check(shifted, value_bits);
+            bindings[num_bound + idx] = Some(builder.hash3([value, zero, zero], Domain::Integer));
+            bind_values.push(value);
+        }
+        for (filter, var) in filters.iter() {
+            let value = solution.value(*var, dataset, filter.datatype).unwrap();
+            let binding = bindings[*var].unwrap();
+            let value = open_value(&mut builder, zero, binding, filter.datatype, value);
+
+            let rhs = i64_to_field(filter.value);
+            // Difference being in range iff the comparison holds.
+            let (scale, constant) = match filter.op {
+                CompareOp::Eq => {
+                    let rhs = builder.constant(rhs);
+                    builder.copy(rhs, value);
+                    continue;
+                }
+                CompareOp::Ne => {
+                    let diff = builder.affine(value, Fr::ONE, -rhs);
+                    builder.assert_nonzero(diff);
+                    continue;
+                }
+                CompareOp::Lt => (-Fr::ONE, rhs - Fr::ONE),
+                CompareOp::Le => (-Fr::ONE, rhs),
+                CompareOp::Gt => (Fr::ONE, -rhs - Fr::ONE),
+                CompareOp::Ge => (Fr::ONE, -rhs),
+            };
+            let diff = builder.affine(value, scale, constant);
+            builder.range_check(diff, value_bits);
+        }
+        for (condition, vars) in conditions.iter() {
+            let mut values = vars
+                .iter()
+                .map(|&(var, datatype)| {
+                    let value = solution.value(var, dataset, datatype).unwrap();
+                    let binding = bindings[var].unwrap();
+                    let cell = open_value(&mut builder, zero, binding, datatype, value);
+                    (cell, value)
+                })
+                .collect::<Vec<_>>()
+                .into_iter();
+            let holds = condition_bit(&mut builder, zero, value_bits, condition, &mut values);
+            let one = builder.constant(Fr::ONE);
+            builder.copy(one, holds);
+        }
+        for (limbs, var) in string_filters.iter() {
+            let (cells, encoding) = builder.hash3_values(*limbs, Domain::String);
+            for (cell, limb) in cells.into_iter().zip(limbs) {
+                let limb = builder.constant(*limb);
+                builder.copy(limb, cell);
+            }
+            builder.copy(bindings[*var].unwrap(), encoding);
+        }
+        if let Some((var, encodings)) = &values {
+            builder.assert_member(bindings[*var].unwrap(), encodings);
+        }
+        if let Some(pattern) = not_exists {
+            let cells = pattern
+                .graph_and_terms()
+                .map(|term| match term {
+                    TermPattern::Variable(variable) => {
+                        let var = variables.iter().position(|v| *v == variable).unwrap();
+                        bindings[var].unwrap()
+                    }
+                    _ => builder.constant(constant_encoding(term).unwrap()),
+                })
+                .collect::<Vec<_>>();
+            let (graph_name, terms) = cells.split_at(cells.len() - 3);
+            let mut leaf = builder.hash3(terms.try_into().unwrap(), Domain::Triple);
+            if let [graph_name] = graph_name {
+                leaf = builder.hash3([leaf, *graph_name, zero], Domain::Quad);
+            }
+            assert_non_member(&mut builder, leaf, &set);
+        }
+        // The key of a group is copied to its result below.
+        if grouped.is_none() {
+            for (offset, var) in projection.iter().enumerate() {
+                let instance = builder.instance(layout.result(idx, offset));
+                builder.copy(bindings[*var].unwrap(), instance);
+            }
+        }
+        if let Some(key) = grouped {
+            key_cells.push(bindings[key].unwrap());
+        }
+        if let Some(var) = counted {
+            let cell = bindings[var].unwrap();
+            // Variable left unbound by the OPTIONAL block or the UNION is never
+            // counted.
+            if var >= num_required {
+                builder.assert_nonzero(cell);
+            }
+            counted_cells.push(cell);
+        }
+    }
+    if counted.is_none() && solutions.len() < layout.num_solutions {
+        let null = builder.constant(NULL_ENCODING);
+        for idx in solutions.len()..layout.num_solutions {
+            for offset in 0..layout.num_projected {
+                let instance = builder.instance(layout.result(idx, offset));
+                builder.copy(null, instance);
+            }
+        }
+    }
+    if counted.is_some() && grouped.is_none() && layout.num_solutions > 0 {
+        for (idx, lhs) in counted_cells.iter().enumerate() {
+            for rhs in counted_cells[idx + 1..].iter() {
+                let diff = builder.sub(*lhs, *rhs);
+                builder.assert_nonzero(diff);
+            }
+        }
+        let count = builder.constant(Fr::from(counted_cells.len() as u64));
+        let instance = builder.instance(layout.result(0, 0));
+        builder.copy(count, instance);
+    }
+    if grouped.is_some() {
+        let mut rows = key_cells.into_iter().zip(counted_cells);
+        let mut prev_key = None;
+        for (group, count) in group_counts.iter().enumerate() {
+            let (keys, values): (Vec<_>, Vec<_>) = rows.by_ref().take(*count).unzip();
+            for key in keys[1..].iter() {
+                builder.copy(keys[0], *key);
+            }
+            let instance = builder.instance(layout.result(group, 0));
+            builder.copy(keys[0], instance);
+            let limbs = builder.limbs(keys[0]);
+            if let Some(prev) = prev_key.replace(limbs) {
+                builder.assert_less_than(prev, limbs);
+            }
+            let mut prev_value = None;
+            for value in values {
+                let limbs = builder.limbs(value);
+                if let Some(prev) = prev_value.replace(limbs) {
+                    builder.assert_less_than(prev, limbs);
+                }
+            }
+            assert_below_modulus(&mut builder, prev_value.unwrap());
+            let count = builder.constant(Fr::from(*count as u64));
+            let instance = builder.instance(layout.result(group, 1));
+            builder.copy(count, instance);
+        }
+        if let Some(last) = prev_key {
+            assert_below_modulus(&mut builder, last);
+        }
+    }
+    if distinct && !projection.is_empty() {
+        let mut prev = None;
+        for idx in 0..solutions.len() {
+            let key = (1..projection.len()).fold(
+                builder.instance(layout.result(idx, 0)),
+                |acc, offset| {
+                    let cell = builder.instance(layout.result(idx, offset));
+                    builder.hash_node(acc, cell)
+                },
+            );
+            let limbs = builder.limbs(key);
+            if let Some(prev) = prev.replace(limbs) {
+                builder.assert_less_than(prev, limbs);
+            }
+        }
+        if let Some(last) = prev {
+            assert_below_modulus(&mut builder, last);
+        }
+    }
+    // With `DISTINCT` the results are already strictly increasing.
+    if let (Some(offset), false) = (ordered, distinct) {
+        let mut prev = None;
+        for idx in 0..solutions.len() {
+            let cell = builder.instance(layout.result(idx, offset));
+            let limbs = builder.limbs(cell);
+            if let Some(prev) = prev.replace(limbs) {
+                builder.assert_less_equal(prev, limbs);
+            }
+        }
+        if let Some(last) = prev {
+            assert_below_modulus(&mut builder, last);
+        }
+    }
+
+    let num_rows = builder.num_rows();
+    let min_k = builder.min_k::<E>();
+    let k = match (shape, padding) {
+        (Some(shape), _) => shape.circuit_info.k.max(min_k),
+        (None, Some(padding)) if padding.k < min_k => {
+            return Err(CompileError::CapacityExceeded {
+                have: dataset.len(),
+                max: 1 << padding.depth,
+                k: padding.k,
+            })
+        }
+        (None, Some(padding)) => padding.k,
+        (None, None) => min_k,
+    };
+    let (circuit_info, witness) = builder.build::<E>(k);
+    let shape = QueryCircuitShape {
+        query: query.clone(),
+        num_solutions: solutions.len(),
+        depth: set.depth(),
+        group_counts,
+        strategy,
+        value_bits,
+        num_rows,
+        layout,
+        circuit_info,
+    };
+    Ok((shape, witness))
+}
+
+/// Returns the encoding of the operator of `filter` in the [`Domain::Filter`]
+/// and [`Domain::Condition`] hashes, offset by 16 for an `xsd:dateTime` one
+/// so it commits to the datatype compared too.
+fn filter_op_encoding(filter: &FilterExpr) -> Fr {
+    let offset = match filter.datatype {
+        FilterDatatype::Integer => 0,
+        FilterDatatype::DateTime => 16,
+    };
+    Fr::from(filter.op as u64 + offset)
+}
+
+/// Opens `binding` as the encoding of the literal of `datatype` valued
+/// `value`, [`integer_encoding`] or [`date_time_encoding`], returning the
+/// cell of `value`.
+///
+/// [`date_time_encoding`]: crate::hash::date_time_encoding
+fn open_value(
+    builder: &mut CircuitBuilder,
+    zero: Cell,
+    binding: Cell,
+    datatype: FilterDatatype,
+    value: i64,
+) -> Cell {
+    let domain = match datatype {
+        FilterDatatype::Integer => Domain::Integer,
+        FilterDatatype::DateTime => Domain::DateTime,
+    };
+    let (cells, encoding) = builder.hash3_values([i64_to_field(value), Fr::ZERO, Fr::ZERO], domain);
+    builder.copy(zero, cells[1]);
+    builder.copy(zero, cells[2]);
+    builder.copy(binding, encoding);
+    cells[0]
+}
+
+/// Returns the boolean cell of whether `condition` holds, given the opened
+/// cell and integer of each of its comparisons from the left in `values`.
+/// `!` is the complement of its operand, `&&` the product and `||` the sum
+/// minus the product of its operands.
+fn condition_bit(
+    builder: &mut CircuitBuilder,
+    zero: Cell,
+    value_bits: usize,
+    condition: &FilterCondition,
+    values: &mut impl Iterator<Item = (Cell, i64)>,
+) -> Cell {
+    match condition {
+        FilterCondition::Compare(filter) => {
+            let (cell, value) = values.next().unwrap();
+            comparison_bit(
+                builder,
+                zero,
+                value_bits,
+                filter.op,
+                cell,
+                value,
+                filter.value,
+            )
+        }
+        FilterCondition::Not(condition) => {
+            let holds = condition_bit(builder, zero, value_bits, condition, values);
+            builder.affine(holds, -Fr::ONE, Fr::ONE)
+        }
+        FilterCondition::And(lhs, rhs) => {
+            let lhs = condition_bit(builder, zero, value_bits, lhs, values);
+            let rhs = condition_bit(builder, zero, value_bits, rhs, values);
+            builder.mul(lhs, rhs)
+        }
+        FilterCondition::Or(lhs, rhs) => {
+            let lhs = condition_bit(builder, zero, value_bits, lhs, values);
+            let rhs = condition_bit(builder, zero, value_bits, rhs, values);
+            let sum = builder.add(lhs, rhs);
+            let product = builder.mul(lhs, rhs);
+            builder.sub(sum, product)
+        }
+    }
+}
+
+/// Returns the boolean cell of whether the integer `value` of `cell` compares
+/// by `op` to `rhs`. Equality is `1 - diff·inv` of the difference, with
+/// `diff` times it constrained to `0` so `inv` has to be its inverse if it's
+/// nonzero. An ordering witnesses the bit and range checks the difference as
+/// in filters if it's `1`, or its complement `-diff - 1` if it's `0`.
+fn comparison_bit(
+    builder: &mut CircuitBuilder,
+    zero: Cell,
+    value_bits: usize,
+    op: CompareOp,
+    cell: Cell,
+    value: i64,
+    rhs: i64,
+) -> Cell {
+    let holds = op.evaluate(value, rhs);
+    let rhs = i64_to_field(rhs);
+    let (scale, constant) = match op {
+        CompareOp::Eq | CompareOp::Ne => {
+            let diff = builder.affine(cell, Fr::ONE, -rhs);
+            let inv = builder.witness(builder.value(diff).invert().unwrap_or(Fr::ZERO));
+            let product = builder.mul(diff, inv);
+            let equal = builder.affine(product, -Fr::ONE, Fr::ONE);
+            let product = builder.mul(diff, equal);
+            builder.copy(zero, product);
+            return match op {
+                CompareOp::Eq => equal,
+                _ => builder.affine(equal, -Fr::ONE, Fr::ONE),
+            };
+        }
+        CompareOp::Lt => (-Fr::ONE, rhs - Fr::ONE),
+        CompareOp::Le => (-Fr::ONE, rhs),
+        CompareOp::Gt => (Fr::ONE, -rhs - Fr::ONE),
+        CompareOp::Ge => (Fr::ONE, -rhs),
+    };
+    let diff = builder.affine(cell, scale, constant);
+    let holds = builder.boolean(holds);
+    // `holds·diff + (1 - holds)·(-diff - 1)`.
+    let product = builder.mul(holds, diff);
+    let doubled = builder.affine(product, Fr::from(2), -Fr::ONE);
+    let checked = builder.sub(doubled, diff);
+    let checked = builder.add(checked, holds);
+    builder.range_check(checked, value_bits);
+    holds
+}
+
+/// Returns the key of a result with `encodings` projected, which
+/// `SELECT DISTINCT` results are sorted by, see
+/// [`DISTINCT`](crate::circuit#distinct).
+fn distinct_key(encodings: Vec<Fr>) -> Fr {
+    encodings.into_iter().reduce(hash_node).unwrap_or(Fr::ZERO)
+}
+
+/// Constrains the integer of `limbs` given by [`CircuitBuilder::limbs`] to be
+/// below the modulus, so they're the unique limbs of their cell.
+fn assert_below_modulus(builder: &mut CircuitBuilder, limbs: [Cell; 2]) {
+    let [lo, hi] = limb_values(-Fr::ONE);
+    let modulus = [lo + Fr::ONE, hi].map(|value| builder.constant(value));
+    builder.assert_less_than(limbs, modulus);
+}
+
+/// Constrains `leaf` not to be a leaf of `set` under the dataset root, by the
+/// neighbors of its [`NonMembershipProof`] at adjacent indices, see
+/// [`FILTER NOT EXISTS`](crate::circuit#filter-not-exists). Without a
+/// neighbor below or above, the leaf is compared to `0` or the modulus minus
+/// one instead, with the other neighbor at the first or last index.
+///
+/// [`NonMembershipProof`]: crate::merkle::NonMembershipProof
+fn assert_non_member(builder: &mut CircuitBuilder, leaf: Cell, set: &MerkleTripleSet) {
+    let proof = set
+        .prove_non_membership(builder.value(leaf))
+        .expect("solution to have no match of the FILTER NOT EXISTS pattern");
+    let root = builder.instance(InstanceLayout::DATASET_ROOT);
+    let zero = builder.constant(Fr::ZERO);
+    let first = builder.boolean(proof.lo.is_none());
+    let last = builder.boolean(proof.hi.is_none());
+    let both = builder.mul(first, last);
+    builder.copy(both, zero);
+    let not_first = builder.affine(first, -Fr::ONE, Fr::ONE);
+    let not_last = builder.affine(last, -Fr::ONE, Fr::ONE);
+
+    let depth = set.depth();
+    let [(lo, lo_root, lo_idx), (hi, hi_root, hi_idx)] = [proof.lo, proof.hi].map(|neighbor| {
+        let (value, path) = neighbor.unwrap_or_else(|| {
+            let siblings = vec![Fr::ZERO; depth];
+            (Fr::ZERO, MerklePath { idx: 0, siblings })
+        });
+        let cell = builder.witness(value);
+        let (root, idx) = builder.merkle_root_with_index(cell, &path);
+        (cell, root, idx)
+    });
+    builder.assert_equal_if(not_first, lo_root, root);
+    builder.assert_equal_if(not_last, hi_root, root);
+    builder.assert_equal_if(first, hi_idx, zero);
+    let last_idx = builder.constant(Fr::from((1u64 << depth) - 1));
+    builder.assert_equal_if(last, lo_idx, last_idx);
+    let middle = builder.mul(not_first, not_last);
+    let next_idx = builder.affine(lo_idx, Fr::ONE, Fr::ONE);
+    builder.assert_equal_if(middle, hi_idx, next_idx);
+
+    let lo = builder.mul(not_first, lo);
+    let hi = builder.mul(not_last, hi);
+    let max = builder.affine(last, -Fr::ONE, Fr::ZERO);
+    let hi = builder.add(hi, max);
+    let [lo, leaf, hi] = [lo, leaf, hi].map(|cell| builder.limbs(cell));
+    builder.assert_less_than(lo, leaf);
+    builder.assert_less_than(leaf, hi);
+    assert_below_modulus(builder, hi);
+}
+
+/// Returns the only pattern of `query` if it's a single triple pattern without
+/// `UNION`, filters, `VALUES`, `BIND` or `FILTER NOT EXISTS`, whose empty
+/// result the circuit proves by [`assert_no_match`], see
+/// [empty results](crate::circuit#empty-results).
+fn scanned_pattern(query: &SelectQuery) -> Option<&TriplePattern> {
+    let filtered = !query.filters.is_empty()
+        || !query.conditions.is_empty()
+        || !query.string_filters.is_empty();
+    match &query.patterns[..] {
+        [pattern]
+            if query.union.is_none()
+                && !filtered
+                && query.values.is_none()
+                && query.binds.is_empty()
+                && query.not_exists.is_empty() =>
+        {
+            Some(pattern)
+        }
+        _ => None,
+    }
+}
+
+/// Constrains no leaf of `set` under the dataset root to match `pattern`, by
+/// opening every leaf as [`PADDING_LEAF`], a triple of the default graph or a
+/// quad, each with its terms, and hashing them into the root. A triple
+/// `pattern` could match, a quad if it's in a `GRAPH` clause and a triple of
+/// the default graph otherwise, is constrained to differ from it in a
+/// constant or a repeated variable, selected by one-hot booleans.
+fn assert_no_match(
+    builder: &mut CircuitBuilder,
+    pattern: &TriplePattern,
+    dataset: &RdfGraph,
+    set: &MerkleTripleSet,
+) {
+    let preimages = (0..dataset.len())
+        .map(|idx| {
+            let (triple, graph_name) = dataset.quad(idx);
+            let leaf = MerkleTripleSet::quad_leaf(&triple, graph_name.as_ref());
+            let (values, graph_name) = quad_values(dataset, idx);
+            let graph_name = dataset.graph_name(idx).map(|_| graph_name);
+            (leaf.to_repr(), (values, graph_name))
+        })
+        .collect::<HashMap<_, _>>();
+    let zero = builder.constant(Fr::ZERO);
+    let padding_leaf = builder.constant(PADDING_LEAF);
+    let leaves = set
+        .padded_leaves()
+        .iter()
+        .map(|leaf| {
+            let preimage = preimages.get(&leaf.to_repr());
+            let (values, graph_name) = preimage.copied().unwrap_or(([Fr::ZERO; 3], None));
+            let padding = builder.boolean(preimage.is_none());
+            let named = builder.boolean(graph_name.is_some());
+            let both = builder.mul(padding, named);
+            builder.copy(both, zero);
+
+            let (terms, triple_leaf) = builder.hash3_values(values, Domain::Triple);
+            let triple_value = builder.value(triple_leaf);
+            let quad_values = [triple_value, graph_name.unwrap_or(Fr::ZERO), Fr::ZERO];
+            let (inputs, quad_leaf) = builder.hash3_values(quad_values, Domain::Quad);
+            builder.copy(triple_leaf, inputs[0]);
+            builder.copy(zero, inputs[2]);
+            // The leaf is the triple leaf, replaced by the quad leaf if named
+            // and by padding if padding.
+            let quad_diff = builder.sub(quad_leaf, triple_leaf);
+            let padding_diff = builder.sub(padding_leaf, triple_leaf);
+            let quad_diff = builder.mul(named, quad_diff);
+            let padding_diff = builder.mul(padding, padding_diff);
+            let leaf = builder.add(triple_leaf, quad_diff);
+            let leaf = builder.add(leaf, padding_diff);
+
+            let (eligible, cells) = match pattern.graph {
+                Some(_) => (named, chain![[inputs[1]], terms].collect_vec()),
+                None => {
+                    let present = builder.affine(padding, -Fr::ONE, Fr::ONE);
+                    (builder.sub(present, named), terms.to_vec())
+                }
+            };
+            let mut bindings = HashMap::new();
+            let diffs = pattern
+                .graph_and_terms()
+                .zip(cells)
+                .filter_map(|(term, cell)| match constant_encoding(term) {
+                    Some(encoding) => {
+                        let constant = builder.constant(encoding);
+                        Some(builder.sub(cell, constant))
+                    }
+                    None => match bindings.entry(term) {
+                        Entry::Occupied(entry) => Some(builder.sub(cell, *entry.get())),
+                        Entry::Vacant(entry) => {
+                            entry.insert(cell);
+                            None
+                        }
+                    },
+                })
+                .collect_vec();
+            let selected = diffs
+                .iter()
+                .position(|diff| builder.value(*diff) != Fr::ZERO)
+                .filter(|_| builder.value(eligible) == Fr::ONE);
+            let (mut num_selected, mut selected_diff) = (zero, zero);
+            for (idx, diff) in diffs.into_iter().enumerate() {
+                let selector = builder.boolean(selected == Some(idx));
+                num_selected = builder.add(num_selected, selector);
+                let diff = builder.mul(selector, diff);
+                selected_diff = builder.add(selected_diff, diff);
+            }
+            builder.copy(num_selected, eligible);
+            let ineligible = builder.affine(eligible, -Fr::ONE, Fr::ONE);
+            let nonzero = builder.add(selected_diff, ineligible);
+            builder.assert_nonzero(nonzero);
+            leaf
+        })
+        .collect_vec();
+    let root = builder.merkle_tree_root(&leaves);
+    let dataset_root = builder.instance(InstanceLayout::DATASET_ROOT);
+    builder.copy(root, dataset_root);
+}
+
+/// Returns the encoding of `term` if it's a constant, which a term of a triple
+/// only shares if it's the same term, so a literal only matches one of same
+/// lexical form, datatype and language tag, see [`encode_term`].
+///
+/// [`encode_term`]: crate::term_encoding::encode_term
+fn constant_encoding(term: &TermPattern) -> Option<Fr> {
+    match term {
+        TermPattern::Variable(_) => None,
+        TermPattern::Iri(iri) => Some(Term::Iri(iri.clone()).encode()),
+        TermPattern::Literal(literal) => Some(Term::Literal(literal.clone()).encode()),
+    }
+}
+
+/// Returns the encodings of the terms of the triple at `idx` of `dataset` and
+/// of the name of its graph, or zero in the default graph.
+fn quad_values(dataset: &RdfGraph, idx: usize) -> ([Fr; 3], Fr) {
+    let values = dataset.triples()[idx].map(|id| dataset.encoding(id));
+    let graph_name = dataset.graph_name(idx);
+    (
+        values,
+        graph_name.map_or(Fr::ZERO, |id| dataset.encoding(id)),
+    )
+}
+
+/// Lays out the leaf of the triple of `values` matched by `pattern`, which
+/// hashes its [`Domain::Triple`] leaf with the encoding `graph_name` of its
+/// graph under [`Domain::Quad`] if `pattern` is in a `GRAPH` clause, as the
+/// [`MerkleTripleSet`] does. Returns the cells in order of
+/// [`TriplePattern::graph_and_terms`] and the leaf.
+fn pattern_leaf(
+    builder: &mut CircuitBuilder,
+    pattern: &TriplePattern,
+    values: [Fr; 3],
+    graph_name: Fr,
+) -> (Vec<Cell>, Cell) {
+    let (cells, leaf) = builder.hash3_values(values, Domain::Triple);
+    if pattern.graph.is_none() {
+        return (cells.to_vec(), leaf);
+    }
+    let leaf_value = builder.value(leaf);
+    let (inputs, quad) = builder.hash3_values([leaf_value, graph_name, Fr::ZERO], Domain::Quad);
+    let zero = builder.constant(Fr::ZERO);
+    builder.copy(leaf, inputs[0]);
+    builder.copy(zero, inputs[2]);
+    (chain![[inputs[1]], cells].collect(), quad)
+}
+
+/// Lays out `patterns` matched by `triples` if given, or over zeros
+/// otherwise, constraining each leaf to be under the dataset root by
+/// `strategy` and each term to equal its constant or binding only if
+/// `selector` is `1`. Returns
+/// the cell of each variable first bound by `patterns`, which isn't scaled by
+/// `selector`.
+#[allow(clippy::too_many_arguments)]
+fn guarded_patterns(
+    builder: &mut CircuitBuilder,
+    selector: Cell,
+    patterns: &[TriplePattern],
+    triples: Option<&[usize]>,
+    variables: &[&Variable],
+    bindings: &[Option<Cell>],
+    dataset: &RdfGraph,
+    set: &MerkleTripleSet,
+    strategy: MatchStrategy,
+) -> Vec<Option<Cell>> {
+    let root = builder.instance(InstanceLayout::DATASET_ROOT);
+    let mut new_bindings = vec![None; variables.len()];
+    for (idx, pattern) in patterns.iter().enumerate() {
+        let (values, graph_name) = match triples {
+            Some(triples) => quad_values(dataset, triples[idx]),
+            None => ([Fr::ZERO; 3], Fr::ZERO),
+        };
+        let (cells, leaf) = pattern_leaf(builder, pattern, values, graph_name);
+        match strategy {
+            MatchStrategy::Merkle => {
+                let path = match triples {
+                    Some(triples) => {
+                        let (triple, name) = dataset.quad(triples[idx]);
+                        set.prove_quad_membership(&triple, name.as_ref()).unwrap()
+                    }
+                    None => MerklePath {
+                        idx: 0,
+                        siblings: vec![Fr::ZERO; set.depth()],
+                    },
+                };
+                let leaf_root = builder.merkle_root(leaf, &path);
+                builder.assert_equal_if(selector, leaf_root, root);
+            }
+            // Unselected, the looked up `0` is found off the table rows.
+            MatchStrategy::Lookup => {
+                let leaf = builder.mul(selector, leaf);
+                builder.lookup(leaf);
+            }
+        }
+        for (term, cell) in pattern.graph_and_terms().zip(cells) {
+            let expected = match term {
+                TermPattern::Variable(variable) => {
+                    let var = variables.iter().position(|v| *v == variable).unwrap();
+                    match bindings[var].or(new_bindings[var]) {
+                        Some(binding) => binding,
+                        None => {
+                            new_bindings[var] = Some(cell);
+                            continue;
+                        }
+                    }
+                }
+                _ => builder.constant(constant_encoding(term).unwrap()),
+            };
+            builder.assert_equal_if(selector, cell, expected);
+        }
+    }
+    new_bindings
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::{
+        circuit::{
+            builder::{witness_poly, Q_TABLE},
+            check_satisfied, compile_bgp, compile_bgp_over_subset, compile_bgp_with_hash,
+            compile_bgp_with_solutions, compile_bgp_with_strategy, compile_bgp_with_value_bits,
+            compile_bgp_within_k, compile_to_k, decode_instances,
+            estimate_circuit_size_with_solutions, query_commitment, triple_capacity, CompileError,
+            InstanceLayout, MatchStrategy, QueryCircuit, WitnessError, NULL_ENCODING,
+            NUM_FILTER_BITS,
+        },
+        eval::{self, evaluate, Binding},
+        hash::{hash_node, integer_encoding, string_limbs, Domain, HashChoice, MAX_STRING_LEN},
+        merkle::MerkleTripleSet,
+        parse_select,
+        query::{Iri, Literal, SelectQuery, TermPattern, Variable, XSD_BOOLEAN, XSD_DATE_TIME},
+        rdf::{parse_nquads, RdfGraph, Term, Triple},
+    };
+    use plonkish_backend::{
+        backend::{hyperplonk::HyperPlonk, PlonkishBackend, PlonkishCircuit, WitnessEncoding},
+        halo2_curves::bn256::{Bn256, Fr},
+        pcs::multilinear::MultilinearKzg,
+        util::{
+            arithmetic::{Field, PrimeField},
+            chain,
+            transcript::{InMemoryTranscript, Keccak256Transcript},
+            Itertools,
+        },
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::HashMap;
+
+    pub(crate) type Pb = HyperPlonk<MultilinearKzg<Bn256>>;
+
+    /// Proves `circuit` and verifies the proof against `instances`.
+    pub(crate) fn prove_and_verify(circuit: &QueryCircuit, instances: &[Vec<Fr>]) -> bool {
+        let circuit_info = circuit.circuit_info().unwrap();
+        let param = Pb::setup(&circuit_info, StdRng::seed_from_u64(0)).unwrap();
+        let (pp, vp) = Pb::preprocess(&param, &circuit_info).unwrap();
+        let proof = {
+            let mut transcript = Keccak256Transcript::default();
+            Pb::prove(&pp, circuit, &mut transcript, StdRng::seed_from_u64(0)).unwrap();
+            transcript.into_proof()
+        };
+        let mut transcript = Keccak256Transcript::from_proof((), proof.as_slice());
+        Pb::verify(&vp, instances, &mut transcript, StdRng::seed_from_u64(0)).is_ok()
+    }
+
+    pub(crate) fn graph() -> RdfGraph {
+        let [alice, bob, carol, knows, name] =
+            ["alice", "bob", "carol", "knows", "name"].map(Term::iri);
+        RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(bob.clone(), knows.clone(), carol.clone()),
+            Triple::new(alice, knows, carol.clone()),
+            Triple::new(carol, name, Term::iri("Carol")),
+        ])
+    }
+
+    #[test]
+    fn bgp() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0].len(), 6);
+        assert_eq!(instances[0][0], query_commitment(&query));
+        assert_eq!(instances[0][1], MerkleTripleSet::from_graph(&graph).root());
+        assert_eq!(instances[0][2], Term::iri("bob").encode());
+        assert_eq!(instances[0][3], Term::iri("Carol").encode());
+        assert!(prove_and_verify(&circuit, &instances));
+
+        for (idx, value) in [(1, Fr::ZERO), (4, Term::iri("carol").encode())] {
+            let mut tampered = instances.clone();
+            tampered[0][idx] = value;
+            assert!(!prove_and_verify(&circuit, &tampered));
+        }
+    }
+
+    #[test]
+    fn numeric_literal() {
+        let graph = parse_nquads(
+            "<a> <p> \"1\"^^<http://www.w3.org/2001/XMLSchema#decimal> .\n\
+             <b> <p> \"1\" .\n",
+        )
+        .unwrap();
+        let query = parse_select(
+            "SELECT ?s { ?s <p> \"1.0\"^^<http://www.w3.org/2001/XMLSchema#decimal> }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][2], Term::iri("a").encode());
+        assert!(prove_and_verify(&circuit, &instances));
+
+        // Nor is a string of the same lexical form.
+        let query = parse_select("SELECT ?s { ?s <p> \"1\" }").unwrap();
+        let solutions = evaluate(&query, &graph).collect_vec();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(
+            graph.term(solutions[0].bindings[0].unwrap()),
+            &Term::iri("b")
+        );
+    }
+
+    #[test]
+    fn join() {
+        let graph = graph();
+        let query = parse_select("SELECT ?x ?z WHERE { ?x <knows> ?y . ?y <knows> ?z }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let [alice, bob, carol, knows] =
+            ["alice", "bob", "carol", "knows"].map(|iri| Term::iri(iri).encode());
+        // Only alice knows bob who knows carol.
+        assert_eq!(instances[0][2..], [alice, carol]);
+        assert!(prove_and_verify(&circuit, &instances));
+
+        // Input row of the leaf hash of each pattern's triple.
+        let k = circuit.circuit_info().unwrap().k;
+        let row_mapping = Pb::row_mapping(k);
+        let witness = circuit.witness();
+        let leaf_row = |triple: [Fr; 3]| {
+            let inputs = chain![triple, [Domain::Triple.tag()]].collect_vec();
+            (0..row_mapping.len())
+                .find(|row| {
+                    (0..4).all(|column| {
+                        witness.witness_polys[column][row_mapping[*row]] == inputs[column]
+                    })
+                })
+                .unwrap()
+        };
+        let lhs = (witness_poly(2), row_mapping[leaf_row([alice, knows, bob])]);
+        let rhs = (witness_poly(0), row_mapping[leaf_row([bob, knows, carol])]);
+        // Both bindings of ?y are in the same copy cycle.
+        assert!(circuit
+            .circuit_info()
+            .unwrap()
+            .permutations
+            .iter()
+            .any(|cycle| cycle.contains(&lhs) && cycle.contains(&rhs)));
+
+        // Second ?y bound to alice instead, disagreeing with the first.
+        let mut tampered = witness.clone();
+        tampered.witness_polys[0][rhs.1] = alice;
+        assert!(check_satisfied::<Pb>(circuit.shape(), &tampered).is_err());
+        let tampered = QueryCircuit::new(circuit.shape().clone(), tampered);
+        assert!(!prove_and_verify(&tampered, &instances));
+    }
+
+    #[test]
+    fn property_path() {
+        let graph = graph();
+        let query = parse_select("SELECT * WHERE { ?x <knows>/<knows> ?z }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let [alice, carol] = ["alice", "carol"].map(|iri| Term::iri(iri).encode());
+        assert_eq!(instances[0][2..], [alice, carol]);
+        assert!(prove_and_verify(&circuit, &instances));
+        let join = parse_select("SELECT ?x ?z WHERE { ?x <knows> ?y . ?y <knows> ?z }").unwrap();
+        assert_eq!(query_commitment(&query), query_commitment(&join));
+
+        // Alice knows carol, but not through anyone.
+        let mut instances = instances;
+        instances[0][2..].copy_from_slice(&[alice, Term::iri("bob").encode()]);
+        assert!(!prove_and_verify(&circuit, &instances));
+        let three = parse_select("SELECT * WHERE { ?x <knows>/<knows>/<knows> ?z }").unwrap();
+        assert!(matches!(
+            compile_bgp::<Pb>(&three, &graph),
+            Err(CompileError::UnsupportedEmpty { .. })
+        ));
+    }
+
+    #[test]
+    fn blank_node() {
+        let [alice, bob, knows, name] = ["alice", "bob", "knows", "name"].map(Term::iri);
+        let [x, y] = ["x", "y"].map(|label| Term::BlankNode(label.to_string()));
+        let triples = [
+            Triple::new(alice.clone(), knows.clone(), x.clone()),
+            Triple::new(bob.clone(), knows, y.clone()),
+            Triple::new(x, name.clone(), Term::iri("Xavier")),
+            Triple::new(bob, name.clone(), Term::iri("Bob")),
+        ];
+        let graph = RdfGraph::from_triples(triples.clone());
+
+        // Binds the blank node `x` of the dataset without exposing it, and
+        // whatever its label.
+        let query =
+            parse_select("SELECT * WHERE { ?a <knows> _:y . _:y <name> <Xavier> }").unwrap();
+        assert_eq!(query.projection, [Variable("a".to_string())]);
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0].len(), 3);
+        assert_eq!(instances[0][2], alice.encode());
+        assert!(prove_and_verify(&circuit, &instances));
+        let named = parse_select("SELECT ?a WHERE { ?a <knows> ?y . ?y <name> <Xavier> }").unwrap();
+        assert_eq!(query_commitment(&query), query_commitment(&named));
+
+        // Without the name of the blank node `x` there's no solution.
+        let other = RdfGraph::from_triples(triples[..2].to_vec());
+        let mut instances = circuit.instances().to_vec();
+        instances[0][InstanceLayout::DATASET_ROOT] = MerkleTripleSet::from_graph(&other).root();
+        assert!(!prove_and_verify(&circuit, &instances));
+        assert_eq!(
+            circuit.shape().witness_for::<Pb>(&other).unwrap_err(),
+            CompileError::NumSolutions {
+                expected: 1,
+                found: 0
+            }
+        );
+        // The emptiness of a join isn't proven.
+        let unmatched =
+            parse_select("SELECT ?a WHERE { ?a <knows> _:y . _:y <name> <Bob> }").unwrap();
+        assert!(matches!(
+            compile_bgp::<Pb>(&unmatched, &graph),
+            Err(CompileError::UnsupportedEmpty { .. })
+        ));
+    }
+
+    #[test]
+    fn canonical_commitment() {
+        let graph = graph();
+        let commitment = |input| query_commitment(&parse_select(input).unwrap());
+        for (lhs, rhs) in [
+            (
+                "SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }",
+                "PREFIX ex: <>\nselect $x ?y {?x ex:knows ?z.?z <name> ?y.}",
+            ),
+            (
+                "SELECT ?a WHERE { ?a <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <T> }",
+                "SELECT ?a WHERE { ?a a <T> }",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b FILTER(?b > 1) FILTER(?b != 3) }",
+                "SELECT ?a { ?a <knows> ?b FILTER(3 != ?b) FILTER(?b > 1) FILTER(?b > 1) }",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b VALUES ?b { <bob> <carol> } }",
+                "SELECT ?a { ?a <knows> ?c } VALUES ?c { <carol> <bob> <carol> }",
+            ),
+            (
+                "SELECT (COUNT(?b) AS ?n) { ?a <knows> ?b }",
+                "SELECT DISTINCT (COUNT(DISTINCT ?b) AS ?m) { ?a <knows> ?b }",
+            ),
+        ] {
+            assert_eq!(commitment(lhs), commitment(rhs), "{lhs} and {rhs}");
+            // Filters of integers match no IRI, so those fail alike as empty.
+            let [lhs, rhs] = [lhs, rhs].map(|input| {
+                let circuit = compile_bgp::<Pb>(&parse_select(input).unwrap(), &graph);
+                circuit.map(|circuit| circuit.instances()[0].clone())
+            });
+            assert_eq!(lhs, rhs);
+        }
+        for (lhs, rhs) in [
+            (
+                "SELECT ?a ?b WHERE { ?a <knows> ?b }",
+                "SELECT ?b ?a WHERE { ?a <knows> ?b }",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b FILTER(?b > 1) }",
+                "SELECT ?a { ?a <knows> ?b FILTER(?b >= 1) }",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b VALUES ?b { <bob> } }",
+                "SELECT ?a { ?a <knows> ?b VALUES ?b { <bob> <carol> } }",
+            ),
+            (
+                "SELECT ?a WHERE { ?a <knows> ?b }",
+                "SELECT DISTINCT ?a WHERE { ?a <knows> ?b }",
+            ),
+        ] {
+            assert_ne!(commitment(lhs), commitment(rhs), "{lhs} and {rhs}");
+        }
+
+        let query =
+            parse_select("SELECT ?a { ?a <knows> ?b VALUES ?b { <carol> <bob> } }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+    }
+
+    #[test]
+    fn filter() {
+        let age = |person: &str, age: i64| {
+            Triple::new(
+                Term::iri(person),
+                Term::iri("age"),
+                Term::Literal(Literal::integer(age)),
+            )
+        };
+        let graph = RdfGraph::from_triples([
+            age("alice", -5),
+            age("bob", 18),
+            age("carol", 30),
+            age("dave", i64::MAX),
+        ]);
+        for (filter, expected) in [
+            ("?age > 18", &["carol", "dave"][..]),
+            ("?age >= 18", &["bob", "carol", "dave"]),
+            ("?age < 18", &["alice"]),
+            ("-5 >= ?age", &["alice"]),
+            ("?age = 30", &["carol"]),
+            ("?age != 30", &["alice", "bob", "dave"]),
+        ] {
+            let query =
+                parse_select(&format!("SELECT ?p {{ ?p <age> ?age FILTER({filter}) }}")).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(
+                instances[0][2..],
+                expected
+                    .iter()
+                    .map(|p| Term::iri(*p).encode())
+                    .collect::<Vec<_>>()
+            );
+            assert!(prove_and_verify(&circuit, &instances));
+        }
+
+        let query = parse_select("SELECT ?p { ?p <age> ?age FILTER(?age > 18) }").unwrap();
+        let unfiltered = parse_select("SELECT ?p { ?p <age> ?age }").unwrap();
+        let circuit = compile_bgp::<Pb>(&unfiltered, &graph).unwrap();
+        let mut instances = circuit.instances().to_vec();
+        instances[0][0] = query_commitment(&query);
+        assert!(!prove_and_verify(&circuit, &instances));
+
+        let query = parse_select("SELECT ?p { ?p <age> ?age FILTER(?x > 18) }").unwrap();
+        assert_eq!(
+            compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnboundVariable(Variable("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn value_bits() {
+        let age = |person: &str, age: i64| {
+            Triple::new(
+                Term::iri(person),
+                Term::iri("age"),
+                Term::Literal(Literal::integer(age)),
+            )
+        };
+        let graph = RdfGraph::from_triples([age("alice", -128), age("bob", 18), age("carol", 127)]);
+        for input in [
+            "SELECT ?p { ?p <age> ?age FILTER(?age > 18) }",
+            "SELECT ?p { ?p <age> ?age FILTER(?age < 0 || ?age >= 100) }",
+            "SELECT ?p ?n { ?p <age> ?age BIND(?age - 1 AS ?n) FILTER(?n > -100) }",
+        ] {
+            let query = parse_select(input).unwrap();
+            let circuit = compile_bgp_with_value_bits::<Pb>(&query, &graph, 8).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(
+                instances,
+                compile_bgp::<Pb>(&query, &graph).unwrap().instances()
+            );
+            assert_eq!(circuit.shape().value_bits(), 8);
+            assert!(prove_and_verify(&circuit, &instances));
+        }
+        let query = parse_select("SELECT ?p { ?p <age> ?age FILTER(?age > 18) }").unwrap();
+        let [narrow, wide] = [8, NUM_FILTER_BITS].map(|value_bits| {
+            compile_bgp_with_value_bits::<Pb>(&query, &graph, value_bits)
+                .unwrap()
+                .shape()
+                .num_rows()
+        });
+        assert!(narrow < wide);
+
+        // Values past 8 bits are rejected, of the dataset, the query or a
+        // `BIND`, also by a witness of the shape.
+        let circuit = compile_bgp_with_value_bits::<Pb>(&query, &graph, 8).unwrap();
+        let other = RdfGraph::from_triples([age("alice", -128), age("bob", 18), age("carol", 128)]);
+        let out_of_range = |value| CompileError::ValueOutOfRange {
+            value,
+            value_bits: 8,
+        };
+        assert_eq!(
+            circuit.shape().witness_for::<Pb>(&other).unwrap_err(),
+            out_of_range(128)
+        );
+        for (input, graph, value) in [
+            ("SELECT ?p { ?p <age> ?age FILTER(?age > 18) }", &other, 128),
+            (
+                "SELECT ?p { ?p <age> ?age FILTER(?age > 300) }",
+                &graph,
+                300,
+            ),
+            (
+                "SELECT ?p ?n { ?p <age> ?age BIND(?age * 2 AS ?n) }",
+                &graph,
+                -256,
+            ),
+        ] {
+            let query = parse_select(input).unwrap();
+            assert_eq!(
+                compile_bgp_with_value_bits::<Pb>(&query, graph, 8).unwrap_err(),
+                out_of_range(value)
+            );
+        }
+        assert_eq!(
+            compile_bgp_with_value_bits::<Pb>(&query, &graph, 65).unwrap_err(),
+            CompileError::UnsupportedValueBits { value_bits: 65 }
+        );
+    }
+
+    #[test]
+    fn filter_condition() {
+        let age = |person: &str, age: i64| {
+            Triple::new(
+                Term::iri(person),
+                Term::iri("age"),
+                Term::Literal(Literal::integer(age)),
+            )
+        };
+        let graph = RdfGraph::from_triples([
+            age("alice", -5),
+            age("bob", 18),
+            age("carol", 30),
+            age("dave", 70),
+        ]);
+        for (filter, expected) in [
+            ("?age > 0 && ?age < 65", &["bob", "carol"][..]),
+            ("?age < 0 || ?age >= 65", &["alice", "dave"]),
+            ("?age = 18 || ?age = 70", &["bob", "dave"]),
+            ("!(?age != 30) || !(?age <= 65)", &["carol", "dave"]),
+            ("(?age < 20 || ?age > 60) && ?age != -5", &["bob", "dave"]),
+        ] {
+            let query =
+                parse_select(&format!("SELECT ?p {{ ?p <age> ?age FILTER({filter}) }}")).unwrap();
+            let expected = expected
+                .iter()
+                .map(|p| Term::iri(*p).encode())
+                .collect::<Vec<_>>();
+            let evaluated = evaluate(&query, &graph)
+                .map(|solution| graph.term(solution.bindings[0].unwrap()).encode())
+                .collect::<Vec<_>>();
+            assert_eq!(evaluated, expected);
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(instances[0][2..], expected);
+            assert!(prove_and_verify(&circuit, &instances));
+        }
+
+        let query =
+            parse_select("SELECT ?p { ?p <age> ?age FILTER(?age < 0 || ?age >= 65) }").unwrap();
+        let other =
+            parse_select("SELECT ?p { ?p <age> ?age FILTER(?age < 0 && ?age >= 65) }").unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&other));
+        let query = parse_select("SELECT ?p { ?p <age> ?age FILTER(?age > 0 || ?x > 0) }").unwrap();
+        assert_eq!(
+            compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnboundVariable(Variable("x".to_string()))
+        );
+    }
+
+    #[test]
+    fn date_time_and_boolean() {
+        let literal = |lexical: &str, datatype: &str| {
+            Term::Literal(Literal::new(lexical, Iri(datatype.to_string())))
+        };
+        let triple = |event: &str, predicate: &str, object: Term| {
+            Triple::new(Term::iri(event), Term::iri(predicate), object)
+        };
+        let graph = RdfGraph::from_triples([
+            triple(
+                "a",
+                "at",
+                literal("2019-12-31T23:30:00-01:00", XSD_DATE_TIME),
+            ),
+            triple(
+                "b",
+                "at",
+                literal("2020-01-01T00:30:00+01:00", XSD_DATE_TIME),
+            ),
+            triple("c", "at", literal("2021-06-01T12:00:00Z", XSD_DATE_TIME)),
+            triple("d", "at", Term::Literal(Literal::integer(1600000000))),
+            triple("a", "done", literal("1", XSD_BOOLEAN)),
+            triple("b", "done", literal("false", XSD_BOOLEAN)),
+        ]);
+        let prefix = "PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>";
+        for (query, expected) in [
+            (
+                "SELECT ?e { ?e <at> ?t FILTER(?t > \"2020-01-01T00:00:00Z\"^^xsd:dateTime) }",
+                &["a", "c"][..],
+            ),
+            (
+                "SELECT ?e { ?e <at> ?t FILTER(\"2020-01-01T01:00:00+01:00\"^^xsd:dateTime > ?t \
+                 || ?t >= \"2021-01-01T00:00:00Z\"^^xsd:dateTime) }",
+                &["b", "c"],
+            ),
+            ("SELECT ?e { ?e <done> true }", &["a"]),
+            ("SELECT ?e { ?e <done> \"0\"^^xsd:boolean }", &["b"]),
+        ] {
+            let query = parse_select(&format!("{prefix} {query}")).unwrap();
+            let expected = expected
+                .iter()
+                .map(|e| Term::iri(*e).encode())
+                .collect::<Vec<_>>();
+            let evaluated = evaluate(&query, &graph)
+                .map(|solution| graph.term(solution.bindings[0].unwrap()).encode())
+                .collect::<Vec<_>>();
+            assert_eq!(evaluated, expected);
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(instances[0][2..], expected);
+            assert!(prove_and_verify(&circuit, &instances));
+        }
+
+        let query = parse_select(&format!(
+            "{prefix} SELECT ?e {{ ?e <at> ?t FILTER(?t > \"2020-01-01T00:00:00Z\"^^xsd:dateTime) }}"
+        ))
+        .unwrap();
+        let integer = parse_select("SELECT ?e { ?e <at> ?t FILTER(?t > 1577836800) }").unwrap();
+        assert_eq!(query.filters[0].value, integer.filters[0].value);
+        assert_ne!(query_commitment(&query), query_commitment(&integer));
+    }
+
+    #[test]
+    fn count() {
+        let graph = graph();
+        let query = parse_select("SELECT (COUNT(?a) AS ?n) { ?a <knows> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][2..], [Fr::from(2)]);
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let mut tampered = instances;
+        tampered[0][2] = Fr::from(3);
+        assert!(!prove_and_verify(&circuit, &tampered));
+    }
+
+    #[test]
+    fn group_by() {
+        let [alice, bob, carol, dog, person, type_] =
+            ["alice", "bob", "carol", "Dog", "Person", "type"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), type_.clone(), person.clone()),
+            Triple::new(bob.clone(), type_.clone(), dog.clone()),
+            Triple::new(carol.clone(), type_.clone(), person.clone()),
+            Triple::new(alice, Term::iri("knows"), carol),
+        ]);
+        let query =
+            parse_select("SELECT ?t (COUNT(?x) AS ?n) WHERE { ?x <type> ?t } GROUP BY ?t").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let mut groups = [(dog.encode(), 1), (person.encode(), 2)];
+        groups.sort_by_key(|(key, _)| {
+            let mut repr = key.to_repr();
+            repr.as_mut().reverse();
+            repr
+        });
+        let results = groups
+            .iter()
+            .flat_map(|(key, count)| [*key, Fr::from(*count as u64)]);
+        assert_eq!(instances[0][2..], results.collect_vec());
+        assert_eq!(
+            circuit.shape().group_counts(),
+            groups.map(|(_, count)| count)
+        );
+        assert!(decode_instances(&instances).unwrap().matches_query(&query));
+        assert!(prove_and_verify(&circuit, &instances));
+
+        for (idx, value) in [(3, Fr::from(3)), (2, Term::iri("Cat").encode())] {
+            let mut tampered = instances.clone();
+            tampered[0][idx] = value;
+            assert!(!prove_and_verify(&circuit, &tampered));
+        }
+
+        // The witness of a dataset of as many solutions in other groups doesn't
+        // fit the shape.
+        let other = RdfGraph::from_triples(
+            ["alice", "bob", "carol"]
+                .map(|x| Triple::new(Term::iri(x), type_.clone(), dog.clone())),
+        );
+        assert_eq!(
+            circuit.shape().witness_for::<Pb>(&other).unwrap_err(),
+            CompileError::GroupCounts {
+                expected: groups.map(|(_, count)| count).to_vec(),
+                found: vec![3],
+            }
+        );
+    }
+
+    #[test]
+    fn batch() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let all = compile_bgp::<Pb>(&query, &graph).unwrap();
+        for num_solutions in [1, 2] {
+            let solutions = evaluate(&query, &graph).take(num_solutions);
+            let circuit = compile_bgp_with_solutions::<Pb>(&query, &graph, solutions).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(circuit.shape().num_solutions(), num_solutions);
+            assert_eq!(instances[0], all.instances()[0][..2 + 2 * num_solutions]);
+            assert!(prove_and_verify(&circuit, &instances));
+        }
+    }
+
+    #[test]
+    fn distinct() {
+        let graph = graph();
+        let key = |value: Fr| {
+            let mut repr = value.to_repr();
+            repr.as_mut().reverse();
+            repr
+        };
+        for (input, num_results) in [
+            ("SELECT DISTINCT ?b { ?a <knows> ?b }", 2),
+            (
+                "SELECT DISTINCT ?b ?n { ?a <knows> ?b OPTIONAL { ?b <name> ?n } }",
+                2,
+            ),
+        ] {
+            let query = parse_select(input).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let layout = *circuit.instance_layout();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(layout.num_solutions, num_results);
+            let keys = (0..num_results)
+                .map(|idx| {
+                    let values = (0..layout.num_projected)
+                        .map(|offset| instances[0][layout.result(idx, offset)]);
+                    key(values.reduce(hash_node).unwrap())
+                })
+                .collect::<Vec<_>>();
+            assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(prove_and_verify(&circuit, &instances));
+
+            let mut swapped = instances.clone();
+            for offset in 0..layout.num_projected {
+                swapped[0].swap(layout.result(0, offset), layout.result(1, offset));
+            }
+            assert!(!prove_and_verify(&circuit, &swapped));
+            let mut repeated = instances.clone();
+            for offset in 0..layout.num_projected {
+                repeated[0][layout.result(1, offset)] = instances[0][layout.result(0, offset)];
+            }
+            assert!(!prove_and_verify(&circuit, &repeated));
+        }
+
+        let query = parse_select("SELECT DISTINCT ?b { ?a <knows> ?b }").unwrap();
+        let all = parse_select("SELECT ?b { ?a <knows> ?b }").unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&all));
+    }
+
+    #[test]
+    fn values() {
+        let graph = graph();
+        let query =
+            parse_select("SELECT ?a ?b { ?a <knows> ?b VALUES ?b { <carol> <dave> } }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let [alice, bob, carol] = ["alice", "bob", "carol"].map(|iri| Term::iri(iri).encode());
+        assert_eq!(instances[0][2..], [bob, carol, alice, carol]);
+        assert!(prove_and_verify(&circuit, &instances));
+
+        // Solution outside of the VALUES proven by the circuit of all.
+        let unrestricted = parse_select("SELECT ?a ?b { ?a <knows> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&unrestricted, &graph).unwrap();
+        let mut instances = circuit.instances().to_vec();
+        assert_ne!(query_commitment(&query), instances[0][0]);
+        instances[0][0] = query_commitment(&query);
+        assert!(!prove_and_verify(&circuit, &instances));
+
+        let other = parse_select("SELECT ?a ?b { ?a <knows> ?b VALUES ?b { <carol> } }").unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&other));
+
+        let query = parse_select("SELECT ?a { ?a <knows> ?b VALUES ?c { <carol> } }").unwrap();
+        assert_eq!(
+            compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnboundVariable(Variable("c".to_string()))
+        );
+    }
+
+    #[test]
+    fn order_by() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?b").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let keys = instances[0][2..]
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .map(|encoding| {
+                let mut repr = encoding.to_repr();
+                repr.as_mut().reverse();
+                repr
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys.len(), 3);
+        assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(prove_and_verify(&circuit, &instances));
+        let unordered = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&unordered));
+
+        let query =
+            parse_select("SELECT DISTINCT ?b WHERE { ?a <knows> ?b } ORDER BY ASC(?b)").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert_eq!(circuit.shape().num_solutions(), 2);
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+
+        for (input, err) in [
+            (
+                "SELECT ?a WHERE { ?a <knows> ?b } ORDER BY ?b",
+                CompileError::UnprojectedOrderVariable(Variable("b".to_string())),
+            ),
+            (
+                "SELECT ?a WHERE { ?a <knows> ?b } ORDER BY ?c",
+                CompileError::UnboundVariable(Variable("c".to_string())),
+            ),
+            (
+                "SELECT ?a WHERE { ?a <knows> ?b } ORDER BY DESC(?a)",
+                CompileError::UnsupportedOrder {
+                    reason: "descending",
+                },
+            ),
+            (
+                "SELECT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?a ?b",
+                CompileError::UnsupportedOrder {
+                    reason: "of several conditions",
+                },
+            ),
+            (
+                "SELECT DISTINCT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?a",
+                CompileError::UnsupportedOrder {
+                    reason: "with DISTINCT of several variables",
+                },
+            ),
+        ] {
+            let query = parse_select(input).unwrap();
+            assert_eq!(
+                compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+                err,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn limit() {
+        let graph = graph();
+        let ordered = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?b").unwrap();
+        let query =
+            parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?b LIMIT 2").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        // Top 2 of the ordered results.
+        let top = compile_bgp::<Pb>(&ordered, &graph).unwrap().instances()[0][2..6].to_vec();
+        assert_eq!(circuit.shape().num_solutions(), 2);
+        assert_eq!(instances[0][2..], top);
+        assert_ne!(query_commitment(&query), query_commitment(&ordered));
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let query = parse_select("SELECT ?a WHERE { ?a <knows> <bob> } LIMIT 3").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let alice = Term::iri("alice").encode();
+        assert_eq!(circuit.shape().num_solutions(), 1);
+        assert_eq!(instances[0][2..], [alice, NULL_ENCODING, NULL_ENCODING]);
+        let inputs = decode_instances(&instances).unwrap();
+        assert!(inputs.matches_query(&query));
+        assert!(prove_and_verify(&circuit, &instances));
+        let mut tampered = instances.clone();
+        tampered[0][4] = alice;
+        assert!(!prove_and_verify(&circuit, &tampered));
+        let mut truncated = inputs;
+        truncated.results.pop();
+        assert!(!truncated.matches_query(&query));
+
+        // Padding is never counted, and only `LIMIT 0` drops the count.
+        let count = "SELECT (COUNT(?b) AS ?c) WHERE { ?a <knows> ?b }";
+        for (limit, results) in [("LIMIT 1", vec![Fr::from(2)]), ("LIMIT 0", vec![])] {
+            let query = parse_select(&format!("{count} {limit}")).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            assert_eq!(circuit.instances()[0][2..], results);
+            assert!(prove_and_verify(&circuit, circuit.instances()));
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let mut graph = graph();
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        graph.insert_quad(
+            Triple::new(bob.clone(), knows.clone(), alice),
+            Some(Term::iri("g")),
+        );
+        graph.insert_quad(Triple::new(carol, knows, bob), Some(Term::iri("h")));
+        let root = MerkleTripleSet::from_graph(&graph).root();
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <likes> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(circuit.shape().num_solutions(), 0);
+        assert_eq!(instances[0], [query_commitment(&query), root]);
+        assert!(decode_instances(&instances).unwrap().matches_query(&query));
+        assert!(prove_and_verify(&circuit, &instances));
+        let mut tampered = instances;
+        tampered[0][InstanceLayout::DATASET_ROOT] = Fr::ZERO;
+        assert!(!prove_and_verify(&circuit, &tampered));
+
+        let query = parse_select("SELECT (COUNT(?b) AS ?c) WHERE { ?a <likes> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][1..], [root, Fr::ZERO]);
+        assert!(prove_and_verify(&circuit, &instances));
+        let mut tampered = instances;
+        tampered[0][2] = Fr::ONE;
+        assert!(!prove_and_verify(&circuit, &tampered));
+
+        for (input, results) in [
+            (
+                "SELECT ?a ?b WHERE { ?a <likes> ?b } LIMIT 2",
+                vec![NULL_ENCODING; 4],
+            ),
+            (
+                "SELECT DISTINCT ?a WHERE { ?a <knows> <dave> } ORDER BY ?a",
+                vec![],
+            ),
+            (
+                "SELECT ?a ?n WHERE { ?a <likes> ?b OPTIONAL { ?b <name> ?n } }",
+                vec![],
+            ),
+            ("SELECT ?a WHERE { GRAPH ?g { ?a <knows> ?a } }", vec![]),
+            ("SELECT ?a WHERE { GRAPH <g> { ?a <knows> <bob> } }", vec![]),
+            ("SELECT ?a WHERE { ?a <knows> ?b } LIMIT 0", vec![]),
+            (
+                "SELECT (COUNT(?a) AS ?c) WHERE { ?a <knows> ?b FILTER(?b > 100) }",
+                vec![Fr::ZERO],
+            ),
+        ] {
+            let query = parse_select(input).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            assert_eq!(circuit.instances()[0][2..], results, "{input}");
+            assert!(prove_and_verify(&circuit, circuit.instances()), "{input}");
+        }
+
+        // Only the emptiness of a single pattern is proven.
+        for input in [
+            "SELECT ?a ?n WHERE { { ?a <likes> ?b } UNION { ?a <hates> ?n } }",
+            "SELECT ?a WHERE { ?a <knows> ?b . ?b <knows> <dave> }",
+            "SELECT ?a WHERE { ?a <knows> ?b FILTER(?b > 100) }",
+        ] {
+            let query = parse_select(input).unwrap();
+            assert!(
+                matches!(
+                    compile_bgp::<Pb>(&query, &graph),
+                    Err(CompileError::UnsupportedEmpty { .. })
+                ),
+                "{input}"
+            );
+        }
+        let query = parse_select("SELECT ?a WHERE { ?a <likes> ?b }").unwrap();
+        assert_eq!(
+            compile_bgp_over_subset::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnsupportedEmpty {
+                reason: "over a disclosed subset"
+            }
+        );
+
+        // No solutions of a query with some aren't proven empty.
+        let query = parse_select("SELECT ?a WHERE { ?a <knows> ?b }").unwrap();
+        let found = evaluate(&query, &graph).count();
+        assert_eq!(
+            compile_bgp_with_solutions::<Pb>(&query, &graph, []).unwrap_err(),
+            CompileError::NumSolutions { expected: 0, found }
+        );
+    }
+
+    #[test]
+    fn not_a_solution() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let bob = Term::iri("bob");
+        let solution = evaluate(&query, &graph)
+            .find(|solution| *graph.term(solution.bindings[1].unwrap()) == bob)
+            .unwrap();
+        let other = parse_select("SELECT ?a WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let filtered =
+            parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b VALUES ?b { <carol> } }").unwrap();
+        let union =
+            parse_select("SELECT ?a WHERE { { ?a <knows> ?b } UNION { ?b <knows> ?a } }").unwrap();
+        let mut unmatched = solution.clone();
+        unmatched.triples[0] += 1;
+        let mut extended = solution.clone();
+        extended.triples.push(0);
+        for (query, solution) in [
+            (&query, unmatched),
+            (&query, extended),
+            (&other, solution.clone()),
+            (&filtered, solution.clone()),
+            (&union, solution.clone()),
+        ] {
+            let solutions = [evaluate(query, &graph).next().unwrap(), solution];
+            assert_eq!(
+                compile_bgp_with_solutions::<Pb>(query, &graph, solutions).unwrap_err(),
+                CompileError::NotASolution { idx: 1 }
+            );
+        }
+    }
+
+    #[test]
+    fn optional() {
+        let graph = graph();
+        let query =
+            parse_select("SELECT ?b ?n WHERE { ?a <knows> ?b OPTIONAL { ?b <name> ?n } }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let [bob, carol, name] = ["bob", "carol", "Carol"].map(|iri| Term::iri(iri).encode());
+        assert_eq!(
+            instances[0][2..],
+            [bob, NULL_ENCODING, carol, name, carol, name]
+        );
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let mut tampered = instances;
+        tampered[0][3] = name;
+        assert!(!prove_and_verify(&circuit, &tampered));
+
+        // Reusing the shape with bob named instead of carol.
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        let other = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(bob.clone(), knows.clone(), carol.clone()),
+            Triple::new(alice, knows, carol),
+            Triple::new(bob, Term::iri("name"), Term::iri("Bob")),
+        ]);
+        let witness = circuit.shape().witness_for::<Pb>(&other).unwrap();
+        let circuit = QueryCircuit::new(circuit.shape().clone(), witness);
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][3], Term::iri("Bob").encode());
+        assert_eq!(instances[0][5], NULL_ENCODING);
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let query = parse_select(
+            "SELECT (COUNT(?n) AS ?c) WHERE { ?a <knows> ?b OPTIONAL { ?b <name> ?n } }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][2..], [Fr::ONE]);
+        assert!(prove_and_verify(&circuit, &instances));
+    }
+
+    #[test]
+    fn union() {
+        let graph = graph();
+        let query = parse_select(
+            "SELECT ?a ?b ?n WHERE { { ?a <knows> ?b } UNION { ?a <name> ?n } . ?a <knows> ?c }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let [alice, bob, carol] = ["alice", "bob", "carol"].map(|iri| Term::iri(iri).encode());
+        // Both alice-knows-? triples joined with both of them and bob-knows-carol
+        // with itself, all in the left branch.
+        assert_eq!(instances[0].len(), 2 + 5 * 3);
+        assert_eq!(instances[0][2..5], [alice, bob, NULL_ENCODING]);
+        assert_eq!(instances[0][5..8], [alice, carol, NULL_ENCODING]);
+        assert_eq!(instances[0][8..11], [bob, carol, NULL_ENCODING]);
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let query =
+            parse_select("SELECT ?a ?n WHERE { { ?a <knows> <bob> } UNION { ?a <name> ?n } }")
+                .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        let name = Term::iri("Carol").encode();
+        assert_eq!(instances[0][2..], [alice, NULL_ENCODING, carol, name]);
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let mut tampered = instances;
+        tampered[0][3] = name;
+        assert!(!prove_and_verify(&circuit, &tampered));
+
+        let query = parse_select(
+            "SELECT ?a WHERE { { ?a <knows> ?b } UNION { ?a <name> ?n } UNION { ?a <x> ?b } }",
+        )
+        .unwrap();
+        assert_eq!(
+            compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnsupportedUnion { num_branches: 3 }
+        );
+        let query = parse_select(
+            "SELECT ?a WHERE { { ?a <knows> ?b } UNION { ?a <name> ?n } OPTIONAL { ?b <name> ?m } }",
+        )
+        .unwrap();
+        assert_eq!(
+            compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnsupportedOptionalVariable(Variable("b".to_string()))
+        );
+    }
+
+    #[test]
+    fn named_graph() {
+        let graph = parse_nquads(
+            "<alice> <knows> <bob> .\n\
+             <alice> <knows> <carol> <g> .\n\
+             <bob> <knows> <carol> <h> .\n\
+             <carol> <name> <Carol> <g> .\n",
+        )
+        .unwrap();
+        let [alice, bob, carol, g, h] =
+            ["alice", "bob", "carol", "g", "h"].map(|iri| Term::iri(iri).encode());
+        let query = parse_select("SELECT ?a ?b ?g WHERE { GRAPH ?g { ?a <knows> ?b } }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][1], MerkleTripleSet::from_graph(&graph).root());
+        assert_eq!(instances[0][2..], [alice, carol, g, bob, carol, h]);
+        assert!(prove_and_verify(&circuit, &instances));
+        let mut tampered = instances;
+        tampered[0][4] = h;
+        assert!(!prove_and_verify(&circuit, &tampered));
+
+        // Patterns outside of `GRAPH` only match the default graph.
+        let default = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&default, &graph).unwrap();
+        assert_eq!(circuit.instances()[0][2..], [alice, bob]);
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+
+        let query = parse_select(
+            "SELECT ?a ?n WHERE { ?a <knows> ?b GRAPH <g> { ?a <knows> ?c . ?c <name> ?n } }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert_eq!(
+            circuit.instances()[0][2..],
+            [alice, Term::iri("Carol").encode()]
+        );
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+        let other = parse_select(
+            "SELECT ?a ?n WHERE { ?a <knows> ?b GRAPH <h> { ?a <knows> ?c . ?c <name> ?n } }",
+        )
+        .unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&other));
+        let named = parse_select("SELECT ?a ?b WHERE { GRAPH ?g { ?a <knows> ?b } }").unwrap();
+        assert_ne!(query_commitment(&default), query_commitment(&named));
+
+        // Guarded by the `OPTIONAL` block, matched or not.
+        let query = parse_select(
+            "SELECT ?b ?g WHERE { ?a <knows> ?b OPTIONAL { GRAPH ?g { ?a <knows> ?c } } }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert_eq!(circuit.instances()[0][2..], [bob, g]);
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+        let query = parse_select(
+            "SELECT ?b ?g WHERE { ?a <knows> ?b OPTIONAL { GRAPH ?g { ?b <knows> ?a } } }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert_eq!(circuit.instances()[0][2..], [bob, NULL_ENCODING]);
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+    }
+
+    #[test]
+    fn not_exists() {
+        let graph = graph();
+        let [alice, bob, carol] = ["alice", "bob", "carol"].map(|iri| Term::iri(iri).encode());
+        let query = parse_select(
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?c } }",
+        );
+        assert_eq!(
+            compile_bgp::<Pb>(&query.unwrap(), &graph).unwrap_err(),
+            CompileError::UnsupportedNotExists {
+                reason: "with a variable not bound by the required patterns"
+            }
+        );
+        let query = parse_select(
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a . ?a <name> ?b } }",
+        );
+        assert_eq!(
+            compile_bgp::<Pb>(&query.unwrap(), &graph).unwrap_err(),
+            CompileError::UnsupportedNotExists {
+                reason: "of several patterns"
+            }
+        );
+
+        let query = parse_select(
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <name> <Carol> } }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][2..], [alice, bob]);
+        assert!(prove_and_verify(&circuit, &instances));
+        let mut tampered = instances;
+        tampered[0][3] = carol;
+        assert!(!prove_and_verify(&circuit, &tampered));
+        let without = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&without));
+
+        // Leaves of every neighbor, with and without padding past the last.
+        let query = parse_select(
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a } }",
+        )
+        .unwrap();
+        let mut padded = graph.clone();
+        padded.insert(Triple::new(
+            Term::iri("dave"),
+            Term::iri("knows"),
+            Term::iri("alice"),
+        ));
+        for graph in [graph, padded] {
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            assert_eq!(
+                circuit.shape().num_solutions(),
+                evaluate(&query, &graph).count()
+            );
+            assert!(prove_and_verify(&circuit, circuit.instances()));
+        }
+    }
+
+    #[test]
+    fn string_filter() {
+        let name = |person: &str, name: &str| {
+            Triple::new(
+                Term::iri(person),
+                Term::iri("name"),
+                Term::Literal(Literal::string(name)),
+            )
+        };
+        let long = "a".repeat(MAX_STRING_LEN);
+        let graph = RdfGraph::from_triples([
+            name("alice", "Alice"),
+            name("bob", "Bob"),
+            name("carol", &long),
+            Triple::new(
+                Term::iri("dave"),
+                Term::iri("name"),
+                Term::Literal(Literal::lang_string("Alice", "en")),
+            ),
+        ]);
+        for (value, expected) in [
+            ("Alice", &["alice"][..]),
+            ("Bob", &["bob"]),
+            (&long, &["carol"]),
+            ("Alicia", &[]),
+            ("alice", &[]),
+        ] {
+            let query = parse_select(&format!(
+                "SELECT ?p {{ ?p <name> ?n FILTER(?n = {value:?}) }}"
+            ))
+            .unwrap();
+            if expected.is_empty() {
+                assert!(matches!(
+                    compile_bgp::<Pb>(&query, &graph),
+                    Err(CompileError::UnsupportedEmpty { .. })
+                ));
+                continue;
+            }
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert_eq!(
+                instances[0][2..],
+                expected
+                    .iter()
+                    .map(|p| Term::iri(*p).encode())
+                    .collect::<Vec<_>>()
+            );
+            assert!(prove_and_verify(&circuit, &instances));
+        }
+
+        // Opening the binding to the limbs of "Bob" instead breaks the
+        // equality with the limbs of "Alice".
+        let query = parse_select(r#"SELECT ?p { ?p <name> ?n FILTER(?n = "Alice") }"#).unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let k = circuit.circuit_info().unwrap().k;
+        let row_mapping = Pb::row_mapping(k);
+        let inputs = chain![string_limbs("Alice").unwrap(), [Domain::String.tag()]].collect_vec();
+        let mut tampered = circuit.witness().clone();
+        let row = (0..row_mapping.len())
+            .map(|row| row_mapping[row])
+            .find(|row| (0..4).all(|column| tampered.witness_polys[column][*row] == inputs[column]))
+            .unwrap();
+        let [length, lo, hi] = string_limbs("Bob").unwrap();
+        for (column, value) in [length, lo, hi].into_iter().enumerate() {
+            tampered.witness_polys[column][row] = value;
+        }
+        assert!(check_satisfied::<Pb>(circuit.shape(), &tampered).is_err());
+        let tampered = QueryCircuit::new(circuit.shape().clone(), tampered);
+        assert!(!prove_and_verify(&tampered, circuit.instances()));
+        let other = parse_select(r#"SELECT ?p { ?p <name> ?n FILTER(?n = "Bob") }"#).unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&other));
+
+        let too_long = format!("{long}a");
+        let query = parse_select(&format!(
+            "SELECT ?p {{ ?p <name> ?n FILTER(?n = {too_long:?}) }}"
+        ))
+        .unwrap();
+        let err = compile_bgp::<Pb>(&query, &graph).unwrap_err();
+        assert_eq!(
+            err,
+            CompileError::StringTooLong {
+                length: MAX_STRING_LEN + 1,
+                max: MAX_STRING_LEN
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "string literal of 63 bytes in FILTER exceeds the bound of 62 bytes"
+        );
+    }
+
+    #[test]
+    fn bind() {
+        let age = |person: &str, age: i64| {
+            Triple::new(
+                Term::iri(person),
+                Term::iri("age"),
+                Term::Literal(Literal::integer(age)),
+            )
+        };
+        let graph = RdfGraph::from_triples([
+            age("alice", -5),
+            age("bob", 18),
+            age("carol", 30),
+            age("dave", i64::MAX),
+        ]);
+        let [alice, bob, carol, dave] =
+            ["alice", "bob", "carol", "dave"].map(|iri| Term::iri(iri).encode());
+
+        // Dave's result overflows.
+        let query = parse_select("SELECT ?p ?m { ?p <age> ?age BIND(?age * -2 AS ?m) }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(
+            instances[0][2..],
+            [
+                alice,
+                integer_encoding(10),
+                bob,
+                integer_encoding(-36),
+                carol,
+                integer_encoding(-60)
+            ]
+        );
+        assert!(prove_and_verify(&circuit, &instances));
+        let mut tampered = instances;
+        tampered[0][3] = integer_encoding(11);
+        assert!(!prove_and_verify(&circuit, &tampered));
+        let other = parse_select("SELECT ?p ?m { ?p <age> ?age BIND(?age + -2 AS ?m) }").unwrap();
+        assert_ne!(query_commitment(&query), query_commitment(&other));
+
+        let query = parse_select(
+            "SELECT ?p { ?p <age> ?age BIND(?age - 20 AS ?d) BIND(1 - ?d AS ?e) FILTER(?e < 0) }",
+        )
+        .unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert_eq!(circuit.instances()[0][2..], [carol, dave]);
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+
+        for (input, variable) in [
+            ("SELECT ?p { ?p <age> ?age BIND(?x + 1 AS ?m) }", "x"),
+            (
+                "SELECT ?p { ?p <age> ?age OPTIONAL { ?p <name> ?n } BIND(?n + 1 AS ?m) }",
+                "n",
+            ),
+            (
+                "SELECT ?p { ?p <age> ?age BIND(?e + 1 AS ?m) BIND(?age + 1 AS ?e) }",
+                "e",
+            ),
+        ] {
+            let query = parse_select(input).unwrap();
+            assert_eq!(
+                compile_bgp::<Pb>(&query, &graph).unwrap_err(),
+                CompileError::UnboundVariable(Variable(variable.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn subset() {
+        let mut graph = graph();
+        for idx in 0..12 {
+            let person = Term::iri(format!("p{idx}"));
+            graph.insert(Triple::new(person, Term::iri("likes"), Term::iri("tea")));
+        }
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let (circuit, proof) = compile_bgp_over_subset::<Pb>(&query, &graph).unwrap();
+        let full = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][2..], full.instances()[0][2..]);
+        assert!(circuit.shape().depth() < full.shape().depth());
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let root = MerkleTripleSet::from_graph(&graph).root();
+        let subset_root = instances[0][InstanceLayout::DATASET_ROOT];
+        assert_ne!(subset_root, root);
+        assert!(proof.verify(subset_root, root, HashChoice::Poseidon));
+        let mut other = graph.clone();
+        other.insert(Triple::new(
+            Term::iri("dave"),
+            Term::iri("knows"),
+            Term::iri("bob"),
+        ));
+        assert!(!proof.verify(
+            subset_root,
+            MerkleTripleSet::from_graph(&other).root(),
+            HashChoice::Poseidon
+        ));
+
+        let query = parse_select(
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a } }",
+        )
+        .unwrap();
+        assert_eq!(
+            compile_bgp_over_subset::<Pb>(&query, &graph).unwrap_err(),
+            CompileError::UnsupportedNotExists {
+                reason: "over a disclosed subset"
+            }
+        );
+    }
+
+    #[test]
+    fn pattern_order() {
+        let mut graph = graph();
+        graph.insert(Triple::new(
+            Term::iri("bob"),
+            Term::iri("name"),
+            Term::iri("Bob"),
+        ));
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        // Both patterns bind one position, but fewer triples match <name>.
+        let order = eval::pattern_order(&query, &graph);
+        assert_eq!(order, [1, 0]);
+        let reordered = query.with_pattern_order(&order);
+        assert_eq!(query.with_pattern_order(&[0, 1]), query);
+        assert_ne!(query_commitment(&query), query_commitment(&reordered));
+
+        let results = |query: &SelectQuery| {
+            let circuit = compile_bgp::<Pb>(query, &graph).unwrap();
+            let instances = circuit.instances().to_vec();
+            assert!(prove_and_verify(&circuit, &instances));
+            instances[0][2..]
+                .chunks(2)
+                .map(|result| result.iter().map(PrimeField::to_repr).collect_vec())
+                .collect_vec()
+        };
+        let (written, optimized) = (results(&query), results(&reordered));
+        assert_ne!(written, optimized);
+        assert_eq!(
+            written.into_iter().sorted().collect_vec(),
+            optimized.into_iter().sorted().collect_vec()
+        );
+
+        let limited = parse_select("SELECT ?a WHERE { ?a <knows> ?b . ?b <name> ?n } LIMIT 1");
+        assert_eq!(eval::pattern_order(&limited.unwrap(), &graph), [0, 1]);
+    }
+
+    #[test]
+    fn binding() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let circuit =
+            compile_bgp_with_solutions::<Pb>(&query, &graph, evaluate(&query, &graph).take(1))
+                .unwrap();
+        let shape = circuit.shape();
+        let [a, b, c] = ["a", "b", "c"].map(|name| Variable(name.to_string()));
+        let [alice, bob, carol] = ["alice", "bob", "carol"].map(Term::iri);
+
+        let binding = Binding::from([(a.clone(), bob.clone()), (b.clone(), carol.clone())]);
+        let witness = shape.witness_for_binding::<Pb>(&graph, &binding).unwrap();
+        assert_eq!(witness.instances()[0][2..], [bob.encode(), carol.encode()]);
+        let instances = witness.instances().to_vec();
+        assert!(prove_and_verify(
+            &QueryCircuit::new(shape.clone(), witness),
+            &instances
+        ));
+        // Of alice knowing bob and carol, the first match is proven.
+        let binding = Binding::from([(a.clone(), alice.clone())]);
+        let witness = shape.witness_for_binding::<Pb>(&graph, &binding).unwrap();
+        assert_eq!(witness.instances()[0][2..], [alice.encode(), bob.encode()]);
+
+        for (binding, err) in [
+            (
+                Binding::from([(a.clone(), carol.clone())]),
+                WitnessError::NoMatch,
+            ),
+            (
+                Binding::from([(a.clone(), Term::iri("dave"))]),
+                WitnessError::NoMatch,
+            ),
+            (
+                Binding::from([(a, bob.clone()), (b, alice)]),
+                WitnessError::NoMatch,
+            ),
+            (
+                Binding::from([(c.clone(), bob)]),
+                WitnessError::UnknownVariable(c),
+            ),
+        ] {
+            assert_eq!(
+                shape
+                    .witness_for_binding::<Pb>(&graph, &binding)
+                    .unwrap_err(),
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn shape() {
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph()).unwrap();
+        let shape = circuit.shape().clone();
+        assert_eq!(shape.depth(), 2);
+        assert_eq!(shape.num_matched_triples(), 3);
+
+        let [dave, erin, frank, knows, name] =
+            ["dave", "erin", "frank", "knows", "name"].map(Term::iri);
+        let mut triples = vec![
+            Triple::new(dave.clone(), knows.clone(), erin.clone()),
+            Triple::new(erin.clone(), knows.clone(), frank.clone()),
+            Triple::new(dave.clone(), knows.clone(), frank.clone()),
+            Triple::new(frank.clone(), name.clone(), Term::iri("Frank")),
+        ];
+        let other = RdfGraph::from_triples(triples.clone());
+        let witness = shape.witness_for::<Pb>(&other).unwrap();
+        let circuit = QueryCircuit::new(shape.clone(), witness);
+        let instances = circuit.instances().to_vec();
+        assert_eq!(instances[0][1], MerkleTripleSet::from_graph(&other).root());
+        assert_eq!(instances[0][2], dave.encode());
+        assert!(prove_and_verify(&circuit, &instances));
+
+        let fewer = RdfGraph::from_triples(triples[..1].to_vec());
+        assert_eq!(
+            shape.witness_for::<Pb>(&fewer).unwrap_err(),
+            CompileError::NumSolutions {
+                expected: 3,
+                found: 1,
+            }
+        );
+        triples.push(Triple::new(erin, name, Term::iri("Erin")));
+        let deeper = RdfGraph::from_triples(triples);
+        assert_eq!(
+            shape.witness_for::<Pb>(&deeper).unwrap_err(),
+            CompileError::Depth { max: 2, found: 3 }
+        );
+    }
+
+    #[test]
+    fn match_strategy() {
+        let graph = graph();
+        for input in [
+            "SELECT ?x ?z WHERE { ?x <knows> ?y . ?y <knows> ?z }",
+            "SELECT ?b ?n WHERE { ?a <knows> ?b OPTIONAL { ?b <name> ?n } }",
+            "SELECT ?a ?n WHERE { { ?a <knows> <bob> } UNION { ?a <name> ?n } }",
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a } }",
+        ] {
+            let query = parse_select(input).unwrap();
+            let merkle = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let lookup =
+                compile_bgp_with_strategy::<Pb>(&query, &graph, MatchStrategy::Lookup).unwrap();
+            assert_eq!(lookup.shape().match_strategy(), MatchStrategy::Lookup);
+            assert_eq!(lookup.instances(), merkle.instances());
+            assert!(merkle.shape().circuit_info().lookups.is_empty());
+            assert_eq!(lookup.shape().circuit_info().lookups.len(), 1);
+            assert_eq!(
+                check_satisfied::<Pb>(lookup.shape(), lookup.witness()),
+                Ok(())
+            );
+            assert!(prove_and_verify(&lookup, merkle.instances()));
+        }
+
+        // Reusing the shape, whose table is of as many leaves.
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let circuit =
+            compile_bgp_with_strategy::<Pb>(&query, &graph, MatchStrategy::Lookup).unwrap();
+        let [dave, erin, frank, knows] = ["dave", "erin", "frank", "knows"].map(Term::iri);
+        let other = RdfGraph::from_triples([
+            Triple::new(dave.clone(), knows.clone(), erin.clone()),
+            Triple::new(erin, knows.clone(), frank.clone()),
+            Triple::new(dave, knows, frank),
+        ]);
+        let witness = circuit.shape().witness_for::<Pb>(&other).unwrap();
+        let instances = witness.instances().to_vec();
+        assert_eq!(instances[0][1], MerkleTripleSet::from_graph(&other).root());
+        let reused = QueryCircuit::new(circuit.shape().clone(), witness);
+        assert!(prove_and_verify(&reused, &instances));
+
+        // Leaf of the table other than the one of the dataset root, which no
+        // longer hashes into it.
+        let circuit_info = circuit.shape().circuit_info();
+        let row_mapping = Pb::row_mapping(circuit_info.k);
+        let table_row = row_mapping
+            .iter()
+            .find(|idx| circuit_info.preprocess_polys[Q_TABLE][**idx] == Fr::ONE)
+            .unwrap();
+        let mut tampered = circuit.witness().clone();
+        tampered.witness_polys[0][*table_row] = Term::iri("dave").encode();
+        assert!(check_satisfied::<Pb>(circuit.shape(), &tampered).is_err());
+    }
+
+    #[test]
+    fn capacity() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let k = compile_bgp::<Pb>(&query, &graph)
+            .unwrap()
+            .shape()
+            .circuit_info()
+            .k;
+        let circuit = compile_bgp_within_k::<Pb>(&query, &graph, k + 1).unwrap();
+        assert!(circuit.shape().circuit_info().k <= k + 1);
+
+        let max = triple_capacity(&query, 3, 1);
+        let err = compile_bgp_within_k::<Pb>(&query, &graph, 1).unwrap_err();
+        assert_eq!(
+            err,
+            CompileError::CapacityExceeded {
+                have: graph.len(),
+                max,
+                k: 1
+            }
+        );
+        assert!(err.to_string().contains("increase k"), "{err}");
+    }
+
+    #[test]
+    fn padded_to_k() {
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        // The knows triples of `graph` and `n` unrelated ones.
+        let graph_of = |n: usize| {
+            RdfGraph::from_triples(chain![
+                [
+                    Triple::new(alice.clone(), knows.clone(), bob.clone()),
+                    Triple::new(bob.clone(), knows.clone(), carol.clone()),
+                    Triple::new(alice.clone(), knows.clone(), carol.clone()),
+                ],
+                (0..n).map(|idx| {
+                    Triple::new(
+                        Term::iri(format!("person{idx}")),
+                        Term::iri("name"),
+                        Term::Literal(Literal::string(format!("Person {idx}"))),
+                    )
+                })
+            ])
+        };
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let [small, large] = [graph_of(0), graph_of(5)];
+        let k = estimate_circuit_size_with_solutions(&query, large.len(), 3).min_k() + 1;
+        let [small, large] = [&small, &large].map(|graph| {
+            let circuit = compile_to_k::<Pb>(&query, graph, k).unwrap();
+            assert_eq!(circuit.shape().circuit_info().k, k);
+            assert!(prove_and_verify(&circuit, circuit.instances()));
+            circuit
+        });
+        assert_eq!(
+            bincode::serialize(&small.circuit_info().unwrap()).unwrap(),
+            bincode::serialize(&large.circuit_info().unwrap()).unwrap()
+        );
+        assert_ne!(small.instances(), large.instances());
+
+        let max = triple_capacity(&query, 3, k);
+        let over = graph_of(max + 1 - 3);
+        assert_eq!(
+            compile_to_k::<Pb>(&query, &over, k).unwrap_err(),
+            CompileError::CapacityExceeded {
+                have: over.len(),
+                max,
+                k,
+            }
+        );
+    }
+
+    #[test]
+    fn literal() {
+        let [carol, name, age] = ["carol", "name", "age"].map(Term::iri);
+        let dataset = |name_literal, age_literal| {
+            RdfGraph::from_triples([
+                Triple::new(carol.clone(), name.clone(), Term::Literal(name_literal)),
+                Triple::new(carol.clone(), age.clone(), Term::Literal(age_literal)),
+            ])
+        };
+        let typed = dataset(Literal::lang_string("Carol", "en"), Literal::integer(42));
+        let plain = dataset(Literal::string("Carol"), Literal::string("42"));
+        for (input, matching, other) in [
+            ("SELECT ?a WHERE { ?a <name> \"Carol\"@en }", &typed, &plain),
+            (
+                "SELECT ?a WHERE { ?a <age> \"42\"^^<http://www.w3.org/2001/XMLSchema#integer> }",
+                &typed,
+                &plain,
+            ),
+            ("SELECT ?a WHERE { ?a <name> \"Carol\" }", &plain, &typed),
+            ("SELECT ?a WHERE { ?a <age> \"42\" }", &plain, &typed),
+        ] {
+            let query = parse_select(input).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, matching).unwrap();
+            assert_eq!(circuit.shape().num_solutions(), 1);
+            assert!(prove_and_verify(&circuit, circuit.instances()));
+
+            // Proof of the matching literal isn't one over the other dataset,
+            // whose literal has another datatype or language tag.
+            let mut instances = circuit.instances().to_vec();
+            instances[0][InstanceLayout::DATASET_ROOT] = MerkleTripleSet::from_graph(other).root();
+            assert!(!prove_and_verify(&circuit, &instances));
+            assert_eq!(
+                circuit.shape().witness_for::<Pb>(other).unwrap_err(),
+                CompileError::NumSolutions {
+                    expected: 1,
+                    found: 0
+                }
+            );
+            let circuit = compile_bgp::<Pb>(&query, other).unwrap();
+            assert_eq!(circuit.shape().num_solutions(), 0);
+        }
+    }
+
+    /// Returns a random graph over a few nodes, two predicates and small
+    /// integers.
+    fn random_graph(rng: &mut impl Rng) -> RdfGraph {
+        let num_triples = rng.gen_range(4..8);
+        RdfGraph::from_triples((0..num_triples).map(|_| {
+            let node = |rng: &mut _| Term::iri(format!("n{}", Rng::gen_range(rng, 0..4)));
+            let object = match rng.gen_bool(0.25) {
+                true => Term::Literal(Literal::integer(rng.gen_range(0..4))),
+                false => node(rng),
+            };
+            Triple::new(
+                node(rng),
+                Term::iri(format!("p{}", rng.gen_range(0..2))),
+                object,
+            )
+        }))
+    }
+
+    /// Returns a random query of one or two joined patterns over `?x`, `?y`
+    /// and `?z`, maybe filtered, projecting some of the bound variables.
+    fn random_query(rng: &mut impl Rng) -> SelectQuery {
+        let mut patterns = vec![format!("?x <p{}> ?y", rng.gen_range(0..2))];
+        let mut bound = vec!["x", "y"];
+        match rng.gen_range(0..4) {
+            0 => {}
+            1 => {
+                patterns.push(format!("?y <p{}> ?z", rng.gen_range(0..2)));
+                bound.push("z");
+            }
+            2 => {
+                patterns.push(format!("?x <p{}> ?z", rng.gen_range(0..2)));
+                bound.push("z");
+            }
+            _ => patterns.push(format!(
+                "?y <p{}> <n{}>",
+                rng.gen_range(0..2),
+                rng.gen_range(0..4)
+            )),
+        }
+        let mut filter = String::new();
+        if rng.gen_bool(0.5) {
+            let variable = bound[rng.gen_range(0..bound.len())];
+            let op = [">", "<", ">=", "!="][rng.gen_range(0..4)];
+            filter = format!("FILTER(?{variable} {op} {})", rng.gen_range(0..4));
+        }
+        let mut projection = bound
+            .iter()
+            .filter(|_| rng.gen_bool(0.5))
+            .map(|variable| format!("?{variable}"))
+            .collect::<Vec<_>>();
+        if projection.is_empty() {
+            projection.push("?x".to_string());
+        }
+        let input = format!(
+            "SELECT {} WHERE {{ {} {filter} }}",
+            projection.join(" "),
+            patterns.join(" . ")
+        );
+        parse_select(&input).unwrap()
+    }
+
+    /// Returns the encodings of the projected variables of every solution of
+    /// the patterns and filters of `query`, by trying every tuple of triples
+    /// in turn instead of [`evaluate`].
+    fn plaintext_results(query: &SelectQuery, graph: &RdfGraph) -> Vec<Fr> {
+        let mut results = Vec::new();
+        let tuples = query
+            .patterns
+            .iter()
+            .map(|_| 0..graph.len())
+            .multi_cartesian_product();
+        for tuple in tuples {
+            let mut bindings = HashMap::new();
+            let matched = query.patterns.iter().zip(&tuple).all(|(pattern, idx)| {
+                let triple = graph.triples()[*idx];
+                pattern
+                    .terms()
+                    .into_iter()
+                    .zip(triple)
+                    .all(|(term, id)| match term {
+                        TermPattern::Variable(variable) => {
+                            *bindings.entry(variable).or_insert(id) == id
+                        }
+                        TermPattern::Iri(iri) => *graph.term(id) == Term::Iri(iri.clone()),
+                        TermPattern::Literal(literal) => {
+                            *graph.term(id) == Term::Literal(literal.clone())
+                        }
+                    })
+            });
+            let filtered = query.filters.iter().all(|filter| {
+                let value = bindings
+                    .get(&filter.variable)
+                    .and_then(|id| graph.term(*id).as_integer());
+                value.map_or(false, |value| filter.op.evaluate(value, filter.value))
+            });
+            if matched && filtered {
+                results.extend(
+                    query
+                        .projection
+                        .iter()
+                        .map(|variable| graph.encoding(bindings[variable])),
+                );
+            }
+        }
+        results
+    }
+
+    #[test]
+    fn random_queries() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut num_nonempty = 0;
+        for _ in 0..8 {
+            let graph = random_graph(&mut rng);
+            let query = random_query(&mut rng);
+            let circuit = match compile_bgp::<Pb>(&query, &graph) {
+                Err(CompileError::UnsupportedEmpty { .. }) => {
+                    assert!(plaintext_results(&query, &graph).is_empty(), "{query:?}");
+                    continue;
+                }
+                circuit => circuit.unwrap(),
+            };
+            let instances = circuit.instances().to_vec();
+            let inputs = decode_instances(&instances).unwrap();
+            assert!(inputs.matches_query(&query));
+            assert_eq!(
+                inputs.results,
+                plaintext_results(&query, &graph),
+                "{query:?}"
+            );
+            assert!(prove_and_verify(&circuit, &instances));
+
+            // Fake result row in place of the last one.
+            if !inputs.results.is_empty() {
+                num_nonempty += 1;
+                let mut tampered = instances.clone();
+                let last = instances[0].len() - query.projection.len();
+                tampered[0][last..].fill(Term::iri("fake").encode());
+                assert!(!prove_and_verify(&circuit, &tampered));
+            }
+        }
+        assert!(num_nonempty > 0);
+    }
+
+    #[test]
+    fn compile_error() {
+        let graph = graph();
+        let query = parse_select("SELECT ?c WHERE { ?a <name> ?b }").unwrap();
+        assert!(matches!(
+            compile_bgp::<Pb>(&query, &graph),
+            Err(CompileError::UnboundVariable(_))
+        ));
+    }
+
+    #[test]
+    fn hash_choice() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a WHERE { ?a <knows> <bob> }").unwrap();
+        let circuit = compile_bgp_with_hash::<Pb>(&query, &graph, HashChoice::Poseidon).unwrap();
+        let set = MerkleTripleSet::from_graph_with_hash(&graph, HashChoice::Poseidon);
+        assert_eq!(
+            circuit.instances()[0][InstanceLayout::DATASET_ROOT],
+            set.root()
+        );
+        assert!(prove_and_verify(&circuit, circuit.instances()));
+
+        let err = compile_bgp_with_hash::<Pb>(&query, &graph, HashChoice::Keccak256).unwrap_err();
+        assert_eq!(err, CompileError::UnsupportedHash(HashChoice::Keccak256));
+        assert!(err.to_string().contains("use Poseidon instead"), "{err}");
+    }
+}
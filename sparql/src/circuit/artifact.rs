@@ -0,0 +1,152 @@
+use crate::circuit::{QueryCircuit, QueryCircuitShape, Witness};
+use std::{
+    fmt::{self, Display},
+    fs, io,
+    path::Path,
+    sync::Arc,
+};
+
+/// Bytes every file written by [`save_circuit`] starts with.
+const MAGIC: [u8; 4] = *b"SPQC";
+
+/// Version of the format written by [`save_circuit`], to be bumped whenever
+/// the serialization of [`QueryCircuitShape`] or [`Witness`] changes.
+pub const CIRCUIT_FORMAT_VERSION: u32 = 13;
+
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+#[derive(Debug)]
+pub enum CircuitFileError {
+    Io(io::Error),
+    /// The file doesn't start with the magic bytes of a circuit.
+    NotACircuit,
+    /// The file is written in another version of the format.
+    Version {
+        expected: u32,
+        found: u32,
+    },
+    /// The body fails to deserialize or doesn't form a [`QueryCircuit`].
+    Malformed(String),
+}
+
+impl From<io::Error> for CircuitFileError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl Display for CircuitFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitFileError::Io(err) => write!(f, "io error: {err}"),
+            CircuitFileError::NotACircuit => write!(f, "not a query circuit file"),
+            CircuitFileError::Version { expected, found } => write!(
+                f,
+                "query circuit file has format version {found}, expected {expected}"
+            ),
+            CircuitFileError::Malformed(reason) => {
+                write!(f, "malformed query circuit file: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CircuitFileError {}
+
+/// Writes `circuit` to `path`, as a header of magic bytes and
+/// [`CIRCUIT_FORMAT_VERSION`] in little-endian followed by the bincode of its
+/// shape and witness, so it can be proven elsewhere without compiling again.
+pub fn save_circuit(
+    circuit: &QueryCircuit,
+    path: impl AsRef<Path>,
+) -> Result<(), CircuitFileError> {
+    let body = bincode::serialize(&(circuit.shape.as_ref(), &circuit.witness))
+        .map_err(|err| CircuitFileError::Malformed(err.to_string()))?;
+    let mut bytes = Vec::with_capacity(HEADER_LEN + body.len());
+    bytes.extend(MAGIC);
+    bytes.extend(CIRCUIT_FORMAT_VERSION.to_le_bytes());
+    bytes.extend(body);
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+/// Reads a circuit written by [`save_circuit`] from `path`, failing with
+/// [`CircuitFileError::Version`] for a file of another format version.
+pub fn load_circuit(path: impl AsRef<Path>) -> Result<QueryCircuit, CircuitFileError> {
+    let bytes = fs::read(path)?;
+    if bytes.len() < HEADER_LEN || bytes[..MAGIC.len()] != MAGIC {
+        return Err(CircuitFileError::NotACircuit);
+    }
+    let found = u32::from_le_bytes(bytes[MAGIC.len()..HEADER_LEN].try_into().unwrap());
+    if found != CIRCUIT_FORMAT_VERSION {
+        return Err(CircuitFileError::Version {
+            expected: CIRCUIT_FORMAT_VERSION,
+            found,
+        });
+    }
+    let (shape, witness): (QueryCircuitShape, Witness) = bincode::deserialize(&bytes[HEADER_LEN..])
+        .map_err(|err| CircuitFileError::Malformed(err.to_string()))?;
+    let num_instances = shape.layout.num_instances();
+    if witness.instances.len() != 1 || witness.instances[0].len() != num_instances {
+        return Err(CircuitFileError::Malformed(format!(
+            "expected a single instance column of {num_instances} instances"
+        )));
+    }
+    Ok(QueryCircuit::new(Arc::new(shape), witness))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{
+            compile_bgp, load_circuit, save_circuit,
+            test::{graph, prove_and_verify, Pb},
+            CircuitFileError, CIRCUIT_FORMAT_VERSION,
+        },
+        parse_select,
+    };
+    use plonkish_backend::backend::PlonkishCircuit;
+    use std::{env, fs, process};
+
+    #[test]
+    fn round_trip() {
+        let dir = env::temp_dir().join(format!("sparql-artifact-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("circuit.bin");
+
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b OPTIONAL { ?b <name> ?n } }");
+        let circuit = compile_bgp::<Pb>(&query.unwrap(), &graph()).unwrap();
+        save_circuit(&circuit, &path).unwrap();
+        let loaded = load_circuit(&path).unwrap();
+        assert_eq!(
+            bincode::serialize(&loaded.circuit_info().unwrap()).unwrap(),
+            bincode::serialize(&circuit.circuit_info().unwrap()).unwrap()
+        );
+        assert_eq!(loaded.shape().query(), circuit.shape().query());
+        assert_eq!(loaded.instance_layout(), circuit.instance_layout());
+        assert_eq!(loaded.instances(), circuit.instances());
+        assert!(prove_and_verify(&loaded, circuit.instances()));
+
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[4..8].copy_from_slice(&(CIRCUIT_FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            load_circuit(&path),
+            Err(CircuitFileError::Version { found, .. }) if found == CIRCUIT_FORMAT_VERSION + 1
+        ));
+        fs::write(&path, b"SPQ").unwrap();
+        assert!(matches!(
+            load_circuit(&path),
+            Err(CircuitFileError::NotACircuit)
+        ));
+        bytes[4..8].copy_from_slice(&CIRCUIT_FORMAT_VERSION.to_le_bytes());
+        bytes.truncate(bytes.len() / 2);
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            load_circuit(&path),
+            Err(CircuitFileError::Malformed(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,752 @@
+use crate::{
+    circuit::Witness,
+    hash::{self, constants, is_full_round, Domain, WIDTH},
+    merkle::MerklePath,
+};
+use plonkish_backend::{
+    backend::{PlonkishCircuitInfo, WitnessEncoding},
+    halo2_curves::bn256::Fr,
+    util::{
+        arithmetic::{Field, PrimeField},
+        expression::{Expression, Query, Rotation},
+        Itertools,
+    },
+};
+use std::{array, collections::HashMap, iter};
+
+pub(crate) const NUM_WITNESS_POLYS: usize = 4;
+
+/// Number of bits of the low limb of [`CircuitBuilder::limbs`].
+pub(crate) const LO_LIMB_BITS: usize = 128;
+/// Number of bits of the high limb of [`CircuitBuilder::limbs`].
+pub(crate) const HI_LIMB_BITS: usize = Fr::NUM_BITS as usize - LO_LIMB_BITS;
+
+// Arithmetic gate `q_l·w_0 + q_r·w_1 + q_m·w_0·w_1 + q_2·w_2 + q_3·w_3 + q_c + q_pi·pi = 0`.
+pub(crate) const Q_L: usize = 0;
+pub(crate) const Q_R: usize = 1;
+pub(crate) const Q_M: usize = 2;
+pub(crate) const Q_2: usize = 3;
+pub(crate) const Q_3: usize = 4;
+pub(crate) const Q_C: usize = 5;
+pub(crate) const Q_PI: usize = 6;
+// Permutation rounds from the state in `w_0..w_3` to the state of next row.
+pub(crate) const Q_FULL: usize = 7;
+pub(crate) const Q_PARTIAL: usize = 8;
+pub(crate) const RC: usize = 9;
+// Ordering of the `(cur, sibling)` in `w_0, w_1` by bit `w_2` into the input
+// of a node hash on next row.
+pub(crate) const Q_SWAP: usize = RC + WIDTH;
+// Accumulation of bit `w_1` into `w_0` as `2·w_0 + w_1` on next row.
+pub(crate) const Q_RANGE: usize = Q_SWAP + 1;
+// Lookup of `w_0` into the values of `w_0` on the rows of the table.
+pub(crate) const Q_LOOKUP: usize = Q_RANGE + 1;
+pub(crate) const Q_TABLE: usize = Q_LOOKUP + 1;
+pub(crate) const NUM_FIXED_POLYS: usize = Q_TABLE + 1;
+
+pub(crate) const INSTANCE_POLY: usize = 0;
+
+pub(crate) fn fixed_poly(idx: usize) -> usize {
+    1 + idx
+}
+
+pub(crate) fn witness_poly(column: usize) -> usize {
+    1 + NUM_FIXED_POLYS + column
+}
+
+/// Witness cell at logical `row`, which is mapped to an evaluation domain
+/// index by [`WitnessEncoding::row_mapping`] only when building.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Cell {
+    pub(crate) row: usize,
+    pub(crate) column: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Row {
+    fixed: [Fr; NUM_FIXED_POLYS],
+    witness: [Fr; NUM_WITNESS_POLYS],
+}
+
+impl Default for Row {
+    fn default() -> Self {
+        Self {
+            fixed: [Fr::ZERO; NUM_FIXED_POLYS],
+            witness: [Fr::ZERO; NUM_WITNESS_POLYS],
+        }
+    }
+}
+
+/// Lays out rows of the fixed gates in [`constraints`], with the first rows
+/// exposing the instances.
+#[derive(Clone, Debug)]
+pub(crate) struct CircuitBuilder {
+    rows: Vec<Row>,
+    copies: Vec<(Cell, Cell)>,
+    instances: Vec<Fr>,
+    constants: HashMap<[u8; 32], Cell>,
+    has_table: bool,
+}
+
+impl CircuitBuilder {
+    pub(crate) fn new(instances: Vec<Fr>) -> Self {
+        let mut builder = Self {
+            rows: Vec::new(),
+            copies: Vec::new(),
+            instances: Vec::new(),
+            constants: HashMap::new(),
+            has_table: false,
+        };
+        for value in instances.iter() {
+            let row = builder.push_row();
+            builder.rows[row].witness[0] = *value;
+            builder.rows[row].fixed[Q_L] = Fr::ONE;
+            builder.rows[row].fixed[Q_PI] = -Fr::ONE;
+        }
+        builder.instances = instances;
+        builder
+    }
+
+    /// Returns the cell constrained to equal instance `idx`.
+    pub(crate) fn instance(&self, idx: usize) -> Cell {
+        assert!(idx < self.instances.len());
+        Cell {
+            row: idx,
+            column: 0,
+        }
+    }
+
+    pub(crate) fn value(&self, cell: Cell) -> Fr {
+        self.rows[cell.row].witness[cell.column]
+    }
+
+    pub(crate) fn copy(&mut self, lhs: Cell, rhs: Cell) {
+        debug_assert_eq!(self.value(lhs), self.value(rhs));
+        self.copies.push((lhs, rhs));
+    }
+
+    /// Returns a cell constrained to equal `value`, shared by every caller
+    /// asking for the same `value`.
+    pub(crate) fn constant(&mut self, value: Fr) -> Cell {
+        if let Some(cell) = self.constants.get(&value.to_repr()) {
+            return *cell;
+        }
+        let row = self.push_row();
+        self.rows[row].witness[0] = value;
+        self.rows[row].fixed[Q_L] = Fr::ONE;
+        self.rows[row].fixed[Q_C] = -value;
+        let cell = Cell { row, column: 0 };
+        self.constants.insert(value.to_repr(), cell);
+        cell
+    }
+
+    /// Returns a cell constrained to equal `scale·cell + constant`.
+    pub(crate) fn affine(&mut self, cell: Cell, scale: Fr, constant: Fr) -> Cell {
+        let row = self.push_row();
+        let value = self.value(cell);
+        self.rows[row].witness[0] = value;
+        self.rows[row].witness[2] = scale * value + constant;
+        self.rows[row].fixed[Q_L] = scale;
+        self.rows[row].fixed[Q_2] = -Fr::ONE;
+        self.rows[row].fixed[Q_C] = constant;
+        self.copy(cell, Cell { row, column: 0 });
+        Cell { row, column: 2 }
+    }
+
+    /// Returns a cell constrained to equal `lhs - rhs`.
+    pub(crate) fn sub(&mut self, lhs: Cell, rhs: Cell) -> Cell {
+        let row = self.push_row();
+        let [lhs_value, rhs_value] = [lhs, rhs].map(|cell| self.value(cell));
+        self.rows[row].witness[0] = lhs_value;
+        self.rows[row].witness[1] = rhs_value;
+        self.rows[row].witness[2] = lhs_value - rhs_value;
+        self.rows[row].fixed[Q_L] = Fr::ONE;
+        self.rows[row].fixed[Q_R] = -Fr::ONE;
+        self.rows[row].fixed[Q_2] = -Fr::ONE;
+        self.copy(lhs, Cell { row, column: 0 });
+        self.copy(rhs, Cell { row, column: 1 });
+        Cell { row, column: 2 }
+    }
+
+    /// Returns a cell constrained to equal `lhs + rhs`.
+    pub(crate) fn add(&mut self, lhs: Cell, rhs: Cell) -> Cell {
+        let row = self.push_row();
+        let [lhs_value, rhs_value] = [lhs, rhs].map(|cell| self.value(cell));
+        self.rows[row].witness[0] = lhs_value;
+        self.rows[row].witness[1] = rhs_value;
+        self.rows[row].witness[2] = lhs_value + rhs_value;
+        self.rows[row].fixed[Q_L] = Fr::ONE;
+        self.rows[row].fixed[Q_R] = Fr::ONE;
+        self.rows[row].fixed[Q_2] = -Fr::ONE;
+        self.copy(lhs, Cell { row, column: 0 });
+        self.copy(rhs, Cell { row, column: 1 });
+        Cell { row, column: 2 }
+    }
+
+    /// Returns a cell constrained to equal `lhs·rhs`.
+    pub(crate) fn mul(&mut self, lhs: Cell, rhs: Cell) -> Cell {
+        let row = self.push_row();
+        let [lhs_value, rhs_value] = [lhs, rhs].map(|cell| self.value(cell));
+        self.rows[row].witness[0] = lhs_value;
+        self.rows[row].witness[1] = rhs_value;
+        self.rows[row].witness[2] = lhs_value * rhs_value;
+        self.rows[row].fixed[Q_M] = Fr::ONE;
+        self.rows[row].fixed[Q_2] = -Fr::ONE;
+        self.copy(lhs, Cell { row, column: 0 });
+        self.copy(rhs, Cell { row, column: 1 });
+        Cell { row, column: 2 }
+    }
+
+    /// Returns a cell witnessing `value`, free until constrained by its uses.
+    pub(crate) fn witness(&mut self, value: Fr) -> Cell {
+        let row = self.push_row();
+        self.rows[row].witness[0] = value;
+        Cell { row, column: 0 }
+    }
+
+    /// Returns a cell witnessing `value`, constrained to be `0` or `1` by
+    /// `w_0·w_1 - w_0 = 0` with `w_1` copied from `w_0`.
+    pub(crate) fn boolean(&mut self, value: bool) -> Cell {
+        let row = self.push_row();
+        let value = Fr::from(value as u64);
+        self.rows[row].witness[0] = value;
+        self.rows[row].witness[1] = value;
+        self.rows[row].fixed[Q_L] = -Fr::ONE;
+        self.rows[row].fixed[Q_M] = Fr::ONE;
+        let cell = Cell { row, column: 0 };
+        self.copy(cell, Cell { row, column: 1 });
+        cell
+    }
+
+    /// Constrains `lhs` to equal `rhs` if `selector` is `1`, by constraining
+    /// `selector·(lhs - rhs)` to `0`.
+    pub(crate) fn assert_equal_if(&mut self, selector: Cell, lhs: Cell, rhs: Cell) {
+        let diff = self.sub(lhs, rhs);
+        let product = self.mul(selector, diff);
+        let zero = self.constant(Fr::ZERO);
+        self.copy(product, zero);
+    }
+
+    /// Constrains `cell` to be non-zero by witnessing its inverse.
+    pub(crate) fn assert_nonzero(&mut self, cell: Cell) {
+        let row = self.push_row();
+        let value = self.value(cell);
+        self.rows[row].witness[0] = value;
+        self.rows[row].witness[1] = value.invert().unwrap();
+        self.rows[row].fixed[Q_M] = Fr::ONE;
+        self.rows[row].fixed[Q_C] = -Fr::ONE;
+        self.copy(cell, Cell { row, column: 0 });
+    }
+
+    /// Constrains `cell` to be one of `values` by constraining the product of
+    /// its differences from them to `0`, accumulated one row per value as
+    /// `acc·cell - value·acc`. No cell is one of no values.
+    pub(crate) fn assert_member(&mut self, cell: Cell, values: &[Fr]) {
+        let mut acc = None;
+        for value in values {
+            acc = Some(match acc {
+                None => self.affine(cell, Fr::ONE, -*value),
+                Some(acc) => {
+                    let row = self.push_row();
+                    let [acc_value, cell_value] = [acc, cell].map(|cell| self.value(cell));
+                    self.rows[row].witness[0] = acc_value;
+                    self.rows[row].witness[1] = cell_value;
+                    self.rows[row].witness[2] = acc_value * (cell_value - value);
+                    self.rows[row].fixed[Q_L] = -*value;
+                    self.rows[row].fixed[Q_M] = Fr::ONE;
+                    self.rows[row].fixed[Q_2] = -Fr::ONE;
+                    self.copy(acc, Cell { row, column: 0 });
+                    self.copy(cell, Cell { row, column: 1 });
+                    Cell { row, column: 2 }
+                }
+            });
+        }
+        let product = acc.unwrap_or_else(|| self.constant(Fr::ONE));
+        let zero = self.constant(Fr::ZERO);
+        self.copy(product, zero);
+    }
+
+    /// Constrains `cell` to be in `[0, 2^num_bits)` by accumulating its bits
+    /// from the most significant one, starting from a row fixed to `0`.
+    pub(crate) fn range_check(&mut self, cell: Cell, num_bits: usize) {
+        let value = self.value(cell).to_repr();
+        let bits = (0..num_bits)
+            .rev()
+            .map(|idx| (value.as_ref()[idx / 8] >> (idx % 8)) & 1 == 1)
+            .collect_vec();
+        let mut acc = Fr::ZERO;
+        for (idx, bit) in bits.into_iter().enumerate() {
+            let row = self.push_row();
+            self.rows[row].witness[0] = acc;
+            self.rows[row].witness[1] = Fr::from(bit as u64);
+            self.rows[row].fixed[Q_RANGE] = Fr::ONE;
+            if idx == 0 {
+                self.rows[row].fixed[Q_L] = Fr::ONE;
+            }
+            acc = acc.double() + Fr::from(bit as u64);
+        }
+        let row = self.push_row();
+        self.rows[row].witness[0] = acc;
+        self.copy(cell, Cell { row, column: 0 });
+    }
+
+    /// Returns the limbs `[lo, hi]` of `cell` with `cell = hi·2^128 + lo`,
+    /// range checked to [`LO_LIMB_BITS`] and [`HI_LIMB_BITS`] bits. As the
+    /// limbs can reach past the modulus, they are only the unique ones of
+    /// `cell` once [`Self::assert_less_than`] the limbs of the modulus.
+    pub(crate) fn limbs(&mut self, cell: Cell) -> [Cell; 2] {
+        let [lo, hi] = limb_values(self.value(cell));
+        let row = self.push_row();
+        self.rows[row].witness[0] = hi;
+        self.rows[row].witness[1] = lo;
+        self.rows[row].witness[2] = self.value(cell);
+        self.rows[row].fixed[Q_L] = Fr::from_u128(1 << 127).double();
+        self.rows[row].fixed[Q_R] = Fr::ONE;
+        self.rows[row].fixed[Q_2] = -Fr::ONE;
+        self.copy(cell, Cell { row, column: 2 });
+        let [lo, hi] = [1, 0].map(|column| Cell { row, column });
+        self.range_check(lo, LO_LIMB_BITS);
+        self.range_check(hi, HI_LIMB_BITS);
+        [lo, hi]
+    }
+
+    /// Constrains the integer of limbs `lhs` to be less than the one of
+    /// `rhs`, both given by [`Self::limbs`] or constants within their bits, by
+    /// range checking the limbs of `rhs - lhs - 1` with a borrow from the high
+    /// limb. As every term is far below the modulus, the checks hold over the
+    /// integers.
+    pub(crate) fn assert_less_than(&mut self, lhs: [Cell; 2], rhs: [Cell; 2]) {
+        self.assert_ordered(lhs, rhs, true)
+    }
+
+    /// Constrains the integer of limbs `lhs` to be at most the one of `rhs`,
+    /// as [`Self::assert_less_than`] but range checking `rhs - lhs`.
+    pub(crate) fn assert_less_equal(&mut self, lhs: [Cell; 2], rhs: [Cell; 2]) {
+        self.assert_ordered(lhs, rhs, false)
+    }
+
+    fn assert_ordered(&mut self, lhs: [Cell; 2], rhs: [Cell; 2], strict: bool) {
+        let [[lhs_lo, lhs_hi], [rhs_lo, rhs_hi]] =
+            [lhs, rhs].map(|limbs| limbs.map(|cell| self.value(cell)));
+        let borrow = match strict {
+            true => self.boolean(limb_u128(rhs_lo) <= limb_u128(lhs_lo)),
+            false => self.boolean(limb_u128(rhs_lo) < limb_u128(lhs_lo)),
+        };
+        let borrow_value = self.value(borrow);
+        let shift = Fr::from_u128(1 << 127).double();
+        let offset = Fr::from(strict as u64);
+
+        // `rhs_lo - lhs_lo + 2^128·borrow - strict - diff_lo = 0`
+        let row = self.push_row();
+        self.rows[row].witness = [
+            rhs_lo,
+            lhs_lo,
+            borrow_value,
+            rhs_lo - lhs_lo + shift * borrow_value - offset,
+        ];
+        self.rows[row].fixed[Q_L] = Fr::ONE;
+        self.rows[row].fixed[Q_R] = -Fr::ONE;
+        self.rows[row].fixed[Q_2] = shift;
+        self.rows[row].fixed[Q_3] = -Fr::ONE;
+        self.rows[row].fixed[Q_C] = -offset;
+        self.copy(rhs[0], Cell { row, column: 0 });
+        self.copy(lhs[0], Cell { row, column: 1 });
+        self.copy(borrow, Cell { row, column: 2 });
+        self.range_check(Cell { row, column: 3 }, LO_LIMB_BITS);
+
+        // `rhs_hi - lhs_hi - borrow - diff_hi = 0`
+        let row = self.push_row();
+        self.rows[row].witness = [rhs_hi, lhs_hi, borrow_value, rhs_hi - lhs_hi - borrow_value];
+        self.rows[row].fixed[Q_L] = Fr::ONE;
+        self.rows[row].fixed[Q_R] = -Fr::ONE;
+        self.rows[row].fixed[Q_2] = -Fr::ONE;
+        self.rows[row].fixed[Q_3] = -Fr::ONE;
+        self.copy(rhs[1], Cell { row, column: 0 });
+        self.copy(lhs[1], Cell { row, column: 1 });
+        self.copy(borrow, Cell { row, column: 2 });
+        self.range_check(Cell { row, column: 3 }, HI_LIMB_BITS);
+    }
+
+    /// Hashes the values of `inputs` under `domain` in-circuit, matching
+    /// [`hash::hash3`].
+    pub(crate) fn hash3(&mut self, inputs: [Cell; 3], domain: Domain) -> Cell {
+        let (cells, output) = self.hash3_values(inputs.map(|cell| self.value(cell)), domain);
+        for (input, cell) in inputs.into_iter().zip(cells) {
+            self.copy(input, cell);
+        }
+        output
+    }
+
+    /// Hashes `values` under `domain` in-circuit, returning the cells assigned
+    /// with `values` and the output.
+    pub(crate) fn hash3_values(&mut self, values: [Fr; 3], domain: Domain) -> ([Cell; 3], Cell) {
+        let [a, b, c] = values;
+        let (input_row, output) = self.permute([a, b, c, domain.tag()]);
+        self.rows[input_row].fixed[Q_3] = Fr::ONE;
+        self.rows[input_row].fixed[Q_C] = -domain.tag();
+        debug_assert_eq!(self.value(output), hash::hash3(values, domain));
+        let inputs = array::from_fn(|column| Cell {
+            row: input_row,
+            column,
+        });
+        (inputs, output)
+    }
+
+    /// Hashes `left` and `right` into a Merkle tree node in-circuit, matching
+    /// [`hash::hash_node`].
+    pub(crate) fn hash_node(&mut self, left: Cell, right: Cell) -> Cell {
+        let row = self.swap_row(left);
+        let right_value = self.value(right);
+        self.rows[row].witness[1] = right_value;
+        self.copy(right, Cell { row, column: 1 });
+        // Fix the swap bit to 0 to keep the order.
+        self.rows[row].witness[2] = Fr::ZERO;
+        self.rows[row].fixed[Q_2] = Fr::ONE;
+        self.node_after_swap(row)
+    }
+
+    /// Hashes `leaf` along `path` in-circuit, matching [`MerklePath::root`].
+    /// The siblings and bits are free witnesses, so the returned root only
+    /// attests that `leaf` is some leaf of the tree.
+    pub(crate) fn merkle_root(&mut self, leaf: Cell, path: &MerklePath) -> Cell {
+        self.merkle_root_with_bits(leaf, path).0
+    }
+
+    /// Hashes `leaf` along `path` as [`Self::merkle_root`], also returning a
+    /// cell constrained to equal the index of `leaf` by the bits of the path,
+    /// so the position of the leaf is attested as well.
+    pub(crate) fn merkle_root_with_index(&mut self, leaf: Cell, path: &MerklePath) -> (Cell, Cell) {
+        let (root, bits) = self.merkle_root_with_bits(leaf, path);
+        let mut idx = None;
+        for (level, bit) in bits.into_iter().enumerate() {
+            let term = match level {
+                0 => bit,
+                _ => self.affine(bit, Fr::from(1u64 << level), Fr::ZERO),
+            };
+            idx = Some(match idx {
+                None => term,
+                Some(idx) => self.add(idx, term),
+            });
+        }
+        let idx = idx.unwrap_or_else(|| self.constant(Fr::ZERO));
+        (root, idx)
+    }
+
+    /// Hashes `leaves` into the root of their Merkle tree in-circuit, matching
+    /// [`MerkleTripleSet::root`] of `2^depth` leaves.
+    ///
+    /// [`MerkleTripleSet::root`]: crate::merkle::MerkleTripleSet::root
+    pub(crate) fn merkle_tree_root(&mut self, leaves: &[Cell]) -> Cell {
+        debug_assert!(leaves.len().is_power_of_two());
+        let mut layer = leaves.to_vec();
+        while layer.len() > 1 {
+            layer = layer
+                .into_iter()
+                .tuples()
+                .map(|(left, right)| self.hash_node(left, right))
+                .collect();
+        }
+        layer[0]
+    }
+
+    /// Lays out `values` as the table of [`Self::lookup`], one row each,
+    /// returning their cells.
+    pub(crate) fn table(&mut self, values: &[Fr]) -> Vec<Cell> {
+        self.has_table = true;
+        values
+            .iter()
+            .map(|value| {
+                let row = self.push_row();
+                self.rows[row].witness[0] = *value;
+                self.rows[row].fixed[Q_TABLE] = Fr::ONE;
+                Cell { row, column: 0 }
+            })
+            .collect()
+    }
+
+    /// Constrains `cell` to be one of the values of [`Self::table`]. As the
+    /// table takes `0` on every other row, `0` is always found.
+    pub(crate) fn lookup(&mut self, cell: Cell) {
+        let row = self.push_row();
+        self.rows[row].witness[0] = self.value(cell);
+        self.rows[row].fixed[Q_LOOKUP] = Fr::ONE;
+        self.copy(cell, Cell { row, column: 0 });
+    }
+
+    /// Returns the root of `leaf` along `path` with the cell of the bit of
+    /// each level.
+    fn merkle_root_with_bits(&mut self, leaf: Cell, path: &MerklePath) -> (Cell, Vec<Cell>) {
+        let mut bits = Vec::with_capacity(path.siblings.len());
+        let root = path
+            .siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |cur, (level, sibling)| {
+                let row = self.swap_row(cur);
+                self.rows[row].witness[1] = *sibling;
+                self.rows[row].witness[2] = Fr::from(path.is_right(level) as u64);
+                bits.push(Cell { row, column: 2 });
+                self.node_after_swap(row)
+            });
+        (root, bits)
+    }
+
+    /// Returns a row with `cur` in `w_0` to be the swap row of a node hash.
+    fn swap_row(&mut self, cur: Cell) -> usize {
+        let row = self.push_row();
+        self.rows[row].witness[0] = self.value(cur);
+        self.copy(cur, Cell { row, column: 0 });
+        row
+    }
+
+    fn node_after_swap(&mut self, row: usize) -> Cell {
+        self.rows[row].fixed[Q_SWAP] = Fr::ONE;
+        let [cur, sibling, bit, _] = self.rows[row].witness;
+        let (left, right) = match bool::from(bit.is_zero()) {
+            true => (cur, sibling),
+            false => (sibling, cur),
+        };
+        let (input_row, output) = self.permute([left, right, Fr::ZERO, Domain::Node.tag()]);
+        debug_assert_eq!(input_row, row + 1);
+        debug_assert_eq!(self.value(output), hash::hash_node(left, right));
+        output
+    }
+
+    /// Lays out the rounds of the permutation on `state`, returning the input
+    /// row and the first element of the output.
+    fn permute(&mut self, mut state: [Fr; WIDTH]) -> (usize, Cell) {
+        let round_constants = &constants().round_constants;
+        let input_row = self.rows.len();
+        for (round, round_constants) in round_constants.iter().enumerate() {
+            let row = self.push_row();
+            self.rows[row].witness = state;
+            self.rows[row].fixed[RC..RC + WIDTH].copy_from_slice(round_constants);
+            let selector = if is_full_round(round) {
+                Q_FULL
+            } else {
+                Q_PARTIAL
+            };
+            self.rows[row].fixed[selector] = Fr::ONE;
+            state = hash::round(state, round);
+        }
+        let row = self.push_row();
+        self.rows[row].witness = state;
+        (input_row, Cell { row, column: 0 })
+    }
+
+    pub(crate) fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    fn push_row(&mut self) -> usize {
+        self.rows.push(Row::default());
+        self.rows.len() - 1
+    }
+
+    /// Returns the smallest `k` whose usable rows under `E` fit the rows.
+    pub(crate) fn min_k<E: WitnessEncoding>(&self) -> usize {
+        let num_rows = self.rows.len();
+        let mut k = num_rows.next_power_of_two().trailing_zeros() as usize;
+        while E::row_mapping(k).len() < num_rows {
+            k += 1;
+        }
+        k
+    }
+
+    /// Lays the rows out over `2^k` rows for the row mapping of `E`, the rows
+    /// past them left all zero, which satisfies every constraint, so a circuit
+    /// padded to the same `k` has the same [`PlonkishCircuitInfo`] however
+    /// many rows are used. `k` has to be at least [`Self::min_k`].
+    pub(crate) fn build<E: WitnessEncoding>(self, k: usize) -> (PlonkishCircuitInfo<Fr>, Witness) {
+        debug_assert!(k >= self.min_k::<E>());
+        let row_mapping = E::row_mapping(k);
+        let n = 1 << k;
+
+        let mut fixed_polys = vec![vec![Fr::ZERO; n]; NUM_FIXED_POLYS];
+        let mut witness_polys = vec![vec![Fr::ZERO; n]; NUM_WITNESS_POLYS];
+        for (row, values) in self.rows.iter().enumerate() {
+            for (poly, value) in fixed_polys.iter_mut().zip(values.fixed) {
+                poly[row_mapping[row]] = value;
+            }
+            for (poly, value) in witness_polys.iter_mut().zip(values.witness) {
+                poly[row_mapping[row]] = value;
+            }
+        }
+
+        let permutations = permutation_cycles(&self.copies, |cell| {
+            (witness_poly(cell.column), row_mapping[cell.row])
+        });
+        let circuit_info = PlonkishCircuitInfo {
+            k,
+            num_instances: vec![self.instances.len()],
+            preprocess_polys: fixed_polys,
+            num_witness_polys: vec![NUM_WITNESS_POLYS],
+            num_challenges: vec![0],
+            constraints: constraints(),
+            lookups: match self.has_table {
+                true => vec![lookup()],
+                false => Vec::new(),
+            },
+            permutations,
+            max_degree: None,
+        };
+        let witness = Witness {
+            instances: vec![self.instances],
+            witness_polys,
+        };
+        (circuit_info, witness)
+    }
+}
+
+/// Returns the values of the limbs `[lo, hi]` of the canonical
+/// representation of `value`, see [`CircuitBuilder::limbs`].
+pub(crate) fn limb_values(value: Fr) -> [Fr; 2] {
+    let repr = value.to_repr();
+    let [lo, hi] = [0, 16].map(|offset| {
+        let bytes = repr.as_ref()[offset..offset + 16].try_into().unwrap();
+        Fr::from_u128(u128::from_le_bytes(bytes))
+    });
+    [lo, hi]
+}
+
+fn limb_u128(limb: Fr) -> u128 {
+    u128::from_le_bytes(limb.to_repr().as_ref()[..16].try_into().unwrap())
+}
+
+/// Returns the closed cycles of cells connected by `copies`, with a singleton
+/// cycle for every witness poly without copies so the set of permutation polys
+/// doesn't depend on the query.
+fn permutation_cycles(
+    copies: &[(Cell, Cell)],
+    poly_row: impl Fn(Cell) -> (usize, usize),
+) -> Vec<Vec<(usize, usize)>> {
+    let mut idx = HashMap::<Cell, usize>::new();
+    let mut parents = Vec::new();
+    let mut cells = Vec::new();
+    let find = |parents: &mut Vec<usize>, mut node: usize| {
+        while parents[node] != node {
+            parents[node] = parents[parents[node]];
+            node = parents[node];
+        }
+        node
+    };
+    for (lhs, rhs) in copies.iter() {
+        let [lhs, rhs] = [lhs, rhs].map(|cell| {
+            *idx.entry(*cell).or_insert_with(|| {
+                parents.push(parents.len());
+                cells.push(*cell);
+                cells.len() - 1
+            })
+        });
+        let [lhs, rhs] = [lhs, rhs].map(|node| find(&mut parents, node));
+        parents[lhs] = rhs;
+    }
+
+    let mut cycles = HashMap::<usize, Vec<(usize, usize)>>::new();
+    for (node, cell) in cells.iter().enumerate() {
+        let root = find(&mut parents, node);
+        cycles.entry(root).or_default().push(poly_row(*cell));
+    }
+    let mut cycles = cycles
+        .into_values()
+        .filter(|cycle| cycle.len() > 1)
+        .map(|cycle| cycle.into_iter().sorted().collect_vec())
+        .sorted()
+        .collect_vec();
+    for column in 0..NUM_WITNESS_POLYS {
+        let poly = witness_poly(column);
+        if !cycles.iter().flatten().any(|(p, _)| *p == poly) {
+            cycles.push(vec![poly_row(Cell { row: 0, column })]);
+        }
+    }
+    cycles
+}
+
+/// Returns a description of constraint `idx` of [`constraints`].
+pub(crate) fn constraint_name(idx: usize) -> String {
+    const SWAP: [&str; 5] = [
+        "boolean bit",
+        "swapped left input",
+        "swapped right input",
+        "zero third input",
+        "node domain tag",
+    ];
+    const RANGE: [&str; 2] = ["boolean bit", "bit accumulation"];
+    match idx {
+        0 => "arithmetic gate".to_string(),
+        idx if idx < 1 + WIDTH => format!("full round of state element {}", idx - 1),
+        idx if idx < 1 + 2 * WIDTH => format!("partial round of state element {}", idx - 1 - WIDTH),
+        idx if idx < 1 + 2 * WIDTH + SWAP.len() => {
+            format!("Merkle swap {}", SWAP[idx - 1 - 2 * WIDTH])
+        }
+        idx => format!("range check {}", RANGE[idx - 1 - 2 * WIDTH - SWAP.len()]),
+    }
+}
+
+/// Returns the lookup of [`CircuitBuilder::lookup`] into
+/// [`CircuitBuilder::table`].
+fn lookup() -> Vec<(Expression<Fr>, Expression<Fr>)> {
+    let fixed = |idx| Expression::<Fr>::Polynomial(Query::new(fixed_poly(idx), Rotation::cur()));
+    let w_0 = Expression::<Fr>::Polynomial(Query::new(witness_poly(0), Rotation::cur()));
+    vec![(fixed(Q_LOOKUP) * &w_0, fixed(Q_TABLE) * w_0)]
+}
+
+fn constraints() -> Vec<Expression<Fr>> {
+    let pi = Expression::Polynomial(Query::new(INSTANCE_POLY, Rotation::cur()));
+    let fixed = |idx| Expression::<Fr>::Polynomial(Query::new(fixed_poly(idx), Rotation::cur()));
+    let w: [_; NUM_WITNESS_POLYS] = array::from_fn(|column| {
+        Expression::Polynomial(Query::new(witness_poly(column), Rotation::cur()))
+    });
+    let w_next: [_; NUM_WITNESS_POLYS] = array::from_fn(|column| {
+        Expression::Polynomial(Query::new(witness_poly(column), Rotation::next()))
+    });
+    let mds = constants().mds;
+
+    let arithmetic = fixed(Q_L) * &w[0]
+        + fixed(Q_R) * &w[1]
+        + fixed(Q_M) * &w[0] * &w[1]
+        + fixed(Q_2) * &w[2]
+        + fixed(Q_3) * &w[3]
+        + fixed(Q_C)
+        + fixed(Q_PI) * pi;
+
+    let added: [_; WIDTH] = array::from_fn(|idx| &w[idx] + fixed(RC + idx));
+    let pow5 = |value: &Expression<Fr>| iter::repeat(value).take(5).product::<Expression<_>>();
+    let full_sboxed = added.iter().map(pow5).collect_vec();
+    let partial_sboxed = iter::once(pow5(&added[0]))
+        .chain(added[1..].iter().cloned())
+        .collect_vec();
+    let mix = |sboxed: &[Expression<Fr>], row: usize| {
+        sboxed
+            .iter()
+            .zip(mds[row])
+            .map(|(value, m)| value * m)
+            .sum::<Expression<_>>()
+    };
+    let full_rounds = (0..WIDTH).map(|idx| fixed(Q_FULL) * (&w_next[idx] - mix(&full_sboxed, idx)));
+    let partial_rounds =
+        (0..WIDTH).map(|idx| fixed(Q_PARTIAL) * (&w_next[idx] - mix(&partial_sboxed, idx)));
+
+    let [cur, sibling, bit, _] = &w;
+    let one = Expression::Constant(Fr::ONE);
+    let q_swap = fixed(Q_SWAP);
+    let swap = [
+        bit * (bit - &one),
+        &w_next[0] - cur - bit * (sibling - cur),
+        &w_next[1] - sibling - bit * (cur - sibling),
+        w_next[2].clone(),
+        &w_next[3] - Expression::Constant(Domain::Node.tag()),
+    ]
+    .map(|constraint| &q_swap * constraint);
+
+    let [acc, bit, ..] = &w;
+    let q_range = fixed(Q_RANGE);
+    let range = [bit * (bit - &one), &w_next[0] - acc * Fr::from(2) - bit]
+        .map(|constraint| &q_range * constraint);
+
+    iter::once(arithmetic)
+        .chain(full_rounds)
+        .chain(partial_rounds)
+        .chain(swap)
+        .chain(range)
+        .collect()
+}
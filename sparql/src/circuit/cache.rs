@@ -0,0 +1,240 @@
+use crate::{
+    circuit::{compile_bgp, query_commitment, CompileError, InstanceLayout, QueryCircuit},
+    query::SelectQuery,
+    rdf::RdfGraph,
+};
+use plonkish_backend::{
+    backend::{PlonkishCircuit, WitnessEncoding},
+    halo2_curves::bn256::Fr,
+    util::arithmetic::PrimeField,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    num::NonZeroUsize,
+};
+
+/// Key of a circuit in a [`CircuitCache`], the [`query_commitment`] of the
+/// query and the [`MerkleTripleSet::root`] of the dataset, which identify the
+/// instances a circuit proves, so a query parsed again or a dataset loaded
+/// again maps to the same key.
+///
+/// [`MerkleTripleSet::root`]: crate::merkle::MerkleTripleSet::root
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CircuitKey {
+    pub query_commitment: [u8; 32],
+    pub dataset_root: [u8; 32],
+}
+
+impl CircuitKey {
+    /// Returns the key of `query` over the dataset of `dataset_root`, its
+    /// [`MerkleTripleSet::root`].
+    ///
+    /// [`MerkleTripleSet::root`]: crate::merkle::MerkleTripleSet::root
+    pub fn new(query: &SelectQuery, dataset_root: Fr) -> Self {
+        Self {
+            query_commitment: query_commitment(query).to_repr(),
+            dataset_root: dataset_root.to_repr(),
+        }
+    }
+}
+
+/// In-memory cache of the circuits of [`compile_bgp`] with witness encoding
+/// `E`, evicting the least recently used one past its capacity, e.g. for a
+/// server compiling the same query over the same dataset for repeated
+/// requests. Each lookup counts as a hit or a miss, see [`Self::hits`] and
+/// [`Self::misses`].
+#[derive(Debug)]
+pub struct CircuitCache<E> {
+    capacity: NonZeroUsize,
+    /// Circuits with the tick of their last use.
+    circuits: HashMap<CircuitKey, (u64, QueryCircuit)>,
+    /// Keys of `circuits` by the tick of their last use, from the least to
+    /// the most recently used.
+    recency: BTreeMap<u64, CircuitKey>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    _marker: PhantomData<fn() -> E>,
+}
+
+impl<E: WitnessEncoding> CircuitCache<E> {
+    /// Returns an empty cache of at most `capacity` circuits.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            capacity,
+            circuits: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the circuit of [`compile_bgp`] of `query` over `dataset`,
+    /// compiling and caching it on a miss. A failed compile isn't cached.
+    ///
+    /// `dataset_root` is the [`MerkleTripleSet::root`] of `dataset`, which
+    /// keys the lookup, so the tree is built once per dataset by the caller
+    /// rather than on every lookup. A compiled circuit is cached under the
+    /// [`InstanceLayout::DATASET_ROOT`] it proves, which is debug asserted to
+    /// be `dataset_root`, so a wrong root never caches a circuit for it.
+    ///
+    /// [`MerkleTripleSet::root`]: crate::merkle::MerkleTripleSet::root
+    pub fn get_or_compile(
+        &mut self,
+        query: &SelectQuery,
+        dataset: &RdfGraph,
+        dataset_root: Fr,
+    ) -> Result<&QueryCircuit, CompileError> {
+        let key = CircuitKey::new(query, dataset_root);
+        self.tick += 1;
+        if self.circuits.contains_key(&key) {
+            self.hits += 1;
+            let (last_used, circuit) = self.circuits.get_mut(&key).unwrap();
+            self.recency.remove(last_used);
+            self.recency.insert(self.tick, key);
+            *last_used = self.tick;
+            return Ok(circuit);
+        }
+        self.misses += 1;
+        let circuit = compile_bgp::<E>(query, dataset)?;
+        let root = circuit.instances()[InstanceLayout::COLUMN][InstanceLayout::DATASET_ROOT];
+        debug_assert_eq!(root, dataset_root, "dataset_root to be the root of dataset");
+        let key = CircuitKey::new(query, root);
+        if let Some((last_used, _)) = self.circuits.remove(&key) {
+            self.recency.remove(&last_used);
+        }
+        if self.circuits.len() == self.capacity.get() {
+            let (_, evicted) = self.recency.pop_first().unwrap();
+            self.circuits.remove(&evicted);
+        }
+        self.recency.insert(self.tick, key);
+        Ok(&self.circuits.entry(key).or_insert((self.tick, circuit)).1)
+    }
+
+    /// Returns the cached circuit of `key` if any, without counting a hit or
+    /// miss or updating its recency.
+    pub fn peek(&self, key: &CircuitKey) -> Option<&QueryCircuit> {
+        self.circuits.get(key).map(|(_, circuit)| circuit)
+    }
+
+    pub fn capacity(&self) -> NonZeroUsize {
+        self.capacity
+    }
+
+    /// Returns the number of cached circuits.
+    pub fn len(&self) -> usize {
+        self.circuits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.circuits.is_empty()
+    }
+
+    /// Returns the number of lookups served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of lookups that compiled the circuit.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Drops every cached circuit, keeping the counters.
+    pub fn clear(&mut self) {
+        self.circuits.clear();
+        self.recency.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{test::Pb, CircuitCache, CircuitKey},
+        merkle::MerkleTripleSet,
+        parse_select,
+        rdf::{RdfGraph, Term, Triple},
+    };
+    use plonkish_backend::backend::PlonkishCircuit;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn hit_and_eviction() {
+        let graph = crate::circuit::test::graph();
+        let query = parse_select("SELECT ?a WHERE { ?a <knows> ?b }").unwrap();
+        let other = parse_select("SELECT ?b WHERE { ?a <knows> ?b }").unwrap();
+        let root = MerkleTripleSet::from_graph(&graph).root();
+        let mut cache = CircuitCache::<Pb>::new(NonZeroUsize::new(1).unwrap());
+
+        let circuit_info = |cache: &mut CircuitCache<Pb>, query| {
+            let circuit = cache.get_or_compile(query, &graph, root).unwrap();
+            bincode::serialize(&circuit.circuit_info().unwrap()).unwrap()
+        };
+        let first = circuit_info(&mut cache, &query);
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        // Parsed again, the query has the same commitment.
+        let reparsed = parse_select("SELECT ?a WHERE { ?a <knows> ?b }").unwrap();
+        assert_eq!(circuit_info(&mut cache, &reparsed), first);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+        assert!(cache.peek(&CircuitKey::new(&query, root)).is_some());
+
+        // A capacity of 1 evicts the first for the other.
+        circuit_info(&mut cache, &other);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert_eq!(cache.len(), 1);
+        circuit_info(&mut cache, &query);
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+
+        // Another dataset is another key.
+        let mut triples = (0..graph.triples().len())
+            .map(|idx| graph.triple(idx))
+            .collect::<Vec<_>>();
+        triples.push(Triple::new(
+            Term::iri("dave"),
+            Term::iri("knows"),
+            Term::iri("alice"),
+        ));
+        let larger = RdfGraph::from_triples(triples);
+        let larger_root = MerkleTripleSet::from_graph(&larger).root();
+        assert_ne!(
+            CircuitKey::new(&query, root),
+            CircuitKey::new(&query, larger_root)
+        );
+        cache.get_or_compile(&query, &larger, larger_root).unwrap();
+        assert_eq!((cache.hits(), cache.misses()), (1, 4));
+    }
+
+    #[test]
+    fn least_recently_used() {
+        let graph = crate::circuit::test::graph();
+        let root = MerkleTripleSet::from_graph(&graph).root();
+        let queries = ["?a", "?b", "?a ?b"]
+            .map(|vars| parse_select(&format!("SELECT {vars} WHERE {{ ?a <knows> ?b }}")).unwrap());
+        let mut cache = CircuitCache::<Pb>::new(NonZeroUsize::new(2).unwrap());
+        for idx in [0, 1, 0, 2] {
+            cache.get_or_compile(&queries[idx], &graph, root).unwrap();
+        }
+        assert_eq!((cache.hits(), cache.misses()), (1, 3));
+
+        // The second was used least recently, so the third evicted it.
+        let cached = queries
+            .iter()
+            .map(|query| cache.peek(&CircuitKey::new(query, root)).is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(cached, [true, false, true]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "dataset_root to be the root of dataset")]
+    fn root_of_another_dataset() {
+        let graph = crate::circuit::test::graph();
+        let query = parse_select("SELECT ?a WHERE { ?a <knows> ?b }").unwrap();
+        let mut cache = CircuitCache::<Pb>::new(NonZeroUsize::new(1).unwrap());
+        let other = MerkleTripleSet::new([]).root();
+        let _ = cache.get_or_compile(&query, &graph, other);
+    }
+}
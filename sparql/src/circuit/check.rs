@@ -0,0 +1,243 @@
+use crate::circuit::{
+    builder::{constraint_name, NUM_FIXED_POLYS},
+    QueryCircuitShape, Witness,
+};
+use plonkish_backend::{
+    backend::WitnessEncoding,
+    halo2_curves::bn256::Fr,
+    util::{
+        arithmetic::{Field, PrimeField},
+        expression::{Expression, Query},
+    },
+};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+};
+
+/// Constraint of a [`QueryCircuitShape`] a [`Witness`] violates, found by
+/// [`check_satisfied`]. Rows are the logical rows laid out by the compiler,
+/// before the row mapping of the backend.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintViolation {
+    /// The witness has `found` instances where the shape has `expected`.
+    NumInstances { expected: usize, found: usize },
+    /// Constraint `constraint` of [`PlonkishCircuitInfo::constraints`] doesn't
+    /// vanish on `row`, evaluating to `value` instead.
+    ///
+    /// [`PlonkishCircuitInfo::constraints`]: plonkish_backend::backend::PlonkishCircuitInfo::constraints
+    Constraint {
+        constraint: usize,
+        description: String,
+        row: usize,
+        value: Fr,
+    },
+    /// Cells `(column, row)` of a copy constraint of
+    /// [`PlonkishCircuitInfo::permutations`] differ.
+    ///
+    /// [`PlonkishCircuitInfo::permutations`]: plonkish_backend::backend::PlonkishCircuitInfo::permutations
+    Copy {
+        lhs: (usize, usize),
+        rhs: (usize, usize),
+    },
+    /// Input of lookup `lookup` of [`PlonkishCircuitInfo::lookups`] on `row`
+    /// isn't in its table.
+    ///
+    /// [`PlonkishCircuitInfo::lookups`]: plonkish_backend::backend::PlonkishCircuitInfo::lookups
+    Lookup { lookup: usize, row: usize },
+}
+
+impl Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstraintViolation::NumInstances { expected, found } => {
+                write!(f, "expected {expected} instances, found {found}")
+            }
+            ConstraintViolation::Constraint {
+                constraint,
+                description,
+                row,
+                value,
+            } => write!(
+                f,
+                "constraint {constraint} ({description}) is {value:?} instead of 0 on row {row}"
+            ),
+            ConstraintViolation::Copy { lhs, rhs } => write!(
+                f,
+                "witness {} on row {} differs from witness {} on row {} it's copied to",
+                lhs.0, lhs.1, rhs.0, rhs.1
+            ),
+            ConstraintViolation::Lookup { lookup, row } => {
+                write!(
+                    f,
+                    "input of lookup {lookup} on row {row} is not in its table"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConstraintViolation {}
+
+/// Checks `witness` against the constraints and copy constraints of the
+/// [`QueryCircuitShape::circuit_info`] the prover uses, laid out for the row
+/// mapping of `E`, without committing to anything. Returns the first
+/// violation in order of rows then constraints, followed by the first
+/// violated copy constraint and then the first input missing from its lookup
+/// table, so an `Ok` means the prover succeeds.
+///
+/// This is much faster than a prove for finding out why a circuit isn't
+/// satisfied, e.g. by a tampered witness or instance.
+pub fn check_satisfied<E: WitnessEncoding>(
+    shape: &QueryCircuitShape,
+    witness: &Witness,
+) -> Result<(), ConstraintViolation> {
+    let circuit_info = shape.circuit_info();
+    let expected = shape.instance_layout().num_instances();
+    let found = witness.instances[0].len();
+    if found != expected {
+        return Err(ConstraintViolation::NumInstances { expected, found });
+    }
+
+    let row_mapping = E::row_mapping(circuit_info.k);
+    let n = 1 << circuit_info.k;
+    let mut instance_poly = vec![Fr::ZERO; n];
+    for (idx, instance) in row_mapping.iter().zip(witness.instances[0].iter()) {
+        instance_poly[*idx] = *instance;
+    }
+    // Polys in order of their index, see `PlonkishCircuitInfo`.
+    let polys = [&instance_poly]
+        .into_iter()
+        .chain(circuit_info.preprocess_polys.iter())
+        .chain(witness.witness_polys.iter())
+        .collect::<Vec<_>>();
+    let witness_offset = 1 + NUM_FIXED_POLYS;
+
+    let num_rows = row_mapping.len();
+    let evaluate = |expression: &Expression<Fr>, row: usize| {
+        let value_of = |query: Query| {
+            let rotated = (row as i64 + query.rotation().0 as i64).rem_euclid(num_rows as i64);
+            polys[query.poly()][row_mapping[rotated as usize]]
+        };
+        expression.evaluate(
+            &|constant| constant,
+            &|_| unreachable!("query circuit has no common polynomials"),
+            &value_of,
+            &|_| unreachable!("query circuit has no challenges"),
+            &|value| -value,
+            &|lhs, rhs| lhs + rhs,
+            &|lhs, rhs| lhs * rhs,
+            &|value, scalar| value * scalar,
+        )
+    };
+    for row in 0..num_rows {
+        for (idx, constraint) in circuit_info.constraints.iter().enumerate() {
+            let value = evaluate(constraint, row);
+            if value != Fr::ZERO {
+                return Err(ConstraintViolation::Constraint {
+                    constraint: idx,
+                    description: constraint_name(idx),
+                    row,
+                    value,
+                });
+            }
+        }
+    }
+
+    let mut logical_rows = vec![0; n];
+    for (row, idx) in row_mapping.iter().enumerate() {
+        logical_rows[*idx] = row;
+    }
+    let cell = |(poly, idx): (usize, usize)| (poly - witness_offset, logical_rows[idx]);
+    for cycle in circuit_info.permutations.iter() {
+        let (first, rest) = cycle.split_first().unwrap();
+        let value = polys[first.0][first.1];
+        if let Some(other) = rest.iter().find(|(poly, idx)| polys[*poly][*idx] != value) {
+            return Err(ConstraintViolation::Copy {
+                lhs: cell(*first),
+                rhs: cell(*other),
+            });
+        }
+    }
+
+    // Rows off the row mapping are zero on both sides, so they don't matter.
+    for (idx, lookup) in circuit_info.lookups.iter().enumerate() {
+        let (inputs, tables): (Vec<_>, Vec<_>) = lookup.iter().cloned().unzip();
+        let values = |expressions: &[Expression<Fr>], row| {
+            expressions
+                .iter()
+                .map(|expression| evaluate(expression, row).to_repr())
+                .collect::<Vec<_>>()
+        };
+        let table = (0..num_rows)
+            .map(|row| values(&tables, row))
+            .collect::<HashSet<_>>();
+        if let Some(row) = (0..num_rows).find(|row| !table.contains(&values(&inputs, *row))) {
+            return Err(ConstraintViolation::Lookup { lookup: idx, row });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{check_satisfied, compile_bgp, test::graph, test::Pb, ConstraintViolation},
+        parse_select,
+        rdf::Term,
+    };
+    use plonkish_backend::{
+        backend::{unihyperplonk::UniHyperPlonk, WitnessEncoding},
+        halo2_curves::bn256::Bn256,
+        pcs::univariate::UnivariateKzg,
+    };
+
+    #[test]
+    fn check_satisfied_witness() {
+        let graph = graph();
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let shape = circuit.shape();
+        assert_eq!(check_satisfied::<Pb>(shape, circuit.witness()), Ok(()));
+        type Uni = UniHyperPlonk<UnivariateKzg<Bn256>, true>;
+        let uni = compile_bgp::<Uni>(&query, &graph).unwrap();
+        assert_eq!(check_satisfied::<Uni>(uni.shape(), uni.witness()), Ok(()));
+
+        // Result other than the binding, differing from its instance row.
+        let fake = Term::iri("carol").encode();
+        let mut tampered = circuit.witness().clone();
+        tampered.instances[0][2] = fake;
+        let err = check_satisfied::<Pb>(shape, &tampered).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                ConstraintViolation::Constraint {
+                    constraint: 0,
+                    row: 2,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert!(err.to_string().contains("arithmetic gate"), "{err}");
+
+        // Instance row agreeing, but not the binding it's copied to.
+        let idx = Pb::row_mapping(shape.circuit_info().k)[2];
+        tampered.witness_polys[0][idx] = fake;
+        let err = check_satisfied::<Pb>(shape, &tampered).unwrap_err();
+        assert!(
+            matches!(err, ConstraintViolation::Copy { lhs, rhs } if lhs == (0, 2) || rhs == (0, 2)),
+            "{err}"
+        );
+
+        let mut truncated = circuit.witness().clone();
+        truncated.instances[0].pop();
+        assert_eq!(
+            check_satisfied::<Pb>(shape, &truncated),
+            Err(ConstraintViolation::NumInstances {
+                expected: 6,
+                found: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use crate::{
+    circuit::{
+        builder::{HI_LIMB_BITS, LO_LIMB_BITS, NUM_WITNESS_POLYS},
+        scanned_pattern, InstanceLayout, MatchStrategy, MAX_COUNT, NUM_FILTER_BITS,
+    },
+    hash::NUM_ROUNDS,
+    query::{CompareOp, FilterCondition, Pattern, SelectQuery},
+};
+
+/// Rows of a permutation, one per round and one for the output.
+const HASH_ROWS: usize = NUM_ROUNDS + 1;
+/// Rows of a [`MerkleTripleSet`] node, the swap row and its hash.
+///
+/// [`MerkleTripleSet`]: crate::merkle::MerkleTripleSet
+const NODE_ROWS: usize = 1 + HASH_ROWS;
+/// Rows of a leaf opened by the scan of an empty result, its triple and quad
+/// hashes, its selection and the comparison of at most four terms.
+const SCAN_LEAF_ROWS: usize = 2 * HASH_ROWS + 34;
+
+/// Upper bound on the size of the circuit [`compile_bgp`] lays out for a
+/// query, see [`estimate_circuit_size`].
+///
+/// [`compile_bgp`]: crate::circuit::compile_bgp
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitSizeEstimate {
+    /// Number of advice columns, which is fixed.
+    pub num_witness_polys: usize,
+    /// Number of solutions the estimate accounts for.
+    pub num_solutions: usize,
+    /// Number of rows, saturating at `usize::MAX`.
+    pub num_rows: usize,
+}
+
+impl CircuitSizeEstimate {
+    /// Returns the smallest `k` whose `2^k - 1` rows fit [`Self::num_rows`],
+    /// which fits the usable rows of both [`HyperPlonk`] and
+    /// [`UniHyperPlonk`].
+    ///
+    /// [`HyperPlonk`]: plonkish_backend::backend::hyperplonk::HyperPlonk
+    /// [`UniHyperPlonk`]: plonkish_backend::backend::unihyperplonk::UniHyperPlonk
+    pub fn min_k(&self) -> usize {
+        match self.num_rows.checked_add(1) {
+            Some(num_rows) => num_rows.next_power_of_two().trailing_zeros() as usize,
+            None => usize::BITS as usize,
+        }
+    }
+}
+
+/// Estimates the size of the circuit of `query` over a dataset of
+/// `dataset_size` triples, bounding the number of solutions by
+/// `dataset_size` to the power of the number of triple patterns, times the
+/// sum of the same for each `UNION` branch and times the same for the
+/// `OPTIONAL` block, or by [`MAX_COUNT`] with a [`Count`] without
+/// `GROUP BY`. The rows are also bounded by the ones of no solution, whose
+/// [empty result](crate::circuit#empty-results) may be proven by a scan of
+/// every triple.
+///
+/// The bound is loose for joins, so with the number of solutions known use
+/// [`estimate_circuit_size_with_solutions`] instead.
+///
+/// [`Count`]: crate::query::Count
+pub fn estimate_circuit_size(query: &SelectQuery, dataset_size: usize) -> CircuitSizeEstimate {
+    let matches = |num_patterns: usize| dataset_size.saturating_pow(num_patterns as u32);
+    let mut num_solutions = matches(query.patterns.len());
+    if let Some(union) = &query.union {
+        let num_branch_matches = union
+            .branches()
+            .into_iter()
+            .map(|branch| matches(branch.len()))
+            .fold(0, usize::saturating_add);
+        num_solutions = num_solutions.saturating_mul(num_branch_matches);
+    }
+    if !query.optional.is_empty() {
+        num_solutions = num_solutions.saturating_mul(matches(query.optional.len()).max(1));
+    }
+    if query.count.is_some() && query.group_by.is_none() {
+        num_solutions = num_solutions.min(MAX_COUNT);
+    }
+    let estimate = estimate_circuit_size_with_solutions(query, dataset_size, num_solutions);
+    // No solution at all proves the empty result by a scan of every leaf,
+    // which can cost more.
+    let empty = estimate_circuit_size_with_solutions(query, dataset_size, 0);
+    CircuitSizeEstimate {
+        num_rows: estimate.num_rows.max(empty.num_rows),
+        ..estimate
+    }
+}
+
+/// Estimates the size of the circuit of `query` with `num_solutions`
+/// solutions over a dataset of `dataset_size` triples, which is an upper
+/// bound as long as the query compiles. With `GROUP BY`, `num_solutions` is
+/// the number of counted values over all groups, and every one is taken to
+/// be its own group.
+pub fn estimate_circuit_size_with_solutions(
+    query: &SelectQuery,
+    dataset_size: usize,
+    num_solutions: usize,
+) -> CircuitSizeEstimate {
+    estimate_circuit_size_with_strategy(query, dataset_size, num_solutions, MatchStrategy::Merkle)
+}
+
+/// Estimates the size of the circuit of [`compile_bgp_with_strategy`] as
+/// [`estimate_circuit_size_with_solutions`], whose matched triples are
+/// proven by `strategy`.
+///
+/// [`compile_bgp_with_strategy`]: crate::circuit::compile_bgp_with_strategy
+pub fn estimate_circuit_size_with_strategy(
+    query: &SelectQuery,
+    dataset_size: usize,
+    num_solutions: usize,
+    strategy: MatchStrategy,
+) -> CircuitSizeEstimate {
+    // Solutions past `LIMIT` aren't proven.
+    let num_solutions = match (&query.count, query.limit) {
+        (None, Some(limit)) => num_solutions.min(limit),
+        (Some(_), Some(0)) => 0,
+        _ => num_solutions,
+    };
+    let depth = dataset_size.next_power_of_two().trailing_zeros() as usize;
+    let branches = query
+        .union
+        .as_ref()
+        .map(Pattern::branches)
+        .unwrap_or_default();
+    let num_union_patterns = branches.iter().map(|branch| branch.len()).sum::<usize>();
+    let num_patterns =
+        query.patterns.len() + num_union_patterns + query.optional.len() + query.not_exists.len();
+    let num_graph_patterns = query
+        .patterns
+        .iter()
+        .chain(branches.iter().copied().flatten())
+        .chain(&query.optional)
+        .chain(&query.not_exists)
+        .filter(|pattern| pattern.graph.is_some())
+        .count();
+    let num_hashed = num_patterns
+        + num_graph_patterns
+        + branches.len()
+        + query.filters.len()
+        + query.count.iter().len()
+        + query.distinct as usize
+        + query
+            .values
+            .as_ref()
+            .map_or(0, |values| 1 + values.terms.len())
+        + query.order_by.len()
+        + query.limit.iter().len()
+        + 2 * query.binds.len()
+        + query.string_filters.len()
+        + query.group_by.iter().len()
+        + query.conditions.iter().map(condition_nodes).sum::<usize>();
+
+    let layout = InstanceLayout::new(query, num_solutions);
+    // Zero, one, count, the limbs of the modulus and the last index of the
+    // tree, the inputs of each commitment hash, the projected variables, the
+    // constants of patterns, filters, operands of binds and limbs of string
+    // filters, and the count of each group.
+    let num_constants = 6
+        + 3 * num_hashed
+        + query.projection.len()
+        + 3 * num_patterns
+        + num_graph_patterns
+        + query.filters.len()
+        + 2 * query.binds.len()
+        + 3 * query.string_filters.len()
+        + query.group_by.iter().len() * layout.num_solutions;
+    let num_commitment_rows =
+        num_hashed * (HASH_ROWS + NODE_ROWS) + query.projection.len() * NODE_ROWS;
+
+    // Per pattern its leaf and its path or lookup, and for a lookup the table
+    // of the leaves and their tree once.
+    let (pattern_rows, num_table_rows) = match strategy {
+        MatchStrategy::Merkle => (HASH_ROWS + depth * NODE_ROWS, 0),
+        MatchStrategy::Lookup => (HASH_ROWS + 1, (1 << depth) + ((1 << depth) - 1) * NODE_ROWS),
+    };
+    // Without solutions, every leaf is opened and hashed into the root once.
+    let num_scan_rows = match num_solutions == 0 && query.limit != Some(0) {
+        true if scanned_pattern(query).is_some() => {
+            1 + (1 << depth) * SCAN_LEAF_ROWS + ((1 << depth) - 1) * NODE_ROWS
+        }
+        _ => 0,
+    };
+    let mut solution_rows = query.patterns.len() * pattern_rows;
+    // Per guarded pattern the guarded root and at most a guarded equality of
+    // 2 rows per term.
+    let guarded_pattern_rows = pattern_rows + 2 + 3 * 2;
+    if !branches.is_empty() {
+        // Selectors, then per term at most a scaling and a sum binding it.
+        solution_rows += 2 + num_union_patterns * (guarded_pattern_rows + 3 * 2);
+    }
+    if !query.optional.is_empty() {
+        // Selector, then per term at most a scaling binding it.
+        solution_rows += 1 + query.optional.len() * (guarded_pattern_rows + 3);
+    }
+    // Per bind the opening of both operands, the operation, its range check
+    // and the encoding of the result.
+    solution_rows += query.binds.len() * (3 * HASH_ROWS + 1 + 1 + NUM_FILTER_BITS + 1);
+    for filter in query.filters.iter() {
+        solution_rows += HASH_ROWS
+            + match filter.op {
+                CompareOp::Eq => 0,
+                CompareOp::Ne => 2,
+                _ => 1 + NUM_FILTER_BITS + 1,
+            };
+    }
+    // Per condition the opening and boolean of each comparison, and the
+    // combination of each connective.
+    for condition in query.conditions.iter() {
+        solution_rows += condition_rows(condition);
+    }
+    // Per string filter the opening of its binding.
+    solution_rows += query.string_filters.len() * HASH_ROWS;
+    // Per pattern in a `GRAPH` clause the quad hash and at most a guarded
+    // equality of its graph.
+    solution_rows += num_graph_patterns * (HASH_ROWS + 2);
+    if query.count.is_some() {
+        // Non-zero check on a counted optional variable.
+        solution_rows += 1;
+    }
+    if let Some(values) = &query.values {
+        // Product of the differences from each term.
+        solution_rows += values.terms.len();
+    }
+    let num_distinctness_rows = match (&query.count, &query.group_by) {
+        (Some(_), None) => num_solutions.saturating_mul(num_solutions.saturating_sub(1)),
+        _ => 0,
+    };
+
+    // Per result its key, limbs and order after the previous one, and the
+    // order of the last one before the modulus, for `DISTINCT` or else
+    // `ORDER BY` without a key.
+    let limbs_rows = 1 + LO_LIMB_BITS + 1 + HI_LIMB_BITS + 1;
+    let less_than_rows = 3 + LO_LIMB_BITS + 1 + HI_LIMB_BITS + 1;
+    // Per pattern of the `FILTER NOT EXISTS` block its leaf, the boundary
+    // selectors, the leaf, path and index of both neighbors, the guarded
+    // equalities and bounds, and the order of the leaf between them below the
+    // modulus.
+    let not_exists_rows =
+        HASH_ROWS + 2 * (1 + depth * (NODE_ROWS + 2)) + 21 + 3 * (limbs_rows + less_than_rows);
+    solution_rows += query.not_exists.len() * not_exists_rows;
+    // Per counted value of `GROUP BY` its limbs and order after the previous
+    // one, and per group the limbs and order of its key and the order of its
+    // last value before the modulus.
+    let num_grouping_rows = match query.group_by {
+        Some(_) => num_solutions.saturating_mul(2 * limbs_rows + 3 * less_than_rows),
+        None => 0,
+    };
+    let num_ordering_rows = match (query.distinct && query.count.is_none(), &query.order_by[..]) {
+        (true, _) => {
+            let key_rows = query.projection.len().saturating_sub(1) * NODE_ROWS;
+            (num_solutions.saturating_add(1)).saturating_mul(key_rows + limbs_rows + less_than_rows)
+        }
+        (false, []) => 0,
+        (false, _) => (num_solutions.saturating_add(1)).saturating_mul(limbs_rows + less_than_rows),
+    };
+
+    let num_rows = [
+        layout.num_instances(),
+        num_constants,
+        num_commitment_rows,
+        num_table_rows,
+        num_scan_rows,
+        num_solutions.saturating_mul(solution_rows),
+        num_distinctness_rows,
+        num_ordering_rows,
+        num_grouping_rows,
+    ]
+    .into_iter()
+    .fold(0, usize::saturating_add);
+    CircuitSizeEstimate {
+        num_witness_polys: NUM_WITNESS_POLYS,
+        num_solutions,
+        num_rows,
+    }
+}
+
+/// Returns the maximum number of triples of a dataset the circuit of `query`
+/// with `num_solutions` solutions fits `2^k - 1` rows over, by
+/// [`estimate_circuit_size_with_solutions`], or 0 if not even a single triple
+/// fits.
+///
+/// Each pattern of each solution proves membership along a path of one
+/// [`MerkleTripleSet`] level per doubling of the triples, so the capacity is
+/// a power of two, doubling with every `NODE_ROWS` rows per pattern and
+/// solution left over from the rest of the circuit. Without solutions, the
+/// scan proving an empty result costs `SCAN_LEAF_ROWS + NODE_ROWS` rows per
+/// triple instead.
+///
+/// [`MerkleTripleSet`]: crate::merkle::MerkleTripleSet
+pub fn triple_capacity(query: &SelectQuery, num_solutions: usize, k: usize) -> usize {
+    let fits = |depth: u32| {
+        estimate_circuit_size_with_solutions(query, 1 << depth, num_solutions).min_k() <= k
+    };
+    match (0..usize::BITS - 1).take_while(|depth| fits(*depth)).last() {
+        Some(depth) => 1 << depth,
+        None => 0,
+    }
+}
+
+/// Returns the number of nodes `condition` hashes into the commitment, one
+/// per comparison and connective.
+fn condition_nodes(condition: &FilterCondition) -> usize {
+    match condition {
+        FilterCondition::Compare(_) => 1,
+        FilterCondition::Not(condition) => 1 + condition_nodes(condition),
+        FilterCondition::And(lhs, rhs) | FilterCondition::Or(lhs, rhs) => {
+            1 + condition_nodes(lhs) + condition_nodes(rhs)
+        }
+    }
+}
+
+/// Returns the rows of a solution evaluating `condition`, see
+/// [`comparison_bit`] and [`condition_bit`].
+///
+/// [`comparison_bit`]: crate::circuit::comparison_bit
+/// [`condition_bit`]: crate::circuit::condition_bit
+fn condition_rows(condition: &FilterCondition) -> usize {
+    match condition {
+        FilterCondition::Compare(filter) => {
+            HASH_ROWS
+                + match filter.op {
+                    CompareOp::Eq => 5,
+                    CompareOp::Ne => 6,
+                    _ => 6 + NUM_FILTER_BITS + 1,
+                }
+        }
+        FilterCondition::Not(condition) => 1 + condition_rows(condition),
+        FilterCondition::And(lhs, rhs) => 1 + condition_rows(lhs) + condition_rows(rhs),
+        FilterCondition::Or(lhs, rhs) => 3 + condition_rows(lhs) + condition_rows(rhs),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{
+            compile_bgp, compile_bgp_with_strategy, estimate_circuit_size,
+            estimate_circuit_size_with_solutions, estimate_circuit_size_with_strategy, test::Pb,
+            triple_capacity, MatchStrategy,
+        },
+        parse_select,
+        query::Literal,
+        rdf::{RdfGraph, Term, Triple},
+    };
+
+    #[test]
+    fn upper_bound() {
+        let [alice, bob, carol, knows, age] =
+            ["alice", "bob", "carol", "knows", "age"].map(Term::iri);
+        let mut graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(bob.clone(), knows.clone(), carol.clone()),
+            Triple::new(alice.clone(), knows.clone(), carol.clone()),
+            Triple::new(
+                alice.clone(),
+                age.clone(),
+                Term::Literal(Literal::integer(30)),
+            ),
+            Triple::new(carol, age, Term::Literal(Literal::integer(17))),
+        ]);
+        graph.insert(Triple::new(
+            alice.clone(),
+            Term::iri("name"),
+            Term::Literal(Literal::string("Alice")),
+        ));
+        graph.insert_quad(Triple::new(bob, knows, alice), Some(Term::iri("g")));
+        for input in [
+            "SELECT ?a ?b WHERE { ?a <knows> ?b }",
+            "SELECT ?a WHERE { ?a <knows> ?b . ?b <knows> ?c }",
+            "SELECT ?a WHERE { ?a <age> ?n FILTER(?n >= 18) FILTER(?n != 3) FILTER(?n = 30) }",
+            "SELECT ?a WHERE { ?a <age> ?n FILTER(?n < 18 || !(?n != 30) || ?n = 40 && ?n > 2) }",
+            "SELECT ?b ?n WHERE { ?a <knows> ?b OPTIONAL { ?b <age> ?n } }",
+            "SELECT (COUNT(?b) AS ?c) WHERE { ?a <knows> ?b }",
+            "SELECT ?b (COUNT(?a) AS ?c) WHERE { ?a <knows> ?b } GROUP BY ?b",
+            "SELECT DISTINCT ?a WHERE { ?a <knows> ?b }",
+            "SELECT DISTINCT ?a ?n WHERE { ?a <knows> ?b OPTIONAL { ?a <age> ?n } }",
+            "SELECT ?a ?n WHERE { { ?a <knows> ?b } UNION { ?a <age> ?n } OPTIONAL { ?a <age> ?m } }",
+            "SELECT ?a WHERE { ?a <knows> ?b VALUES ?b { <bob> <carol> <dave> } }",
+            "SELECT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?b",
+            "SELECT ?a ?b WHERE { ?a <knows> ?b } ORDER BY ?b LIMIT 2",
+            "SELECT ?a WHERE { ?a <knows> ?b } LIMIT 5",
+            "SELECT ?a ?g WHERE { GRAPH ?g { ?a <knows> ?b } }",
+            "SELECT ?a ?c WHERE { ?a <knows> ?b OPTIONAL { GRAPH <g> { ?b <knows> ?c } } }",
+            "SELECT ?a ?b WHERE { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a } }",
+            "SELECT ?a WHERE { ?a <age> ?n FILTER NOT EXISTS { GRAPH <g> { ?a <knows> <bob> } } }",
+            "SELECT ?a ?c WHERE { ?a <age> ?n BIND(?n * 2 AS ?c) BIND(?c - 1 AS ?d) FILTER(?d > 10) }",
+            r#"SELECT ?a WHERE { ?a <name> ?n FILTER(?n = "Alice") FILTER("Alice" = ?n) }"#,
+            "SELECT ?a WHERE { ?a <likes> ?b }",
+            "SELECT ?a WHERE { GRAPH ?g { ?a <knows> ?a } }",
+            "SELECT ?a WHERE { GRAPH <g> { ?a <age> <bob> } }",
+        ] {
+            let query = parse_select(input).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let shape = circuit.shape();
+            let estimate = estimate_circuit_size(&query, graph.len());
+            assert!(estimate.num_solutions >= shape.num_solutions());
+            assert!(estimate.num_rows >= shape.num_rows());
+            assert!(estimate.min_k() >= shape.circuit_info().k);
+            assert_eq!(
+                estimate.num_witness_polys,
+                shape.circuit_info().num_witness_polys[0]
+            );
+
+            let estimate =
+                estimate_circuit_size_with_solutions(&query, graph.len(), shape.num_solutions());
+            assert!(estimate.num_rows >= shape.num_rows());
+            assert!(estimate.min_k() >= shape.circuit_info().k);
+
+            let circuit =
+                compile_bgp_with_strategy::<Pb>(&query, &graph, MatchStrategy::Lookup).unwrap();
+            let estimate = estimate_circuit_size_with_strategy(
+                &query,
+                graph.len(),
+                shape.num_solutions(),
+                MatchStrategy::Lookup,
+            );
+            assert!(estimate.num_rows >= circuit.shape().num_rows());
+        }
+    }
+
+    #[test]
+    fn capacity() {
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b . ?b <knows> ?a }").unwrap();
+        let k = estimate_circuit_size_with_solutions(&query, 1 << 10, 4).min_k();
+        let capacity = triple_capacity(&query, 4, k);
+        assert!(capacity >= 1 << 10);
+        assert!(estimate_circuit_size_with_solutions(&query, capacity, 4).min_k() <= k);
+        assert!(estimate_circuit_size_with_solutions(&query, capacity + 1, 4).min_k() > k);
+        assert_eq!(triple_capacity(&query, 4, 1), 0);
+    }
+}
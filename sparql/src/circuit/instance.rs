@@ -0,0 +1,520 @@
+use crate::{
+    circuit::{query_commitment, NULL_ENCODING},
+    eval::Binding,
+    query::{Literal, SelectQuery, Variable},
+    rdf::{RdfGraph, Term},
+};
+use plonkish_backend::{halo2_curves::bn256::Fr, util::arithmetic::PrimeField};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Write},
+    slice::Chunks,
+};
+
+/// Layout of the public instances of a [`QueryCircuit`], which all live in
+/// the single instance column [`Self::COLUMN`]:
+///
+/// | Row                            | Value                                  |
+/// | ------------------------------ | -------------------------------------- |
+/// | [`Self::QUERY_COMMITMENT`]     | [`query_commitment`] of the query      |
+/// | [`Self::DATASET_ROOT`]         | [`MerkleTripleSet::root`] of dataset   |
+/// | [`Self::RESULTS`] + `i·m + j`  | Encoding of variable `j` of            |
+/// |                                | [`Self::variables`] of solution `i`,   |
+/// |                                | with `m` projected                     |
+///
+/// Results are encoded by [`Term::encode`], or [`NULL_ENCODING`] for a
+/// variable left unbound by the `OPTIONAL` block, except for a query
+/// projecting a [`Count`], whose single result is the count. With `GROUP BY`
+/// the results are the encoding of the key and the count of each group,
+/// in order of the encoding of the key, and `m` is `2`. A proof only attests
+/// that at least a count of distinct values match, so the column
+/// [`Self::count_lower_bound`] of a count is a lower bound.
+///
+/// With `LIMIT n` there are `n` results, of which those past the proven
+/// solutions are padding of [`NULL_ENCODING`] for every projected variable,
+/// so they come after the real ones in any `ORDER BY`. A count has a single
+/// result unless `n` is `0`, and padding is never counted.
+///
+/// [`Count`]: crate::query::Count
+/// [`NULL_ENCODING`]: crate::circuit::NULL_ENCODING
+/// [`QueryCircuit`]: crate::circuit::QueryCircuit
+/// [`MerkleTripleSet::root`]: crate::merkle::MerkleTripleSet::root
+/// [`Term::encode`]: crate::rdf::Term::encode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceLayout {
+    pub num_projected: usize,
+    pub num_solutions: usize,
+    /// Column of [`Self::variables`] of a count, which is only a lower bound
+    /// of the distinct values matching, or `None` without a [`Count`].
+    ///
+    /// [`Count`]: crate::query::Count
+    pub count_lower_bound: Option<usize>,
+}
+
+impl InstanceLayout {
+    pub const COLUMN: usize = 0;
+    pub const QUERY_COMMITMENT: usize = 0;
+    pub const DATASET_ROOT: usize = 1;
+    pub const RESULTS: usize = 2;
+
+    /// Returns the layout of the results of `query` with `num_solutions`
+    /// proven, or the number of groups with `GROUP BY`, see above.
+    pub fn new(query: &SelectQuery, num_solutions: usize) -> Self {
+        match (&query.count, &query.group_by) {
+            (Some(_), Some(_)) => Self {
+                num_projected: 2,
+                num_solutions,
+                count_lower_bound: Some(1),
+            },
+            (Some(_), None) => Self {
+                num_projected: 1,
+                num_solutions: query.limit.map_or(1, |limit| limit.min(1)),
+                count_lower_bound: Some(0),
+            },
+            (None, _) => Self {
+                num_projected: query.projection.len(),
+                num_solutions: query.limit.unwrap_or(num_solutions),
+                count_lower_bound: None,
+            },
+        }
+    }
+
+    /// Returns the variable of each result column of `query` in order, the
+    /// `j` of the rows above, which is the order of its `SELECT` clause
+    /// rather than the one its patterns bind them in, e.g. `?b` then `?a` for
+    /// `SELECT ?b ?a WHERE { ?a <p> ?b }`. A count is its alias, after the
+    /// key with `GROUP BY`.
+    pub fn variables(query: &SelectQuery) -> Vec<&Variable> {
+        match (&query.count, &query.group_by) {
+            (Some(count), Some(key)) => vec![key, &count.alias],
+            (Some(count), None) => vec![&count.alias],
+            (None, _) => query.projection.iter().collect(),
+        }
+    }
+
+    /// Returns the result column of `variable` in [`Self::variables`] of
+    /// `query`, as taken by [`Self::result`], or `None` if it's not a result.
+    pub fn column(query: &SelectQuery, variable: &Variable) -> Option<usize> {
+        Self::variables(query).iter().position(|v| *v == variable)
+    }
+
+    /// Returns the row of projected variable `var` of solution `solution`.
+    pub fn result(&self, solution: usize, var: usize) -> usize {
+        assert!(solution < self.num_solutions && var < self.num_projected);
+        Self::RESULTS + solution * self.num_projected + var
+    }
+
+    pub fn num_instances(&self) -> usize {
+        Self::RESULTS + self.num_solutions * self.num_projected
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    NumColumns { found: usize },
+    NumRows { found: usize },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NumColumns { found } => {
+                write!(f, "expected 1 instance column, found {found}")
+            }
+            DecodeError::NumRows { found } => write!(
+                f,
+                "expected at least {} instances, found {found}",
+                InstanceLayout::RESULTS
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// What a proof of a [`QueryCircuit`] attests, decoded by
+/// [`decode_instances`].
+///
+/// [`QueryCircuit`]: crate::circuit::QueryCircuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryPublicInputs {
+    pub query_commitment: Fr,
+    pub dataset_root: Fr,
+    /// Encodings of the projected variables of every solution, solution by
+    /// solution, each in order of [`InstanceLayout::variables`].
+    pub results: Vec<Fr>,
+}
+
+impl QueryPublicInputs {
+    /// Returns whether these are the instances of `query`, so the results
+    /// split into its projection, and number `n` under `LIMIT n`.
+    pub fn matches_query(&self, query: &SelectQuery) -> bool {
+        let layout = InstanceLayout::new(query, 0);
+        self.query_commitment == query_commitment(query)
+            && match (layout.num_projected, query.limit) {
+                (0, _) => self.results.is_empty(),
+                (num_projected, Some(_)) => {
+                    self.results.len() == layout.num_solutions * num_projected
+                }
+                (num_projected, None) => self.results.len() % num_projected == 0,
+            }
+    }
+
+    /// Returns the results of each solution with `num_projected` projected
+    /// variables, which is empty without projected variables since the number
+    /// of solutions isn't exposed then.
+    pub fn solutions(&self, num_projected: usize) -> Chunks<Fr> {
+        match num_projected {
+            0 => [].chunks(1),
+            _ => self.results.chunks(num_projected),
+        }
+    }
+}
+
+/// Decodes the instances of a [`QueryCircuit`] by [`InstanceLayout`].
+///
+/// [`QueryCircuit`]: crate::circuit::QueryCircuit
+pub fn decode_instances(instances: &[Vec<Fr>]) -> Result<QueryPublicInputs, DecodeError> {
+    let [column] = instances else {
+        return Err(DecodeError::NumColumns {
+            found: instances.len(),
+        });
+    };
+    if column.len() < InstanceLayout::RESULTS {
+        return Err(DecodeError::NumRows {
+            found: column.len(),
+        });
+    }
+    Ok(QueryPublicInputs {
+        query_commitment: column[InstanceLayout::QUERY_COMMITMENT],
+        dataset_root: column[InstanceLayout::DATASET_ROOT],
+        results: column[InstanceLayout::RESULTS..].to_vec(),
+    })
+}
+
+/// Terms by their [`Term::encode`], to map the results of a proof back to
+/// terms by [`results_to_bindings`]. The encoding of a term is a one-way
+/// hash, so only terms known up front, e.g. of the vocabulary or the
+/// dataset, are recovered.
+#[derive(Clone, Debug, Default)]
+pub struct TermDictionary {
+    terms: HashMap<[u8; 32], Term>,
+}
+
+impl TermDictionary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the dictionary of every term of `graph`.
+    pub fn from_graph(graph: &RdfGraph) -> Self {
+        let mut dictionary = Self::new();
+        for triple in graph.triples() {
+            for id in triple {
+                dictionary
+                    .terms
+                    .insert(graph.encoding(*id).to_repr(), graph.term(*id).clone());
+            }
+        }
+        dictionary
+    }
+
+    /// Inserts `term` by its encoding, which is the one of its
+    /// [`Term::canonical`] form, so the canonical form is the one returned.
+    pub fn insert(&mut self, term: Term) {
+        let term = term.canonical().into_owned();
+        self.terms.insert(term.encode().to_repr(), term);
+    }
+
+    /// Returns the term encoded as `encoding`, if known.
+    pub fn get(&self, encoding: Fr) -> Option<&Term> {
+        self.terms.get(&encoding.to_repr())
+    }
+}
+
+impl FromIterator<Term> for TermDictionary {
+    fn from_iter<I: IntoIterator<Item = Term>>(terms: I) -> Self {
+        let mut dictionary = Self::new();
+        for term in terms {
+            dictionary.insert(term);
+        }
+        dictionary
+    }
+}
+
+/// Returns the binding of each solution of `decoded`, the instances of a
+/// proof of `query`, see [`InstanceLayout`], with each result mapped back to
+/// its term by `dictionary`.
+///
+/// A count encodes its value directly, so it's an `xsd:integer` literal
+/// without the dictionary, which is only a lower bound as the proof only
+/// attests that at least as many distinct values match, see
+/// [`InstanceLayout::count_lower_bound`]. Every term is encoded by a one-way hash, literals
+/// included, so a term absent from `dictionary` can only be shown by its
+/// hash, as a blank node labeled by the hex of its encoding. A variable of
+/// [`NULL_ENCODING`] is left unbound, so the `LIMIT` padding past the proven
+/// solutions is an empty binding.
+pub fn results_to_bindings(
+    decoded: &QueryPublicInputs,
+    query: &SelectQuery,
+    dictionary: &TermDictionary,
+) -> Vec<Binding> {
+    let variables = InstanceLayout::variables(query);
+    let term = |encoding: Fr| match dictionary.get(encoding) {
+        Some(term) => term.clone(),
+        None => Term::BlankNode(format!("{encoding:?}")),
+    };
+    let counted = query.count.as_ref().map(|_| variables.len() - 1);
+    decoded
+        .solutions(variables.len())
+        .map(|results| {
+            variables
+                .iter()
+                .zip(results)
+                .enumerate()
+                .filter_map(|(offset, (variable, encoding))| {
+                    let value = match count_value(*encoding) {
+                        Some(count) if Some(offset) == counted => {
+                            Term::Literal(Literal::integer(count))
+                        }
+                        _ if *encoding == NULL_ENCODING => return None,
+                        _ => term(*encoding),
+                    };
+                    Some(((*variable).clone(), value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Returns the count a result encodes, or `None` if it's out of range, which
+/// no count of a proof is.
+fn count_value(encoding: Fr) -> Option<i64> {
+    let repr = encoding.to_repr();
+    let (value, rest) = repr.as_ref().split_at(8);
+    if rest.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    i64::try_from(u64::from_le_bytes(value.try_into().unwrap())).ok()
+}
+
+/// Formats every instance in hex on its own line, e.g. to see what a failed
+/// verification was given, with what its row holds by `layout` if known,
+/// such as the dataset root or a result, with a count marked as the lower
+/// bound it is. A row past the layout, or a column other than
+/// [`InstanceLayout::COLUMN`], is marked as unexpected.
+pub fn format_instances(instances: &[Vec<Fr>], layout: Option<&InstanceLayout>) -> String {
+    let mut formatted = String::new();
+    for (column, values) in instances.iter().enumerate() {
+        writeln!(formatted, "instance column {column}:").unwrap();
+        for (row, value) in values.iter().enumerate() {
+            write!(formatted, "  {row:>4}: {value:?}").unwrap();
+            let Some(layout) = layout else {
+                formatted.push('\n');
+                continue;
+            };
+            let meaning = match row {
+                _ if column != InstanceLayout::COLUMN || row >= layout.num_instances() => {
+                    "unexpected".to_string()
+                }
+                InstanceLayout::QUERY_COMMITMENT => "query commitment".to_string(),
+                InstanceLayout::DATASET_ROOT => "dataset root".to_string(),
+                _ => {
+                    let idx = row - InstanceLayout::RESULTS;
+                    let (solution, var) = (idx / layout.num_projected, idx % layout.num_projected);
+                    if layout.count_lower_bound == Some(var) {
+                        format!("lower bound of the count of solution {solution}")
+                    } else {
+                        format!("result of projected variable {var} of solution {solution}")
+                    }
+                }
+            };
+            writeln!(formatted, " ({meaning})").unwrap();
+        }
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        circuit::{
+            compile_bgp, decode_instances, format_instances, results_to_bindings, test::Pb,
+            DecodeError, InstanceLayout, TermDictionary,
+        },
+        eval::{evaluate, Binding},
+        merkle::MerkleTripleSet,
+        parse_select,
+        query::{Literal, Variable},
+        rdf::Term,
+    };
+    use plonkish_backend::{
+        backend::PlonkishCircuit, halo2_curves::bn256::Fr, util::arithmetic::Field,
+    };
+
+    #[test]
+    fn decode() {
+        let graph = crate::circuit::test::graph();
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let layout = circuit.instance_layout();
+        assert_eq!(
+            *layout,
+            InstanceLayout {
+                num_projected: 2,
+                num_solutions: 2,
+                count_lower_bound: None,
+            }
+        );
+        assert_eq!(layout.num_instances(), circuit.instances()[0].len());
+
+        let inputs = decode_instances(circuit.instances()).unwrap();
+        assert!(inputs.matches_query(&query));
+        assert_eq!(
+            inputs.dataset_root,
+            MerkleTripleSet::from_graph(&graph).root()
+        );
+        let solution = [Term::iri("alice"), Term::iri("Carol")].map(|term| term.encode());
+        assert_eq!(inputs.solutions(2).nth(1).unwrap(), solution);
+        assert_eq!(
+            circuit.instances()[0][layout.result(1, 1)],
+            Term::iri("Carol").encode()
+        );
+
+        let other = parse_select("SELECT ?a WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        assert!(!inputs.matches_query(&other));
+        assert_eq!(
+            decode_instances(&[]).unwrap_err(),
+            DecodeError::NumColumns { found: 0 }
+        );
+    }
+
+    #[test]
+    fn select_order() {
+        let graph = crate::circuit::test::graph();
+        let query = parse_select("SELECT ?b ?a WHERE { ?a <knows> ?b }").unwrap();
+        let (a, b) = (Variable("a".to_string()), Variable("b".to_string()));
+        assert_eq!(InstanceLayout::variables(&query), [&b, &a]);
+        assert_eq!(InstanceLayout::column(&query, &a), Some(1));
+        assert_eq!(
+            InstanceLayout::column(&query, &Variable("c".to_string())),
+            None
+        );
+
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let decoded = decode_instances(circuit.instances()).unwrap();
+        let expected = evaluate(&query, &graph)
+            .flat_map(|solution| {
+                let encoding = |variable: &str| {
+                    let var = query
+                        .variables_with_binds()
+                        .iter()
+                        .position(|v| v.0 == variable);
+                    solution.encoding(var.unwrap(), &graph).unwrap()
+                };
+                [encoding("b"), encoding("a")]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decoded.results, expected);
+        let dictionary = TermDictionary::from_graph(&graph);
+        for (binding, results) in results_to_bindings(&decoded, &query, &dictionary)
+            .iter()
+            .zip(decoded.solutions(2))
+        {
+            assert_eq!(binding[&b].encode(), results[0]);
+            assert_eq!(binding[&a].encode(), results[1]);
+        }
+
+        let swapped = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        let circuit = compile_bgp::<Pb>(&swapped, &graph).unwrap();
+        let swapped = decode_instances(circuit.instances()).unwrap();
+        let reordered = swapped
+            .solutions(2)
+            .flat_map(|results| [results[1], results[0]])
+            .collect::<Vec<_>>();
+        assert_eq!(decoded.results, reordered);
+        assert_ne!(decoded.query_commitment, swapped.query_commitment);
+    }
+
+    #[test]
+    fn bindings() {
+        let graph = crate::circuit::test::graph();
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let decoded = decode_instances(circuit.instances()).unwrap();
+        let expected = evaluate(&query, &graph)
+            .map(|solution| {
+                let variables = query.variables();
+                query
+                    .projection
+                    .iter()
+                    .map(|variable| {
+                        let var = variables.iter().position(|v| *v == variable).unwrap();
+                        let term = graph.term(solution.bindings[var].unwrap());
+                        (variable.clone(), term.clone())
+                    })
+                    .collect::<Binding>()
+            })
+            .collect::<Vec<_>>();
+        let dictionary = TermDictionary::from_graph(&graph);
+        assert_eq!(results_to_bindings(&decoded, &query, &dictionary), expected);
+
+        // Without "Carol" in the dictionary, it's only shown by its hash.
+        let dictionary = [Term::iri("alice"), Term::iri("bob")]
+            .into_iter()
+            .collect::<TermDictionary>();
+        let bindings = results_to_bindings(&decoded, &query, &dictionary);
+        let carol = Term::BlankNode(format!("{:?}", Term::iri("Carol").encode()));
+        assert!(bindings
+            .iter()
+            .all(|binding| binding.values().last() == Some(&carol)));
+
+        let dictionary = TermDictionary::new();
+        for (input, count) in [
+            ("SELECT (COUNT(?a) AS ?c) { ?a <knows> ?b }", 2),
+            ("SELECT (COUNT(?a) AS ?c) { ?a <knows> <nobody> }", 0),
+        ] {
+            let query = parse_select(input).unwrap();
+            let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+            let decoded = decode_instances(circuit.instances()).unwrap();
+            let binding = Binding::from([(
+                query.count.as_ref().unwrap().alias.clone(),
+                Term::Literal(Literal::integer(count)),
+            )]);
+            assert_eq!(
+                results_to_bindings(&decoded, &query, &dictionary),
+                [binding]
+            );
+        }
+    }
+
+    #[test]
+    fn format() {
+        let graph = crate::circuit::test::graph();
+        let query = parse_select("SELECT ?a ?n WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        let mut instances = circuit.instances().to_vec();
+        instances[0].push(Fr::ONE);
+        let formatted = format_instances(&instances, Some(circuit.instance_layout()));
+        let lines = formatted.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1 + instances[0].len());
+        assert_eq!(lines[0], "instance column 0:");
+        let root = MerkleTripleSet::from_graph(&graph).root();
+        assert_eq!(lines[2], format!("     1: {root:?} (dataset root)"));
+        assert!(lines[6].ends_with(" (result of projected variable 1 of solution 1)"));
+        assert!(lines[7].ends_with(" (unexpected)"));
+
+        let raw = format_instances(&instances, None);
+        assert_eq!(raw.lines().nth(2).unwrap(), format!("     1: {root:?}"));
+
+        let query =
+            parse_select("SELECT ?a (COUNT(?b) AS ?n) { ?a <knows> ?b } GROUP BY ?a").unwrap();
+        let circuit = compile_bgp::<Pb>(&query, &graph).unwrap();
+        assert_eq!(circuit.instance_layout().count_lower_bound, Some(1));
+        let formatted = format_instances(circuit.instances(), Some(circuit.instance_layout()));
+        let lines = formatted.lines().collect::<Vec<_>>();
+        assert!(lines[3].ends_with(" (result of projected variable 0 of solution 0)"));
+        assert!(lines[4].ends_with(" (lower bound of the count of solution 0)"));
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    circuit::{compile, CompileError, InstanceLayout, MatchStrategy},
+    eval::{evaluate, evaluate_binding, Binding},
+    query::{SelectQuery, Variable},
+    rdf::RdfGraph,
+};
+use plonkish_backend::{
+    backend::{PlonkishCircuitInfo, WitnessEncoding},
+    halo2_curves::bn256::Fr,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// Fixed structure of a [`QueryCircuit`], which only depends on the query, the
+/// number of solutions, the depth of the [`MerkleTripleSet`], the
+/// [`MatchStrategy`] and with
+/// `GROUP BY` the count of each group, which its results disclose, so a `pp`
+/// and `vp` preprocessed from [`Self::circuit_info`] stay valid for the
+/// witness of any dataset given by [`Self::witness_for`].
+///
+/// [`QueryCircuit`]: crate::circuit::QueryCircuit
+/// [`MerkleTripleSet`]: crate::merkle::MerkleTripleSet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueryCircuitShape {
+    pub(crate) query: SelectQuery,
+    pub(crate) num_solutions: usize,
+    pub(crate) depth: usize,
+    pub(crate) group_counts: Vec<usize>,
+    pub(crate) strategy: MatchStrategy,
+    pub(crate) value_bits: usize,
+    pub(crate) num_rows: usize,
+    pub(crate) layout: InstanceLayout,
+    pub(crate) circuit_info: PlonkishCircuitInfo<Fr>,
+}
+
+impl QueryCircuitShape {
+    pub fn query(&self) -> &SelectQuery {
+        &self.query
+    }
+
+    /// Returns the number of solutions proven, which for a [`Count`] is the
+    /// count, or the sum of the counts of the groups with `GROUP BY`.
+    ///
+    /// [`Count`]: crate::query::Count
+    pub fn num_solutions(&self) -> usize {
+        self.num_solutions
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns the bits of the integers of filters and `BIND`, see
+    /// [`compile_bgp_with_value_bits`].
+    ///
+    /// [`compile_bgp_with_value_bits`]: crate::circuit::compile_bgp_with_value_bits
+    pub fn value_bits(&self) -> usize {
+        self.value_bits
+    }
+
+    /// Returns the count of each group of `GROUP BY` in order of their keys,
+    /// empty without one.
+    pub fn group_counts(&self) -> &[usize] {
+        &self.group_counts
+    }
+
+    /// Returns how the matched triples are proven in the dataset.
+    pub fn match_strategy(&self) -> MatchStrategy {
+        self.strategy
+    }
+
+    /// Returns the number of triples proven to be in the dataset, one per
+    /// triple pattern of each solution. The patterns of the `UNION` branches
+    /// and the `OPTIONAL` block are counted whether they match or not, as
+    /// their membership is proven either way.
+    pub fn num_matched_triples(&self) -> usize {
+        let num_union_patterns = self.query.union.as_ref().map_or(0, |union| {
+            union.branches().iter().map(|branch| branch.len()).sum()
+        });
+        let num_patterns =
+            self.query.patterns.len() + num_union_patterns + self.query.optional.len();
+        self.num_solutions * num_patterns
+    }
+
+    /// Returns the number of rows laid out, before padding to `2^k`.
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn instance_layout(&self) -> &InstanceLayout {
+        &self.layout
+    }
+
+    pub fn circuit_info(&self) -> &PlonkishCircuitInfo<Fr> {
+        &self.circuit_info
+    }
+
+    /// Returns the witness of the query over `dataset` laid out for the row
+    /// mapping of `E`, which has to be the one `self` is compiled for. Fails
+    /// if the number of solutions or the count of a group differs, or the
+    /// dataset needs a deeper Merkle tree, whose root is otherwise taken over
+    /// `2^depth` leaves.
+    pub fn witness_for<E: WitnessEncoding>(
+        &self,
+        dataset: &RdfGraph,
+    ) -> Result<Witness, CompileError> {
+        let solutions = evaluate(&self.query, dataset).collect();
+        let (shape, witness) = compile::<E>(
+            &self.query,
+            dataset,
+            Some(self),
+            None,
+            self.strategy,
+            self.value_bits,
+            solutions,
+        )?;
+        debug_assert!(
+            shape.circuit_info.k == self.circuit_info.k
+                && shape.circuit_info.preprocess_polys == self.circuit_info.preprocess_polys
+                && shape.circuit_info.permutations == self.circuit_info.permutations
+        );
+        Ok(witness)
+    }
+
+    /// Returns the witness of the query over `dataset` as [`Self::witness_for`],
+    /// but proving the first solution of [`evaluate_binding`] of `binding`,
+    /// so a caller picks the result proven. Fails with
+    /// [`WitnessError::UnknownVariable`] for a variable the query doesn't
+    /// have, with [`WitnessError::NoMatch`] if the binding isn't a solution
+    /// over `dataset`, and otherwise as [`Self::witness_for`], so `self` has
+    /// to be of a single solution.
+    pub fn witness_for_binding<E: WitnessEncoding>(
+        &self,
+        dataset: &RdfGraph,
+        binding: &Binding,
+    ) -> Result<Witness, WitnessError> {
+        let variables = self.query.variables_with_binds();
+        if let Some(variable) = binding.keys().find(|v| !variables.contains(v)) {
+            return Err(WitnessError::UnknownVariable(variable.clone()));
+        }
+        let solution = evaluate_binding(&self.query, dataset, binding)
+            .next()
+            .ok_or(WitnessError::NoMatch)?;
+        let (_, witness) = compile::<E>(
+            &self.query,
+            dataset,
+            Some(self),
+            None,
+            self.strategy,
+            self.value_bits,
+            vec![solution],
+        )?;
+        Ok(witness)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WitnessError {
+    /// Variable of the binding the query doesn't have.
+    UnknownVariable(Variable),
+    /// Binding that isn't a solution of the query over the dataset, e.g. of a
+    /// term whose triple isn't in it or failing a filter.
+    NoMatch,
+    Compile(CompileError),
+}
+
+impl From<CompileError> for WitnessError {
+    fn from(err: CompileError) -> Self {
+        Self::Compile(err)
+    }
+}
+
+impl Display for WitnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessError::UnknownVariable(variable) => {
+                write!(f, "variable {variable} of the binding is not in the query")
+            }
+            WitnessError::NoMatch => {
+                write!(f, "binding is not a solution of the query over the dataset")
+            }
+            WitnessError::Compile(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for WitnessError {}
+
+/// Instances and witness polys of a [`QueryCircuit`] over a dataset.
+///
+/// [`QueryCircuit`]: crate::circuit::QueryCircuit
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Witness {
+    pub(crate) instances: Vec<Vec<Fr>>,
+    pub(crate) witness_polys: Vec<Vec<Fr>>,
+}
+
+impl Witness {
+    pub fn instances(&self) -> &[Vec<Fr>] {
+        &self.instances
+    }
+}
@@ -0,0 +1,978 @@
+use crate::{
+    hash::integer_encoding,
+    query::{FilterDatatype, Operand, SelectQuery, TermPattern, TriplePattern, Variable},
+    rdf::{RdfGraph, Term, TermId},
+};
+use plonkish_backend::{
+    halo2_curves::bn256::Fr,
+    util::{arithmetic::PrimeField, Either},
+};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    iter,
+};
+
+/// Terms chosen for some variables of a query, e.g. outside the crate to pick
+/// the result to prove, see [`evaluate_binding`].
+pub type Binding = BTreeMap<Variable, Term>;
+
+/// Solution of a basic graph pattern joined with the `UNION` and left-joined
+/// with the `OPTIONAL` block.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Solution {
+    /// Binding of each variable in order of [`SelectQuery::variables`], which
+    /// is `None` only for a variable of the unmatched `OPTIONAL` block or of
+    /// another branch of the `UNION`.
+    pub bindings: Vec<Option<TermId>>,
+    /// Index into [`RdfGraph::triples`] of the triple matched by each pattern,
+    /// which is in the default graph unless the pattern is in a `GRAPH`
+    /// clause.
+    pub triples: Vec<usize>,
+    /// Index of the branch of the `UNION` matched in order of
+    /// [`Pattern::branches`], with the index into [`RdfGraph::triples`] of the
+    /// triple matched by each of its patterns, or `None` without `UNION`.
+    ///
+    /// [`Pattern::branches`]: crate::query::Pattern::branches
+    pub union: Option<(usize, Vec<usize>)>,
+    /// Index into [`RdfGraph::triples`] of the triple matched by each optional
+    /// pattern, or `None` if the `OPTIONAL` block is absent or unmatched.
+    pub optional: Option<Vec<usize>>,
+    /// Integer bound by each of [`SelectQuery::binds`] in order, which every
+    /// yielded solution has.
+    pub binds: Vec<i64>,
+}
+
+impl Solution {
+    /// Returns the encoding of the binding of the variable at `var` in order
+    /// of [`SelectQuery::variables_with_binds`], or `None` if it's unbound.
+    pub fn encoding(&self, var: usize, graph: &RdfGraph) -> Option<Fr> {
+        match var.checked_sub(self.bindings.len()) {
+            None => self.bindings[var].map(|id| graph.encoding(id)),
+            Some(idx) => self.binds.get(idx).map(|value| integer_encoding(*value)),
+        }
+    }
+
+    /// Returns the integer bound to the variable at `var` as
+    /// [`Self::encoding`], or `None` if it's unbound or not an integer literal.
+    pub fn integer(&self, var: usize, graph: &RdfGraph) -> Option<i64> {
+        self.value(var, graph, FilterDatatype::Integer)
+    }
+
+    /// Returns the value of the literal of `datatype` bound to the variable
+    /// at `var` as [`Self::encoding`], see [`FilterDatatype::value`], or
+    /// `None` if it's unbound or not a literal of `datatype`.
+    pub fn value(&self, var: usize, graph: &RdfGraph, datatype: FilterDatatype) -> Option<i64> {
+        match var.checked_sub(self.bindings.len()) {
+            None => match graph.term(self.bindings[var]?) {
+                Term::Literal(literal) => datatype.value(literal),
+                _ => None,
+            },
+            Some(_) if datatype != FilterDatatype::Integer => None,
+            Some(idx) => self.binds.get(idx).copied(),
+        }
+    }
+}
+
+/// Evaluates the basic graph pattern of `query` against `graph` and yields the
+/// solutions extended by every `BIND` and satisfying every filter, the
+/// `FILTER NOT EXISTS` and the `VALUES`, in order of
+/// [`evaluate_bgp`]. With
+/// [`SelectQuery::distinct`], only the first solution of each projected
+/// bindings is yielded, unless the query projects a [`Count`].
+///
+/// Solutions are found as they're consumed, so a prover can take one or a
+/// batch of them to [`compile_bgp_with_solutions`] without evaluating the rest.
+///
+/// [`Count`]: crate::query::Count
+/// [`compile_bgp_with_solutions`]: crate::circuit::compile_bgp_with_solutions
+pub fn evaluate<'a>(
+    query: &'a SelectQuery,
+    graph: &'a RdfGraph,
+) -> impl Iterator<Item = Solution> + 'a {
+    let variables = query.variables();
+    let all_variables = query.variables_with_binds();
+    let position = move |variable: &Variable| all_variables.iter().position(|v| *v == variable);
+    let filters = query
+        .filters
+        .iter()
+        .map(|filter| (filter, position(&filter.variable)))
+        .collect::<Vec<_>>();
+    let conditions = query
+        .conditions
+        .iter()
+        .map(|condition| {
+            let vars = condition
+                .comparisons()
+                .into_iter()
+                .map(|filter| (filter.variable.clone(), position(&filter.variable)))
+                .collect::<Vec<_>>();
+            (condition, vars)
+        })
+        .collect::<Vec<_>>();
+    // A string not in the graph binds no solution.
+    let string_filters = query
+        .string_filters
+        .iter()
+        .map(|filter| {
+            let id = graph.term_id(&Term::Literal(filter.literal()));
+            (position(&filter.variable), id)
+        })
+        .collect::<Vec<_>>();
+    let projection = query.projection.iter().map(&position).collect::<Vec<_>>();
+    let binds = query
+        .binds
+        .iter()
+        .map(|bind| {
+            let operand = |operand: &Operand| match operand {
+                Operand::Variable(variable) => Err(position(variable)),
+                Operand::Integer(value) => Ok(*value),
+            };
+            (operand(&bind.lhs), bind.op, operand(&bind.rhs))
+        })
+        .collect::<Vec<_>>();
+    // Terms of the VALUES not in the graph bind no solution, but may equal an
+    // integer of a BIND.
+    let values = query.values.as_ref().map(|values| {
+        let var = position(&values.variable);
+        let ids = values
+            .terms
+            .iter()
+            .filter_map(|term| match term {
+                TermPattern::Variable(_) => None,
+                TermPattern::Iri(iri) => graph.term_id(&Term::Iri(iri.clone())),
+                TermPattern::Literal(literal) => graph.term_id(&Term::Literal(literal.clone())),
+            })
+            .collect::<HashSet<_>>();
+        let integers = values
+            .terms
+            .iter()
+            .filter_map(|term| match term {
+                TermPattern::Literal(literal) => literal.as_integer(),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        (var, ids, integers)
+    });
+    // Variables only in the NOT EXISTS block come last, unbound by any
+    // solution. A constant not in the graph matches nothing.
+    let not_exists = match query.not_exists.is_empty() {
+        true => None,
+        false => {
+            let mut variables = variables.clone();
+            for variable in query.not_exists.iter().flat_map(TriplePattern::variables) {
+                if !variables.contains(&variable) {
+                    variables.push(variable);
+                }
+            }
+            slots(&query.not_exists, &variables, graph).map(|slots| (slots, variables.len()))
+        }
+    };
+    let mut projected = HashSet::new();
+    evaluate_bgp(query, graph)
+        .filter_map(move |mut solution| {
+            for (lhs, op, rhs) in binds.iter() {
+                let [lhs, rhs] = [lhs, rhs].map(|operand| match operand {
+                    Ok(value) => Some(*value),
+                    Err(var) => var.and_then(|var| solution.integer(var, graph)),
+                });
+                let value = op.evaluate(lhs?, rhs?)?;
+                solution.binds.push(value);
+            }
+            Some(solution)
+        })
+        .filter(move |solution| {
+            filters.iter().all(|(filter, var)| {
+                let value = var.and_then(|var| solution.value(var, graph, filter.datatype));
+                value.map_or(false, |value| filter.op.evaluate(value, filter.value))
+            })
+        })
+        .filter(move |solution| {
+            conditions.iter().all(|(condition, vars)| {
+                let value = |variable: &Variable, datatype| {
+                    let (_, var) = vars.iter().find(|(v, _)| v == variable)?;
+                    var.and_then(|var| solution.value(var, graph, datatype))
+                };
+                condition.evaluate(&value) == Some(true)
+            })
+        })
+        .filter(move |solution| {
+            string_filters.iter().all(|(var, id)| match (var, id) {
+                (Some(var), Some(id)) if *var < solution.bindings.len() => {
+                    solution.bindings[*var] == Some(*id)
+                }
+                _ => false,
+            })
+        })
+        .filter(move |solution| {
+            let Some((slots, num_variables)) = &not_exists else {
+                return true;
+            };
+            let mut bindings = solution.bindings.clone();
+            bindings.resize(*num_variables, None);
+            Matches::new(graph, slots.clone(), bindings)
+                .next()
+                .is_none()
+        })
+        .filter(move |solution| {
+            values.iter().all(|(var, ids, integers)| match var {
+                Some(var) if *var < solution.bindings.len() => {
+                    solution.bindings[*var].map_or(false, |id| ids.contains(&id))
+                }
+                Some(var) => integers.contains(&solution.binds[*var - solution.bindings.len()]),
+                None => false,
+            })
+        })
+        .filter(move |solution| {
+            if !query.distinct || query.count.is_some() {
+                return true;
+            }
+            // Encodings rather than terms, since a BIND binds no term.
+            let bindings = projection
+                .iter()
+                .map(|var| var.and_then(|var| solution.encoding(var, graph)))
+                .map(|encoding| encoding.map(|encoding| encoding.to_repr()))
+                .collect::<Vec<_>>();
+            projected.insert(bindings)
+        })
+}
+
+/// Returns the subgraph of the triples of `graph` matched by a pattern of any
+/// solution of `query` by [`evaluate`], in order of [`RdfGraph::triples`].
+/// Without `FILTER NOT EXISTS`, which may match triples of `graph` not in the
+/// subgraph, `query` has the same solutions over the subgraph in the same
+/// order.
+pub fn matched_subgraph(query: &SelectQuery, graph: &RdfGraph) -> RdfGraph {
+    let matched = evaluate(query, graph)
+        .flat_map(|solution| {
+            let union = solution.union.into_iter().flat_map(|(_, triples)| triples);
+            let optional = solution.optional.into_iter().flatten();
+            solution.triples.into_iter().chain(union).chain(optional)
+        })
+        .collect::<BTreeSet<_>>();
+    let mut subgraph = RdfGraph::new();
+    for idx in matched {
+        let (triple, graph_name) = graph.quad(idx);
+        subgraph.insert_quad(triple, graph_name);
+    }
+    subgraph
+}
+
+/// Returns an order of the patterns of `query` for
+/// [`SelectQuery::with_pattern_order`] picking the most selective pattern
+/// first: the one with the most positions bound by a constant or a variable
+/// of the patterns before it, then the one with the fewest matches in `graph`
+/// on its own, then the first one written. Joining the selective patterns
+/// first keeps fewer partial matches alive in [`evaluate`].
+///
+/// Keeps the order written with a `LIMIT`, which takes the first solutions
+/// in the order the patterns give them.
+pub fn pattern_order(query: &SelectQuery, graph: &RdfGraph) -> Vec<usize> {
+    let written = (0..query.patterns.len()).collect::<Vec<_>>();
+    if query.limit.is_some() {
+        return written;
+    }
+    let variables = query.variables();
+    let num_matches = query
+        .patterns
+        .iter()
+        .map(
+            |pattern| match slots(std::slice::from_ref(pattern), &variables, graph) {
+                Some(slots) => Matches::new(graph, slots, vec![None; variables.len()]).count(),
+                None => 0,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let mut bound = HashSet::new();
+    let mut remaining = written;
+    let mut order = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let (position, _) = remaining
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, idx)| {
+                let num_bound = query.patterns[**idx]
+                    .graph_and_terms()
+                    .filter(|term| term.as_variable().map_or(true, |var| bound.contains(var)))
+                    .count();
+                (Reverse(num_bound), num_matches[**idx], **idx)
+            })
+            .unwrap();
+        let idx = remaining.remove(position);
+        bound.extend(query.patterns[idx].variables());
+        order.push(idx);
+    }
+    order
+}
+
+/// Yields the solutions of [`evaluate`] binding each variable of `binding` to
+/// its term, of a pattern or a `BIND`, whose triples are therefore in `graph`
+/// and whose filters hold. A variable of `binding` the query doesn't have
+/// binds no solution, and one the query has but `binding` lacks binds any
+/// term.
+pub fn evaluate_binding<'a>(
+    query: &'a SelectQuery,
+    graph: &'a RdfGraph,
+    binding: &'a Binding,
+) -> impl Iterator<Item = Solution> + 'a {
+    let variables = query.variables_with_binds();
+    let encodings = binding
+        .iter()
+        .map(|(variable, term)| {
+            let var = variables.iter().position(|v| *v == variable)?;
+            Some((var, term.encode()))
+        })
+        .collect::<Option<Vec<_>>>();
+    evaluate(query, graph).filter(move |solution| {
+        encodings.as_ref().is_some_and(|encodings| {
+            encodings
+                .iter()
+                .all(|(var, encoding)| solution.encoding(*var, graph) == Some(*encoding))
+        })
+    })
+}
+
+/// Evaluates the basic graph pattern of `query` against `graph`, yielding
+/// every solution in order of the matched triples of the first pattern, then
+/// the second, and so on. Each solution is extended by every match of each
+/// branch of the `UNION` in the same order, then by every match of the
+/// `OPTIONAL` block, or kept as is without any.
+pub fn evaluate_bgp<'a>(
+    query: &'a SelectQuery,
+    graph: &'a RdfGraph,
+) -> impl Iterator<Item = Solution> + 'a {
+    let variables = query.variables();
+    let num_variables = variables.len();
+    let patterns = slots(&query.patterns, &variables, graph);
+    let branches = query.union.as_ref().map(|union| {
+        union
+            .branches()
+            .into_iter()
+            .map(|branch| slots(branch, &variables, graph))
+            .collect::<Vec<_>>()
+    });
+    let optional = match query.optional.is_empty() {
+        true => None,
+        false => slots(&query.optional, &variables, graph),
+    };
+
+    patterns
+        .into_iter()
+        .flat_map(move |patterns| Matches::new(graph, patterns, vec![None; num_variables]))
+        .map(|(bindings, triples)| Solution {
+            bindings,
+            triples,
+            union: None,
+            optional: None,
+            binds: Vec::new(),
+        })
+        .flat_map(move |solution| {
+            let Some(branches) = &branches else {
+                return Either::Left(iter::once(solution));
+            };
+            let branches = branches.clone().into_iter().enumerate();
+            Either::Right(branches.flat_map(move |(branch, slots)| {
+                let solution = solution.clone();
+                let bindings = solution.bindings.clone();
+                slots
+                    .map(|slots| Matches::new(graph, slots, bindings))
+                    .into_iter()
+                    .flatten()
+                    .map(move |(bindings, triples)| Solution {
+                        bindings,
+                        union: Some((branch, triples)),
+                        ..solution.clone()
+                    })
+            }))
+        })
+        .flat_map(move |solution| {
+            let Some(slots) = &optional else {
+                return Either::Left(iter::once(solution));
+            };
+            let mut matches = Matches::new(graph, slots.clone(), solution.bindings.clone())
+                .map({
+                    let solution = solution.clone();
+                    move |(bindings, triples)| Solution {
+                        bindings,
+                        optional: Some(triples),
+                        ..solution.clone()
+                    }
+                })
+                .peekable();
+            match matches.peek().is_none() {
+                true => Either::Left(iter::once(solution)),
+                false => Either::Right(matches),
+            }
+        })
+}
+
+/// Returns the slots of `patterns`, or `None` if a constant isn't in `graph`
+/// so nothing matches.
+fn slots(
+    patterns: &[TriplePattern],
+    variables: &[&Variable],
+    graph: &RdfGraph,
+) -> Option<Vec<Slots>> {
+    let slot = |term: &TermPattern| match term {
+        TermPattern::Variable(variable) => Some(Slot::Variable(var_idx(variables, variable))),
+        TermPattern::Iri(iri) => Some(Slot::Constant(graph.term_id(&Term::Iri(iri.clone()))?)),
+        TermPattern::Literal(literal) => Some(Slot::Constant(
+            graph.term_id(&Term::Literal(literal.clone()))?,
+        )),
+    };
+    patterns
+        .iter()
+        .map(|pattern| {
+            let [subject, predicate, object] = pattern.terms().map(slot);
+            let graph = match &pattern.graph {
+                Some(term) => Some(slot(term)?),
+                None => None,
+            };
+            Some(Slots {
+                terms: [subject?, predicate?, object?],
+                graph,
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Slot {
+    Variable(usize),
+    Constant(TermId),
+}
+
+/// Slots of the terms of a pattern, and of its graph if in a `GRAPH` clause,
+/// which then only matches triples of a named graph, and otherwise only ones
+/// of the default graph.
+#[derive(Clone, Copy, Debug)]
+struct Slots {
+    terms: [Slot; 3],
+    graph: Option<Slot>,
+}
+
+fn var_idx(variables: &[&Variable], variable: &Variable) -> usize {
+    variables.iter().position(|v| *v == variable).unwrap()
+}
+
+/// Iterator over every match of `patterns` extending `bindings`, as the
+/// bindings and the matched triple of each pattern, searched depth first in
+/// order of [`RdfGraph::triples`].
+///
+/// Each pattern is only tried against the triples of [`RdfGraph::triples_with`]
+/// a term bound by a constant or an earlier pattern, which are in the same
+/// order, so a join through a variable looks up its matches rather than
+/// scanning the graph, and only the partial match of the current solution
+/// is held.
+struct Matches<'a> {
+    graph: &'a RdfGraph,
+    patterns: Vec<Slots>,
+    bindings: Vec<Option<TermId>>,
+    /// Candidates of each pattern matched so far, with the position in them
+    /// of the matched triple, its index and the variables it bound.
+    stack: Vec<(Candidates<'a>, usize, usize, Vec<usize>)>,
+    /// Candidates of the next pattern.
+    candidates: Candidates<'a>,
+    /// Position in `candidates` of the next triple to try.
+    next: usize,
+    done: bool,
+}
+
+/// Indices of the triples a pattern can match, all of them if `None`.
+type Candidates<'a> = Option<&'a [usize]>;
+
+impl<'a> Matches<'a> {
+    fn new(graph: &'a RdfGraph, patterns: Vec<Slots>, bindings: Vec<Option<TermId>>) -> Self {
+        let mut matches = Self {
+            graph,
+            patterns,
+            bindings,
+            stack: Vec::new(),
+            candidates: None,
+            next: 0,
+            done: false,
+        };
+        matches.candidates = matches.candidates();
+        matches
+    }
+
+    /// Returns the fewest triples of a term of the next pattern that is
+    /// bound, or `None` if none is.
+    fn candidates(&self) -> Candidates<'a> {
+        let pattern = self.patterns.get(self.stack.len())?;
+        pattern
+            .terms
+            .iter()
+            .enumerate()
+            .filter_map(|(position, slot)| {
+                let term = match slot {
+                    Slot::Constant(id) => *id,
+                    Slot::Variable(var) => self.bindings[*var]?,
+                };
+                Some(self.graph.triples_with(position, term))
+            })
+            .min_by_key(|candidates| candidates.len())
+    }
+
+    /// Binds the unbound variables of `pattern` to the triple at `idx`,
+    /// returning them if it matches, or leaving the bindings as they were
+    /// otherwise.
+    fn bind(&mut self, pattern: Slots, idx: usize) -> Option<Vec<usize>> {
+        let graph_name = self.graph.graph_name(idx);
+        let graph = match (pattern.graph, graph_name) {
+            (None, None) => None,
+            (Some(slot), Some(graph_name)) => Some((slot, graph_name)),
+            _ => return None,
+        };
+        let triple = self.graph.triples()[idx];
+        let mut bound = Vec::new();
+        for (slot, term) in graph
+            .into_iter()
+            .chain(pattern.terms.into_iter().zip(triple))
+        {
+            let matched = match slot {
+                Slot::Constant(id) => id == term,
+                Slot::Variable(var) => match self.bindings[var] {
+                    Some(id) => id == term,
+                    None => {
+                        self.bindings[var] = Some(term);
+                        bound.push(var);
+                        true
+                    }
+                },
+            };
+            if !matched {
+                self.unbind(bound);
+                return None;
+            }
+        }
+        Some(bound)
+    }
+
+    fn unbind(&mut self, bound: Vec<usize>) {
+        for var in bound {
+            self.bindings[var] = None;
+        }
+    }
+
+    /// Undoes the last matched pattern to try it against the following
+    /// triples, or ends the search if none is matched.
+    fn backtrack(&mut self) {
+        match self.stack.pop() {
+            Some((candidates, position, _, bound)) => {
+                self.unbind(bound);
+                self.candidates = candidates;
+                self.next = position + 1;
+            }
+            None => self.done = true,
+        }
+    }
+}
+
+impl Iterator for Matches<'_> {
+    type Item = (Vec<Option<TermId>>, Vec<usize>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let Some(pattern) = self.patterns.get(self.stack.len()).copied() else {
+                let triples = self.stack.iter().map(|(_, _, idx, _)| *idx).collect();
+                let bindings = self.bindings.clone();
+                self.backtrack();
+                return Some((bindings, triples));
+            };
+            let position = self.next;
+            let idx = match self.candidates {
+                Some(candidates) => candidates.get(position).copied(),
+                None => (position < self.graph.len()).then_some(position),
+            };
+            let Some(idx) = idx else {
+                self.backtrack();
+                continue;
+            };
+            self.next += 1;
+            if let Some(bound) = self.bind(pattern, idx) {
+                self.stack.push((self.candidates, position, idx, bound));
+                self.candidates = self.candidates();
+                self.next = 0;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        eval::{evaluate, evaluate_bgp},
+        parse_select,
+        query::Literal,
+        rdf::{parse_nquads, RdfGraph, Term, Triple},
+    };
+
+    #[test]
+    fn join() {
+        let [alice, bob, carol, knows, name] =
+            ["alice", "bob", "carol", "knows", "name"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(bob.clone(), knows.clone(), carol.clone()),
+            Triple::new(alice.clone(), knows.clone(), carol.clone()),
+            Triple::new(carol.clone(), name.clone(), Term::iri("Carol")),
+        ]);
+        let query = parse_select("SELECT ?a WHERE { ?a <knows> ?b . ?b <name> ?n }").unwrap();
+        let solutions = evaluate_bgp(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(
+            solutions
+                .iter()
+                .map(|solution| solution.triples.clone())
+                .collect::<Vec<_>>(),
+            [[1, 3], [2, 3]]
+        );
+        assert_eq!(graph.term(solutions[0].bindings[0].unwrap()), &bob);
+
+        let query = parse_select("SELECT ?a WHERE { ?a <unknown> ?b }").unwrap();
+        assert_eq!(evaluate_bgp(&query, &graph).next(), None);
+    }
+
+    #[test]
+    fn filter() {
+        let [alice, bob, carol, age] = ["alice", "bob", "carol", "age"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice, age.clone(), Term::Literal(Literal::integer(17))),
+            Triple::new(
+                bob.clone(),
+                age.clone(),
+                Term::Literal(Literal::integer(18)),
+            ),
+            Triple::new(carol, age, Term::Literal(Literal::string("18"))),
+        ]);
+        let query = parse_select("SELECT ?p { ?p <age> ?age FILTER(?age >= 18) }").unwrap();
+        let solutions = evaluate(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(graph.term(solutions[0].bindings[0].unwrap()), &bob);
+    }
+
+    #[test]
+    fn condition_error() {
+        let [alice, bob, age, score] = ["alice", "bob", "age", "score"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(
+                alice.clone(),
+                age.clone(),
+                Term::Literal(Literal::integer(17)),
+            ),
+            Triple::new(alice, score.clone(), Term::iri("high")),
+            Triple::new(bob.clone(), age, Term::Literal(Literal::integer(30))),
+            Triple::new(bob.clone(), score, Term::Literal(Literal::integer(9))),
+        ]);
+        // SPARQL absorbs the error of comparing alice's IRI score in
+        // `true || error`, but both operands have to be literals here.
+        let query =
+            parse_select("SELECT ?p { ?p <age> ?a . ?p <score> ?s FILTER(?a < 18 || ?s > 5) }")
+                .unwrap();
+        let solutions = evaluate(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(graph.term(solutions[0].bindings[0].unwrap()), &bob);
+    }
+
+    #[test]
+    fn distinct() {
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(alice.clone(), knows.clone(), carol.clone()),
+            Triple::new(bob.clone(), knows, carol),
+        ]);
+        let query = parse_select("SELECT ?a { ?a <knows> ?b }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 3);
+        let query = parse_select("SELECT DISTINCT ?a { ?a <knows> ?b }").unwrap();
+        let solutions = evaluate(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(
+            solutions
+                .iter()
+                .map(|solution| graph.term(solution.bindings[0].unwrap()))
+                .collect::<Vec<_>>(),
+            [&alice, &bob]
+        );
+        let query = parse_select("SELECT DISTINCT (COUNT(?b) AS ?n) { ?a <knows> ?b }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 3);
+    }
+
+    #[test]
+    fn values() {
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(alice, knows.clone(), carol.clone()),
+            Triple::new(bob, knows, carol.clone()),
+        ]);
+        let query =
+            parse_select("SELECT ?a ?b { ?a <knows> ?b VALUES ?b { <carol> <dave> } }").unwrap();
+        let solutions = evaluate(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(
+            solutions
+                .iter()
+                .map(|solution| solution.triples.clone())
+                .collect::<Vec<_>>(),
+            [[1], [2]]
+        );
+        assert!(solutions
+            .iter()
+            .all(|solution| graph.term(solution.bindings[1].unwrap()) == &carol));
+
+        let query = parse_select("SELECT ?a { ?a <knows> ?b } VALUES ?b { }").unwrap();
+        assert_eq!(evaluate(&query, &graph).next(), None);
+    }
+
+    #[test]
+    fn optional() {
+        let [alice, bob, carol, knows, name] =
+            ["alice", "bob", "carol", "knows", "name"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(alice.clone(), knows.clone(), carol.clone()),
+            Triple::new(carol.clone(), name.clone(), Term::iri("Carol")),
+            Triple::new(carol.clone(), name.clone(), Term::iri("Caz")),
+        ]);
+        let query =
+            parse_select("SELECT ?b ?n { ?a <knows> ?b OPTIONAL { ?b <name> ?n } }").unwrap();
+        let solutions = evaluate(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(
+            solutions
+                .iter()
+                .map(|solution| (solution.triples[0], solution.optional.clone()))
+                .collect::<Vec<_>>(),
+            [(0, None), (1, Some(vec![2])), (1, Some(vec![3]))]
+        );
+        assert_eq!(solutions[0].bindings[2], None);
+
+        let query =
+            parse_select("SELECT ?b { ?a <knows> ?b OPTIONAL { ?b <name> ?n } FILTER(?n > 0) }")
+                .unwrap();
+        assert_eq!(evaluate(&query, &graph).next(), None);
+        let query =
+            parse_select("SELECT ?b { ?a <knows> ?b OPTIONAL { ?b <unknown> ?n } }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 2);
+    }
+
+    #[test]
+    fn union() {
+        let [alice, bob, carol, knows, likes] =
+            ["alice", "bob", "carol", "knows", "likes"].map(Term::iri);
+        let graph = RdfGraph::from_triples([
+            Triple::new(alice.clone(), knows.clone(), bob.clone()),
+            Triple::new(alice.clone(), likes.clone(), bob.clone()),
+            Triple::new(bob.clone(), likes.clone(), carol.clone()),
+        ]);
+        let query = parse_select(
+            "SELECT ?a ?b ?c { { ?a <knows> ?b } UNION { ?a <likes> ?c } . ?a <knows> ?d }",
+        )
+        .unwrap();
+        let solutions = evaluate(&query, &graph).collect::<Vec<_>>();
+        assert_eq!(
+            solutions
+                .iter()
+                .map(|solution| solution.union.clone().unwrap())
+                .collect::<Vec<_>>(),
+            [(0, vec![0]), (1, vec![1])]
+        );
+        // Variable order is ?a ?d ?b ?c, with ?c unbound by the left branch.
+        assert_eq!(solutions[0].bindings[3], None);
+        assert_eq!(solutions[1].bindings[2], None);
+
+        let query =
+            parse_select("SELECT ?a { { ?a <likes> ?b } UNION { ?a <likes> ?b } }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 4);
+        let query =
+            parse_select("SELECT ?a { { ?a <unknown> ?b } UNION { ?a <knows> ?b } }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 1);
+    }
+
+    #[test]
+    fn graph() {
+        let graph = parse_nquads(
+            "<alice> <knows> <bob> .\n\
+             <alice> <knows> <carol> <g> .\n\
+             <bob> <knows> <carol> <h> .\n\
+             <carol> <name> \"Carol\" <g> .\n",
+        )
+        .unwrap();
+        let triples = |query: &str| {
+            let query = parse_select(query).unwrap();
+            evaluate(&query, &graph)
+                .map(|solution| solution.triples)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(triples("SELECT * { ?a <knows> ?b }"), [[0]]);
+        assert_eq!(
+            triples("SELECT * { GRAPH ?g { ?a <knows> ?b } }"),
+            [[1], [2]]
+        );
+        assert_eq!(triples("SELECT * { GRAPH <h> { ?a <knows> ?b } }"), [[2]]);
+        assert_eq!(
+            triples("SELECT * { GRAPH ?g { ?a <knows> ?b . ?b <name> ?n } }"),
+            [[1, 3]]
+        );
+        // Same variable binding the graph of both patterns.
+        assert_eq!(
+            triples("SELECT * { GRAPH ?g { ?a <knows> ?b } GRAPH ?g { ?c <name> ?n } }"),
+            [[1, 3]]
+        );
+        assert!(triples("SELECT * { GRAPH <unknown> { ?a <knows> ?b } }").is_empty());
+
+        let query = parse_select("SELECT ?g { GRAPH ?g { <bob> <knows> ?b } }").unwrap();
+        let solution = evaluate(&query, &graph).next().unwrap();
+        assert_eq!(graph.term(solution.bindings[0].unwrap()), &Term::iri("h"));
+    }
+
+    #[test]
+    fn not_exists() {
+        let graph = parse_nquads(
+            "<alice> <knows> <bob> .\n\
+             <bob> <knows> <alice> .\n\
+             <bob> <knows> <carol> .\n\
+             <carol> <name> \"Carol\" <g> .\n",
+        )
+        .unwrap();
+        let triples = |query: &str| {
+            let query = parse_select(query).unwrap();
+            evaluate(&query, &graph)
+                .map(|solution| solution.triples)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            triples("SELECT * { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a } }"),
+            [[2]]
+        );
+        // A variable only in the block matches any term.
+        assert_eq!(
+            triples("SELECT * { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?c } }"),
+            [[2]]
+        );
+        // Only the named graph has the name.
+        assert_eq!(
+            triples("SELECT * { ?a <knows> ?b FILTER NOT EXISTS { ?b <name> ?n } }").len(),
+            3
+        );
+        assert_eq!(
+            triples("SELECT * { ?a <knows> ?b FILTER NOT EXISTS { GRAPH ?g { ?b <name> ?n } } }"),
+            [[0], [1]]
+        );
+        // A constant not in the graph matches nothing.
+        assert_eq!(
+            triples("SELECT * { ?a <knows> ?b FILTER NOT EXISTS { ?b <likes> ?a } }").len(),
+            3
+        );
+    }
+
+    #[test]
+    fn bind() {
+        let graph = parse_nquads(
+            "<alice> <age> \"30\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n\
+             <bob> <age> \"4611686018427387904\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n\
+             <carol> <age> \"17\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n\
+             <dave> <age> \"old\" .\n",
+        )
+        .unwrap();
+        let binds = |query: &str| {
+            let query = parse_select(query).unwrap();
+            evaluate(&query, &graph)
+                .map(|solution| solution.binds)
+                .collect::<Vec<_>>()
+        };
+        // Bob overflows and Dave isn't an integer.
+        assert_eq!(
+            binds("SELECT * { ?a <age> ?n BIND(?n * 2 AS ?m) BIND(?m - ?n AS ?k) }"),
+            [[60, 30], [34, 17]]
+        );
+        assert_eq!(
+            binds("SELECT ?a (?n * 2 AS ?m) (?m - ?n AS ?k) { ?a <age> ?n }"),
+            [[60, 30], [34, 17]]
+        );
+        assert_eq!(
+            binds("SELECT * { ?a <age> ?n BIND(?n + 1 AS ?m) FILTER(?m >= 18) VALUES ?m { 31 } }"),
+            [[31]]
+        );
+        let query = parse_select("SELECT DISTINCT ?m { ?a <age> ?n BIND(?n - ?n AS ?m) }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 1);
+    }
+
+    #[test]
+    fn index_join() {
+        // Edges of three predicates between 8 nodes, from a fixed LCG.
+        let mut state = 7u64;
+        let mut next = |n: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % n
+        };
+        let [p, q, r] = ["p", "q", "r"].map(Term::iri);
+        let graph = RdfGraph::from_triples((0..60).map(|_| {
+            let [subject, predicate, object] = [next(8), next(3), next(8)];
+            Triple::new(
+                Term::iri(format!("n{subject}")),
+                [&p, &q, &r][predicate as usize].clone(),
+                Term::iri(format!("n{object}")),
+            )
+        }));
+        let triples = (0..graph.len())
+            .map(|idx| graph.triple(idx))
+            .collect::<Vec<_>>();
+
+        // Every combination of triples in order, joined on the shared terms.
+        let eager = |patterns: &[[Option<&Term>; 3]; 3]| {
+            let mut matches = Vec::new();
+            for (i, j, k) in (0..triples.len())
+                .flat_map(|i| (0..triples.len()).map(move |j| (i, j)))
+                .flat_map(|(i, j)| (0..triples.len()).map(move |k| (i, j, k)))
+            {
+                let [a, b, c] = [i, j, k].map(|idx| &triples[idx]);
+                let constants = patterns.iter().zip([a, b, c]).all(|(pattern, triple)| {
+                    pattern
+                        .iter()
+                        .zip([&triple.subject, &triple.predicate, &triple.object])
+                        .all(|(constant, term)| constant.map_or(true, |constant| constant == term))
+                });
+                if constants
+                    && a.object == b.subject
+                    && b.object == c.subject
+                    && c.object == a.subject
+                {
+                    matches.push(vec![i, j, k]);
+                }
+            }
+            matches
+        };
+        let lazy = |query: &str| {
+            let query = parse_select(query).unwrap();
+            evaluate_bgp(&query, &graph)
+                .map(|solution| solution.triples)
+                .collect::<Vec<_>>()
+        };
+        let n0 = Term::iri("n0");
+        for (query, patterns) in [
+            (
+                "SELECT * { ?a ?x ?b . ?b ?y ?c . ?c ?z ?a }",
+                [[None; 3]; 3],
+            ),
+            (
+                "SELECT * { ?a <p> ?b . ?b <q> ?c . ?c ?z ?a }",
+                [[None, Some(&p), None], [None, Some(&q), None], [None; 3]],
+            ),
+            (
+                "SELECT * { <n0> ?x ?b . ?b ?y ?c . ?c <r> <n0> }",
+                [
+                    [Some(&n0), None, None],
+                    [None; 3],
+                    [None, Some(&r), Some(&n0)],
+                ],
+            ),
+        ] {
+            let eager = eager(&patterns);
+            assert!(!eager.is_empty());
+            assert_eq!(lazy(query), eager);
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! Poseidon-style permutation over [`Fr`] with width [`WIDTH`], shared by the
+//! native hashing here and the constraints in [`crate::circuit`], so that
+//! digests computed off-circuit agree with the ones computed in-circuit.
+//!
+//! The permutation follows the Poseidon structure with `x^5` S-boxes,
+//! [`NUM_FULL_ROUNDS`] full and [`NUM_PARTIAL_ROUNDS`] partial rounds and a
+//! Cauchy MDS matrix, but its round constants are derived from Keccak256 rather
+//! than the Grain LFSR of the reference instantiation, so digests don't match
+//! other Poseidon implementations.
+
+use plonkish_backend::{
+    halo2_curves::bn256::Fr,
+    util::{
+        arithmetic::{Field, FromUniformBytes, PrimeField},
+        chain,
+        hash::{Hash, Keccak256},
+    },
+};
+use std::{
+    array,
+    fmt::{self, Display},
+    sync::OnceLock,
+};
+
+pub const WIDTH: usize = 4;
+pub const NUM_FULL_ROUNDS: usize = 8;
+pub const NUM_PARTIAL_ROUNDS: usize = 56;
+pub const NUM_ROUNDS: usize = NUM_FULL_ROUNDS + NUM_PARTIAL_ROUNDS;
+
+/// Domain separating the uses of the permutation, absorbed into the last
+/// element of the state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Domain {
+    /// Leaf of a triple `(s, p, o)`.
+    Triple,
+    /// Internal node of a binary Merkle tree.
+    Node,
+    /// Triple pattern of a query, variables encoded by [`variable_encoding`].
+    Pattern,
+    /// Integer literal `v` as `(v, 0, 0)`, see [`integer_encoding`].
+    Integer,
+    /// Filter of a query as `(variable, op, value)`, with `op` offset by 16
+    /// for one of `xsd:dateTime` literals.
+    Filter,
+    /// Aggregate of a query as `(variable, 0, 0)`.
+    Aggregate,
+    /// Triple pattern of the `OPTIONAL` block of a query, as [`Self::Pattern`].
+    Optional,
+    /// Triple pattern of a `UNION` branch of a query as [`Self::Pattern`],
+    /// with each branch led by `(branch, num_patterns, 0)`.
+    Union,
+    /// Modifier `DISTINCT` of a query as `(0, 0, 0)`.
+    Distinct,
+    /// Term allowed by the `VALUES` of a query as `(term, 0, 0)`, led by
+    /// `(variable, num_terms, 0)`.
+    Values,
+    /// Condition of the `ORDER BY` of a query as `(variable, descending, 0)`.
+    Order,
+    /// Number `n` of results of the `LIMIT` of a query as `(n, 0, 0)`.
+    Limit,
+    /// Leaf of a triple in a named graph `g` as `(leaf, g, 0)`, with `leaf`
+    /// the [`Self::Triple`] leaf of the triple.
+    Quad,
+    /// Graph of a triple pattern of a query in a `GRAPH` clause as
+    /// `(pattern, graph, 0)`, with `pattern` its index in the query.
+    Graph,
+    /// Triple pattern of the `FILTER NOT EXISTS` block of a query, as
+    /// [`Self::Pattern`].
+    NotExists,
+    /// `BIND` of a query as `(variable, op, 0)` then `(lhs, rhs, 0)`, with
+    /// each integer operand by [`integer_encoding`].
+    Bind,
+    /// `xsd:string` literal as its [`string_limbs`], see [`string_encoding`].
+    String,
+    /// String filter of a query as `(variable, value, 0)`, with `value` the
+    /// encoding of the string literal.
+    StringFilter,
+    /// Variable of the `GROUP BY` of a query as `(variable, 0, 0)`.
+    Group,
+    /// Node of a boolean filter condition of a query in postfix order, a
+    /// comparison as [`Self::Filter`] and a `!`, `&&` or `||` as `(0, n, 0)`
+    /// with `n` of `6`, `7` or `8`, past the discriminants of the operators.
+    Condition,
+    /// `xsd:dateTime` literal of `s` seconds since the Unix epoch as
+    /// `(s, 0, 0)`, see [`date_time_encoding`].
+    DateTime,
+}
+
+impl Domain {
+    pub fn tag(&self) -> Fr {
+        Fr::from(*self as u64 + 1)
+    }
+}
+
+pub struct Constants {
+    pub round_constants: [[Fr; WIDTH]; NUM_ROUNDS],
+    pub mds: [[Fr; WIDTH]; WIDTH],
+}
+
+pub fn constants() -> &'static Constants {
+    static CONSTANTS: OnceLock<Constants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let round_constants = array::from_fn(|round| {
+            array::from_fn(|idx| {
+                let seed = [round as u32, idx as u32].map(u32::to_le_bytes).concat();
+                hash_to_field(b"sparql-poseidon-round-constant", &seed)
+            })
+        });
+        let mds = array::from_fn(|row| {
+            array::from_fn(|col| Fr::from((row + WIDTH + col) as u64).invert().unwrap())
+        });
+        Constants {
+            round_constants,
+            mds,
+        }
+    })
+}
+
+pub fn is_full_round(round: usize) -> bool {
+    !(NUM_FULL_ROUNDS / 2..NUM_FULL_ROUNDS / 2 + NUM_PARTIAL_ROUNDS).contains(&round)
+}
+
+/// Applies round `round` to `state`, which is what a single row of
+/// permutation constraints checks against the state of the next row.
+pub fn round(state: [Fr; WIDTH], round: usize) -> [Fr; WIDTH] {
+    let Constants {
+        round_constants,
+        mds,
+    } = constants();
+    let sboxed: [Fr; WIDTH] = array::from_fn(|idx| {
+        let value = state[idx] + round_constants[round][idx];
+        if idx == 0 || is_full_round(round) {
+            value.square().square() * value
+        } else {
+            value
+        }
+    });
+    array::from_fn(|row| {
+        mds[row]
+            .iter()
+            .zip(sboxed.iter())
+            .map(|(m, value)| *m * value)
+            .sum()
+    })
+}
+
+pub fn permute(mut state: [Fr; WIDTH]) -> [Fr; WIDTH] {
+    for idx in 0..NUM_ROUNDS {
+        state = round(state, idx);
+    }
+    state
+}
+
+/// Hashes 3 elements under `domain`.
+pub fn hash3(inputs: [Fr; 3], domain: Domain) -> Fr {
+    let [a, b, c] = inputs;
+    permute([a, b, c, domain.tag()])[0]
+}
+
+/// Hashes a Merkle tree node from its `left` and `right` children.
+pub fn hash_node(left: Fr, right: Fr) -> Fr {
+    permute([left, right, Fr::ZERO, Domain::Node.tag()])[0]
+}
+
+/// Hash of the leaves and nodes of a [`MerkleTripleSet`], which the circuit
+/// has to constrain as well for its root to match the in-circuit one.
+///
+/// [`MerkleTripleSet`]: crate::merkle::MerkleTripleSet
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HashChoice {
+    /// The permutation of this module, by [`hash3`] and [`hash_node`], which
+    /// costs a row per round in-circuit.
+    #[default]
+    Poseidon,
+    /// Keccak256 of the inputs and the domain tag reduced by
+    /// [`hash_to_field`], which is cheap natively but isn't constrained
+    /// in-circuit yet.
+    Keccak256,
+}
+
+impl HashChoice {
+    /// Hashes 3 elements under `domain`.
+    pub fn hash3(&self, inputs: [Fr; 3], domain: Domain) -> Fr {
+        match self {
+            HashChoice::Poseidon => hash3(inputs, domain),
+            HashChoice::Keccak256 => {
+                let data = chain![inputs, [domain.tag()]]
+                    .flat_map(|value| value.to_repr())
+                    .collect::<Vec<_>>();
+                hash_to_field(b"sparql-keccak256-hash3", &data)
+            }
+        }
+    }
+
+    /// Hashes a Merkle tree node from its `left` and `right` children.
+    pub fn hash_node(&self, left: Fr, right: Fr) -> Fr {
+        self.hash3([left, right, Fr::ZERO], Domain::Node)
+    }
+
+    /// Returns whether the circuit constrains the hash, which only
+    /// [`Self::Poseidon`] is so far.
+    pub fn is_supported_in_circuit(&self) -> bool {
+        matches!(self, HashChoice::Poseidon)
+    }
+}
+
+impl Display for HashChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HashChoice::Poseidon => write!(f, "Poseidon"),
+            HashChoice::Keccak256 => write!(f, "Keccak256"),
+        }
+    }
+}
+
+/// Returns the encoding of the query variable at `idx` in a triple pattern,
+/// which is kept small to never collide with the hashed term encodings.
+pub fn variable_encoding(idx: usize) -> Fr {
+    -Fr::from(idx as u64 + 1)
+}
+
+/// Returns `value` as field element, with negative values wrapping around
+/// the modulus.
+pub fn i64_to_field(value: i64) -> Fr {
+    match value.is_negative() {
+        true => -Fr::from(value.unsigned_abs()),
+        false => Fr::from(value as u64),
+    }
+}
+
+/// Returns the encoding of the integer literal of `value`, which the circuit
+/// can open to compare `value` in filters.
+pub fn integer_encoding(value: i64) -> Fr {
+    hash3([i64_to_field(value), Fr::ZERO, Fr::ZERO], Domain::Integer)
+}
+
+/// Returns the encoding of the `xsd:dateTime` literal of `seconds` since the
+/// Unix epoch, which the circuit can open to compare `seconds` in filters as
+/// it does an integer by [`integer_encoding`].
+pub fn date_time_encoding(seconds: i64) -> Fr {
+    hash3(
+        [i64_to_field(seconds), Fr::ZERO, Fr::ZERO],
+        Domain::DateTime,
+    )
+}
+
+/// Maximum length in bytes of the lexical form of an `xsd:string` literal
+/// encoded by [`string_encoding`], packed into [`STRING_LIMB_BYTES`] bytes
+/// per limb.
+pub const MAX_STRING_LEN: usize = 2 * STRING_LIMB_BYTES;
+
+/// Number of bytes packed into a limb of [`string_limbs`], which keeps it
+/// below the modulus.
+pub const STRING_LIMB_BYTES: usize = 31;
+
+/// Returns the length in bytes of `lexical` followed by its bytes packed
+/// little-endian into two limbs of [`STRING_LIMB_BYTES`] bytes padded with
+/// zeros, or `None` if it's longer than [`MAX_STRING_LEN`]. The length tells
+/// trailing zero bytes apart from the padding.
+pub fn string_limbs(lexical: &str) -> Option<[Fr; 3]> {
+    let bytes = lexical.as_bytes();
+    if bytes.len() > MAX_STRING_LEN {
+        return None;
+    }
+    let limb = |idx: usize| {
+        let mut repr = [0; 32];
+        let chunk = bytes.chunks(STRING_LIMB_BYTES).nth(idx).unwrap_or_default();
+        repr[..chunk.len()].copy_from_slice(chunk);
+        Fr::from_repr(repr).unwrap()
+    };
+    Some([Fr::from(bytes.len() as u64), limb(0), limb(1)])
+}
+
+/// Returns the encoding of the `xsd:string` literal of `lexical` as the hash
+/// of its [`string_limbs`], which the circuit can open to compare the limbs
+/// with the ones of a constant, or `None` if it's longer than
+/// [`MAX_STRING_LEN`].
+pub fn string_encoding(lexical: &str) -> Option<Fr> {
+    Some(hash3(string_limbs(lexical)?, Domain::String))
+}
+
+/// Maps `data` to a uniformly distributed field element by reducing 64 bytes
+/// of Keccak256 output under `domain`.
+pub fn hash_to_field(domain: &[u8], data: &[u8]) -> Fr {
+    let mut bytes = [0; 64];
+    for (counter, chunk) in bytes.chunks_mut(32).enumerate() {
+        let input = [domain, &[counter as u8], data].concat();
+        chunk.copy_from_slice(&Keccak256::digest(input));
+    }
+    Fr::from_uniform_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hash::{constants, hash3, hash_node, permute, Domain, HashChoice, WIDTH};
+    use plonkish_backend::{halo2_curves::bn256::Fr, util::arithmetic::Field};
+
+    #[test]
+    fn mds_is_invertible() {
+        let mds = constants().mds;
+        let det = |m: &[[Fr; WIDTH]; WIDTH]| {
+            let mut m = *m;
+            let mut det = Fr::ONE;
+            for col in 0..WIDTH {
+                let pivot = (col..WIDTH).find(|row| m[*row][col] != Fr::ZERO);
+                let Some(pivot) = pivot else { return Fr::ZERO };
+                if pivot != col {
+                    m.swap(pivot, col);
+                    det = -det;
+                }
+                det *= m[col][col];
+                let inv = m[col][col].invert().unwrap();
+                for row in col + 1..WIDTH {
+                    let factor = m[row][col] * inv;
+                    for idx in col..WIDTH {
+                        let value = m[col][idx];
+                        m[row][idx] -= factor * value;
+                    }
+                }
+            }
+            det
+        };
+        assert_ne!(det(&mds), Fr::ZERO);
+    }
+
+    #[test]
+    fn domain_separation() {
+        let inputs = [Fr::from(1), Fr::from(2), Fr::ZERO];
+        assert_ne!(
+            hash3(inputs, Domain::Triple),
+            hash3(inputs, Domain::Pattern)
+        );
+        assert_ne!(
+            hash3(inputs, Domain::Node),
+            hash_node(inputs[0], inputs[1] + Fr::ONE)
+        );
+        assert_eq!(hash3(inputs, Domain::Node), hash_node(inputs[0], inputs[1]));
+        assert_ne!(permute([Fr::ZERO; WIDTH]), [Fr::ZERO; WIDTH]);
+    }
+
+    #[test]
+    fn hash_choice() {
+        let inputs = [Fr::from(1), Fr::from(2), Fr::ZERO];
+        let poseidon = HashChoice::Poseidon;
+        assert_eq!(
+            poseidon.hash3(inputs, Domain::Triple),
+            hash3(inputs, Domain::Triple)
+        );
+        assert_eq!(
+            poseidon.hash_node(inputs[0], inputs[1]),
+            hash_node(inputs[0], inputs[1])
+        );
+
+        let keccak = HashChoice::Keccak256;
+        assert_ne!(
+            keccak.hash3(inputs, Domain::Triple),
+            hash3(inputs, Domain::Triple)
+        );
+        assert_ne!(
+            keccak.hash3(inputs, Domain::Triple),
+            keccak.hash3(inputs, Domain::Pattern)
+        );
+        assert_eq!(
+            keccak.hash3(inputs, Domain::Node),
+            keccak.hash_node(inputs[0], inputs[1])
+        );
+        assert!(poseidon.is_supported_in_circuit() && !keccak.is_supported_in_circuit());
+    }
+}
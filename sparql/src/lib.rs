@@ -0,0 +1,21 @@
+pub mod circuit;
+pub mod eval;
+pub mod hash;
+pub mod merkle;
+pub mod query;
+pub mod rdf;
+pub mod term_encoding;
+
+pub use circuit::{
+    check_satisfied, compile_bgp, compile_bgp_over_subset, compile_bgp_with_hash,
+    compile_bgp_with_solutions, compile_bgp_with_strategy, compile_bgp_with_value_bits,
+    compile_bgp_within_k, compile_to_k, decode_instances, estimate_circuit_size, format_instances,
+    load_circuit, query_commitment, results_to_bindings, save_circuit, triple_capacity,
+    CircuitCache, CircuitFileError, CircuitKey, CircuitSizeEstimate, CompileError,
+    ConstraintViolation, MatchStrategy, QueryCircuit, QueryCircuitShape, QueryPublicInputs,
+    TermDictionary, Witness, WitnessError,
+};
+pub use hash::HashChoice;
+pub use merkle::{CapacityExceeded, MerklePath, MerkleTripleSet, NonMembershipProof, SubsetProof};
+pub use query::{parse_select, ParseError, ParseErrorKind, SelectQuery};
+pub use rdf::{RdfGraph, Term, Triple};
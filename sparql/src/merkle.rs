@@ -0,0 +1,695 @@
+use crate::{
+    hash::{Domain, HashChoice},
+    rdf::{RdfGraph, Term, Triple},
+};
+use plonkish_backend::{
+    halo2_curves::bn256::Fr,
+    util::{
+        arithmetic::{Field, PrimeField},
+        Itertools,
+    },
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+/// Leaf padding a [`MerkleTripleSet`], the maximum `-1` of the field, which no
+/// leaf hits but with negligible probability, so padding sorts last.
+pub const PADDING_LEAF: Fr = Fr::ONE.neg();
+
+/// Binary Merkle tree over the leaves of a set of triples, where the leaf of
+/// `(s, p, o)` is [`hash3`] of the [`Term::encode`] of each term under
+/// [`Domain::Triple`], and nodes are [`hash_node`] of their children, or the
+/// ones of another [`HashChoice`] by [`Self::new_with_hash`].
+///
+/// A triple in a named graph `g` is a quad, whose leaf hashes the leaf of the
+/// triple with the encoding of `g` under [`Domain::Quad`], so a set of triples
+/// only in the default graph has the same root as before named graphs.
+///
+/// Leaves are sorted as integers below the modulus and deduplicated so the
+/// root only depends on the set of triples, then padded with
+/// [`PADDING_LEAF`] to a power of two, or to `2^depth` leaves by
+/// [`Self::with_depth`]. Every leaf is then below the next but padding, so
+/// the leaves at two adjacent indices bound a gap without any leaf, which
+/// [`Self::prove_non_membership`] opens for a leaf in it.
+///
+/// [`Term::encode`]: crate::rdf::Term::encode
+/// [`hash3`]: crate::hash::hash3
+/// [`hash_node`]: crate::hash::hash_node
+#[derive(Clone, Debug)]
+pub struct MerkleTripleSet {
+    layers: Vec<Vec<Fr>>,
+    leaf_idx: HashMap<[u8; 32], usize>,
+    hash: HashChoice,
+}
+
+impl MerkleTripleSet {
+    pub fn new(triples: impl IntoIterator<Item = Triple>) -> Self {
+        Self::new_with_hash(triples, HashChoice::Poseidon)
+    }
+
+    /// Returns the set of `triples` as [`Self::new`], but hashed with `hash`.
+    /// The circuit only constrains the hashes that are
+    /// [`HashChoice::is_supported_in_circuit`], so the root of another is only
+    /// useful off-circuit, and [`compile_bgp_with_hash`] rejects it.
+    ///
+    /// [`compile_bgp_with_hash`]: crate::circuit::compile_bgp_with_hash
+    pub fn new_with_hash(triples: impl IntoIterator<Item = Triple>, hash: HashChoice) -> Self {
+        Self::from_quads_with_hash(triples.into_iter().map(|triple| (triple, None)), hash)
+    }
+
+    /// Returns the set of `quads`, each a triple with the name of its graph,
+    /// or `None` for the default graph.
+    pub fn from_quads_with_hash(
+        quads: impl IntoIterator<Item = (Triple, Option<Term>)>,
+        hash: HashChoice,
+    ) -> Self {
+        let leaves = quads
+            .into_iter()
+            .map(|(triple, graph_name)| {
+                Self::quad_leaf_with_hash(&triple, graph_name.as_ref(), hash)
+            })
+            .sorted_by_key(integer_order_key)
+            .dedup()
+            .collect_vec();
+        let depth = leaves.len().next_power_of_two().trailing_zeros() as usize;
+        Self::from_leaves(leaves, depth, hash)
+    }
+
+    /// Returns the set of `triples` as [`Self::new`], or an error before any
+    /// hashing if it has more than `max` distinct triples, e.g. the
+    /// [`triple_capacity`] of a circuit.
+    ///
+    /// [`triple_capacity`]: crate::circuit::triple_capacity
+    pub fn with_capacity(
+        triples: impl IntoIterator<Item = Triple>,
+        max: usize,
+    ) -> Result<Self, CapacityExceeded> {
+        let triples = triples.into_iter().unique().collect_vec();
+        if triples.len() > max {
+            return Err(CapacityExceeded {
+                have: triples.len(),
+                max,
+            });
+        }
+        Ok(Self::new(triples))
+    }
+
+    fn from_leaves(leaves: Vec<Fr>, depth: usize, hash: HashChoice) -> Self {
+        let leaf_idx = leaves
+            .iter()
+            .enumerate()
+            .map(|(idx, leaf)| (leaf.to_repr(), idx))
+            .collect();
+
+        let mut layer = leaves;
+        layer.resize(1 << depth, PADDING_LEAF);
+        let mut layers = vec![layer];
+        while layers.last().unwrap().len() > 1 {
+            let layer = layers
+                .last()
+                .unwrap()
+                .iter()
+                .tuples()
+                .map(|(left, right)| hash.hash_node(*left, *right))
+                .collect();
+            layers.push(layer);
+        }
+        Self {
+            layers,
+            leaf_idx,
+            hash,
+        }
+    }
+
+    pub fn from_graph(graph: &RdfGraph) -> Self {
+        Self::from_graph_with_hash(graph, HashChoice::Poseidon)
+    }
+
+    /// Returns the set of the triples of `graph` with the names of their
+    /// graphs as [`Self::from_quads_with_hash`].
+    pub fn from_graph_with_hash(graph: &RdfGraph, hash: HashChoice) -> Self {
+        Self::from_quads_with_hash((0..graph.len()).map(|idx| graph.quad(idx)), hash)
+    }
+
+    /// Returns the set padded to `2^depth` leaves, or `None` if it has more
+    /// leaves than that.
+    pub fn with_depth(self, depth: usize) -> Option<Self> {
+        if depth < self.depth() {
+            return None;
+        }
+        let mut leaves = self.layers.into_iter().next().unwrap();
+        leaves.truncate(self.leaf_idx.len());
+        Some(Self::from_leaves(leaves, depth, self.hash))
+    }
+
+    /// Adds `triple` in the default graph to the set, returning the new root.
+    /// See [`Self::insert_quad`].
+    pub fn insert(&mut self, triple: &Triple) -> Fr {
+        self.insert_quad(triple, None)
+    }
+
+    /// Adds `triple` in the graph named `graph_name` to the set, returning
+    /// the new root, which is the root of the set rebuilt from its triples
+    /// and padded to the same depth by [`Self::with_depth`].
+    ///
+    /// The leaves stay sorted, so the ones above the new leaf shift by one
+    /// and only the nodes over them are rehashed, unless the set is full and
+    /// is rebuilt one level deeper. The depth never shrinks, so a circuit
+    /// shape of that depth keeps fitting the set as it changes.
+    pub fn insert_quad(&mut self, triple: &Triple, graph_name: Option<&Term>) -> Fr {
+        let leaf = Self::quad_leaf_with_hash(triple, graph_name, self.hash);
+        if self.leaf_idx.contains_key(&leaf.to_repr()) {
+            return self.root();
+        }
+        let num_leaves = self.leaf_idx.len();
+        let key = integer_order_key(&leaf);
+        let idx = self
+            .leaves()
+            .partition_point(|leaf| integer_order_key(leaf) < key);
+        let leaves = &mut self.layers[0];
+        if num_leaves == leaves.len() {
+            let mut leaves = leaves.clone();
+            leaves.insert(idx, leaf);
+            *self = Self::from_leaves(leaves, self.depth() + 1, self.hash);
+            return self.root();
+        }
+        leaves.insert(idx, leaf);
+        leaves.pop();
+        self.rehash(idx, num_leaves + 1)
+    }
+
+    /// Removes `triple` in the default graph from the set, returning the new
+    /// root. See [`Self::remove_quad`].
+    pub fn remove(&mut self, triple: &Triple) -> Fr {
+        self.remove_quad(triple, None)
+    }
+
+    /// Removes `triple` in the graph named `graph_name` from the set if it's
+    /// in it, returning the new root as [`Self::insert_quad`], whose leaves
+    /// above it shift down by one and are rehashed.
+    pub fn remove_quad(&mut self, triple: &Triple, graph_name: Option<&Term>) -> Fr {
+        let leaf = Self::quad_leaf_with_hash(triple, graph_name, self.hash);
+        let Some(idx) = self.leaf_idx.remove(&leaf.to_repr()) else {
+            return self.root();
+        };
+        let num_leaves = self.leaf_idx.len() + 1;
+        let leaves = &mut self.layers[0];
+        leaves.remove(idx);
+        leaves.push(PADDING_LEAF);
+        self.rehash(idx, num_leaves)
+    }
+
+    /// Reindexes the leaves from `start` and rehashes the nodes over the
+    /// leaves from `start` to `end`, the ones past either being unchanged,
+    /// returning the new root.
+    fn rehash(&mut self, start: usize, end: usize) -> Fr {
+        for (idx, leaf) in self.layers[0].iter().enumerate().take(end).skip(start) {
+            if *leaf != PADDING_LEAF {
+                self.leaf_idx.insert(leaf.to_repr(), idx);
+            }
+        }
+        let (mut start, mut end) = (start, end);
+        for level in 1..self.layers.len() {
+            (start, end) = (start / 2, end.div_ceil(2));
+            for idx in start..end {
+                let children = &self.layers[level - 1];
+                let node = self
+                    .hash
+                    .hash_node(children[2 * idx], children[2 * idx + 1]);
+                self.layers[level][idx] = node;
+            }
+        }
+        self.root()
+    }
+
+    pub fn leaf(triple: &Triple) -> Fr {
+        Self::leaf_with_hash(triple, HashChoice::Poseidon)
+    }
+
+    /// Returns the leaf of `triple` as [`Self::leaf`], but hashed with `hash`.
+    pub fn leaf_with_hash(triple: &Triple, hash: HashChoice) -> Fr {
+        let Triple {
+            subject,
+            predicate,
+            object,
+        } = triple;
+        hash.hash3(
+            [subject, predicate, object].map(|term| term.encode()),
+            Domain::Triple,
+        )
+    }
+
+    pub fn quad_leaf(triple: &Triple, graph_name: Option<&Term>) -> Fr {
+        Self::quad_leaf_with_hash(triple, graph_name, HashChoice::Poseidon)
+    }
+
+    /// Returns the leaf of `triple` in the graph named `graph_name`, which is
+    /// [`Self::leaf_with_hash`] in the default graph.
+    pub fn quad_leaf_with_hash(triple: &Triple, graph_name: Option<&Term>, hash: HashChoice) -> Fr {
+        let leaf = Self::leaf_with_hash(triple, hash);
+        match graph_name {
+            Some(graph_name) => hash.hash3([leaf, graph_name.encode(), Fr::ZERO], Domain::Quad),
+            None => leaf,
+        }
+    }
+
+    pub fn root(&self) -> Fr {
+        self.layers.last().unwrap()[0]
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub fn hash(&self) -> HashChoice {
+        self.hash
+    }
+
+    /// Returns the leaves of the set in order, without padding.
+    pub fn leaves(&self) -> &[Fr] {
+        &self.layers[0][..self.leaf_idx.len()]
+    }
+
+    /// Returns the `2^depth` leaves of the tree in order, with padding.
+    pub(crate) fn padded_leaves(&self) -> &[Fr] {
+        &self.layers[0]
+    }
+
+    /// Returns the path from the leaf of `triple` in the default graph to the
+    /// root, or `None` if `triple` isn't in the set.
+    pub fn prove_membership(&self, triple: &Triple) -> Option<MerklePath> {
+        self.prove_quad_membership(triple, None)
+    }
+
+    /// Returns the path from the leaf of `triple` in the graph named
+    /// `graph_name` to the root, or `None` if it isn't in the set.
+    pub fn prove_quad_membership(
+        &self,
+        triple: &Triple,
+        graph_name: Option<&Term>,
+    ) -> Option<MerklePath> {
+        let leaf = Self::quad_leaf_with_hash(triple, graph_name, self.hash);
+        let idx = *self.leaf_idx.get(&leaf.to_repr())?;
+        Some(self.path(idx))
+    }
+
+    /// Returns the leaves at the adjacent indices around `leaf` with their
+    /// paths, or `None` if `leaf` is in the set or [`PADDING_LEAF`].
+    pub fn prove_non_membership(&self, leaf: Fr) -> Option<NonMembershipProof> {
+        if leaf == PADDING_LEAF || self.leaf_idx.contains_key(&leaf.to_repr()) {
+            return None;
+        }
+        let leaves = &self.layers[0];
+        let key = integer_order_key(&leaf);
+        let idx = leaves.partition_point(|leaf| integer_order_key(leaf) < key);
+        let neighbor = |idx: usize| (leaves[idx], self.path(idx));
+        Some(NonMembershipProof {
+            lo: idx.checked_sub(1).map(neighbor),
+            hi: (idx < leaves.len()).then(|| neighbor(idx)),
+        })
+    }
+
+    /// Returns the path of each leaf of `subset` in this set, or `None` if a
+    /// leaf isn't in it or the hashes differ.
+    pub fn prove_subset(&self, subset: &MerkleTripleSet) -> Option<SubsetProof> {
+        if subset.hash != self.hash {
+            return None;
+        }
+        let leaves = subset
+            .leaves()
+            .iter()
+            .map(|leaf| {
+                let idx = *self.leaf_idx.get(&leaf.to_repr())?;
+                Some((*leaf, self.path(idx)))
+            })
+            .collect::<Option<_>>()?;
+        Some(SubsetProof { leaves })
+    }
+
+    fn path(&self, idx: usize) -> MerklePath {
+        let siblings = self
+            .layers
+            .iter()
+            .take(self.depth())
+            .enumerate()
+            .map(|(level, layer)| layer[(idx >> level) ^ 1])
+            .collect();
+        MerklePath { idx, siblings }
+    }
+}
+
+/// Returns a key ordering field elements as integers below the modulus, which
+/// is their big-endian representation.
+pub(crate) fn integer_order_key(value: &Fr) -> [u8; 32] {
+    let mut repr = value.to_repr();
+    repr.reverse();
+    repr
+}
+
+/// Authentication path of the leaf at `idx`, with the sibling at each level
+/// from the leaves up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath {
+    pub idx: usize,
+    pub siblings: Vec<Fr>,
+}
+
+impl MerklePath {
+    /// Returns whether the node at `level` is a right child, which is the bit
+    /// ordering it with its sibling.
+    pub fn is_right(&self, level: usize) -> bool {
+        (self.idx >> level) & 1 == 1
+    }
+
+    /// Returns the root reached by hashing `leaf` along the path.
+    pub fn root(&self, leaf: Fr) -> Fr {
+        self.root_with_hash(leaf, HashChoice::Poseidon)
+    }
+
+    /// Returns the root reached by hashing `leaf` along the path with `hash`,
+    /// which has to be the [`MerkleTripleSet::hash`] the path is of.
+    pub fn root_with_hash(&self, leaf: Fr, hash: HashChoice) -> Fr {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |cur, (level, sibling)| match self.is_right(level) {
+                false => hash.hash_node(cur, *sibling),
+                true => hash.hash_node(*sibling, cur),
+            })
+    }
+}
+
+/// Proof that a leaf isn't in a [`MerkleTripleSet`] by the leaves at two
+/// adjacent indices below and above it, whose order the set is sorted in, see
+/// [`MerkleTripleSet::prove_non_membership`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonMembershipProof {
+    /// Greatest leaf below with its path, or `None` below the first leaf.
+    pub lo: Option<(Fr, MerklePath)>,
+    /// Least leaf above with its path at the index after `lo`, which is
+    /// [`PADDING_LEAF`] past the last leaf unless the set is full, or `None`
+    /// past it.
+    pub hi: Option<(Fr, MerklePath)>,
+}
+
+impl NonMembershipProof {
+    /// Returns whether the proof shows `leaf` isn't under `root` of a set
+    /// hashed with `hash`, which holds as long as the set is sorted as a
+    /// [`MerkleTripleSet`] is.
+    pub fn verify(&self, leaf: Fr, root: Fr, hash: HashChoice) -> bool {
+        let key = integer_order_key(&leaf);
+        let opens =
+            |(neighbor, path): &(Fr, MerklePath)| path.root_with_hash(*neighbor, hash) == root;
+        let adjacent = match (&self.lo, &self.hi) {
+            (Some((_, lo)), Some((_, hi))) => hi.idx == lo.idx + 1,
+            (None, Some((_, hi))) => hi.idx == 0,
+            (Some((_, lo)), None) => lo.idx + 1 == 1 << lo.siblings.len(),
+            (None, None) => false,
+        };
+        adjacent
+            && self
+                .lo
+                .iter()
+                .all(|lo| integer_order_key(&lo.0) < key && opens(lo))
+            && self
+                .hi
+                .iter()
+                .all(|hi| key < integer_order_key(&hi.0) && opens(hi))
+    }
+}
+
+/// Proof that the set of a subset root is contained in the set of a parent
+/// root by the path of each leaf of the subset under the parent, see
+/// [`MerkleTripleSet::prove_subset`].
+///
+/// The verifier learns the leaves of the subset and their indices in the
+/// parent, but not the triples they're of, unless it can guess them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubsetProof {
+    /// Leaf of the subset in order with its path under the parent.
+    pub leaves: Vec<(Fr, MerklePath)>,
+}
+
+impl SubsetProof {
+    /// Returns whether the proof shows every leaf of the set of
+    /// `subset_root` is a leaf of the set of `root`, both hashed with `hash`,
+    /// which rebuilds the subset from its leaves.
+    pub fn verify(&self, subset_root: Fr, root: Fr, hash: HashChoice) -> bool {
+        let sorted = self
+            .leaves
+            .iter()
+            .tuple_windows()
+            .all(|((lhs, _), (rhs, _))| integer_order_key(lhs) < integer_order_key(rhs));
+        let leaves = self.leaves.iter().map(|(leaf, _)| *leaf).collect_vec();
+        let depth = leaves.len().next_power_of_two().trailing_zeros() as usize;
+        sorted
+            && !leaves.contains(&PADDING_LEAF)
+            && MerkleTripleSet::from_leaves(leaves, depth, hash).root() == subset_root
+            && self
+                .leaves
+                .iter()
+                .all(|(leaf, path)| path.root_with_hash(*leaf, hash) == root)
+    }
+}
+
+/// Number of triples exceeding the capacity of a [`MerkleTripleSet`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityExceeded {
+    pub have: usize,
+    pub max: usize,
+}
+
+impl Display for CapacityExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} triples exceed capacity of {}", self.have, self.max)
+    }
+}
+
+impl std::error::Error for CapacityExceeded {}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hash::HashChoice,
+        merkle::{CapacityExceeded, MerkleTripleSet, PADDING_LEAF},
+        rdf::{parse_nquads, Term, Triple},
+    };
+    use plonkish_backend::{halo2_curves::bn256::Fr, util::arithmetic::Field};
+
+    #[test]
+    fn membership() {
+        let triples = (0..5)
+            .map(|idx| {
+                Triple::new(
+                    Term::iri(format!("s{idx}")),
+                    Term::iri("p"),
+                    Term::iri(format!("o{idx}")),
+                )
+            })
+            .collect::<Vec<_>>();
+        let set = MerkleTripleSet::new(triples.clone());
+        assert_eq!(set.depth(), 3);
+        for triple in triples.iter() {
+            let path = set.prove_membership(triple).unwrap();
+            assert_eq!(path.root(MerkleTripleSet::leaf(triple)), set.root());
+        }
+        let absent = Triple::new(Term::iri("s0"), Term::iri("p"), Term::iri("o1"));
+        assert!(set.prove_membership(&absent).is_none());
+
+        let reordered = MerkleTripleSet::new(triples.iter().rev().chain(&triples[..1]).cloned());
+        assert_eq!(reordered.root(), set.root());
+
+        let bounded = MerkleTripleSet::with_capacity(triples.iter().chain(&triples).cloned(), 5);
+        assert_eq!(bounded.unwrap().root(), set.root());
+        assert_eq!(
+            MerkleTripleSet::with_capacity(triples.clone(), 4).unwrap_err(),
+            CapacityExceeded { have: 5, max: 4 }
+        );
+
+        let deeper = set.clone().with_depth(5).unwrap();
+        assert_eq!(deeper.depth(), 5);
+        let path = deeper.prove_membership(&triples[4]).unwrap();
+        assert_eq!(path.root(MerkleTripleSet::leaf(&triples[4])), deeper.root());
+        assert!(set.with_depth(2).is_none());
+    }
+
+    #[test]
+    fn subset() {
+        let triples = (0..6)
+            .map(|idx| Triple::new(Term::iri(format!("s{idx}")), Term::iri("p"), Term::iri("o")))
+            .collect::<Vec<_>>();
+        let set = MerkleTripleSet::new(triples.clone());
+        let subset = MerkleTripleSet::new(triples[1..3].iter().rev().cloned());
+        assert_eq!(subset.leaves().len(), 2);
+        let proof = set.prove_subset(&subset).unwrap();
+        assert!(proof.verify(subset.root(), set.root(), HashChoice::Poseidon));
+        assert!(!proof.verify(set.root(), set.root(), HashChoice::Poseidon));
+        assert!(!proof.verify(subset.root(), subset.root(), HashChoice::Poseidon));
+        let mut reordered = proof.clone();
+        reordered.leaves.reverse();
+        assert!(!reordered.verify(subset.root(), set.root(), HashChoice::Poseidon));
+
+        let absent = Triple::new(Term::iri("s0"), Term::iri("q"), Term::iri("o"));
+        let other = MerkleTripleSet::new([triples[0].clone(), absent]);
+        assert!(set.prove_subset(&other).is_none());
+        let keccak = MerkleTripleSet::new_with_hash(triples[1..3].to_vec(), HashChoice::Keccak256);
+        assert!(set.prove_subset(&keccak).is_none());
+    }
+
+    #[test]
+    fn hash_choice() {
+        let triples = (0..3)
+            .map(|idx| Triple::new(Term::iri(format!("s{idx}")), Term::iri("p"), Term::iri("o")))
+            .collect::<Vec<_>>();
+        let poseidon = MerkleTripleSet::new_with_hash(triples.clone(), HashChoice::Poseidon);
+        assert_eq!(
+            poseidon.root(),
+            MerkleTripleSet::new(triples.clone()).root()
+        );
+
+        let hash = HashChoice::Keccak256;
+        let keccak = MerkleTripleSet::new_with_hash(triples.clone(), hash);
+        assert_eq!(keccak.hash(), hash);
+        assert_ne!(keccak.root(), poseidon.root());
+        let keccak = keccak.with_depth(4).unwrap();
+        for triple in triples.iter() {
+            let path = keccak.prove_membership(triple).unwrap();
+            let leaf = MerkleTripleSet::leaf_with_hash(triple, hash);
+            assert_eq!(path.root_with_hash(leaf, hash), keccak.root());
+            assert_ne!(path.root(leaf), keccak.root());
+        }
+    }
+
+    #[test]
+    fn quads() {
+        let graph = parse_nquads("<a> <p> <b> .\n<a> <p> <b> <g> .\n<a> <p> <c> <h> .\n").unwrap();
+        let set = MerkleTripleSet::from_graph(&graph);
+        assert_eq!(set.depth(), 2);
+        let triple = graph.triple(0);
+        let [g, h] = [Term::iri("g"), Term::iri("h")];
+        for graph_name in [None, Some(&g)] {
+            let path = set.prove_quad_membership(&triple, graph_name).unwrap();
+            let leaf = MerkleTripleSet::quad_leaf(&triple, graph_name);
+            assert_eq!(path.root(leaf), set.root());
+        }
+        assert_eq!(
+            MerkleTripleSet::quad_leaf(&triple, None),
+            MerkleTripleSet::leaf(&triple)
+        );
+        assert!(set.prove_quad_membership(&triple, Some(&h)).is_none());
+        assert!(set.prove_membership(&graph.triple(2)).is_none());
+
+        let default = MerkleTripleSet::new([graph.triple(0), graph.triple(2)]);
+        assert_ne!(default.root(), set.root());
+    }
+
+    #[test]
+    fn incremental() {
+        let triples = (0..6)
+            .map(|idx| Triple::new(Term::iri(format!("s{idx}")), Term::iri("p"), Term::iri("o")))
+            .collect::<Vec<_>>();
+        let mut set = MerkleTripleSet::new(triples[..2].to_vec());
+        let mut members = triples[..2].to_vec();
+        let check = |set: &MerkleTripleSet, members: &[Triple]| {
+            let rebuilt = MerkleTripleSet::new(members.to_vec())
+                .with_depth(set.depth())
+                .unwrap();
+            assert_eq!(set.root(), rebuilt.root());
+            assert_eq!(set.leaves(), rebuilt.leaves());
+            for triple in triples.iter() {
+                let leaf = MerkleTripleSet::leaf(triple);
+                match members.contains(triple) {
+                    true => {
+                        let path = set.prove_membership(triple).unwrap();
+                        assert_eq!(path.root(leaf), set.root());
+                    }
+                    false => {
+                        let proof = set.prove_non_membership(leaf).unwrap();
+                        assert!(proof.verify(leaf, set.root(), HashChoice::Poseidon));
+                    }
+                }
+            }
+        };
+
+        // Growing past 2 and 4 leaves deepens the set.
+        for triple in triples[2..].iter().rev() {
+            let root = set.insert(triple);
+            members.push(triple.clone());
+            assert_eq!(root, set.root());
+            check(&set, &members);
+        }
+        assert_eq!(set.depth(), 3);
+        assert_eq!(set.insert(&triples[0]), set.root());
+        check(&set, &members);
+
+        // Removing doesn't shrink it.
+        for idx in [3, 0, 5, 1] {
+            let root = set.remove(&triples[idx]);
+            members.retain(|triple| *triple != triples[idx]);
+            assert_eq!(root, set.root());
+            check(&set, &members);
+        }
+        assert_eq!(set.depth(), 3);
+        assert_eq!(set.remove(&triples[0]), set.root());
+        set.insert(&triples[3]);
+        members.push(triples[3].clone());
+        check(&set, &members);
+
+        let [g, h] = [Term::iri("g"), Term::iri("h")];
+        let mut quads = MerkleTripleSet::new([]);
+        quads.insert_quad(&triples[0], Some(&g));
+        quads.insert_quad(&triples[0], Some(&h));
+        quads.remove_quad(&triples[0], Some(&g));
+        let path = quads.prove_quad_membership(&triples[0], Some(&h)).unwrap();
+        let leaf = MerkleTripleSet::quad_leaf(&triples[0], Some(&h));
+        assert_eq!(path.root(leaf), quads.root());
+        assert!(quads.prove_quad_membership(&triples[0], Some(&g)).is_none());
+    }
+
+    #[test]
+    fn non_membership() {
+        let triples = (0..3)
+            .map(|idx| Triple::new(Term::iri(format!("s{idx}")), Term::iri("p"), Term::iri("o")))
+            .collect::<Vec<_>>();
+        let hash = HashChoice::Poseidon;
+        for (set, padded) in [
+            (MerkleTripleSet::new(triples.clone()), true),
+            (MerkleTripleSet::new(triples[..2].iter().cloned()), false),
+        ] {
+            let root = set.root();
+            for triple in triples.iter() {
+                let leaf = MerkleTripleSet::leaf(triple);
+                if set.prove_membership(triple).is_some() {
+                    assert!(set.prove_non_membership(leaf).is_none());
+                    continue;
+                }
+                let proof = set.prove_non_membership(leaf).unwrap();
+                assert!(proof.verify(leaf, root, hash));
+            }
+            // Below the first leaf, and past the last one into padding or
+            // out of a full set.
+            let min = set.prove_non_membership(Fr::ZERO).unwrap();
+            assert!(min.lo.is_none() && min.verify(Fr::ZERO, root, hash));
+            let max = -Fr::from(2);
+            let proof = set.prove_non_membership(max).unwrap();
+            assert!(proof.verify(max, root, hash));
+            assert_eq!(
+                proof.hi.as_ref().map(|(leaf, _)| *leaf),
+                padded.then_some(PADDING_LEAF)
+            );
+            assert!(set.prove_non_membership(PADDING_LEAF).is_none());
+
+            // A member isn't in a gap, nor does a gap show another leaf absent.
+            let member = MerkleTripleSet::leaf(&triples[0]);
+            assert!(!min.verify(member, root, hash));
+            let mut tampered = proof;
+            tampered.lo = None;
+            assert!(!tampered.verify(max, root, hash));
+        }
+        let empty = MerkleTripleSet::new([]);
+        let proof = empty.prove_non_membership(Fr::ONE).unwrap();
+        assert!(proof.verify(Fr::ONE, empty.root(), hash));
+    }
+}
@@ -0,0 +1,893 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+mod parser;
+
+pub use parser::{parse_select, ParseError, ParseErrorKind, MAX_PATH_LENGTH};
+
+pub const XSD_STRING: &str = "http://www.w3.org/2001/XMLSchema#string";
+pub const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+pub const XSD_DECIMAL: &str = "http://www.w3.org/2001/XMLSchema#decimal";
+pub const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+pub const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+pub const XSD_DATE_TIME: &str = "http://www.w3.org/2001/XMLSchema#dateTime";
+pub const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+pub const RDF_LANG_STRING: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#langString";
+
+/// `SELECT` query over a basic graph pattern joined with at most one `UNION`,
+/// optionally left-joined with the basic graph pattern of a single `OPTIONAL`
+/// block. Nested `OPTIONAL` blocks aren't supported.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectQuery {
+    /// Projected variables in order. `SELECT *` projects every variable of
+    /// `patterns` in order of first appearance.
+    pub projection: Vec<Variable>,
+    /// Count projected instead of `projection`, which is then empty, or
+    /// after the key of `group_by`, which is then the whole projection.
+    pub count: Option<Count>,
+    /// Variable of `GROUP BY`, whose bindings split the solutions into the
+    /// groups of one [`Self::count`] each.
+    pub group_by: Option<Variable>,
+    /// Whether the query is `SELECT DISTINCT`, reporting solutions with the
+    /// same projected bindings once.
+    pub distinct: bool,
+    /// Triple patterns joined by `.`.
+    pub patterns: Vec<TriplePattern>,
+    /// `UNION` joined with `patterns`.
+    pub union: Option<Pattern>,
+    /// Triple patterns of the `OPTIONAL` block, empty without one.
+    pub optional: Vec<TriplePattern>,
+    /// Filters every solution has to satisfy.
+    pub filters: Vec<FilterExpr>,
+    /// String filters every solution has to satisfy.
+    pub string_filters: Vec<StringFilter>,
+    /// Boolean combinations of integer comparisons every solution has to
+    /// satisfy, each `FILTER` of a `||` or `!`. The comparisons a `FILTER`
+    /// is a conjunction of otherwise are in `filters` and `string_filters`.
+    pub conditions: Vec<FilterCondition>,
+    /// Triple patterns of the `FILTER NOT EXISTS` block, which no solution
+    /// extends to a match of, empty without one. Its variables are only bound
+    /// by other patterns, so it doesn't add any to [`Self::variables`].
+    pub not_exists: Vec<TriplePattern>,
+    /// `BIND` of each computed variable in order, each bound after
+    /// [`Self::variables`], see [`Self::variables_with_binds`]. Those of the
+    /// projection, e.g. `SELECT (?a + 1 AS ?b)`, come after the group's.
+    pub binds: Vec<Bind>,
+    /// `VALUES` block restricting a variable to a set of terms.
+    pub values: Option<Values>,
+    /// Conditions of `ORDER BY` in order of precedence, empty without one.
+    pub order_by: Vec<OrderCondition>,
+    /// Maximum number of results of `LIMIT`, taken after `ORDER BY`.
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Variable(pub String);
+
+impl Variable {
+    /// Returns the anonymous variable the blank node `_:label` of a pattern
+    /// stands for, whose name no `?name` has. It binds like any other
+    /// variable but is never projected, so it's an existential over the
+    /// terms of the dataset, and matches blank nodes of the dataset by their
+    /// binding rather than their label.
+    pub fn blank_node(label: &str) -> Self {
+        Variable(format!("_:{label}"))
+    }
+
+    /// Returns the anonymous variable of the `idx`-th node between two steps
+    /// of a sequence path, which is a blank node no `_:label` can name.
+    pub fn path_node(idx: usize) -> Self {
+        Variable(format!("_:path-{idx}"))
+    }
+
+    pub fn is_blank_node(&self) -> bool {
+        self.0.starts_with("_:")
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_blank_node() {
+            true => write!(f, "{}", self.0),
+            false => write!(f, "?{}", self.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Iri(pub String);
+
+impl Iri {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Iri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Literal {
+    pub lexical: String,
+    pub datatype: Iri,
+    /// Language tag, lowercased, set only when `datatype` is
+    /// [`RDF_LANG_STRING`].
+    pub language: Option<String>,
+}
+
+impl Literal {
+    pub fn new(lexical: impl Into<String>, datatype: Iri) -> Self {
+        Self {
+            lexical: lexical.into(),
+            datatype,
+            language: None,
+        }
+    }
+
+    pub fn string(lexical: impl Into<String>) -> Self {
+        Self::new(lexical, Iri(XSD_STRING.to_string()))
+    }
+
+    pub fn integer(value: i64) -> Self {
+        Self::new(value.to_string(), Iri(XSD_INTEGER.to_string()))
+    }
+
+    pub fn boolean(value: bool) -> Self {
+        Self::new(value.to_string(), Iri(XSD_BOOLEAN.to_string()))
+    }
+
+    /// Returns the `xsd:dateTime` literal of `seconds` since the Unix epoch
+    /// in canonical form, which is in UTC, e.g. `"1970-01-01T00:00:00Z"` of
+    /// `0`. `seconds` is expected to be within the years 1 to 9999.
+    pub fn date_time(seconds: i64) -> Self {
+        Self::new(format_date_time(seconds), Iri(XSD_DATE_TIME.to_string()))
+    }
+
+    pub fn lang_string(lexical: impl Into<String>, language: &str) -> Self {
+        Self {
+            language: Some(language.to_ascii_lowercase()),
+            ..Self::new(lexical, Iri(RDF_LANG_STRING.to_string()))
+        }
+    }
+
+    /// Returns the value of an `xsd:integer` literal in canonical form within
+    /// the range of `i64`, which is what filters can compare.
+    pub fn as_integer(&self) -> Option<i64> {
+        if self.datatype.as_str() != XSD_INTEGER {
+            return None;
+        }
+        let value = self.lexical.parse::<i64>().ok()?;
+        (value.to_string() == self.lexical).then_some(value)
+    }
+
+    /// Returns the seconds since the Unix epoch of an `xsd:dateTime` literal
+    /// in canonical form, see [`Literal::date_time`], which is what filters
+    /// can compare.
+    pub fn as_date_time(&self) -> Option<i64> {
+        if self.datatype.as_str() != XSD_DATE_TIME {
+            return None;
+        }
+        let seconds = date_time_seconds(&self.lexical)?;
+        (format_date_time(seconds) == self.lexical).then_some(seconds)
+    }
+
+    /// Returns the literal of the canonical form of the value of an
+    /// `xsd:integer`, `xsd:decimal`, `xsd:boolean` or `xsd:dateTime` literal,
+    /// e.g. `"1.0"^^xsd:decimal` of `"01"^^xsd:decimal` or
+    /// `"1"^^xsd:decimal`, `"true"^^xsd:boolean` of `"1"^^xsd:boolean` and
+    /// `"2020-01-01T00:00:00Z"^^xsd:dateTime` of
+    /// `"2020-01-01T01:00:00+01:00"^^xsd:dateTime`, or `None` if it's of
+    /// another datatype, not a valid lexical form of its datatype or already
+    /// canonical. Literals of the same value and datatype then have the same
+    /// canonical form, while ones of different datatypes never do.
+    pub fn canonical(&self) -> Option<Literal> {
+        let lexical = match self.datatype.as_str() {
+            XSD_INTEGER => canonical_integer(&self.lexical)?,
+            XSD_DECIMAL => canonical_decimal(&self.lexical)?,
+            XSD_BOOLEAN => canonical_boolean(&self.lexical)?.to_string(),
+            XSD_DATE_TIME => format_date_time(date_time_seconds(&self.lexical)?),
+            _ => return None,
+        };
+        (lexical != self.lexical).then(|| Self::new(lexical, self.datatype.clone()))
+    }
+}
+
+/// Returns the canonical form of the `xsd:integer` of `lexical`, without a
+/// `+` or leading zeros, or `None` if it's not one.
+fn canonical_integer(lexical: &str) -> Option<String> {
+    let (negative, digits) = split_sign(lexical);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = match digits.trim_start_matches('0') {
+        "" => return Some("0".to_string()),
+        digits => digits,
+    };
+    Some(format!("{}{digits}", if negative { "-" } else { "" }))
+}
+
+/// Returns the canonical form of the `xsd:decimal` of `lexical`, which has a
+/// digit on both sides of the `.` and no other leading or trailing zeros, or
+/// `None` if it's not one.
+fn canonical_decimal(lexical: &str) -> Option<String> {
+    let (negative, digits) = split_sign(lexical);
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() && frac.is_empty()
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let int = match int.trim_start_matches('0') {
+        "" => "0",
+        int => int,
+    };
+    let frac = match frac.trim_end_matches('0') {
+        "" => "0",
+        frac => frac,
+    };
+    let negative = negative && (int, frac) != ("0", "0");
+    Some(format!("{}{int}.{frac}", if negative { "-" } else { "" }))
+}
+
+/// Returns the canonical form of the `xsd:boolean` of `lexical`, or `None` if
+/// it's not one.
+fn canonical_boolean(lexical: &str) -> Option<&'static str> {
+    match lexical {
+        "true" | "1" => Some("true"),
+        "false" | "0" => Some("false"),
+        _ => None,
+    }
+}
+
+/// Returns the seconds since the Unix epoch of the `xsd:dateTime` of
+/// `lexical`, `YYYY-MM-DDThh:mm:ss` with an optional fraction of seconds and
+/// timezone `Z` or `±hh:mm`, or `None` if it's not one. One without a
+/// timezone is taken to be in UTC. Only years 1 to 9999 in UTC and whole
+/// seconds, so a fraction of zeros only, are supported.
+fn date_time_seconds(lexical: &str) -> Option<i64> {
+    fn number(digits: Option<&str>) -> Option<i64> {
+        let digits = digits?;
+        match digits.bytes().all(|b| b.is_ascii_digit()) {
+            true => digits.parse().ok(),
+            false => None,
+        }
+    }
+    let field = |start: usize, len: usize| number(lexical.get(start..start + len));
+    let separators = lexical
+        .as_bytes()
+        .get(..19)
+        .map(|b| [b[4], b[7], b[10], b[13], b[16]]);
+    if separators != Some(*b"--T::") {
+        return None;
+    }
+    let (year, month, day) = (field(0, 4)?, field(5, 2)?, field(8, 2)?);
+    let (hour, minute, second) = (field(11, 2)?, field(14, 2)?, field(17, 2)?);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if year == 0
+        || !(1..=12).contains(&month)
+        || !(1..=days_in_month).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+    let mut rest = lexical.get(19..)?;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.len()
+            - fraction
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        if digits == 0 || fraction[..digits].bytes().any(|b| b != b'0') {
+            return None;
+        }
+        rest = &fraction[digits..];
+    }
+    let offset = match rest {
+        "" | "Z" => 0,
+        _ => {
+            let sign = match rest.as_bytes()[0] {
+                b'+' => 1,
+                b'-' => -1,
+                _ => return None,
+            };
+            if rest.len() != 6 || rest.as_bytes()[3] != b':' {
+                return None;
+            }
+            let (hours, minutes) = (number(rest.get(1..3))?, number(rest.get(4..6))?);
+            if hours > 14 || minutes > 59 || hours == 14 && minutes > 0 {
+                return None;
+            }
+            sign * (hours * 60 + minutes) * 60
+        }
+    };
+    let seconds =
+        days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+    let min = days_from_civil(1, 1, 1) * 86400;
+    let max = days_from_civil(9999, 12, 31) * 86400 + 86399;
+    (min..=max).contains(&seconds).then_some(seconds)
+}
+
+/// Returns the canonical lexical form of the `xsd:dateTime` of `seconds` since
+/// the Unix epoch, `YYYY-MM-DDThh:mm:ssZ` in UTC.
+fn format_date_time(seconds: i64) -> String {
+    let (days, time) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (time / 3600, time / 60 % 60, time % 60);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// Returns the days since the Unix epoch of a date in the proleptic Gregorian
+/// calendar, counting years from March so the leap day is last.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Returns the year, month and day of the date `days` since the Unix epoch,
+/// the inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// Returns whether `lexical` starts with `-` and the rest after a sign.
+fn split_sign(lexical: &str) -> (bool, &str) {
+    match lexical.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, lexical.strip_prefix('+').unwrap_or(lexical)),
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.language {
+            Some(language) => write!(f, "{:?}@{language}", self.lexical),
+            None => write!(f, "{:?}^^{}", self.lexical, self.datatype),
+        }
+    }
+}
+
+/// Subject, predicate or object position of a [`TriplePattern`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TermPattern {
+    Variable(Variable),
+    Iri(Iri),
+    Literal(Literal),
+}
+
+impl TermPattern {
+    pub fn as_variable(&self) -> Option<&Variable> {
+        match self {
+            TermPattern::Variable(variable) => Some(variable),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TermPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TermPattern::Variable(variable) => write!(f, "{variable}"),
+            TermPattern::Iri(iri) => write!(f, "{iri}"),
+            TermPattern::Literal(literal) => write!(f, "{literal}"),
+        }
+    }
+}
+
+/// Graph pattern of a `UNION`, whose branches are basic graph patterns.
+///
+/// As SPARQL's bag semantics, a solution matching both branches is a solution
+/// once per branch.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Pattern {
+    /// Triple patterns joined by `.`.
+    Bgp(Vec<TriplePattern>),
+    /// Solutions of either pattern.
+    Union(Box<Pattern>, Box<Pattern>),
+}
+
+impl Pattern {
+    /// Returns the triple patterns of each branch from the left, flattening
+    /// nested unions.
+    pub fn branches(&self) -> Vec<&[TriplePattern]> {
+        match self {
+            Pattern::Bgp(patterns) => vec![patterns],
+            Pattern::Union(lhs, rhs) => {
+                let mut branches = lhs.branches();
+                branches.extend(rhs.branches());
+                branches
+            }
+        }
+    }
+}
+
+/// Triple pattern matching the triples of the default graph, or with
+/// [`Self::graph`] the ones of the named graphs of a `GRAPH` clause.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct TriplePattern {
+    pub subject: TermPattern,
+    pub predicate: TermPattern,
+    pub object: TermPattern,
+    /// Graph of `GRAPH <iri> { ... }` or `GRAPH ?g { ... }`, a variable then
+    /// binding the name of any named graph, or `None` outside of one.
+    pub graph: Option<TermPattern>,
+}
+
+impl TriplePattern {
+    pub fn terms(&self) -> [&TermPattern; 3] {
+        [&self.subject, &self.predicate, &self.object]
+    }
+
+    /// Returns the graph if any, then the terms, which is the order they're
+    /// written in.
+    pub fn graph_and_terms(&self) -> impl Iterator<Item = &TermPattern> {
+        self.graph.iter().chain(self.terms())
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = &Variable> {
+        self.graph_and_terms().filter_map(TermPattern::as_variable)
+    }
+}
+
+/// `(COUNT(?variable) AS ?alias)`, counting the distinct values bound to
+/// `variable` as `COUNT(DISTINCT ?variable)` does, which is also accepted.
+/// A proof of the count only attests it as a lower bound, see
+/// [`InstanceLayout::count_lower_bound`].
+///
+/// [`InstanceLayout::count_lower_bound`]: crate::circuit::InstanceLayout::count_lower_bound
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Count {
+    pub variable: Variable,
+    pub alias: Variable,
+}
+
+impl Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(COUNT(DISTINCT {}) AS {})", self.variable, self.alias)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CompareOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CompareOp {
+    /// Returns the operator comparing the operands in swapped order.
+    pub fn flip(&self) -> Self {
+        match self {
+            CompareOp::Lt => CompareOp::Gt,
+            CompareOp::Le => CompareOp::Ge,
+            CompareOp::Gt => CompareOp::Lt,
+            CompareOp::Ge => CompareOp::Le,
+            CompareOp::Eq | CompareOp::Ne => *self,
+        }
+    }
+
+    pub fn evaluate(&self, lhs: i64, rhs: i64) -> bool {
+        match self {
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+            CompareOp::Eq => lhs == rhs,
+            CompareOp::Ne => lhs != rhs,
+        }
+    }
+}
+
+impl Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// Datatype of the literals compared by a [`FilterExpr`], each valued as an
+/// `i64`.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum FilterDatatype {
+    /// `xsd:integer` valued by [`Literal::as_integer`].
+    #[default]
+    Integer,
+    /// `xsd:dateTime` valued by [`Literal::as_date_time`], so ones in
+    /// different timezones compare by the instant they denote.
+    DateTime,
+}
+
+impl FilterDatatype {
+    /// Returns the value of `literal` if it's of the datatype in canonical
+    /// form.
+    pub fn value(&self, literal: &Literal) -> Option<i64> {
+        match self {
+            FilterDatatype::Integer => literal.as_integer(),
+            FilterDatatype::DateTime => literal.as_date_time(),
+        }
+    }
+
+    /// Returns the literal of the datatype valued `value`.
+    pub fn literal(&self, value: i64) -> Literal {
+        match self {
+            FilterDatatype::Integer => Literal::integer(value),
+            FilterDatatype::DateTime => Literal::date_time(value),
+        }
+    }
+}
+
+/// `FILTER(?variable op value)` comparing the literal of `datatype` bound to
+/// `variable` with `value`, where a binding other than a literal of it, see
+/// [`FilterDatatype::value`], fails the filter.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct FilterExpr {
+    pub variable: Variable,
+    pub op: CompareOp,
+    pub value: i64,
+    pub datatype: FilterDatatype,
+}
+
+impl FilterExpr {
+    /// Returns `value` as written in a query, a bare integer for an
+    /// `xsd:integer` one.
+    fn value_str(&self) -> String {
+        match self.datatype {
+            FilterDatatype::Integer => self.value.to_string(),
+            datatype => datatype.literal(self.value).to_string(),
+        }
+    }
+}
+
+impl Display for FilterExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "FILTER({} {} {})",
+            self.variable,
+            self.op,
+            self.value_str()
+        )
+    }
+}
+
+/// Boolean combination by `&&`, `||` and `!` of the comparisons of a
+/// `FILTER`, e.g. `FILTER(?age < 18 || !(?age < 65))`.
+///
+/// Every comparison is evaluated rather than short-circuited, as the circuit
+/// proves each, so a solution with a compared variable bound to anything but
+/// a literal of the datatype of the comparison, see [`FilterDatatype::value`],
+/// fails the whole condition. This deviates from the error semantics of
+/// SPARQL, where such a comparison is an error that `true || error` and
+/// `false && error` absorb, as the circuit opens each compared binding as a
+/// literal of its datatype and couldn't prove one isn't.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum FilterCondition {
+    Compare(FilterExpr),
+    Not(Box<FilterCondition>),
+    And(Box<FilterCondition>, Box<FilterCondition>),
+    Or(Box<FilterCondition>, Box<FilterCondition>),
+}
+
+impl FilterCondition {
+    /// Returns the comparisons of the condition from the left.
+    pub fn comparisons(&self) -> Vec<&FilterExpr> {
+        match self {
+            FilterCondition::Compare(filter) => vec![filter],
+            FilterCondition::Not(condition) => condition.comparisons(),
+            FilterCondition::And(lhs, rhs) | FilterCondition::Or(lhs, rhs) => {
+                let mut comparisons = lhs.comparisons();
+                comparisons.extend(rhs.comparisons());
+                comparisons
+            }
+        }
+    }
+
+    /// Returns whether the condition holds with the `value` of each variable
+    /// as a literal of the datatype compared, or `None` if a compared variable
+    /// has none, whichever the other operands of `||` and `&&` are, see
+    /// [`FilterCondition`].
+    pub fn evaluate(
+        &self,
+        value: &impl Fn(&Variable, FilterDatatype) -> Option<i64>,
+    ) -> Option<bool> {
+        Some(match self {
+            FilterCondition::Compare(filter) => {
+                let lhs = value(&filter.variable, filter.datatype)?;
+                filter.op.evaluate(lhs, filter.value)
+            }
+            FilterCondition::Not(condition) => !condition.evaluate(value)?,
+            FilterCondition::And(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(value)?, rhs.evaluate(value)?);
+                lhs && rhs
+            }
+            FilterCondition::Or(lhs, rhs) => {
+                let (lhs, rhs) = (lhs.evaluate(value)?, rhs.evaluate(value)?);
+                lhs || rhs
+            }
+        })
+    }
+}
+
+impl Display for FilterCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterCondition::Compare(filter) => {
+                write!(
+                    f,
+                    "{} {} {}",
+                    filter.variable,
+                    filter.op,
+                    filter.value_str()
+                )
+            }
+            FilterCondition::Not(condition) => write!(f, "!({condition})"),
+            FilterCondition::And(lhs, rhs) => write!(f, "({lhs}) && ({rhs})"),
+            FilterCondition::Or(lhs, rhs) => write!(f, "({lhs}) || ({rhs})"),
+        }
+    }
+}
+
+/// `FILTER(?variable = "value")` matching a `variable` bound to the
+/// `xsd:string` literal `value`, where any other binding, a language-tagged
+/// string included, fails the filter.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct StringFilter {
+    pub variable: Variable,
+    pub value: String,
+}
+
+impl StringFilter {
+    /// Returns the literal `value` a binding has to be.
+    pub fn literal(&self) -> Literal {
+        Literal::string(self.value.clone())
+    }
+}
+
+impl Display for StringFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FILTER({} = {:?})", self.variable, self.value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl ArithOp {
+    /// Returns `lhs op rhs`, or `None` if it's out of the range of an `i64`.
+    pub fn evaluate(&self, lhs: i64, rhs: i64) -> Option<i64> {
+        match self {
+            ArithOp::Add => lhs.checked_add(rhs),
+            ArithOp::Sub => lhs.checked_sub(rhs),
+            ArithOp::Mul => lhs.checked_mul(rhs),
+        }
+    }
+}
+
+impl Display for ArithOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            ArithOp::Add => "+",
+            ArithOp::Sub => "-",
+            ArithOp::Mul => "*",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// Operand of a [`Bind`], a variable bound to an integer or an integer
+/// constant.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Operand {
+    Variable(Variable),
+    Integer(i64),
+}
+
+impl Operand {
+    pub fn as_variable(&self) -> Option<&Variable> {
+        match self {
+            Operand::Variable(variable) => Some(variable),
+            Operand::Integer(_) => None,
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Variable(variable) => write!(f, "{variable}"),
+            Operand::Integer(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// `BIND(lhs op rhs AS ?variable)` binding `variable` to the integer literal
+/// of `lhs op rhs`. Operands and results are `i64`, and a solution with an
+/// operand bound to anything but an integer literal, see
+/// [`Literal::as_integer`], or a result out of that range is dropped as by a
+/// filter rather than leaving `variable` unbound. `xsd:decimal` isn't
+/// supported, so an operand bound to a decimal drops the solution too, and a
+/// decimal operand of the query fails to parse.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Bind {
+    pub lhs: Operand,
+    pub op: ArithOp,
+    pub rhs: Operand,
+    pub variable: Variable,
+}
+
+impl Bind {
+    /// Returns the variables of the operands.
+    pub fn operand_variables(&self) -> impl Iterator<Item = &Variable> {
+        [&self.lhs, &self.rhs]
+            .into_iter()
+            .filter_map(Operand::as_variable)
+    }
+}
+
+impl Display for Bind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BIND({} {} {} AS {})",
+            self.lhs, self.op, self.rhs, self.variable
+        )
+    }
+}
+
+/// `VALUES ?variable { term ... }` restricting the term bound to `variable` to
+/// one of `terms`, which are IRIs or literals, where an unbound variable
+/// fails the restriction as it does a filter.
+///
+/// Only a single variable is supported yet. A block of several, e.g.
+/// `VALUES (?a ?b) { (<x> <y>) }`, would need a membership check of the tuple
+/// of bindings, and `UNDEF` one skipping its column.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Values {
+    pub variable: Variable,
+    pub terms: Vec<TermPattern>,
+}
+
+impl Display for Values {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VALUES {} {{", self.variable)?;
+        for term in self.terms.iter() {
+            write!(f, " {term}")?;
+        }
+        write!(f, " }}")
+    }
+}
+
+/// Condition of `ORDER BY`, `?variable` or `ASC(?variable)` ordering the
+/// results by the term bound to `variable` ascending, and `DESC(?variable)`
+/// descending.
+///
+/// Terms are ordered by their [`Term::encode`] as integers below the modulus,
+/// which the circuit can compare, not by the SPARQL ordering of terms, so
+/// e.g. integer literals aren't in numeric order.
+///
+/// [`Term::encode`]: crate::rdf::Term::encode
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct OrderCondition {
+    pub variable: Variable,
+    pub descending: bool,
+}
+
+impl Display for OrderCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.descending {
+            false => write!(f, "ASC({})", self.variable),
+            true => write!(f, "DESC({})", self.variable),
+        }
+    }
+}
+
+impl SelectQuery {
+    /// Returns every variable of `patterns`, then the ones only in the
+    /// branches of `union`, then the ones only in `optional`, in order of
+    /// first appearance.
+    pub fn variables(&self) -> Vec<&Variable> {
+        let union = self.union.iter().flat_map(Pattern::branches).flatten();
+        distinct_variables(self.patterns.iter().chain(union).chain(&self.optional))
+    }
+
+    /// Returns [`Self::variables`] followed by the variable of each of
+    /// `binds`, which is the order solutions bind them in.
+    pub fn variables_with_binds(&self) -> Vec<&Variable> {
+        let mut variables = self.variables();
+        variables.extend(self.binds.iter().map(|bind| &bind.variable));
+        variables
+    }
+
+    /// Returns the number of variables of `patterns`, which come first in
+    /// [`Self::variables`] and are bound by every solution.
+    pub fn num_required_variables(&self) -> usize {
+        distinct_variables(&self.patterns).len()
+    }
+
+    /// Returns the query with the pattern at `order[i]` of `patterns` as its
+    /// `i`-th one, e.g. by [`pattern_order`] or any order of the caller.
+    /// Patterns are joined, so the query has the same results, though in the
+    /// order its solutions now come in, which only matters to `LIMIT`.
+    ///
+    /// The order numbers the variables and lays out the circuit, so it's a
+    /// different query to [`query_commitment`], which the verifier has to be
+    /// given as reordered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` isn't a permutation of the indices of `patterns`.
+    ///
+    /// [`pattern_order`]: crate::eval::pattern_order
+    /// [`query_commitment`]: crate::circuit::query_commitment
+    pub fn with_pattern_order(&self, order: &[usize]) -> SelectQuery {
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        assert!(
+            sorted.into_iter().eq(0..self.patterns.len()),
+            "{order:?} isn't a permutation of {} patterns",
+            self.patterns.len()
+        );
+        SelectQuery {
+            patterns: order
+                .iter()
+                .map(|idx| self.patterns[*idx].clone())
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+fn distinct_variables<'a>(
+    patterns: impl IntoIterator<Item = &'a TriplePattern>,
+) -> Vec<&'a Variable> {
+    let mut variables = Vec::new();
+    for variable in patterns.into_iter().flat_map(TriplePattern::variables) {
+        if !variables.contains(&variable) {
+            variables.push(variable);
+        }
+    }
+    variables
+}
@@ -0,0 +1,2384 @@
+use crate::query::{
+    ArithOp, Bind, CompareOp, Count, FilterCondition, FilterDatatype, FilterExpr, Iri, Literal,
+    Operand, OrderCondition, Pattern, SelectQuery, StringFilter, TermPattern, TriplePattern,
+    Values, Variable, RDF_TYPE, XSD_DATE_TIME, XSD_DECIMAL, XSD_DOUBLE, XSD_STRING,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    ops::Range,
+};
+
+/// Maximum number of steps of a sequence path `<p>/<q>`.
+pub const MAX_PATH_LENGTH: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// Byte offset into the input where parsing failed, which `span` starts
+    /// at.
+    pub offset: usize,
+    /// Byte range of the input the error is about, e.g. to underline it,
+    /// which is the offending token, the unterminated IRI or literal, or
+    /// empty at the end of the input.
+    pub span: Range<usize>,
+    pub kind: ParseErrorKind,
+    /// What the parser expected instead of the token at `span`, which the
+    /// message also says, for a [`ParseErrorKind::UnexpectedToken`] or
+    /// [`ParseErrorKind::UnexpectedEnd`].
+    pub expected: Option<String>,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Returns the 1-based line and column in characters of `offset` in
+    /// `input`, which has to be the parsed input.
+    pub fn line_column(&self, input: &str) -> (usize, usize) {
+        let before = &input[..self.offset];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let line = before.matches('\n').count() + 1;
+        (line, before[line_start..].chars().count() + 1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// Token other than the [`ParseError::expected`] one.
+    UnexpectedToken,
+    /// End of the input where [`ParseError::expected`] was expected.
+    UnexpectedEnd,
+    /// Prefixed name of a prefix not declared by a `PREFIX`.
+    UnknownPrefix,
+    /// `<` without a matching `>` before whitespace or the end of the input.
+    UnterminatedIri,
+    /// String literal without a closing `"` before a line break or the end of
+    /// the input.
+    UnterminatedLiteral,
+    /// Invalid escape sequence or an integer out of the range of an `i64`.
+    InvalidLiteral,
+    /// Valid SPARQL that isn't supported yet.
+    Unsupported,
+    /// Query that isn't valid SPARQL otherwise, e.g. a blank node predicate.
+    Invalid,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a `SELECT` query over a basic graph pattern with filters, a `UNION`
+/// and a trailing `OPTIONAL` block, e.g.
+/// `SELECT ?a ?b WHERE { ?a <p> ?b . FILTER(?b > 18) OPTIONAL { ?b <q> ?c } }`
+/// or `SELECT ?a WHERE { { ?a <p> ?b } UNION { ?a <q> ?b } }`.
+///
+/// The query can be led by `PREFIX` declarations, e.g.
+/// `PREFIX foaf: <http://xmlns.com/foaf/0.1/>`, whose prefixed names such as
+/// `foaf:name` are expanded to full IRIs while parsing, so the query only
+/// holds full IRIs.
+///
+/// A `VALUES ?x { <a> <b> }` block of a single variable can be given in the
+/// group or after it.
+///
+/// A blank node `_:label` as subject or object is the
+/// [`Variable::blank_node`] of `label`, which `SELECT *` doesn't project.
+///
+/// `ORDER BY` can follow the group, before a trailing `VALUES`.
+///
+/// `GROUP BY ?x` of a single variable can follow the group, before `ORDER BY`,
+/// for a query projecting `?x` and a [`Count`], e.g.
+/// `SELECT ?t (COUNT(?x) AS ?n) WHERE { ?x <type> ?t } GROUP BY ?t`, but not
+/// with `DISTINCT`, `ORDER BY` or `LIMIT` yet.
+///
+/// A single `FILTER NOT EXISTS { ... }` of triple patterns can be given in
+/// the group, see [`SelectQuery::not_exists`].
+///
+/// A `GRAPH <iri> { ... }` or `GRAPH ?g { ... }` of triple patterns can be
+/// given in the group, a branch of the `UNION` or the `OPTIONAL` block, its
+/// patterns then matching the named graph of [`TriplePattern::graph`].
+///
+/// `BIND(lhs op rhs AS ?x)` of `+`, `-` or `*` over variables and integers can
+/// be given in the group, binding a variable not bound otherwise, see
+/// [`Bind`]. A decimal or double operand fails with
+/// [`ParseErrorKind::Unsupported`].
+///
+/// `(lhs op rhs AS ?x)` of the same can be projected alongside variables, e.g.
+/// `SELECT ?a (?n + 1 AS ?m) WHERE { ?a <age> ?n }`, which is a `BIND` after
+/// the group's, so its operands are bound by the group or an earlier
+/// projection.
+///
+/// A sequence path `?a <p>/<q> ?b` of up to [`MAX_PATH_LENGTH`] IRIs is the
+/// triple patterns of its steps joined through [`Variable::path_node`]s,
+/// while `*` and `+` paths are not supported.
+///
+/// `FILTER(?x = "string")` compares a variable with an `xsd:string` literal,
+/// see [`StringFilter`], while other filters compare integers.
+///
+/// A malformed query fails with a [`ParseError`] of the byte span of the
+/// offending token in `input` and its [`ParseErrorKind`], e.g. to underline
+/// it in an editor.
+pub fn parse_select(input: &str) -> Result<SelectQuery, ParseError> {
+    let mut parser = Parser {
+        input,
+        offset: 0,
+        prefixes: HashMap::new(),
+        path_nodes: 0,
+    };
+    parser.prologue()?;
+    let query = parser.select_query()?;
+    parser.skip_ws();
+    if parser.offset != input.len() {
+        return Err(parser.expected("end of input"));
+    }
+    Ok(query)
+}
+
+#[derive(Default)]
+struct GroupGraphPattern {
+    patterns: Vec<TriplePattern>,
+    union: Option<Pattern>,
+    optional: Vec<TriplePattern>,
+    filters: Vec<FilterExpr>,
+    string_filters: Vec<StringFilter>,
+    conditions: Vec<FilterCondition>,
+    not_exists: Option<Vec<TriplePattern>>,
+    binds: Vec<Bind>,
+    values: Option<Values>,
+}
+
+/// Condition of a `FILTER` as parsed, where a string comparison can only be
+/// a conjunct, see [`Parser::add_filter`].
+enum ParsedFilter {
+    Compare(FilterExpr),
+    /// String comparison at the span of the input it's parsed from.
+    String(StringFilter, Range<usize>),
+    Not(Box<ParsedFilter>),
+    And(Box<ParsedFilter>, Box<ParsedFilter>),
+    Or(Box<ParsedFilter>, Box<ParsedFilter>),
+}
+
+/// Constant side of a comparison in a `FILTER`.
+enum FilterOperand {
+    /// Value of a literal of the datatype, see [`FilterDatatype::value`].
+    Value(i64, FilterDatatype),
+    /// Lexical form of an `xsd:string` literal.
+    String(String),
+}
+
+impl ParsedFilter {
+    /// Returns the condition of integer comparisons, or the span of a string
+    /// comparison in it.
+    fn into_condition(self) -> Result<FilterCondition, Range<usize>> {
+        let boxed = |filter: Box<ParsedFilter>| filter.into_condition().map(Box::new);
+        Ok(match self {
+            ParsedFilter::Compare(filter) => FilterCondition::Compare(filter),
+            ParsedFilter::String(_, span) => return Err(span),
+            ParsedFilter::Not(filter) => FilterCondition::Not(boxed(filter)?),
+            ParsedFilter::And(lhs, rhs) => FilterCondition::And(boxed(lhs)?, boxed(rhs)?),
+            ParsedFilter::Or(lhs, rhs) => FilterCondition::Or(boxed(lhs)?, boxed(rhs)?),
+        })
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+    /// IRI of each prefix declared by the prologue, `""` being the default
+    /// prefix `:`.
+    prefixes: HashMap<&'a str, String>,
+    /// Number of [`Variable::path_node`]s of the sequence paths so far.
+    path_nodes: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses the `PREFIX name: <iri>` declarations, where a later declaration
+    /// of the same prefix takes over.
+    fn prologue(&mut self) -> Result<(), ParseError> {
+        while self.eat_keyword("PREFIX") {
+            self.skip_ws();
+            let name = self.take_while(is_name_char);
+            if !self.rest().starts_with(':') {
+                return Err(self.expected("prefix name ending in ':'"));
+            }
+            self.offset += 1;
+            self.skip_ws();
+            if self.peek() != Some('<') {
+                return Err(self.expected("IRI"));
+            }
+            let Iri(iri) = self.iri()?.unwrap();
+            self.prefixes.insert(name, iri);
+        }
+        Ok(())
+    }
+
+    fn select_query(&mut self) -> Result<SelectQuery, ParseError> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.eat_keyword("DISTINCT");
+        self.skip_ws();
+        let select_all = self.eat("*");
+        let mut projection = Vec::new();
+        let mut projected_binds = Vec::new();
+        let mut count = None;
+        if !select_all {
+            loop {
+                if let Some(variable) = self.variable()? {
+                    projection.push(variable);
+                    continue;
+                }
+                self.skip_ws();
+                let offset = self.offset;
+                if !self.eat("(") {
+                    break;
+                }
+                if self.peek_keyword("COUNT") {
+                    count = Some(self.count()?);
+                    break;
+                }
+                self.offset = offset;
+                let bind = self.bind()?;
+                projection.push(bind.variable.clone());
+                projected_binds.push((bind, offset));
+            }
+            if projection.is_empty() && count.is_none() {
+                return Err(self.expected("variable, '*' or '('"));
+            }
+        }
+        self.eat_keyword("WHERE");
+        let GroupGraphPattern {
+            patterns,
+            union,
+            optional,
+            filters,
+            string_filters,
+            conditions,
+            not_exists,
+            binds,
+            mut values,
+        } = self.group_graph_pattern()?;
+        let group_by = self.group_by(select_all, distinct, &projection, count.is_some())?;
+        let order_by = match self.eat_keyword("ORDER") {
+            true => {
+                self.expect_keyword("BY")?;
+                self.order_conditions()?
+            }
+            false => Vec::new(),
+        };
+        let limit = match self.eat_keyword("LIMIT") {
+            true => Some(self.limit()?),
+            false => None,
+        };
+        if group_by.is_some() && (!order_by.is_empty() || limit.is_some()) {
+            return Err(self.error(
+                ParseErrorKind::Unsupported,
+                "GROUP BY with ORDER BY or LIMIT is not supported",
+            ));
+        }
+        self.skip_ws();
+        if self.peek_keyword("VALUES") {
+            if values.is_some() {
+                return Err(self.error(
+                    ParseErrorKind::Unsupported,
+                    "more than one VALUES is not supported",
+                ));
+            }
+            self.eat_keyword("VALUES");
+            values = Some(self.values()?);
+        }
+
+        let mut query = SelectQuery {
+            projection,
+            count,
+            group_by,
+            distinct,
+            patterns,
+            union,
+            optional,
+            filters,
+            string_filters,
+            conditions,
+            not_exists: not_exists.unwrap_or_default(),
+            binds,
+            values,
+            order_by,
+            limit,
+        };
+        self.projected_binds(&mut query, projected_binds)?;
+        if select_all {
+            query.projection = query
+                .variables_with_binds()
+                .into_iter()
+                .filter(|variable| !variable.is_blank_node())
+                .cloned()
+                .collect();
+        }
+        Ok(query)
+    }
+
+    /// Appends the `(lhs op rhs AS ?variable)` of the projection at their
+    /// offsets to the binds of `query`, as if bound right after its group,
+    /// where each operand has to be bound by the group or an earlier one.
+    fn projected_binds(
+        &mut self,
+        query: &mut SelectQuery,
+        projected: Vec<(Bind, usize)>,
+    ) -> Result<(), ParseError> {
+        let mut bound = query
+            .variables_with_binds()
+            .into_iter()
+            .cloned()
+            .collect::<HashSet<_>>();
+        for (idx, (bind, offset)) in projected.iter().enumerate() {
+            let unbound = bind.operand_variables().find(|v| !bound.contains(*v));
+            let message = match unbound {
+                Some(variable)
+                    if projected[idx..]
+                        .iter()
+                        .any(|(b, _)| b.variable == *variable) =>
+                {
+                    format!(
+                        "projection of {} references {variable} before it is projected",
+                        bind.variable
+                    )
+                }
+                Some(variable) => {
+                    format!(
+                        "projection of {} references {variable}, which is not bound by WHERE",
+                        bind.variable
+                    )
+                }
+                None if !bound.insert(bind.variable.clone()) => {
+                    format!("projected variable {} is already bound", bind.variable)
+                }
+                None => continue,
+            };
+            self.offset = *offset;
+            return Err(self.error(ParseErrorKind::Invalid, &message));
+        }
+        query
+            .binds
+            .extend(projected.into_iter().map(|(bind, _)| bind));
+        Ok(())
+    }
+
+    /// Parses the `GROUP BY` of a single variable if any, which has to be the
+    /// only projected variable, next to a count.
+    fn group_by(
+        &mut self,
+        select_all: bool,
+        distinct: bool,
+        projection: &[Variable],
+        counted: bool,
+    ) -> Result<Option<Variable>, ParseError> {
+        if !self.eat_keyword("GROUP") {
+            if counted && !projection.is_empty() {
+                return Err(self.error(
+                    ParseErrorKind::Invalid,
+                    &format!(
+                        "variable {} projected with COUNT is not grouped",
+                        projection[0]
+                    ),
+                ));
+            }
+            return Ok(None);
+        }
+        self.expect_keyword("BY")?;
+        let Some(variable) = self.variable()? else {
+            return Err(self.expected("variable"));
+        };
+        if self.variable()?.is_some() {
+            return Err(self.error(
+                ParseErrorKind::Unsupported,
+                "GROUP BY of more than one variable is not supported",
+            ));
+        }
+        if select_all {
+            return Err(self.error(
+                ParseErrorKind::Invalid,
+                "SELECT * with GROUP BY is not allowed",
+            ));
+        }
+        if let Some(other) = projection.iter().find(|v| **v != variable) {
+            return Err(self.error(
+                ParseErrorKind::Invalid,
+                &format!("variable {other} is projected but not grouped"),
+            ));
+        }
+        let reason = match (counted, projection.is_empty(), distinct) {
+            (false, _, _) => "GROUP BY without COUNT is not supported",
+            (_, true, _) => "GROUP BY of an unprojected variable is not supported",
+            (_, _, true) => "GROUP BY with DISTINCT is not supported",
+            _ => return Ok(Some(variable)),
+        };
+        Err(self.error(ParseErrorKind::Unsupported, reason))
+    }
+
+    /// Parses the conditions of `ORDER BY`, each `?variable`, `ASC(?variable)`
+    /// or `DESC(?variable)`.
+    fn order_conditions(&mut self) -> Result<Vec<OrderCondition>, ParseError> {
+        let mut conditions = Vec::new();
+        loop {
+            if let Some(variable) = self.variable()? {
+                conditions.push(OrderCondition {
+                    variable,
+                    descending: false,
+                });
+                continue;
+            }
+            let descending = if self.eat_keyword("ASC") {
+                false
+            } else if self.eat_keyword("DESC") {
+                true
+            } else {
+                break;
+            };
+            self.expect("(")?;
+            let Some(variable) = self.variable()? else {
+                return Err(self.expected("variable"));
+            };
+            self.expect(")")?;
+            conditions.push(OrderCondition {
+                variable,
+                descending,
+            });
+        }
+        if conditions.is_empty() {
+            return Err(self.expected("variable, ASC or DESC"));
+        }
+        Ok(conditions)
+    }
+
+    /// Parses the number of results after `LIMIT`.
+    fn limit(&mut self) -> Result<usize, ParseError> {
+        self.skip_ws();
+        let start = self.offset;
+        let digits = self.take_while(|c| c.is_ascii_digit());
+        if digits.is_empty() {
+            return Err(self.expected("number of results"));
+        }
+        digits.parse().map_err(|_| {
+            let span = start..self.offset;
+            self.error_at(
+                span,
+                ParseErrorKind::InvalidLiteral,
+                &format!("invalid LIMIT {digits:?}"),
+            )
+        })
+    }
+
+    /// Parses `COUNT([DISTINCT] ?variable) AS ?alias)` after the opening `(`.
+    fn count(&mut self) -> Result<Count, ParseError> {
+        self.expect_keyword("COUNT")?;
+        self.expect("(")?;
+        self.eat_keyword("DISTINCT");
+        let Some(variable) = self.variable()? else {
+            return Err(self.expected("variable"));
+        };
+        self.expect(")")?;
+        self.expect_keyword("AS")?;
+        let Some(alias) = self.variable()? else {
+            return Err(self.expected("variable"));
+        };
+        self.expect(")")?;
+        Ok(Count { variable, alias })
+    }
+
+    /// Parses the triple patterns, the `UNION`, the patterns of the
+    /// `OPTIONAL` block, the filters and the `VALUES` of a group, where the
+    /// `OPTIONAL` block can only be followed by filters and `VALUES`. A nested group not followed by
+    /// `UNION` is joined with the triple patterns.
+    fn group_graph_pattern(&mut self) -> Result<GroupGraphPattern, ParseError> {
+        self.expect("{")?;
+        let mut group = GroupGraphPattern::default();
+        let mut optional = None;
+        let mut bind_offsets = Vec::new();
+        loop {
+            if self.eat("}") {
+                break;
+            }
+            self.skip_ws();
+            if self.peek_keyword("BIND") {
+                bind_offsets.push(self.offset);
+                self.eat_keyword("BIND");
+                group.binds.push(self.bind()?);
+                self.eat(".");
+                continue;
+            }
+            if self.eat_keyword("FILTER") {
+                self.skip_ws();
+                let offset = self.offset;
+                if self.eat_keyword("EXISTS") {
+                    self.offset = offset;
+                    return Err(self.error(
+                        ParseErrorKind::Unsupported,
+                        "FILTER EXISTS is not supported",
+                    ));
+                }
+                if self.eat_keyword("NOT") {
+                    self.expect_keyword("EXISTS")?;
+                    if group.not_exists.is_some() {
+                        self.offset = offset;
+                        return Err(self.error(
+                            ParseErrorKind::Unsupported,
+                            "more than one NOT EXISTS is not supported",
+                        ));
+                    }
+                    group.not_exists = Some(self.nested_group("NOT EXISTS")?);
+                } else {
+                    let filter = self.filter()?;
+                    self.add_filter(&mut group, filter)?;
+                }
+                self.eat(".");
+                continue;
+            }
+            self.skip_ws();
+            if self.peek_keyword("VALUES") {
+                if group.values.is_some() {
+                    return Err(self.error(
+                        ParseErrorKind::Unsupported,
+                        "more than one VALUES is not supported",
+                    ));
+                }
+                self.eat_keyword("VALUES");
+                group.values = Some(self.values()?);
+                self.eat(".");
+                continue;
+            }
+            if optional.is_some() {
+                return Err(match self.peek_keyword("OPTIONAL") {
+                    true => self.error(
+                        ParseErrorKind::Unsupported,
+                        "more than one OPTIONAL block is not supported",
+                    ),
+                    false => self.expected("FILTER, VALUES or '}' after OPTIONAL block"),
+                });
+            }
+            if self.eat_keyword("OPTIONAL") {
+                optional = Some(self.nested_group("OPTIONAL")?);
+                self.eat(".");
+                continue;
+            }
+            if self.eat_keyword("GRAPH") {
+                group.patterns.extend(self.graph_group()?);
+                self.eat(".");
+                continue;
+            }
+            if self.rest().starts_with('{') {
+                let offset = self.offset;
+                let patterns = self.nested_group("UNION")?;
+                if !self.peek_keyword("UNION") {
+                    group.patterns.extend(patterns);
+                } else if group.union.is_some() {
+                    self.offset = offset;
+                    return Err(self.error(
+                        ParseErrorKind::Unsupported,
+                        "more than one UNION is not supported",
+                    ));
+                } else {
+                    let mut union = Pattern::Bgp(patterns);
+                    while self.eat_keyword("UNION") {
+                        let rhs = Pattern::Bgp(self.nested_group("UNION")?);
+                        union = Pattern::Union(Box::new(union), Box::new(rhs));
+                    }
+                    group.union = Some(union);
+                }
+                self.eat(".");
+                continue;
+            }
+            group.patterns.extend(self.triple_patterns()?);
+            if !self.eat(".")
+                && !self.peek_keyword("FILTER")
+                && !self.peek_keyword("OPTIONAL")
+                && !self.peek_keyword("VALUES")
+                && !self.peek_keyword("GRAPH")
+                && !self.peek_keyword("BIND")
+                && !self.rest().starts_with('{')
+            {
+                self.expect("}")?;
+                break;
+            }
+        }
+        group.optional = optional.unwrap_or_default();
+        let union = group.union.iter().flat_map(Pattern::branches).flatten();
+        let mut bound = group
+            .patterns
+            .iter()
+            .chain(union)
+            .chain(&group.optional)
+            .flat_map(TriplePattern::variables)
+            .collect::<HashSet<_>>();
+        for (bind, offset) in group.binds.iter().zip(bind_offsets) {
+            if !bound.insert(&bind.variable) {
+                self.offset = offset;
+                return Err(self.error(
+                    ParseErrorKind::Invalid,
+                    &format!("BIND variable {} is already bound", bind.variable),
+                ));
+            }
+        }
+        Ok(group)
+    }
+
+    /// Parses the triple patterns of a nested `{ ... }` of `keyword`, which is
+    /// `OPTIONAL` or `UNION`.
+    fn nested_group(&mut self, keyword: &str) -> Result<Vec<TriplePattern>, ParseError> {
+        self.expect("{")?;
+        let mut patterns = Vec::new();
+        loop {
+            if self.eat("}") {
+                return Ok(patterns);
+            }
+            self.skip_ws();
+            if self.peek_keyword("OPTIONAL") {
+                return Err(match keyword {
+                    "OPTIONAL" => self.error(
+                        ParseErrorKind::Unsupported,
+                        "nested OPTIONAL is not supported",
+                    ),
+                    _ => self.error(
+                        ParseErrorKind::Unsupported,
+                        &format!("OPTIONAL in {keyword} is not supported"),
+                    ),
+                });
+            }
+            if self.peek_keyword("FILTER") {
+                return Err(self.error(
+                    ParseErrorKind::Unsupported,
+                    &format!("FILTER in {keyword} is not supported"),
+                ));
+            }
+            if self.peek_keyword("BIND") {
+                return Err(self.error(
+                    ParseErrorKind::Unsupported,
+                    &format!("BIND in {keyword} is not supported"),
+                ));
+            }
+            if self.rest().starts_with('{') {
+                return Err(self.error(
+                    ParseErrorKind::Unsupported,
+                    &format!("nested group in {keyword} is not supported"),
+                ));
+            }
+            if self.peek_keyword("GRAPH") {
+                if keyword == "GRAPH" {
+                    return Err(
+                        self.error(ParseErrorKind::Unsupported, "nested GRAPH is not supported")
+                    );
+                }
+                self.eat_keyword("GRAPH");
+                patterns.extend(self.graph_group()?);
+                self.eat(".");
+                continue;
+            }
+            patterns.extend(self.triple_patterns()?);
+            if !self.eat(".")
+                && !self.peek_keyword("OPTIONAL")
+                && !self.peek_keyword("FILTER")
+                && !self.peek_keyword("BIND")
+                && !self.peek_keyword("GRAPH")
+            {
+                self.expect("}")?;
+                return Ok(patterns);
+            }
+        }
+    }
+
+    /// Parses `<iri> { ... }` or `?g { ... }` after `GRAPH`, returning the
+    /// triple patterns of the group in that graph.
+    fn graph_group(&mut self) -> Result<Vec<TriplePattern>, ParseError> {
+        let graph = match self.variable()? {
+            Some(variable) => TermPattern::Variable(variable),
+            None => match self.iri()? {
+                Some(iri) => TermPattern::Iri(iri),
+                None => return Err(self.expected("graph IRI or variable")),
+            },
+        };
+        let mut patterns = self.nested_group("GRAPH")?;
+        for pattern in patterns.iter_mut() {
+            pattern.graph = Some(graph.clone());
+        }
+        Ok(patterns)
+    }
+
+    /// Parses `?variable { term ... }` after `VALUES`, whose terms are IRIs or
+    /// literals.
+    fn values(&mut self) -> Result<Values, ParseError> {
+        self.skip_ws();
+        if self.rest().starts_with('(') {
+            return Err(self.error(
+                ParseErrorKind::Unsupported,
+                "VALUES of a variable list is not supported",
+            ));
+        }
+        let Some(variable) = self.variable()? else {
+            return Err(self.expected("variable"));
+        };
+        self.expect("{")?;
+        let mut terms = Vec::new();
+        while !self.eat("}") {
+            if self.peek_keyword("UNDEF") {
+                return Err(self.error(
+                    ParseErrorKind::Unsupported,
+                    "UNDEF in VALUES is not supported",
+                ));
+            }
+            if let Some(iri) = self.iri()? {
+                terms.push(TermPattern::Iri(iri));
+            } else if let Some(literal) = self.literal()? {
+                terms.push(TermPattern::Literal(literal));
+            } else {
+                return Err(self.expected("IRI, literal or '}'"));
+            }
+        }
+        Ok(Values { variable, terms })
+    }
+
+    /// Parses the parenthesized condition of a `FILTER`, comparisons combined
+    /// by `||`, `&&` and `!` in order of increasing precedence and
+    /// parenthesized.
+    fn filter(&mut self) -> Result<ParsedFilter, ParseError> {
+        self.expect("(")?;
+        let filter = self.filter_or()?;
+        self.expect(")")?;
+        Ok(filter)
+    }
+
+    fn filter_or(&mut self) -> Result<ParsedFilter, ParseError> {
+        let mut filter = self.filter_and()?;
+        while self.eat("||") {
+            let rhs = self.filter_and()?;
+            filter = ParsedFilter::Or(Box::new(filter), Box::new(rhs));
+        }
+        Ok(filter)
+    }
+
+    fn filter_and(&mut self) -> Result<ParsedFilter, ParseError> {
+        let mut filter = self.filter_unary()?;
+        while self.eat("&&") {
+            let rhs = self.filter_unary()?;
+            filter = ParsedFilter::And(Box::new(filter), Box::new(rhs));
+        }
+        Ok(filter)
+    }
+
+    fn filter_unary(&mut self) -> Result<ParsedFilter, ParseError> {
+        if self.eat("!") {
+            return Ok(ParsedFilter::Not(Box::new(self.filter_unary()?)));
+        }
+        self.skip_ws();
+        if self.rest().starts_with('(') {
+            return self.filter();
+        }
+        self.comparison()
+    }
+
+    /// Parses `?variable op value` or `value op ?variable` of an integer or
+    /// `xsd:dateTime` literal `value`, or `?variable = "string"` or
+    /// `"string" = ?variable` as a [`StringFilter`].
+    fn comparison(&mut self) -> Result<ParsedFilter, ParseError> {
+        self.skip_ws();
+        let start = self.offset;
+        let filter = match self.variable()? {
+            Some(variable) => {
+                self.skip_ws();
+                let op_start = self.offset;
+                let op = self.compare_op()?;
+                let op_span = op_start..self.offset;
+                match self.filter_operand()? {
+                    FilterOperand::Value(value, datatype) => FilterExpr {
+                        variable,
+                        op,
+                        value,
+                        datatype,
+                    },
+                    FilterOperand::String(_) if op != CompareOp::Eq => {
+                        return Err(self.unsupported_string_op(op_span, op));
+                    }
+                    FilterOperand::String(value) => {
+                        let filter = StringFilter { variable, value };
+                        return Ok(ParsedFilter::String(filter, start..self.offset));
+                    }
+                }
+            }
+            None => {
+                let operand = self.filter_operand()?;
+                self.skip_ws();
+                let op_start = self.offset;
+                let op = self.compare_op()?;
+                if matches!(operand, FilterOperand::String(_)) && op != CompareOp::Eq {
+                    return Err(self.unsupported_string_op(op_start..self.offset, op));
+                }
+                let Some(variable) = self.variable()? else {
+                    return Err(self.expected("variable"));
+                };
+                match operand {
+                    FilterOperand::Value(value, datatype) => FilterExpr {
+                        variable,
+                        op: op.flip(),
+                        value,
+                        datatype,
+                    },
+                    FilterOperand::String(value) => {
+                        let filter = StringFilter { variable, value };
+                        return Ok(ParsedFilter::String(filter, start..self.offset));
+                    }
+                }
+            }
+        };
+        Ok(ParsedFilter::Compare(filter))
+    }
+
+    /// Adds the conjuncts of `filter` to `group`, each comparison to its
+    /// filters or string filters and any other to its conditions, which fails
+    /// for one with a string comparison.
+    fn add_filter(
+        &self,
+        group: &mut GroupGraphPattern,
+        filter: ParsedFilter,
+    ) -> Result<(), ParseError> {
+        match filter {
+            ParsedFilter::Compare(filter) => group.filters.push(filter),
+            ParsedFilter::String(filter, _) => group.string_filters.push(filter),
+            ParsedFilter::And(lhs, rhs) => {
+                self.add_filter(group, *lhs)?;
+                self.add_filter(group, *rhs)?;
+            }
+            filter => {
+                let condition = filter.into_condition().map_err(|span| {
+                    let message = "string comparison under || or ! is not supported";
+                    self.error_at(span, ParseErrorKind::Unsupported, message)
+                })?;
+                group.conditions.push(condition);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the constant of a comparison, an integer, an `xsd:dateTime`
+    /// literal in any timezone or the `xsd:string` literal of a
+    /// [`StringFilter`].
+    fn filter_operand(&mut self) -> Result<FilterOperand, ParseError> {
+        self.skip_ws();
+        if self.peek() != Some('"') {
+            let value = self.filter_value()?;
+            return Ok(FilterOperand::Value(value, FilterDatatype::Integer));
+        }
+        let offset = self.offset;
+        let literal = self.string_literal()?;
+        let span = offset..self.offset;
+        match literal.datatype.as_str() {
+            XSD_DATE_TIME => {
+                let canonical = literal.canonical();
+                match canonical.as_ref().unwrap_or(&literal).as_date_time() {
+                    Some(value) => Ok(FilterOperand::Value(value, FilterDatatype::DateTime)),
+                    None => {
+                        let message = format!("invalid xsd:dateTime literal {:?}", literal.lexical);
+                        Err(self.error_at(span, ParseErrorKind::InvalidLiteral, &message))
+                    }
+                }
+            }
+            XSD_STRING if literal.language.is_none() => Ok(FilterOperand::String(literal.lexical)),
+            _ => Err(self.error_at(
+                span,
+                ParseErrorKind::Unsupported,
+                "FILTER of a literal other than a string or xsd:dateTime is not supported",
+            )),
+        }
+    }
+
+    /// Returns the error of comparing a string literal by `op` at `span`,
+    /// which only `=` does.
+    fn unsupported_string_op(&self, span: Range<usize>, op: CompareOp) -> ParseError {
+        let message = format!("{op} of a string literal is not supported");
+        self.error_at(span, ParseErrorKind::Unsupported, &message)
+    }
+
+    /// Parses `(lhs op rhs AS ?variable)` after `BIND`, whose operands are
+    /// variables or integers.
+    fn bind(&mut self) -> Result<Bind, ParseError> {
+        self.expect("(")?;
+        let lhs = self.operand()?;
+        self.skip_ws();
+        let ops = [
+            ("+", ArithOp::Add),
+            ("-", ArithOp::Sub),
+            ("*", ArithOp::Mul),
+        ];
+        let Some((_, op)) = ops.into_iter().find(|(token, _)| self.eat(token)) else {
+            return Err(self.expected("'+', '-' or '*'"));
+        };
+        let rhs = self.operand()?;
+        self.expect_keyword("AS")?;
+        let Some(variable) = self.variable()? else {
+            return Err(self.expected("variable"));
+        };
+        self.expect(")")?;
+        Ok(Bind {
+            lhs,
+            op,
+            rhs,
+            variable,
+        })
+    }
+
+    /// Parses a variable or integer operand of a `BIND`, failing with
+    /// [`ParseErrorKind::Unsupported`] for a decimal or double, e.g. `1.5`,
+    /// `.5`, `1e3` or `"1.5"^^xsd:decimal`, rather than truncating it.
+    fn operand(&mut self) -> Result<Operand, ParseError> {
+        if let Some(variable) = self.variable()? {
+            return Ok(Operand::Variable(variable));
+        }
+        self.skip_ws();
+        let start = self.offset;
+        if self.peek() == Some('"') {
+            let literal = self.string_literal()?;
+            let span = start..self.offset;
+            let datatype = match literal.datatype.as_str() {
+                XSD_DECIMAL => "decimal",
+                XSD_DOUBLE => "double",
+                _ => {
+                    self.offset = start;
+                    return Err(self.expected("variable or integer"));
+                }
+            };
+            let message = format!("{datatype} operand of BIND is not supported");
+            return Err(self.error_at(span, ParseErrorKind::Unsupported, &message));
+        }
+        let rest = self.rest();
+        let unsigned = rest.trim_start_matches(['+', '-']);
+        let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let mut len = rest.len() - unsigned.len() + digits(unsigned);
+        let fraction = rest[len..].strip_prefix('.').map_or(0, digits);
+        if fraction > 0 {
+            len += 1 + fraction;
+        }
+        if len == rest.len() - unsigned.len() {
+            return Err(self.expected("variable or integer"));
+        }
+        let exponent = rest[len..]
+            .strip_prefix(['e', 'E'])
+            .map(|exponent| exponent.trim_start_matches(['+', '-']))
+            .filter(|exponent| digits(exponent) > 0);
+        let datatype = match exponent {
+            Some(exponent) => {
+                len = rest.len() - exponent.len() + digits(exponent);
+                "double"
+            }
+            None if fraction > 0 => "decimal",
+            None => return Ok(Operand::Integer(self.filter_value()?)),
+        };
+        let message = format!("{datatype} operand of BIND is not supported");
+        Err(self.error_at(start..start + len, ParseErrorKind::Unsupported, &message))
+    }
+
+    fn compare_op(&mut self) -> Result<CompareOp, ParseError> {
+        // Two-character operators first so `<=` isn't taken as `<`.
+        let ops = [
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("!=", CompareOp::Ne),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+            ("=", CompareOp::Eq),
+        ];
+        match ops.into_iter().find(|(token, _)| self.eat(token)) {
+            Some((_, op)) => Ok(op),
+            None => Err(self.expected("comparison operator")),
+        }
+    }
+
+    fn filter_value(&mut self) -> Result<i64, ParseError> {
+        self.skip_ws();
+        if !matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '+' || c == '-') {
+            return Err(self.expected("integer"));
+        }
+        let literal = self.integer_literal()?;
+        Ok(literal.as_integer().unwrap())
+    }
+
+    /// Parses a triple pattern, or a sequence path `?s <p>/<q> ?o` of up to
+    /// [`MAX_PATH_LENGTH`] IRIs into a pattern per step, chained through a
+    /// fresh [`Variable::path_node`] between consecutive steps.
+    fn triple_patterns(&mut self) -> Result<Vec<TriplePattern>, ParseError> {
+        let subject = self.node("subject")?;
+        self.skip_ws();
+        let mut predicates = vec![(self.offset, self.predicate()?)];
+        loop {
+            self.skip_ws();
+            let rest = self.rest();
+            if rest.starts_with('*')
+                || (rest.starts_with('+') && !rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+            {
+                let message = format!("property path {} is not supported", &rest[..1]);
+                return Err(self.error(ParseErrorKind::Unsupported, &message));
+            }
+            if !rest.starts_with('/') {
+                break;
+            }
+            if predicates.len() == MAX_PATH_LENGTH {
+                let message =
+                    format!("property path of more than {MAX_PATH_LENGTH} steps is not supported");
+                return Err(self.error(ParseErrorKind::Unsupported, &message));
+            }
+            self.offset += 1;
+            self.skip_ws();
+            predicates.push((self.offset, self.predicate()?));
+        }
+        if predicates.len() > 1 {
+            if let Some((offset, _)) = predicates
+                .iter()
+                .find(|(_, predicate)| !matches!(predicate, TermPattern::Iri(_)))
+            {
+                self.offset = *offset;
+                return Err(self.error(
+                    ParseErrorKind::Invalid,
+                    "property path of other than IRIs is not allowed",
+                ));
+            }
+        }
+        let object = self.node("object")?;
+        let (_, last) = predicates.pop().unwrap();
+        let mut patterns = Vec::with_capacity(predicates.len() + 1);
+        let mut subject = subject;
+        for (_, predicate) in predicates {
+            let node = TermPattern::Variable(Variable::path_node(self.path_nodes));
+            self.path_nodes += 1;
+            patterns.push(TriplePattern {
+                subject,
+                predicate,
+                object: node.clone(),
+                graph: None,
+            });
+            subject = node;
+        }
+        patterns.push(TriplePattern {
+            subject,
+            predicate: last,
+            object,
+            graph: None,
+        });
+        Ok(patterns)
+    }
+
+    fn predicate(&mut self) -> Result<TermPattern, ParseError> {
+        if self.eat_keyword("a") {
+            return Ok(TermPattern::Iri(Iri(RDF_TYPE.to_string())));
+        }
+        self.skip_ws();
+        if self.rest().starts_with("_:") {
+            return Err(self.error(
+                ParseErrorKind::Invalid,
+                "blank node as predicate is not allowed",
+            ));
+        }
+        self.term("predicate")
+    }
+
+    /// Parses a subject or object, which is a term or a blank node `_:label`
+    /// standing for [`Variable::blank_node`].
+    fn node(&mut self, position: &str) -> Result<TermPattern, ParseError> {
+        self.skip_ws();
+        if !self.rest().starts_with("_:") {
+            return self.term(position);
+        }
+        self.offset += 2;
+        let label = self.take_while(is_name_char);
+        if label.is_empty() {
+            return Err(self.expected("blank node label"));
+        }
+        Ok(TermPattern::Variable(Variable::blank_node(label)))
+    }
+
+    fn term(&mut self, position: &str) -> Result<TermPattern, ParseError> {
+        if let Some(variable) = self.variable()? {
+            return Ok(TermPattern::Variable(variable));
+        }
+        if let Some(iri) = self.iri()? {
+            return Ok(TermPattern::Iri(iri));
+        }
+        if let Some(literal) = self.literal()? {
+            return Ok(TermPattern::Literal(literal));
+        }
+        Err(self.expected(&format!("{position} variable, IRI or literal")))
+    }
+
+    fn variable(&mut self) -> Result<Option<Variable>, ParseError> {
+        self.skip_ws();
+        if !matches!(self.peek(), Some('?' | '$')) {
+            return Ok(None);
+        }
+        self.offset += 1;
+        let name = self.take_while(is_name_char);
+        if name.is_empty() {
+            return Err(self.expected("variable name"));
+        }
+        Ok(Some(Variable(name.to_string())))
+    }
+
+    fn iri(&mut self) -> Result<Option<Iri>, ParseError> {
+        self.skip_ws();
+        if self.peek() != Some('<') {
+            return self.prefixed_name();
+        }
+        let start = self.offset;
+        self.offset += 1;
+        let iri = self.take_while(|c| c != '>' && !c.is_whitespace());
+        if !self.rest().starts_with('>') {
+            let span = start..self.offset;
+            return Err(self.error_at(span, ParseErrorKind::UnterminatedIri, "unterminated IRI"));
+        }
+        self.offset += 1;
+        Ok(Some(Iri(iri.to_string())))
+    }
+
+    /// Parses `prefix:local` into the IRI of `prefix` followed by `local`,
+    /// whose dots are kept unless trailing.
+    fn prefixed_name(&mut self) -> Result<Option<Iri>, ParseError> {
+        let start = self.offset;
+        if matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        let name = self.take_while(is_name_char);
+        if !self.rest().starts_with(':') {
+            self.offset = start;
+            return Ok(None);
+        }
+        let Some(iri) = self.prefixes.get(name) else {
+            let span = start..self.offset + 1;
+            let message = format!("unknown prefix \"{name}:\"");
+            return Err(self.error_at(span, ParseErrorKind::UnknownPrefix, &message));
+        };
+        let mut iri = iri.clone();
+        self.offset += 1;
+        let local_start = self.offset;
+        let local = self.take_while(|c| is_name_char(c) || c == '-' || c == '.');
+        let local = local.trim_end_matches('.');
+        self.offset = local_start + local.len();
+        iri.push_str(local);
+        Ok(Some(Iri(iri)))
+    }
+
+    fn literal(&mut self) -> Result<Option<Literal>, ParseError> {
+        self.skip_ws();
+        match self.peek() {
+            Some('"') => self.string_literal().map(Some),
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' => {
+                self.integer_literal().map(Some)
+            }
+            _ => {
+                let rest = self.rest();
+                let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                let value = match &rest[..len] {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Ok(None),
+                };
+                self.offset += len;
+                Ok(Some(Literal::boolean(value)))
+            }
+        }
+    }
+
+    fn string_literal(&mut self) -> Result<Literal, ParseError> {
+        let start = self.offset;
+        self.offset += 1;
+        let mut lexical = String::new();
+        loop {
+            let Some(c) = self.peek() else {
+                let span = start..self.offset;
+                let message = "unterminated string literal";
+                return Err(self.error_at(span, ParseErrorKind::UnterminatedLiteral, message));
+            };
+            self.offset += c.len_utf8();
+            match c {
+                '"' => break,
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        c => {
+                            let end = self.offset + c.map_or(0, char::len_utf8);
+                            let span = self.offset - 1..end;
+                            let message = "invalid escape sequence";
+                            return Err(self.error_at(
+                                span,
+                                ParseErrorKind::InvalidLiteral,
+                                message,
+                            ));
+                        }
+                    };
+                    self.offset += 1;
+                    lexical.push(escaped);
+                }
+                '\n' | '\r' => {
+                    let span = start..self.offset - 1;
+                    let message = "line break in string literal";
+                    return Err(self.error_at(span, ParseErrorKind::UnterminatedLiteral, message));
+                }
+                c => lexical.push(c),
+            }
+        }
+        if self.rest().starts_with('@') {
+            self.offset += 1;
+            let language = self.take_while(|c| c.is_ascii_alphanumeric() || c == '-');
+            if language.is_empty() {
+                return Err(self.expected("language tag"));
+            }
+            return Ok(Literal::lang_string(lexical, language));
+        }
+        let mut literal = Literal::string(lexical);
+        if self.rest().starts_with("^^") {
+            self.offset += 2;
+            literal.datatype = match self.iri()? {
+                Some(datatype) => datatype,
+                None => return Err(self.expected("datatype IRI")),
+            };
+        }
+        Ok(literal)
+    }
+
+    fn integer_literal(&mut self) -> Result<Literal, ParseError> {
+        let start = self.offset;
+        if matches!(self.peek(), Some('+' | '-')) {
+            self.offset += 1;
+        }
+        self.take_while(|c| c.is_ascii_digit());
+        let lexical = &self.input[start..self.offset];
+        match lexical.parse::<i64>() {
+            Ok(value) => Ok(Literal::integer(value)),
+            Err(_) => {
+                let message = format!("invalid integer literal {lexical:?}");
+                Err(self.error_at(start..self.offset, ParseErrorKind::InvalidLiteral, &message))
+            }
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.offset += len;
+        &rest[..len]
+    }
+
+    /// Skips whitespace and `#` comments.
+    fn skip_ws(&mut self) {
+        loop {
+            self.take_while(char::is_whitespace);
+            if !self.rest().starts_with('#') {
+                return;
+            }
+            self.take_while(|c| c != '\n');
+        }
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        let eaten = self.rest().starts_with(token);
+        if eaten {
+            self.offset += token.len();
+        }
+        eaten
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.expected(&format!("'{token}'"))),
+        }
+    }
+
+    /// Eats `keyword` case-insensitively if it isn't followed by more name
+    /// characters.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.skip_ws();
+        let rest = self.rest();
+        let eaten = rest
+            .get(..keyword.len())
+            .map_or(false, |prefix| prefix.eq_ignore_ascii_case(keyword))
+            && !rest[keyword.len()..].starts_with(is_name_char);
+        if eaten {
+            self.offset += keyword.len();
+        }
+        eaten
+    }
+
+    fn peek_keyword(&mut self, keyword: &str) -> bool {
+        let offset = self.offset;
+        let eaten = self.eat_keyword(keyword);
+        self.offset = offset;
+        eaten
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.expected(keyword)),
+        }
+    }
+
+    /// Returns an error about the token at the offset, see [`Self::token_len`].
+    fn error(&self, kind: ParseErrorKind, message: &str) -> ParseError {
+        self.error_at(self.offset..self.offset + self.token_len(), kind, message)
+    }
+
+    fn error_at(&self, span: Range<usize>, kind: ParseErrorKind, message: &str) -> ParseError {
+        ParseError {
+            offset: span.start,
+            span,
+            kind,
+            expected: None,
+            message: message.to_string(),
+        }
+    }
+
+    fn expected(&mut self, what: &str) -> ParseError {
+        self.skip_ws();
+        let (kind, found) = match self.rest().split_whitespace().next() {
+            Some(token) => (ParseErrorKind::UnexpectedToken, format!("{token:?}")),
+            None => (ParseErrorKind::UnexpectedEnd, "end of input".to_string()),
+        };
+        ParseError {
+            expected: Some(what.to_string()),
+            ..self.error(kind, &format!("expected {what}, found {found}"))
+        }
+    }
+
+    /// Returns the length in bytes of the token at the offset, which is a
+    /// name, variable or IRI, or else a single character.
+    fn token_len(&self) -> usize {
+        let rest = self.rest();
+        let name_len = |rest: &str| rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        match rest.chars().next() {
+            None => 0,
+            Some(c) if is_name_char(c) => name_len(rest),
+            Some('?' | '$') => 1 + name_len(&rest[1..]),
+            Some('<') => match rest.find(|c: char| c == '>' || c.is_whitespace()) {
+                Some(idx) if rest[idx..].starts_with('>') => idx + 1,
+                _ => 1,
+            },
+            Some(c) => c.len_utf8(),
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use crate::query::{
+        parse_select, ArithOp, Bind, CompareOp, Count, FilterCondition, FilterDatatype, FilterExpr,
+        Iri, Literal, Operand, OrderCondition, ParseErrorKind, Pattern, StringFilter, TermPattern,
+        TriplePattern, Values, Variable, RDF_TYPE,
+    };
+
+    fn var(name: &str) -> TermPattern {
+        TermPattern::Variable(Variable(name.to_string()))
+    }
+
+    fn iri(iri: &str) -> TermPattern {
+        TermPattern::Iri(Iri(iri.to_string()))
+    }
+
+    #[test]
+    fn basic_graph_pattern() {
+        let query = parse_select(
+            "SELECT ?a ?b WHERE { ?a <http://ex.org/p> ?b . ?b <http://ex.org/q> \"c\" . }",
+        )
+        .unwrap();
+        assert_eq!(
+            query.projection,
+            [Variable("a".to_string()), Variable("b".to_string())]
+        );
+        assert_eq!(
+            query.patterns,
+            [
+                TriplePattern {
+                    subject: var("a"),
+                    predicate: iri("http://ex.org/p"),
+                    object: var("b"),
+                    graph: None,
+                },
+                TriplePattern {
+                    subject: var("b"),
+                    predicate: iri("http://ex.org/q"),
+                    object: TermPattern::Literal(Literal::string("c")),
+                    graph: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn select_all_and_keywords() {
+        let query = parse_select("select * { ?s a ?o . ?o <p> -42 }").unwrap();
+        assert_eq!(
+            query.projection,
+            [Variable("s".to_string()), Variable("o".to_string())]
+        );
+        assert_eq!(query.patterns[0].predicate, iri(RDF_TYPE));
+        assert_eq!(
+            query.patterns[1].object,
+            TermPattern::Literal(Literal::integer(-42))
+        );
+
+        let query = parse_select("SELECT ?s { ?s <name> \"Bob\"@EN-gb }").unwrap();
+        assert!(!query.distinct);
+        assert_eq!(
+            query.patterns[0].object,
+            TermPattern::Literal(Literal::lang_string("Bob", "en-GB"))
+        );
+
+        for input in [
+            "SELECT DISTINCT ?s { ?s a ?o }",
+            "select distinct* { ?s a ?o }",
+        ] {
+            let query = parse_select(input).unwrap();
+            assert!(query.distinct);
+            assert_eq!(query.projection[0], Variable("s".to_string()));
+        }
+    }
+
+    #[test]
+    fn filter() {
+        let query = parse_select(
+            "SELECT ?p { ?p <age> ?age FILTER(?age >= 18) . ?p <id> ?id . FILTER (-3 < ?id) }",
+        )
+        .unwrap();
+        assert_eq!(query.patterns.len(), 2);
+        assert_eq!(
+            query.filters,
+            [
+                FilterExpr {
+                    variable: Variable("age".to_string()),
+                    op: CompareOp::Ge,
+                    value: 18,
+                    datatype: FilterDatatype::Integer,
+                },
+                FilterExpr {
+                    variable: Variable("id".to_string()),
+                    op: CompareOp::Gt,
+                    value: -3,
+                    datatype: FilterDatatype::Integer,
+                },
+            ]
+        );
+
+        let err = parse_select("SELECT ?p { ?p <age> ?age FILTER(?age ~ 18) }").unwrap_err();
+        assert_eq!(err.message, "expected comparison operator, found \"~\"");
+    }
+
+    #[test]
+    fn filter_date_time() {
+        let query = parse_select(
+            "PREFIX xsd: <http://www.w3.org/2001/XMLSchema#> \
+             SELECT ?e { ?e <at> ?t . ?e <done> true \
+             FILTER(\"2020-01-01T01:00:00+01:00\"^^xsd:dateTime <= ?t) }",
+        )
+        .unwrap();
+        let filter = FilterExpr {
+            variable: Variable("t".to_string()),
+            op: CompareOp::Ge,
+            value: 1577836800,
+            datatype: FilterDatatype::DateTime,
+        };
+        assert_eq!(query.filters, [filter]);
+        assert_eq!(
+            query.filters[0].to_string(),
+            "FILTER(?t >= \"2020-01-01T00:00:00Z\"^^<http://www.w3.org/2001/XMLSchema#dateTime>)"
+        );
+        assert_eq!(
+            query.patterns[1].object,
+            TermPattern::Literal(Literal::boolean(true))
+        );
+
+        let err = parse_select(
+            "SELECT ?e { ?e <at> ?t \
+             FILTER(?t > \"2020-13-01T00:00:00Z\"^^<http://www.w3.org/2001/XMLSchema#dateTime>) }",
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, ParseErrorKind::InvalidLiteral);
+        assert_eq!(
+            err.message,
+            "invalid xsd:dateTime literal \"2020-13-01T00:00:00Z\""
+        );
+    }
+
+    #[test]
+    fn filter_condition() {
+        let query = parse_select(
+            "SELECT ?p { ?p <age> ?age FILTER(?age > 18 && (?age < 65)) \
+             FILTER(?age < 18 || !(?age <= 65) && ?age != 70) }",
+        )
+        .unwrap();
+        let compare = |op, value| FilterExpr {
+            variable: Variable("age".to_string()),
+            op,
+            value,
+            datatype: FilterDatatype::Integer,
+        };
+        assert_eq!(
+            query.filters,
+            [compare(CompareOp::Gt, 18), compare(CompareOp::Lt, 65)]
+        );
+        let condition = |op, value| Box::new(FilterCondition::Compare(compare(op, value)));
+        assert_eq!(
+            query.conditions,
+            [FilterCondition::Or(
+                condition(CompareOp::Lt, 18),
+                Box::new(FilterCondition::And(
+                    Box::new(FilterCondition::Not(condition(CompareOp::Le, 65))),
+                    condition(CompareOp::Ne, 70),
+                )),
+            )]
+        );
+        assert_eq!(
+            query.conditions[0].to_string(),
+            "(?age < 18) || ((!(?age <= 65)) && (?age != 70))"
+        );
+
+        let query =
+            parse_select(r#"SELECT ?p { ?p <name> ?n FILTER(?n = "Alice" && ?n != 1) }"#).unwrap();
+        assert_eq!(query.string_filters.len(), 1);
+        assert_eq!(query.filters.len(), 1);
+
+        let err = parse_select(r#"SELECT ?p { ?p <name> ?n FILTER(?n = "Alice" || ?n > 1) }"#)
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "string comparison under || or ! is not supported"
+        );
+        assert_eq!(err.kind, ParseErrorKind::Unsupported);
+        assert_eq!(err.span, 32..44);
+    }
+
+    #[test]
+    fn string_filter() {
+        let query = parse_select(
+            r#"SELECT ?p { ?p <name> ?n FILTER(?n = "Al\"ice") FILTER ("Bob" = ?n) FILTER(?n > 1) }"#,
+        )
+        .unwrap();
+        let filter = |value: &str| StringFilter {
+            variable: Variable("n".to_string()),
+            value: value.to_string(),
+        };
+        assert_eq!(query.string_filters, [filter("Al\"ice"), filter("Bob")]);
+        assert_eq!(query.filters.len(), 1);
+        assert_eq!(
+            query.string_filters[0].to_string(),
+            r#"FILTER(?n = "Al\"ice")"#
+        );
+
+        for (input, message, span) in [
+            (
+                r#"SELECT ?p { ?p <name> ?n FILTER(?n != "Alice") }"#,
+                "!= of a string literal is not supported",
+                35..37,
+            ),
+            (
+                r#"SELECT ?p { ?p <name> ?n FILTER("Alice" < ?n) }"#,
+                "< of a string literal is not supported",
+                40..41,
+            ),
+            (
+                r#"SELECT ?p { ?p <name> ?n FILTER(?n = "Alice"@en) }"#,
+                "FILTER of a literal other than a string or xsd:dateTime is not supported",
+                37..47,
+            ),
+        ] {
+            let err = parse_select(input).unwrap_err();
+            assert_eq!(err.message, message);
+            assert_eq!(err.kind, ParseErrorKind::Unsupported);
+            assert_eq!(err.span, span);
+        }
+    }
+
+    #[test]
+    fn count() {
+        for input in [
+            "SELECT (COUNT(?x) AS ?n) WHERE { ?x <type> <Person> }",
+            "SELECT (count(distinct ?x) as ?n) { ?x <type> <Person> }",
+        ] {
+            let query = parse_select(input).unwrap();
+            assert!(query.projection.is_empty());
+            assert_eq!(
+                query.count,
+                Some(Count {
+                    variable: Variable("x".to_string()),
+                    alias: Variable("n".to_string()),
+                })
+            );
+        }
+
+        let err = parse_select("SELECT (COUNT(?x)) { ?x <type> <Person> }").unwrap_err();
+        assert_eq!(err.message, "expected AS, found \")\"");
+    }
+
+    #[test]
+    fn group_by() {
+        let query =
+            parse_select("SELECT ?t (COUNT(?x) AS ?n) WHERE { ?x <type> ?t } GROUP BY ?t").unwrap();
+        assert_eq!(query.projection, [Variable("t".to_string())]);
+        assert_eq!(query.group_by, Some(Variable("t".to_string())));
+        assert_eq!(
+            query.count,
+            Some(Count {
+                variable: Variable("x".to_string()),
+                alias: Variable("n".to_string()),
+            })
+        );
+
+        for (input, kind, message) in [
+            (
+                "SELECT ?t (COUNT(?x) AS ?n) { ?x <type> ?t }",
+                ParseErrorKind::Invalid,
+                "variable ?t projected with COUNT is not grouped",
+            ),
+            (
+                "SELECT ?x (COUNT(?x) AS ?n) { ?x <type> ?t } GROUP BY ?t",
+                ParseErrorKind::Invalid,
+                "variable ?x is projected but not grouped",
+            ),
+            (
+                "SELECT * { ?x <type> ?t } GROUP BY ?t",
+                ParseErrorKind::Invalid,
+                "SELECT * with GROUP BY is not allowed",
+            ),
+            (
+                "SELECT ?t { ?x <type> ?t } GROUP BY ?t",
+                ParseErrorKind::Unsupported,
+                "GROUP BY without COUNT is not supported",
+            ),
+            (
+                "SELECT (COUNT(?x) AS ?n) { ?x <type> ?t } GROUP BY ?t",
+                ParseErrorKind::Unsupported,
+                "GROUP BY of an unprojected variable is not supported",
+            ),
+            (
+                "SELECT ?t (COUNT(?x) AS ?n) { ?x <type> ?t } GROUP BY ?t ?x",
+                ParseErrorKind::Unsupported,
+                "GROUP BY of more than one variable is not supported",
+            ),
+            (
+                "SELECT ?t (COUNT(?x) AS ?n) { ?x <type> ?t } GROUP BY ?t LIMIT 1",
+                ParseErrorKind::Unsupported,
+                "GROUP BY with ORDER BY or LIMIT is not supported",
+            ),
+        ] {
+            let err = parse_select(input).unwrap_err();
+            assert_eq!((err.kind, err.message.as_str()), (kind, message), "{input}");
+        }
+    }
+
+    #[test]
+    fn optional() {
+        let query = parse_select(
+            "SELECT * { ?p <knows> ?q . OPTIONAL { ?q <name> ?n . ?q <age> ?age } FILTER(?age > 3) }",
+        )
+        .unwrap();
+        assert_eq!(query.patterns.len(), 1);
+        assert_eq!(
+            query.optional,
+            [
+                TriplePattern {
+                    subject: var("q"),
+                    predicate: iri("name"),
+                    object: var("n"),
+                    graph: None,
+                },
+                TriplePattern {
+                    subject: var("q"),
+                    predicate: iri("age"),
+                    object: var("age"),
+                    graph: None,
+                },
+            ]
+        );
+        assert_eq!(query.filters.len(), 1);
+        assert_eq!(query.projection.len(), 4);
+        assert_eq!(query.num_required_variables(), 2);
+
+        for (input, message) in [
+            (
+                "SELECT ?p { ?p <knows> ?q OPTIONAL { ?q <name> ?n OPTIONAL { ?n <x> ?y } } }",
+                "nested OPTIONAL is not supported",
+            ),
+            (
+                "SELECT ?p { ?p <knows> ?q OPTIONAL { ?q <name> ?n } OPTIONAL { ?q <age> ?a } }",
+                "more than one OPTIONAL block is not supported",
+            ),
+            (
+                "SELECT ?p { OPTIONAL { ?p <name> ?n } ?p <knows> ?q }",
+                "expected FILTER, VALUES or '}' after OPTIONAL block, found \"?p\"",
+            ),
+            (
+                "SELECT ?p { ?p <knows> ?q OPTIONAL { ?q <name> ?n FILTER(?n > 1) } }",
+                "FILTER in OPTIONAL is not supported",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn union() {
+        let query = parse_select(
+            "SELECT * { ?x a <Person> { ?x <p1> ?y } UNION { ?x <p2> ?z . ?z <q> ?y } . { ?x <r> ?w } }",
+        )
+        .unwrap();
+        assert_eq!(query.patterns.len(), 2);
+        let p1 = TriplePattern {
+            subject: var("x"),
+            predicate: iri("p1"),
+            object: var("y"),
+            graph: None,
+        };
+        let p2 = TriplePattern {
+            subject: var("x"),
+            predicate: iri("p2"),
+            object: var("z"),
+            graph: None,
+        };
+        let q = TriplePattern {
+            subject: var("z"),
+            predicate: iri("q"),
+            object: var("y"),
+            graph: None,
+        };
+        assert_eq!(
+            query.union,
+            Some(Pattern::Union(
+                Box::new(Pattern::Bgp(vec![p1.clone()])),
+                Box::new(Pattern::Bgp(vec![p2.clone(), q.clone()])),
+            ))
+        );
+        assert_eq!(
+            query.projection,
+            ["x", "w", "y", "z"].map(|name| Variable(name.to_string()))
+        );
+
+        let query = parse_select("SELECT ?x { {?x <p1> ?y} UNION {?x <p2> ?z} UNION {} }").unwrap();
+        assert_eq!(
+            query.union.unwrap().branches(),
+            [&[p1][..], &[p2][..], &[][..]]
+        );
+
+        for (input, message) in [
+            (
+                "SELECT ?x { { ?x <p> ?y } UNION { ?x <q> ?y } { ?x <r> ?y } UNION { ?x <s> ?y } }",
+                "more than one UNION is not supported",
+            ),
+            (
+                "SELECT ?x { { ?x <p> ?y } UNION { ?x <q> ?y OPTIONAL { ?y <r> ?z } } }",
+                "OPTIONAL in UNION is not supported",
+            ),
+            (
+                "SELECT ?x { { ?x <p> ?y } UNION { { ?x <q> ?y } } }",
+                "nested group in UNION is not supported",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn values() {
+        let expected = Values {
+            variable: Variable("b".to_string()),
+            terms: vec![
+                iri("http://ex.org/bob"),
+                TermPattern::Literal(Literal::string("Bob")),
+                TermPattern::Literal(Literal::integer(3)),
+            ],
+        };
+        for input in [
+            "PREFIX ex: <http://ex.org/> SELECT ?a { ?a <knows> ?b VALUES ?b { ex:bob \"Bob\" 3 } }",
+            "SELECT ?a { VALUES ?b { <http://ex.org/bob> \"Bob\" 3 } . ?a <knows> ?b }",
+            "SELECT ?a { ?a <knows> ?b OPTIONAL { ?b <name> ?n } VALUES ?b { <http://ex.org/bob> \"Bob\" 3 } }",
+            "SELECT ?a WHERE { ?a <knows> ?b } VALUES ?b { <http://ex.org/bob> \"Bob\" 3 }",
+        ] {
+            let query = parse_select(input).unwrap();
+            assert_eq!(query.patterns.len(), 1, "{input}");
+            assert_eq!(query.values.as_ref(), Some(&expected), "{input}");
+        }
+        let query = parse_select("SELECT ?a { ?a <knows> ?b VALUES ?b {} }").unwrap();
+        assert_eq!(query.values.unwrap().terms, []);
+
+        for (input, message) in [
+            (
+                "SELECT ?a { ?a <knows> ?b VALUES (?a ?b) { (<x> <y>) } }",
+                "VALUES of a variable list is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b VALUES ?b { <x> UNDEF } }",
+                "UNDEF in VALUES is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b VALUES ?b { <x> ?c } }",
+                "expected IRI, literal or '}', found \"?c\"",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b VALUES ?b { <x> } } VALUES ?a { <y> }",
+                "more than one VALUES is not supported",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn limit() {
+        let query =
+            parse_select("SELECT ?a { ?a <knows> ?b } ORDER BY ?a limit 10 VALUES ?b { <x> }")
+                .unwrap();
+        assert_eq!(query.limit, Some(10));
+        assert_eq!(query.order_by.len(), 1);
+        assert!(query.values.is_some());
+        let query = parse_select("SELECT ?a { ?a <knows> ?b } LIMIT 0").unwrap();
+        assert_eq!(query.limit, Some(0));
+        assert_eq!(
+            parse_select("SELECT ?a { ?a <knows> ?b }").unwrap().limit,
+            None
+        );
+
+        for (input, message) in [
+            (
+                "SELECT ?a { ?a <knows> ?b } LIMIT ?a",
+                "expected number of results, found \"?a\"",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b } LIMIT 99999999999999999999999",
+                "invalid LIMIT \"99999999999999999999999\"",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn graph() {
+        let query = parse_select(
+            "SELECT * { ?a <knows> ?b GRAPH ?g { ?b <name> ?n . ?b <age> ?x } . GRAPH <h> { ?a <p> ?y } }",
+        )
+        .unwrap();
+        assert_eq!(query.patterns.len(), 4);
+        assert_eq!(query.patterns[0].graph, None);
+        assert_eq!(
+            query.patterns[1],
+            TriplePattern {
+                subject: var("b"),
+                predicate: iri("name"),
+                object: var("n"),
+                graph: Some(var("g")),
+            }
+        );
+        assert_eq!(query.patterns[2].graph, Some(var("g")));
+        assert_eq!(query.patterns[3].graph, Some(iri("h")));
+        let names = query
+            .projection
+            .iter()
+            .map(Variable::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b", "g", "n", "x", "y"]);
+
+        let query =
+            parse_select("SELECT ?a { ?a <p> ?b OPTIONAL { GRAPH ?g { ?b <q> ?c } } }").unwrap();
+        assert_eq!(query.optional[0].graph, Some(var("g")));
+        let query =
+            parse_select("SELECT ?a { { GRAPH <g> { ?a <p> ?b } } UNION { ?a <q> ?b } }").unwrap();
+        assert_eq!(query.union.unwrap().branches()[0][0].graph, Some(iri("g")));
+
+        for (input, message) in [
+            (
+                "SELECT ?a { GRAPH \"g\" { ?a <p> ?b } }",
+                "expected graph IRI or variable, found \"\\\"g\\\"\"",
+            ),
+            (
+                "SELECT ?a { GRAPH ?g { GRAPH ?h { ?a <p> ?b } } }",
+                "nested GRAPH is not supported",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn not_exists() {
+        let query = parse_select(
+            "SELECT ?a { ?a <knows> ?b FILTER NOT EXISTS { ?b <knows> ?a . ?a <age> ?n } FILTER(?n > 1) }",
+        )
+        .unwrap();
+        assert_eq!(query.patterns.len(), 1);
+        assert_eq!(
+            query.not_exists[1],
+            TriplePattern {
+                subject: var("a"),
+                predicate: iri("age"),
+                object: var("n"),
+                graph: None,
+            }
+        );
+        assert_eq!(query.filters.len(), 1);
+        let query =
+            parse_select("SELECT * { ?a <p> ?b filter not exists { GRAPH ?g { ?b <p> ?c } } }")
+                .unwrap();
+        assert_eq!(query.not_exists[0].graph, Some(var("g")));
+        // Variables only in the block aren't solution variables.
+        let names = query
+            .projection
+            .iter()
+            .map(Variable::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "b"]);
+
+        for (input, message) in [
+            (
+                "SELECT ?a { ?a <p> ?b FILTER EXISTS { ?b <p> ?a } }",
+                "FILTER EXISTS is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b FILTER NOT EXISTS { ?b <p> ?a } FILTER NOT EXISTS { ?a <q> ?b } }",
+                "more than one NOT EXISTS is not supported",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+        assert!(parse_select("SELECT ?a { ?a <p> ?b FILTER NOT { ?b <p> ?a } }").is_err());
+    }
+
+    #[test]
+    fn bind() {
+        let query = parse_select(
+            "SELECT * { ?a <age> ?n bind(?n * -2 AS ?m) . BIND(3 - ?m AS ?k) FILTER(?k > 1) }",
+        )
+        .unwrap();
+        let variable = |name: &str| Variable(name.to_string());
+        assert_eq!(
+            query.binds,
+            [
+                Bind {
+                    lhs: Operand::Variable(variable("n")),
+                    op: ArithOp::Mul,
+                    rhs: Operand::Integer(-2),
+                    variable: variable("m"),
+                },
+                Bind {
+                    lhs: Operand::Integer(3),
+                    op: ArithOp::Sub,
+                    rhs: Operand::Variable(variable("m")),
+                    variable: variable("k"),
+                },
+            ]
+        );
+        assert_eq!(query.binds[1].to_string(), "BIND(3 - ?m AS ?k)");
+        assert_eq!(query.filters.len(), 1);
+        // Variables of binds are solution variables.
+        let names = query
+            .projection
+            .iter()
+            .map(Variable::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a", "n", "m", "k"]);
+
+        for (input, message) in [
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + 1 AS ?b) }",
+                "BIND variable ?b is already bound",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + 1 AS ?c) BIND(?b + 2 AS ?c) }",
+                "BIND variable ?c is already bound",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + 1.5 AS ?c) }",
+                "decimal operand of BIND is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(-.5 * ?b AS ?c) }",
+                "decimal operand of BIND is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + 1e3 AS ?c) }",
+                "double operand of BIND is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + \"1.5\"^^<http://www.w3.org/2001/XMLSchema#decimal> AS ?c) }",
+                "decimal operand of BIND is not supported",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + \"x\" AS ?c) }",
+                "expected variable or integer, found \"\\\"x\\\"\"",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b / 2 AS ?c) }",
+                "expected '+', '-' or '*', found \"/\"",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b BIND(?b + <x> AS ?c) }",
+                "expected variable or integer, found \"<x>\"",
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b OPTIONAL { ?b <q> ?c BIND(?c + 1 AS ?d) } }",
+                "BIND in OPTIONAL is not supported",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn projected_bind() {
+        let query = parse_select(
+            "SELECT ?a (?n + 1 AS ?m) (?m * ?k AS ?j) { ?a <age> ?n BIND(?n - 2 AS ?k) }",
+        )
+        .unwrap();
+        let variable = |name: &str| Variable(name.to_string());
+        assert_eq!(
+            query.projection,
+            [variable("a"), variable("m"), variable("j")]
+        );
+        assert_eq!(
+            query.binds[1..],
+            [
+                Bind {
+                    lhs: Operand::Variable(variable("n")),
+                    op: ArithOp::Add,
+                    rhs: Operand::Integer(1),
+                    variable: variable("m"),
+                },
+                Bind {
+                    lhs: Operand::Variable(variable("m")),
+                    op: ArithOp::Mul,
+                    rhs: Operand::Variable(variable("k")),
+                    variable: variable("j"),
+                },
+            ]
+        );
+        assert_eq!(
+            query.variables_with_binds(),
+            [
+                &variable("a"),
+                &variable("n"),
+                &variable("k"),
+                &variable("m"),
+                &variable("j")
+            ]
+        );
+
+        let query = parse_select("SELECT (?n + 1 AS ?m) (COUNT(?a) AS ?c) { ?a <age> ?n }");
+        assert_eq!(
+            query.unwrap_err().message,
+            "variable ?m projected with COUNT is not grouped"
+        );
+        for (input, message, offset) in [
+            (
+                "SELECT ?a (?x + 1 AS ?c) { ?a <p> ?b }",
+                "projection of ?c references ?x, which is not bound by WHERE",
+                10,
+            ),
+            (
+                "SELECT ?a (?c + 1 AS ?d) (?b - 1 AS ?c) { ?a <p> ?b }",
+                "projection of ?d references ?c before it is projected",
+                10,
+            ),
+            (
+                "SELECT (?b + 1 AS ?a) { ?a <p> ?b }",
+                "projected variable ?a is already bound",
+                7,
+            ),
+            (
+                "SELECT (?b + 1 AS ?c) (?b + 2 AS ?c) { ?a <p> ?b }",
+                "projected variable ?c is already bound",
+                22,
+            ),
+        ] {
+            let err = parse_select(input).unwrap_err();
+            assert_eq!((err.message.as_str(), err.offset), (message, offset));
+        }
+    }
+
+    #[test]
+    fn order_by() {
+        let query = parse_select(
+            "SELECT ?a ?b { ?a <knows> ?b } order by ?b DESC(?a) ASC( ?b ) VALUES ?b { <x> }",
+        )
+        .unwrap();
+        let condition = |name: &str, descending| OrderCondition {
+            variable: Variable(name.to_string()),
+            descending,
+        };
+        assert_eq!(
+            query.order_by,
+            [
+                condition("b", false),
+                condition("a", true),
+                condition("b", false)
+            ]
+        );
+        assert!(query.values.is_some());
+        assert_eq!(query.order_by[1].to_string(), "DESC(?a)");
+        assert_eq!(
+            parse_select("SELECT ?a { ?a <knows> ?b }")
+                .unwrap()
+                .order_by,
+            []
+        );
+
+        for (input, message) in [
+            (
+                "SELECT ?a { ?a <knows> ?b } ORDER ?a",
+                "expected BY, found \"?a\"",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b } ORDER BY <x>",
+                "expected variable, ASC or DESC, found \"<x>\"",
+            ),
+            (
+                "SELECT ?a { ?a <knows> ?b } ORDER BY ASC(<x>)",
+                "expected variable, found \"<x>)\"",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn blank_node() {
+        let query =
+            parse_select("SELECT * { ?a <knows> _:b . _:b <name> ?n OPTIONAL { _:b <age> _:c } }")
+                .unwrap();
+        let [b, c] = ["b", "c"].map(Variable::blank_node);
+        assert_eq!(
+            query.projection,
+            [Variable("a".to_string()), Variable("n".to_string())]
+        );
+        assert_eq!(query.patterns[0].object, TermPattern::Variable(b.clone()));
+        assert_eq!(query.patterns[1].subject, TermPattern::Variable(b.clone()));
+        assert_eq!(query.optional[0].object, TermPattern::Variable(c.clone()));
+        assert_eq!(query.variables()[1..3], [&b, &Variable("n".to_string())]);
+        assert_eq!(b.to_string(), "_:b");
+        assert_ne!(b, Variable("b".to_string()));
+
+        for (input, message) in [
+            (
+                "SELECT ?a { ?a _:p ?b }",
+                "blank node as predicate is not allowed",
+            ),
+            (
+                "SELECT ?a { ?a <knows> _: }",
+                "expected blank node label, found \"}\"",
+            ),
+        ] {
+            assert_eq!(parse_select(input).unwrap_err().message, message);
+        }
+    }
+
+    #[test]
+    fn property_path() {
+        let query =
+            parse_select("PREFIX : <http://e/> SELECT * { ?a :knows/:knows/a ?c . ?a <p>/<q> 1 }")
+                .unwrap();
+        assert_eq!(
+            query.projection,
+            [Variable("a".to_string()), Variable("c".to_string())]
+        );
+        let [n0, n1, n2] = [0, 1, 2].map(|idx| TermPattern::Variable(Variable::path_node(idx)));
+        let knows = iri("http://e/knows");
+        let pattern =
+            |subject: &TermPattern, predicate: TermPattern, object: &TermPattern| TriplePattern {
+                subject: subject.clone(),
+                predicate,
+                object: object.clone(),
+                graph: None,
+            };
+        assert_eq!(
+            query.patterns,
+            [
+                pattern(&var("a"), knows.clone(), &n0),
+                pattern(&n0, knows, &n1),
+                pattern(&n1, iri(RDF_TYPE), &var("c")),
+                pattern(&var("a"), iri("p"), &n2),
+                pattern(&n2, iri("q"), &TermPattern::Literal(Literal::integer(1))),
+            ]
+        );
+        assert_ne!(Variable::path_node(0), Variable::blank_node("path0"));
+
+        // A `+` followed by a digit is an integer object.
+        let query = parse_select("SELECT ?a { ?a <p> +1 }").unwrap();
+        assert_eq!(query.patterns.len(), 1);
+
+        for (input, message, kind, span) in [
+            (
+                "SELECT ?a { ?a <p>* ?b }",
+                "property path * is not supported",
+                ParseErrorKind::Unsupported,
+                18..19,
+            ),
+            (
+                "SELECT ?a { ?a <p> + ?b }",
+                "property path + is not supported",
+                ParseErrorKind::Unsupported,
+                19..20,
+            ),
+            (
+                "SELECT ?a { ?a <p>/<p>/<p>/<p>/<p> ?b }",
+                "property path of more than 4 steps is not supported",
+                ParseErrorKind::Unsupported,
+                30..31,
+            ),
+            (
+                "SELECT ?a { ?a <p>/?q ?b }",
+                "property path of other than IRIs is not allowed",
+                ParseErrorKind::Invalid,
+                19..21,
+            ),
+        ] {
+            let err = parse_select(input).unwrap_err();
+            assert_eq!(err.message, message);
+            assert_eq!(err.kind, kind);
+            assert_eq!(err.span, span);
+        }
+    }
+
+    #[test]
+    fn prefixes() {
+        let query = parse_select(
+            "PREFIX foaf: <http://xmlns.com/foaf/0.1/>
+             prefix : <http://ex.org/>
+             PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>
+             SELECT ?a WHERE { ?a foaf:knows :bob . :bob foaf:age \"30\"^^xsd:integer . ?a a :Person.v2. }",
+        )
+        .unwrap();
+        assert_eq!(
+            query.patterns,
+            [
+                TriplePattern {
+                    subject: var("a"),
+                    predicate: iri("http://xmlns.com/foaf/0.1/knows"),
+                    object: iri("http://ex.org/bob"),
+                    graph: None,
+                },
+                TriplePattern {
+                    subject: iri("http://ex.org/bob"),
+                    predicate: iri("http://xmlns.com/foaf/0.1/age"),
+                    object: TermPattern::Literal(Literal::integer(30)),
+                    graph: None,
+                },
+                TriplePattern {
+                    subject: var("a"),
+                    predicate: iri(RDF_TYPE),
+                    object: iri("http://ex.org/Person.v2"),
+                    graph: None,
+                },
+            ]
+        );
+
+        let query = parse_select("PREFIX : <a/> PREFIX : <b/> SELECT ?a { ?a :p ?b }").unwrap();
+        assert_eq!(query.patterns[0].predicate, iri("b/p"));
+
+        let input = "PREFIX foaf: <http://xmlns.com/foaf/0.1/> SELECT ?a { ?a ex:knows ?b }";
+        let err = parse_select(input).unwrap_err();
+        assert_eq!(err.offset, input.find("ex:").unwrap());
+        assert_eq!(err.message, "unknown prefix \"ex:\"");
+        let err = parse_select("SELECT ?a { ?a :knows ?b }").unwrap_err();
+        assert_eq!(err.message, "unknown prefix \":\"");
+        let err = parse_select("PREFIX foaf <http://xmlns.com/foaf/0.1/> SELECT ?a { ?a <p> ?b }")
+            .unwrap_err();
+        assert_eq!(
+            err.message,
+            "expected prefix name ending in ':', found \"<http://xmlns.com/foaf/0.1/>\""
+        );
+    }
+
+    #[test]
+    fn error_offset() {
+        let input = "SELECT ?a WHERE { ?a <p> }";
+        let err = parse_select(input).unwrap_err();
+        assert_eq!(err.offset, input.find('}').unwrap());
+
+        let err = parse_select("SELECT WHERE { ?a <p> ?b }").unwrap_err();
+        assert_eq!(err.offset, 7);
+
+        let err = parse_select("SELECT ?a { ?a <p ?b }").unwrap_err();
+        assert_eq!(err.offset, 15);
+        assert_eq!(err.message, "unterminated IRI");
+
+        let err = parse_select("SELECT ?a { ?a <p> ?b } ?c").unwrap_err();
+        assert_eq!(err.offset, 24);
+    }
+
+    #[test]
+    fn error_span() {
+        for (input, kind, token, expected) in [
+            (
+                "SELECT ?a WHERE {\n  ?a <p> ?b .\n  ?b <q }",
+                ParseErrorKind::UnterminatedIri,
+                "<q",
+                None,
+            ),
+            (
+                "PREFIX ex: <http://ex.org/>\nSELECT ?a {\n  ?a foaf:knows ?b\n}",
+                ParseErrorKind::UnknownPrefix,
+                "foaf:",
+                None,
+            ),
+            (
+                "SELECT ?a {\n  ?a <p> ?b\n  FILTER(?b >> 1)\n}",
+                ParseErrorKind::UnexpectedToken,
+                ">",
+                Some("integer"),
+            ),
+            (
+                "SELECT ?a {\n  ?a <p> \"open\n}",
+                ParseErrorKind::UnterminatedLiteral,
+                "\"open",
+                None,
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b } LIMIT 99999999999999999999999",
+                ParseErrorKind::InvalidLiteral,
+                "99999999999999999999999",
+                None,
+            ),
+            (
+                "SELECT ?a {\n  ?a <p> ?b\n  OPTIONAL { ?b <q> ?c FILTER(?c > 1) }\n}",
+                ParseErrorKind::Unsupported,
+                "FILTER",
+                None,
+            ),
+            (
+                "SELECT ?a { ?a <p> ?b ",
+                ParseErrorKind::UnexpectedEnd,
+                "",
+                Some("'}'"),
+            ),
+        ] {
+            let err = parse_select(input).unwrap_err();
+            assert_eq!(err.kind, kind, "{input}");
+            assert_eq!(&input[err.span.clone()], token, "{input}");
+            assert_eq!(err.offset, err.span.start);
+            assert_eq!(err.expected.as_deref(), expected, "{input}");
+        }
+
+        let input = "SELECT ?a {\n  ?a <p> ?b .\n  ?b <q }";
+        let err = parse_select(input).unwrap_err();
+        assert_eq!(err.line_column(input), (3, 6));
+        let input = "SELECT ?a { ?é <p> ?b } ?c";
+        let err = parse_select(input).unwrap_err();
+        assert_eq!(err.line_column(input), (1, 25));
+        assert_eq!(&input[err.span], "?c");
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    query::{Iri, Literal},
+    term_encoding::encode_term,
+};
+use plonkish_backend::halo2_curves::bn256::Fr;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+};
+
+mod loader;
+
+pub use loader::{
+    load_many, load_nquads, load_ntriples, load_turtle, parse_nquads, parse_ntriples, parse_turtle,
+    RdfError,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Term {
+    Iri(Iri),
+    Literal(Literal),
+    BlankNode(String),
+}
+
+impl Term {
+    pub fn iri(iri: impl Into<String>) -> Self {
+        Term::Iri(Iri(iri.into()))
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Term::Literal(literal) => literal.as_integer(),
+            _ => None,
+        }
+    }
+
+    pub fn as_date_time(&self) -> Option<i64> {
+        match self {
+            Term::Literal(literal) => literal.as_date_time(),
+            _ => None,
+        }
+    }
+
+    /// Returns the term of the [`Literal::canonical`] form of a numeric,
+    /// boolean or dateTime literal, or `self` if it has none.
+    pub fn canonical(&self) -> Cow<'_, Term> {
+        match self {
+            Term::Literal(literal) => match literal.canonical() {
+                Some(canonical) => Cow::Owned(Term::Literal(canonical)),
+                None => Cow::Borrowed(self),
+            },
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// Returns the field element representing `self` in circuits, which is
+    /// the single element of [`encode_term`].
+    pub fn encode(&self) -> Fr {
+        encode_term(self)[0]
+    }
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Iri(iri) => write!(f, "{iri}"),
+            Term::Literal(literal) => write!(f, "{literal}"),
+            Term::BlankNode(label) => write!(f, "_:{label}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Triple {
+    pub subject: Term,
+    pub predicate: Term,
+    pub object: Term,
+}
+
+impl Triple {
+    pub fn new(subject: Term, predicate: Term, object: Term) -> Self {
+        Self {
+            subject,
+            predicate,
+            object,
+        }
+    }
+}
+
+/// Index of an interned [`Term`] in an [`RdfGraph`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermId(pub usize);
+
+/// Dataset of triples over interned terms, each term stored and encoded once.
+/// Each triple is in the default graph, or in the named graph of
+/// [`Self::graph_name`] if inserted as a quad by [`Self::insert_quad`].
+///
+/// The dataset is a set, so inserting a triple already in the same graph is
+/// dropped rather than matched twice by every query, and counted by
+/// [`Self::num_duplicates`].
+#[derive(Clone, Debug, Default)]
+pub struct RdfGraph {
+    terms: Vec<Term>,
+    encodings: Vec<Fr>,
+    term_ids: HashMap<Term, TermId>,
+    triples: Vec<[TermId; 3]>,
+    graph_names: Vec<Option<TermId>>,
+    quads: HashSet<([TermId; 3], Option<TermId>)>,
+    /// Indices into `triples` of the triples of each term at each position.
+    index: [HashMap<TermId, Vec<usize>>; 3],
+    num_duplicates: usize,
+}
+
+impl RdfGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_triples(triples: impl IntoIterator<Item = Triple>) -> Self {
+        let mut graph = Self::new();
+        for triple in triples {
+            graph.insert(triple);
+        }
+        graph
+    }
+
+    /// Inserts `triple` into the default graph, returning whether it wasn't
+    /// there yet.
+    pub fn insert(&mut self, triple: Triple) -> bool {
+        self.insert_quad(triple, None)
+    }
+
+    /// Inserts `triple` into the graph named `graph_name`, or the default
+    /// graph if `None`, returning whether it wasn't there yet. A duplicate is
+    /// dropped, see [`Self::num_duplicates`].
+    pub fn insert_quad(&mut self, triple: Triple, graph_name: Option<Term>) -> bool {
+        let triple =
+            [triple.subject, triple.predicate, triple.object].map(|term| self.intern(term));
+        let graph_name = graph_name.map(|term| self.intern(term));
+        if !self.quads.insert((triple, graph_name)) {
+            self.num_duplicates += 1;
+            return false;
+        }
+        for (index, term) in self.index.iter_mut().zip(triple) {
+            index.entry(term).or_default().push(self.triples.len());
+        }
+        self.triples.push(triple);
+        self.graph_names.push(graph_name);
+        true
+    }
+
+    /// Returns the number of insertions dropped as duplicates of a triple
+    /// already in the same graph, which is `0` unless the source had
+    /// duplicates, e.g. of merged datasets.
+    pub fn num_duplicates(&self) -> usize {
+        self.num_duplicates
+    }
+
+    /// Returns the id of `term`, interning its [`Term::canonical`] form if
+    /// it's new, so numeric literals of the same value and datatype share an
+    /// id and [`Self::term`] returns the canonical one.
+    pub fn intern(&mut self, term: Term) -> TermId {
+        let term = match term.canonical() {
+            Cow::Owned(canonical) => canonical,
+            Cow::Borrowed(_) => term,
+        };
+        if let Some(id) = self.term_ids.get(&term) {
+            return *id;
+        }
+        let id = TermId(self.terms.len());
+        self.encodings.push(term.encode());
+        self.terms.push(term.clone());
+        self.term_ids.insert(term, id);
+        id
+    }
+
+    /// Returns the id of `term`, or of any term of the same canonical form.
+    pub fn term_id(&self, term: &Term) -> Option<TermId> {
+        self.term_ids.get(term.canonical().as_ref()).copied()
+    }
+
+    pub fn term(&self, id: TermId) -> &Term {
+        &self.terms[id.0]
+    }
+
+    pub fn encoding(&self, id: TermId) -> Fr {
+        self.encodings[id.0]
+    }
+
+    /// Returns the triple at `idx` of [`Self::triples`] with its terms.
+    pub fn triple(&self, idx: usize) -> Triple {
+        let [subject, predicate, object] = self.triples[idx].map(|id| self.term(id).clone());
+        Triple::new(subject, predicate, object)
+    }
+
+    /// Returns the triple at `idx` of [`Self::triples`] with the name of its
+    /// graph, or `None` if it's in the default graph.
+    pub fn quad(&self, idx: usize) -> (Triple, Option<Term>) {
+        let graph_name = self.graph_names[idx].map(|id| self.term(id).clone());
+        (self.triple(idx), graph_name)
+    }
+
+    pub fn triples(&self) -> &[[TermId; 3]] {
+        &self.triples
+    }
+
+    /// Returns the indices into [`Self::triples`] in ascending order of the
+    /// triples of `term` as subject, predicate or object for `position` of
+    /// `0`, `1` or `2`.
+    pub fn triples_with(&self, position: usize, term: TermId) -> &[usize] {
+        self.index[position].get(&term).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the name of the graph of the triple at `idx` of
+    /// [`Self::triples`], or `None` if it's in the default graph.
+    pub fn graph_name(&self, idx: usize) -> Option<TermId> {
+        self.graph_names[idx]
+    }
+
+    /// Returns whether any triple is in a named graph.
+    pub fn has_named_graphs(&self) -> bool {
+        self.graph_names.iter().any(Option::is_some)
+    }
+
+    pub fn len(&self) -> usize {
+        self.triples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triples.is_empty()
+    }
+}
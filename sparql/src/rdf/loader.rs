@@ -0,0 +1,762 @@
+use crate::{
+    query::{Iri, Literal, RDF_TYPE, XSD_BOOLEAN, XSD_DECIMAL, XSD_DOUBLE, XSD_INTEGER},
+    rdf::{RdfGraph, Term, Triple},
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum RdfError {
+    Io(io::Error),
+    /// Syntax error at 1-based `line` of the input.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// Error of the file at `path` of [`load_many`].
+    File {
+        path: PathBuf,
+        err: Box<RdfError>,
+    },
+}
+
+impl Display for RdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RdfError::Io(err) => write!(f, "{err}"),
+            RdfError::Syntax { line, message } => write!(f, "{message} at line {line}"),
+            RdfError::File { path, err } => write!(f, "{}: {err}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for RdfError {}
+
+impl From<io::Error> for RdfError {
+    fn from(err: io::Error) -> Self {
+        RdfError::Io(err)
+    }
+}
+
+/// Loads the N-Triples file at `path`.
+pub fn load_ntriples(path: &Path) -> Result<RdfGraph, RdfError> {
+    parse_ntriples(&fs::read_to_string(path)?)
+}
+
+/// Loads the Turtle file at `path`, see [`parse_turtle`] for the supported
+/// subset.
+pub fn load_turtle(path: &Path) -> Result<RdfGraph, RdfError> {
+    parse_turtle(&fs::read_to_string(path)?)
+}
+
+/// Loads the N-Quads file at `path`, see [`parse_nquads`].
+pub fn load_nquads(path: &Path) -> Result<RdfGraph, RdfError> {
+    parse_nquads(&fs::read_to_string(path)?)
+}
+
+/// Loads the files at `paths` into a single graph, each as Turtle if its
+/// extension is `ttl`, as N-Quads if it's `nq` and as N-Triples otherwise.
+/// A triple of several files is only kept once, see
+/// [`RdfGraph::num_duplicates`], so the [`MerkleTripleSet`] of the graph is
+/// the same in any order of `paths`. Blank node labels are shared across
+/// the files as within one. Fails on the first file that doesn't load with
+/// [`RdfError::File`] of its path.
+///
+/// [`MerkleTripleSet`]: crate::merkle::MerkleTripleSet
+pub fn load_many(paths: &[&Path]) -> Result<RdfGraph, RdfError> {
+    let mut graph = RdfGraph::new();
+    for path in paths {
+        let load = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("ttl") => load_turtle,
+            Some("nq") => load_nquads,
+            _ => load_ntriples,
+        };
+        let file = load(path).map_err(|err| RdfError::File {
+            path: path.to_path_buf(),
+            err: Box::new(err),
+        })?;
+        for idx in 0..file.len() {
+            let (triple, graph_name) = file.quad(idx);
+            graph.insert_quad(triple, graph_name);
+        }
+        graph.num_duplicates += file.num_duplicates();
+    }
+    Ok(graph)
+}
+
+pub fn parse_ntriples(input: &str) -> Result<RdfGraph, RdfError> {
+    Parser::new(input, false).document()
+}
+
+/// Parses N-Quads, whose statements are N-Triples followed by an optional
+/// graph label, an IRI or a blank node naming the graph of the triple, or the
+/// default graph without.
+pub fn parse_nquads(input: &str) -> Result<RdfGraph, RdfError> {
+    Parser {
+        quads: true,
+        ..Parser::new(input, false)
+    }
+    .document()
+}
+
+/// Parses Turtle with `@prefix`/`PREFIX` directives, prefixed names, `a`,
+/// predicate lists with `;`, object lists with `,`, and numeric and boolean
+/// shorthand literals. Relative IRIs aren't resolved, and `@base`, anonymous
+/// blank nodes `[]`, collections and long strings are rejected.
+pub fn parse_turtle(input: &str) -> Result<RdfGraph, RdfError> {
+    Parser::new(input, true).document()
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+    turtle: bool,
+    quads: bool,
+    prefixes: HashMap<String, String>,
+    graph: RdfGraph,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str, turtle: bool) -> Self {
+        Self {
+            input,
+            offset: 0,
+            turtle,
+            quads: false,
+            prefixes: HashMap::new(),
+            graph: RdfGraph::new(),
+        }
+    }
+
+    fn document(mut self) -> Result<RdfGraph, RdfError> {
+        loop {
+            self.skip_blank();
+            if self.rest().is_empty() {
+                return Ok(self.graph);
+            }
+            if self.turtle && self.directive()? {
+                continue;
+            }
+            self.triples()?;
+            self.expect('.')?;
+            if !self.turtle {
+                self.expect_line_end()?;
+            }
+        }
+    }
+
+    /// Parses a prefix directive if any.
+    fn directive(&mut self) -> Result<bool, RdfError> {
+        let sparql_style = self.eat_word("PREFIX", true);
+        if !sparql_style && !self.eat_word("@prefix", false) {
+            if self.rest().starts_with("@base") || self.eat_word("BASE", true) {
+                return Err(self.error("base directive is not supported"));
+            }
+            return Ok(false);
+        }
+        self.skip_ws();
+        let prefix = self.take_while(is_name_char).to_string();
+        if !self.rest().starts_with(':') {
+            return Err(self.expected("':'"));
+        }
+        self.offset += 1;
+        self.skip_ws();
+        let iri = match self.iri_ref()? {
+            Some(iri) => iri,
+            None => return Err(self.expected("IRI")),
+        };
+        self.prefixes.insert(prefix, iri);
+        if !sparql_style {
+            self.expect('.')?;
+        }
+        Ok(true)
+    }
+
+    fn triples(&mut self) -> Result<(), RdfError> {
+        let subject = self.subject()?;
+        loop {
+            let predicate = self.predicate()?;
+            loop {
+                let object = self.object()?;
+                let triple = Triple::new(subject.clone(), predicate.clone(), object);
+                let graph_name = match self.quads {
+                    true => self.graph_label()?,
+                    false => None,
+                };
+                self.graph.insert_quad(triple, graph_name);
+                if !(self.turtle && self.eat(',')) {
+                    break;
+                }
+            }
+            if !(self.turtle && self.eat(';')) {
+                return Ok(());
+            }
+            // Trailing `;` before the end of the statement.
+            self.skip_ws();
+            if self.rest().starts_with('.') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn subject(&mut self) -> Result<Term, RdfError> {
+        self.skip_ws();
+        if let Some(label) = self.blank_node()? {
+            return Ok(Term::BlankNode(label));
+        }
+        match self.iri()? {
+            Some(iri) => Ok(Term::Iri(iri)),
+            None => Err(self.expected("subject IRI or blank node")),
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Term, RdfError> {
+        self.skip_ws();
+        if self.turtle && self.eat_word("a", false) {
+            return Ok(Term::iri(RDF_TYPE));
+        }
+        match self.iri()? {
+            Some(iri) => Ok(Term::Iri(iri)),
+            None => Err(self.expected("predicate IRI")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Term, RdfError> {
+        self.skip_ws();
+        if let Some(label) = self.blank_node()? {
+            return Ok(Term::BlankNode(label));
+        }
+        if let Some(literal) = self.literal()? {
+            return Ok(Term::Literal(literal));
+        }
+        match self.iri()? {
+            Some(iri) => Ok(Term::Iri(iri)),
+            None => Err(self.expected("object IRI, blank node or literal")),
+        }
+    }
+
+    fn graph_label(&mut self) -> Result<Option<Term>, RdfError> {
+        self.skip_ws();
+        if let Some(label) = self.blank_node()? {
+            return Ok(Some(Term::BlankNode(label)));
+        }
+        Ok(self.iri()?.map(Term::Iri))
+    }
+
+    fn blank_node(&mut self) -> Result<Option<String>, RdfError> {
+        if self.turtle && self.rest().starts_with('[') {
+            return Err(self.error("anonymous blank node is not supported"));
+        }
+        if !self.rest().starts_with("_:") {
+            return Ok(None);
+        }
+        self.offset += 2;
+        let label = self.name(|c| is_name_char(c) || c == '-');
+        if label.is_empty() {
+            return Err(self.expected("blank node label"));
+        }
+        Ok(Some(label.to_string()))
+    }
+
+    /// Parses an IRI reference or, in Turtle, a prefixed name.
+    fn iri(&mut self) -> Result<Option<Iri>, RdfError> {
+        if let Some(iri) = self.iri_ref()? {
+            return Ok(Some(Iri(iri)));
+        }
+        if !self.turtle
+            || !self
+                .rest()
+                .starts_with(|c: char| is_name_char(c) || c == ':')
+        {
+            return Ok(None);
+        }
+        let start = self.offset;
+        let prefix = self.take_while(is_name_char).to_string();
+        if !self.rest().starts_with(':') {
+            self.offset = start;
+            return Ok(None);
+        }
+        self.offset += 1;
+        let local = self.name(|c| is_name_char(c) || c == '-');
+        match self.prefixes.get(&prefix) {
+            Some(namespace) => Ok(Some(Iri(format!("{namespace}{local}")))),
+            None => {
+                self.offset = start;
+                Err(self.error(&format!("undefined prefix {prefix:?}")))
+            }
+        }
+    }
+
+    fn iri_ref(&mut self) -> Result<Option<String>, RdfError> {
+        if !self.rest().starts_with('<') {
+            return Ok(None);
+        }
+        let start = self.offset;
+        self.offset += 1;
+        let mut iri = String::new();
+        loop {
+            match self.peek() {
+                Some('>') => break,
+                Some('\\') => iri.push(self.escape(false)?),
+                Some(c) if !c.is_whitespace() => {
+                    self.offset += c.len_utf8();
+                    iri.push(c);
+                }
+                _ => {
+                    self.offset = start;
+                    return Err(self.error("unterminated IRI"));
+                }
+            }
+        }
+        self.offset += 1;
+        Ok(Some(iri))
+    }
+
+    fn literal(&mut self) -> Result<Option<Literal>, RdfError> {
+        match self.peek() {
+            Some('"') => self.string_literal().map(Some),
+            Some('\'') if self.turtle => self.string_literal().map(Some),
+            Some(c) if self.turtle && (c.is_ascii_digit() || matches!(c, '+' | '-' | '.')) => {
+                self.numeric_literal().map(Some)
+            }
+            _ if self.turtle => {
+                let boolean = ["true", "false"]
+                    .into_iter()
+                    .find(|value| self.eat_word(value, false));
+                Ok(boolean.map(|value| Literal::new(value, Iri(XSD_BOOLEAN.to_string()))))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn string_literal(&mut self) -> Result<Literal, RdfError> {
+        let start = self.offset;
+        let quote = self.peek().unwrap();
+        if self.rest().starts_with(&quote.to_string().repeat(3)) {
+            return Err(self.error("long string literal is not supported"));
+        }
+        self.offset += 1;
+        let mut lexical = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => break,
+                Some('\\') => lexical.push(self.escape(true)?),
+                Some('\n' | '\r') | None => {
+                    self.offset = start;
+                    return Err(self.error("unterminated string literal"));
+                }
+                Some(c) => {
+                    self.offset += c.len_utf8();
+                    lexical.push(c);
+                }
+            }
+        }
+        self.offset += 1;
+
+        if self.rest().starts_with('@') {
+            self.offset += 1;
+            let language = self.take_while(|c| c.is_ascii_alphanumeric() || c == '-');
+            if language.is_empty() {
+                return Err(self.expected("language tag"));
+            }
+            return Ok(Literal::lang_string(lexical, language));
+        }
+        if self.rest().starts_with("^^") {
+            self.offset += 2;
+            return match self.iri()? {
+                Some(datatype) => Ok(Literal::new(lexical, datatype)),
+                None => Err(self.expected("datatype IRI")),
+            };
+        }
+        Ok(Literal::string(lexical))
+    }
+
+    fn numeric_literal(&mut self) -> Result<Literal, RdfError> {
+        let start = self.offset;
+        if matches!(self.peek(), Some('+' | '-')) {
+            self.offset += 1;
+        }
+        let integer = self.take_while(|c| c.is_ascii_digit()).len();
+        let mut datatype = XSD_INTEGER;
+        // A `.` not followed by a digit terminates the statement instead.
+        if self.rest().starts_with('.')
+            && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            self.offset += 1;
+            self.take_while(|c| c.is_ascii_digit());
+            datatype = XSD_DECIMAL;
+        } else if integer == 0 {
+            self.offset = start;
+            return Err(self.expected("numeric literal"));
+        }
+        if self.rest().starts_with(['e', 'E']) {
+            self.offset += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.offset += 1;
+            }
+            if self.take_while(|c| c.is_ascii_digit()).is_empty() {
+                return Err(self.expected("exponent"));
+            }
+            datatype = XSD_DOUBLE;
+        }
+        let lexical = &self.input[start..self.offset];
+        Ok(Literal::new(lexical, Iri(datatype.to_string())))
+    }
+
+    /// Parses an escape sequence, where only unicode escapes are allowed in
+    /// IRIs.
+    fn escape(&mut self, in_string: bool) -> Result<char, RdfError> {
+        let start = self.offset;
+        self.offset += 1;
+        let escaped = match self.peek() {
+            Some('u' | 'U') => {
+                let len = if self.peek() == Some('u') { 4 } else { 8 };
+                self.offset += 1;
+                let hex = self.rest().get(..len).unwrap_or_default();
+                let c = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+                self.offset += len;
+                c
+            }
+            Some(c) if in_string => {
+                self.offset += 1;
+                match c {
+                    't' => Some('\t'),
+                    'b' => Some('\u{8}'),
+                    'n' => Some('\n'),
+                    'r' => Some('\r'),
+                    'f' => Some('\u{c}'),
+                    '"' | '\'' | '\\' => Some(c),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        escaped.ok_or_else(|| {
+            self.offset = start;
+            self.error("invalid escape sequence")
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.offset += len;
+        &rest[..len]
+    }
+
+    /// Takes a name of chars matching `f` or `.`, except a trailing `.` which
+    /// terminates the statement instead.
+    fn name(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let name = self.take_while(|c| f(c) || c == '.').trim_end_matches('.');
+        self.offset -= rest.len() - self.rest().len() - name.len();
+        name
+    }
+
+    /// Skips whitespace between terms, which in N-Triples doesn't cross a line
+    /// break to keep one triple per line.
+    fn skip_ws(&mut self) {
+        match self.turtle {
+            true => self.skip_blank(),
+            false => {
+                self.take_while(|c| c != '\n' && c.is_whitespace());
+            }
+        }
+    }
+
+    /// Skips whitespace, line breaks and `#` comments.
+    fn skip_blank(&mut self) {
+        loop {
+            self.take_while(char::is_whitespace);
+            if !self.rest().starts_with('#') {
+                return;
+            }
+            self.take_while(|c| c != '\n');
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        let eaten = self.rest().starts_with(c);
+        if eaten {
+            self.offset += 1;
+        }
+        eaten
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), RdfError> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(self.expected(&format!("'{c}'"))),
+        }
+    }
+
+    /// Ensures nothing but a comment follows on the current line.
+    fn expect_line_end(&mut self) -> Result<(), RdfError> {
+        self.take_while(|c| c != '\n' && c.is_whitespace());
+        if self.rest().starts_with('#') {
+            self.take_while(|c| c != '\n');
+        }
+        match self.rest().is_empty() || self.rest().starts_with(['\n', '\r']) {
+            true => Ok(()),
+            false => Err(self.expected("end of line")),
+        }
+    }
+
+    /// Eats `word` if it isn't followed by more name characters, which would
+    /// make it a prefixed name instead.
+    fn eat_word(&mut self, word: &str, ignore_case: bool) -> bool {
+        let rest = self.rest();
+        let eaten = rest
+            .get(..word.len())
+            .map_or(false, |prefix| match ignore_case {
+                true => prefix.eq_ignore_ascii_case(word),
+                false => prefix == word,
+            })
+            && !rest[word.len()..].starts_with(|c| is_name_char(c) || c == ':');
+        if eaten {
+            self.offset += word.len();
+        }
+        eaten
+    }
+
+    fn error(&self, message: &str) -> RdfError {
+        RdfError::Syntax {
+            line: self.input[..self.offset].matches('\n').count() + 1,
+            message: message.to_string(),
+        }
+    }
+
+    fn expected(&mut self, what: &str) -> RdfError {
+        let found = match self.rest().split_whitespace().next() {
+            Some(token) => format!("{token:?}"),
+            None => "end of input".to_string(),
+        };
+        self.error(&format!("expected {what}, found {found}"))
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        eval::evaluate,
+        merkle::MerkleTripleSet,
+        parse_select,
+        query::{Iri, Literal, XSD_DECIMAL, XSD_INTEGER},
+        rdf::{
+            load_many, load_ntriples, parse_nquads, parse_ntriples, parse_turtle, RdfError, Term,
+            Triple,
+        },
+    };
+    use std::{env, fs, process};
+
+    fn triples(graph: &crate::rdf::RdfGraph) -> Vec<Triple> {
+        (0..graph.len()).map(|idx| graph.triple(idx)).collect()
+    }
+
+    #[test]
+    fn ntriples() {
+        let graph = parse_ntriples(
+            "# comment\n\
+             <http://ex.org/a> <http://ex.org/p> _:b0 .\n\
+             \n\
+             _:b0 <http://ex.org/name> \"B\\u00F6b\\n\"@en . # trailing\n\
+             _:b0 <http://ex.org/age> \"42\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n",
+        )
+        .unwrap();
+        let [a, p, name, age] =
+            ["a", "p", "name", "age"].map(|local| Term::iri(format!("http://ex.org/{local}")));
+        let b0 = Term::BlankNode("b0".to_string());
+        assert_eq!(
+            triples(&graph),
+            [
+                Triple::new(a, p, b0.clone()),
+                Triple::new(
+                    b0.clone(),
+                    name,
+                    Term::Literal(Literal::lang_string("Böb\n", "en"))
+                ),
+                Triple::new(b0, age, Term::Literal(Literal::integer(42))),
+            ]
+        );
+        assert_eq!(
+            graph.term_id(&Term::BlankNode("b0".to_string())).unwrap().0,
+            2
+        );
+    }
+
+    #[test]
+    fn duplicates() {
+        let dir = env::temp_dir().join(format!("sparql-loader-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("merged.nt");
+        fs::write(
+            &path,
+            "<alice> <knows> <bob> .\n\
+             <bob> <knows> <carol> .\n\
+             <alice>  <knows> <bob> . # again\n\
+             _:b0 <name> \"B\" .\n\
+             _:b0 <name> \"B\" .\n",
+        )
+        .unwrap();
+        let graph = load_ntriples(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph.num_duplicates(), 2);
+
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        let baseline = [
+            Triple::new(alice, knows.clone(), bob.clone()),
+            Triple::new(bob, knows, carol),
+            Triple::new(
+                Term::BlankNode("b0".to_string()),
+                Term::iri("name"),
+                Term::Literal(Literal::string("B")),
+            ),
+        ];
+        assert_eq!(triples(&graph), baseline);
+        assert_eq!(
+            MerkleTripleSet::from_graph(&graph).root(),
+            MerkleTripleSet::new(baseline).root()
+        );
+        let query = parse_select("SELECT ?a ?b WHERE { ?a <knows> ?b }").unwrap();
+        assert_eq!(evaluate(&query, &graph).count(), 2);
+
+        // The same triple in another graph isn't a duplicate.
+        let graph = parse_nquads("<a> <p> <b> .\n<a> <p> <b> <g> .\n<a> <p> <b> <g> .\n").unwrap();
+        assert_eq!((graph.len(), graph.num_duplicates()), (2, 1));
+        assert!(!graph.clone().insert(graph.triple(0)));
+    }
+
+    #[test]
+    fn load_many_files() {
+        let dir = env::temp_dir().join(format!("sparql-load-many-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let [nt, ttl, invalid] = ["a.nt", "b.ttl", "c.nt"].map(|name| dir.join(name));
+        fs::write(&nt, "<alice> <knows> <bob> .\n<bob> <knows> <carol> .\n").unwrap();
+        // Carol knowing alice twice is a duplicate within the file.
+        fs::write(
+            &ttl,
+            "<bob> <knows> <carol> .\n<carol> <knows> <alice>, <alice> .\n",
+        )
+        .unwrap();
+        fs::write(&invalid, "<alice> <knows> .\n").unwrap();
+        let graph = load_many(&[&nt, &ttl]).unwrap();
+        let reversed = load_many(&[&ttl, &nt]).unwrap();
+        let err = load_many(&[&nt, &invalid]).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let [alice, bob, carol, knows] = ["alice", "bob", "carol", "knows"].map(Term::iri);
+        assert_eq!(
+            triples(&graph),
+            [
+                Triple::new(alice.clone(), knows.clone(), bob),
+                Triple::new(Term::iri("bob"), knows.clone(), carol.clone()),
+                Triple::new(carol, knows, alice),
+            ]
+        );
+        assert_eq!(graph.num_duplicates(), 2);
+        assert_eq!(
+            MerkleTripleSet::from_graph(&graph).root(),
+            MerkleTripleSet::from_graph(&reversed).root()
+        );
+        match err {
+            RdfError::File { path, err } => {
+                assert_eq!(path, invalid);
+                assert!(matches!(*err, RdfError::Syntax { line: 1, .. }));
+            }
+            err => panic!("expected error of a file, found {err}"),
+        }
+    }
+
+    #[test]
+    fn nquads() {
+        let graph = parse_nquads(
+            "<a> <p> <b> <g> .\n\
+             <a> <p> <b> .\n\
+             _:b0 <name> \"B\" _:g . # trailing\n",
+        )
+        .unwrap();
+        assert_eq!(graph.len(), 3);
+        assert_eq!(triples(&graph)[0], triples(&graph)[1]);
+        let graph_names = (0..graph.len())
+            .map(|idx| graph.graph_name(idx).map(|id| graph.term(id).clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            graph_names,
+            [
+                Some(Term::iri("g")),
+                None,
+                Some(Term::BlankNode("g".to_string()))
+            ]
+        );
+        assert!(graph.has_named_graphs());
+        assert!(!parse_ntriples("<a> <p> <b> .\n")
+            .unwrap()
+            .has_named_graphs());
+
+        assert!(parse_ntriples("<a> <p> <b> <g> .\n").is_err());
+        assert!(parse_nquads("<a> <p> <b> \"g\" .\n").is_err());
+        assert!(parse_nquads("<a> <p> <b> <g> <h> .\n").is_err());
+    }
+
+    #[test]
+    fn turtle() {
+        let graph = parse_turtle(
+            "@prefix ex: <http://ex.org/> .\n\
+             PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>\n\
+             ex:a a ex:Person ;\n\
+             \x20   ex:knows ex:b, ex:c ;\n\
+             \x20   ex:age 42 ; ex:height 1.8 ;\n\
+             \x20   ex:id \"7\"^^xsd:integer .\n",
+        )
+        .unwrap();
+        let ex = |local: &str| Term::iri(format!("http://ex.org/{local}"));
+        let a = ex("a");
+        assert_eq!(graph.len(), 6);
+        assert_eq!(
+            triples(&graph)[2],
+            Triple::new(a.clone(), ex("knows"), ex("c"))
+        );
+        assert_eq!(
+            triples(&graph)[4],
+            Triple::new(
+                a.clone(),
+                ex("height"),
+                Term::Literal(Literal::new("1.8", Iri(XSD_DECIMAL.to_string())))
+            )
+        );
+        assert_eq!(
+            triples(&graph)[5].object,
+            Term::Literal(Literal::new("7", Iri(XSD_INTEGER.to_string())))
+        );
+    }
+
+    #[test]
+    fn error_line() {
+        let line = |result: Result<_, RdfError>| match result {
+            Err(RdfError::Syntax { line, .. }) => line,
+            _ => panic!("expected syntax error"),
+        };
+        assert_eq!(line(parse_ntriples("<a> <p> <b> .\n<a> <p> \"b .\n")), 2);
+        assert_eq!(line(parse_ntriples("<a> <p> <b> . <a> <p> <c> .\n")), 1);
+        assert_eq!(line(parse_ntriples("<a>\n<p> <b> .\n")), 1);
+        assert_eq!(line(parse_turtle("<a> <p>\n\n  ex:b .\n")), 3);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    hash::{date_time_encoding, hash_to_field, integer_encoding, string_encoding},
+    query::{Literal, XSD_STRING},
+    rdf::Term,
+};
+use plonkish_backend::halo2_curves::bn256::Fr;
+
+/// Number of field elements every term is encoded to, which is one so a
+/// triple fits the three inputs of [`hash3`].
+///
+/// [`hash3`]: crate::hash::hash3
+pub const TERM_WIDTH: usize = 1;
+
+const KIND_IRI: u8 = 0;
+const KIND_BLANK_NODE: u8 = 1;
+const KIND_LITERAL: u8 = 2;
+
+/// Returns the [`TERM_WIDTH`] field elements encoding `term`, which every term
+/// goes through, from [`RdfGraph`] interning to the leaves of
+/// [`MerkleTripleSet`] and the constants of a query circuit.
+///
+/// Numeric, boolean and dateTime literals are encoded by their
+/// [`Term::canonical`] form, so ones of the same value and datatype, e.g.
+/// `"1"^^xsd:decimal` and `"1.0"^^xsd:decimal`, or `"1"^^xsd:boolean` and
+/// `"true"^^xsd:boolean`, are encoded the same, and match each other in
+/// queries. Integer literals, see [`Literal::as_integer`], are encoded as
+/// [`integer_encoding`] of their value and dateTime ones, see
+/// [`Literal::as_date_time`], as [`date_time_encoding`] of theirs so filters
+/// can open them, and
+/// `xsd:string` literals within [`MAX_STRING_LEN`] bytes as
+/// [`string_encoding`] of their lexical form so string filters can open
+/// them. Any other term is hashed by [`hash_to_field`] from its
+/// [`canonical_bytes`].
+///
+/// [`MAX_STRING_LEN`]: crate::hash::MAX_STRING_LEN
+/// [`RdfGraph`]: crate::rdf::RdfGraph
+/// [`MerkleTripleSet`]: crate::merkle::MerkleTripleSet
+pub fn encode_term(term: &Term) -> Vec<Fr> {
+    let term = &*term.canonical();
+    if let Some(value) = term.as_integer() {
+        return vec![integer_encoding(value)];
+    }
+    if let Some(seconds) = term.as_date_time() {
+        return vec![date_time_encoding(seconds)];
+    }
+    if let Term::Literal(Literal {
+        lexical,
+        datatype,
+        language: None,
+    }) = term
+    {
+        if let (XSD_STRING, Some(encoding)) = (datatype.as_str(), string_encoding(lexical)) {
+            return vec![encoding];
+        }
+    }
+    vec![hash_to_field(b"sparql-term", &canonical_bytes(term))]
+}
+
+/// Returns the serialization of `term` hashed by [`encode_term`], which is a
+/// kind byte followed by each component prefixed by its length as `u64` in
+/// little-endian, so distinct terms never share bytes. A literal has its
+/// lexical form, datatype and language tag if any as components, where the
+/// lexical form of a numeric literal is its [`Term::canonical`] one.
+pub fn canonical_bytes(term: &Term) -> Vec<u8> {
+    let term = &*term.canonical();
+    let mut bytes = Vec::new();
+    let mut push = |component: &str| {
+        bytes.extend((component.len() as u64).to_le_bytes());
+        bytes.extend(component.as_bytes());
+    };
+    let kind = match term {
+        Term::Iri(iri) => {
+            push(iri.as_str());
+            KIND_IRI
+        }
+        Term::BlankNode(label) => {
+            push(label);
+            KIND_BLANK_NODE
+        }
+        Term::Literal(Literal {
+            lexical,
+            datatype,
+            language,
+        }) => {
+            push(lexical);
+            push(datatype.as_str());
+            if let Some(language) = language {
+                push(language);
+            }
+            KIND_LITERAL
+        }
+    };
+    bytes.insert(0, kind);
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        hash::{
+            date_time_encoding, hash3, integer_encoding, string_encoding, Domain, MAX_STRING_LEN,
+        },
+        merkle::MerkleTripleSet,
+        query::{Iri, Literal, XSD_BOOLEAN, XSD_DATE_TIME, XSD_DECIMAL, XSD_INTEGER},
+        rdf::{RdfGraph, Term, Triple},
+        term_encoding::{encode_term, TERM_WIDTH},
+    };
+    use plonkish_backend::util::arithmetic::PrimeField;
+    use std::collections::HashSet;
+
+    fn corpus() -> Vec<Term> {
+        let integer = |lexical: &str| Literal::new(lexical, Iri(XSD_INTEGER.to_string()));
+        let decimal = |lexical: &str| Literal::new(lexical, Iri(XSD_DECIMAL.to_string()));
+        vec![
+            Term::iri("http://ex.org/a"),
+            Term::iri("http://ex.org/A"),
+            Term::iri(""),
+            Term::BlankNode("http://ex.org/a".to_string()),
+            Term::BlankNode("b0".to_string()),
+            Term::Literal(Literal::string("http://ex.org/a")),
+            Term::Literal(Literal::string("")),
+            Term::Literal(Literal::string("a\"^^<b>")),
+            Term::Literal(Literal::new("a", Iri("\"^^<b>".to_string()))),
+            Term::Literal(Literal::lang_string("chat", "en")),
+            Term::Literal(Literal::lang_string("chat", "fr")),
+            Term::Literal(Literal::new("chat", Iri("en".to_string()))),
+            Term::Literal(Literal::string("1")),
+            Term::Literal(Literal::integer(1)),
+            Term::Literal(Literal::integer(-1)),
+            Term::Literal(integer("1a")),
+            Term::Literal(decimal("1")),
+            Term::Literal(decimal("-1.5")),
+            Term::Literal(decimal("1a")),
+            Term::Literal(Literal::integer(i64::MAX)),
+            Term::Literal(integer("9223372036854775808")),
+            Term::Literal(Literal::string("a".repeat(MAX_STRING_LEN))),
+            Term::Literal(Literal::string("a".repeat(MAX_STRING_LEN + 1))),
+            Term::Literal(Literal::string("a\0")),
+            Term::Literal(Literal::string("a\0\0")),
+            Term::Literal(Literal::boolean(true)),
+            Term::Literal(Literal::boolean(false)),
+            Term::Literal(Literal::date_time(0)),
+            Term::Literal(Literal::date_time(1)),
+        ]
+    }
+
+    #[test]
+    fn deterministic() {
+        let corpus = corpus();
+        for term in corpus.iter() {
+            let encoding = encode_term(term);
+            assert_eq!(encoding.len(), TERM_WIDTH);
+            assert_eq!(encode_term(&term.clone()), encoding);
+            assert_eq!(term.encode(), encoding[0]);
+        }
+        assert_eq!(
+            encode_term(&Term::Literal(Literal::integer(-1))),
+            [integer_encoding(-1)]
+        );
+        assert_eq!(
+            encode_term(&Term::Literal(Literal::string("chat"))),
+            [string_encoding("chat").unwrap()]
+        );
+        assert_eq!(string_encoding(&"a".repeat(MAX_STRING_LEN + 1)), None);
+
+        let triple = Triple::new(corpus[0].clone(), corpus[1].clone(), corpus[13].clone());
+        let graph = RdfGraph::from_triples([triple.clone()]);
+        let encodings = [&corpus[0], &corpus[1], &corpus[13]].map(|term| encode_term(term)[0]);
+        assert_eq!(graph.triples()[0].map(|id| graph.encoding(id)), encodings);
+        assert_eq!(
+            MerkleTripleSet::leaf(&triple),
+            hash3(encodings, Domain::Triple)
+        );
+    }
+
+    #[test]
+    fn numeric_canonical_form() {
+        let literal = |lexical: &str, datatype: &str| {
+            Term::Literal(Literal::new(lexical, Iri(datatype.to_string())))
+        };
+        for (datatype, lexicals) in [
+            (XSD_INTEGER, ["1", "01", "+1", "+0001"].as_slice()),
+            (XSD_INTEGER, &["0", "-0", "+000"]),
+            (XSD_DECIMAL, &["1.0", "1", "01.00", "+1.", "1.000"]),
+            (XSD_DECIMAL, &["0.0", "-0", ".0", "-.000", "000"]),
+            (XSD_DECIMAL, &["-0.5", "-.5", "-000.50"]),
+        ] {
+            let canonical = literal(lexicals[0], datatype);
+            assert_eq!(canonical.canonical().as_ref(), &canonical);
+            for lexical in lexicals {
+                let term = literal(lexical, datatype);
+                assert_eq!(term.canonical().as_ref(), &canonical);
+                assert_eq!(encode_term(&term), encode_term(&canonical));
+            }
+        }
+        assert_eq!(
+            encode_term(&literal("+1", XSD_INTEGER)),
+            [integer_encoding(1)]
+        );
+
+        // Still distinct from a string or a literal of another datatype.
+        let one = encode_term(&literal("1.0", XSD_DECIMAL));
+        assert_ne!(one, encode_term(&Term::Literal(Literal::string("1"))));
+        assert_ne!(one, encode_term(&Term::Literal(Literal::string("1.0"))));
+        assert_ne!(one, encode_term(&Term::Literal(Literal::integer(1))));
+        for lexical in ["", ".", "-", "1.2.3", "1e0", "0x1"] {
+            let term = literal(lexical, XSD_DECIMAL);
+            assert_eq!(term.canonical().as_ref(), &term);
+        }
+    }
+
+    #[test]
+    fn boolean_and_date_time_canonical_form() {
+        let literal = |lexical: &str, datatype: &str| {
+            Term::Literal(Literal::new(lexical, Iri(datatype.to_string())))
+        };
+        for (datatype, lexicals) in [
+            (XSD_BOOLEAN, ["true", "1"].as_slice()),
+            (XSD_BOOLEAN, &["false", "0"]),
+            (
+                XSD_DATE_TIME,
+                &[
+                    "2020-01-01T00:00:00Z",
+                    "2020-01-01T01:00:00+01:00",
+                    "2019-12-31T19:30:00-04:30",
+                    "2020-01-01T00:00:00.000Z",
+                    "2020-01-01T00:00:00",
+                ],
+            ),
+        ] {
+            let canonical = literal(lexicals[0], datatype);
+            assert_eq!(canonical.canonical().as_ref(), &canonical);
+            for lexical in lexicals {
+                let term = literal(lexical, datatype);
+                assert_eq!(term.canonical().as_ref(), &canonical);
+                assert_eq!(encode_term(&term), encode_term(&canonical));
+            }
+        }
+        assert_eq!(
+            Term::Literal(Literal::boolean(true)),
+            literal("true", XSD_BOOLEAN)
+        );
+        assert_ne!(
+            encode_term(&literal("1", XSD_BOOLEAN)),
+            encode_term(&Term::Literal(Literal::integer(1)))
+        );
+
+        let seconds = 1577836800;
+        assert_eq!(Literal::date_time(seconds).as_date_time(), Some(seconds));
+        assert_eq!(
+            encode_term(&literal("2020-01-01T01:00:00+01:00", XSD_DATE_TIME)),
+            [date_time_encoding(seconds)]
+        );
+        assert_ne!(date_time_encoding(seconds), integer_encoding(seconds));
+        for (seconds, lexical) in [
+            (0, "1970-01-01T00:00:00Z"),
+            (-1, "1969-12-31T23:59:59Z"),
+            (951825600, "2000-02-29T12:00:00Z"),
+            (-62135596800, "0001-01-01T00:00:00Z"),
+            (253402300799, "9999-12-31T23:59:59Z"),
+        ] {
+            let date_time = Literal::date_time(seconds);
+            assert_eq!(date_time.lexical, lexical);
+            assert_eq!(date_time.as_date_time(), Some(seconds));
+        }
+        for lexical in [
+            "true",
+            "2020-01-01",
+            "2019-02-29T00:00:00Z",
+            "2020-01-01T24:00:00Z",
+            "2020-01-01T00:00:00.5Z",
+            "2020-01-01T00:00:00+15:00",
+            "2020-01-01T00:00:00+0100",
+            "0000-01-01T00:00:00Z",
+            "0001-01-01T00:00:00+01:00",
+            "+2020-01-01T00:00:00Z",
+        ] {
+            let term = literal(lexical, XSD_DATE_TIME);
+            assert_eq!(term.canonical().as_ref(), &term);
+            assert_eq!(term.as_date_time(), None);
+        }
+    }
+
+    #[test]
+    fn no_collision() {
+        let corpus = corpus();
+        let encodings = corpus
+            .iter()
+            .map(|term| {
+                encode_term(term)
+                    .iter()
+                    .map(PrimeField::to_repr)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!(encodings.len(), corpus.len());
+    }
+}